@@ -1,12 +1,23 @@
+use std::future::Future;
+#[cfg(feature = "std")]
 use std::iter;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Context;
+use std::thread;
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use event_listener::{Event, EventListener};
+#[cfg(feature = "std")]
+use event_listener::EventListener;
+use event_listener::{Event, LocalEvent};
+use waker_fn::waker_fn;
 
+#[cfg(feature = "std")]
 const COUNT: usize = 8000;
 
 fn bench_events(c: &mut Criterion) {
+    #[cfg(feature = "std")]
     c.bench_function("notify_and_wait", |b| {
         let ev = Event::new();
         let mut handles = iter::repeat_with(|| EventListener::new(&ev))
@@ -29,7 +40,173 @@ fn bench_events(c: &mut Criterion) {
             }
         });
     });
+
+    #[cfg(feature = "std")]
+    c.bench_function("notify_one_and_wait", |b| {
+        let ev = Event::new();
+        let mut handle = EventListener::new(&ev);
+
+        b.iter(|| {
+            // SAFETY: The handle is not moved out.
+            let mut listener = unsafe { Pin::new_unchecked(&mut handle) };
+            listener.as_mut().listen();
+
+            ev.notify_one();
+
+            listener.wait();
+        });
+    });
+
+    c.bench_function("listen_notify_poll", |b| {
+        let ev = Event::new();
+        let waker = waker_fn(|| ());
+        let mut cx = Context::from_waker(&waker);
+
+        b.iter(|| {
+            let mut listener = ev.listen();
+            assert!(listener.as_mut().poll(&mut cx).is_pending());
+            ev.notify(1);
+            assert!(listener.as_mut().poll(&mut cx).is_ready());
+        });
+    });
+
+    c.bench_function("local_listen_notify_poll", |b| {
+        let ev = LocalEvent::new();
+        let waker = waker_fn(|| ());
+        let mut cx = Context::from_waker(&waker);
+
+        b.iter(|| {
+            let mut listener = ev.listen();
+            assert!(Pin::new(&mut listener).poll(&mut cx).is_pending());
+            ev.notify(1);
+            assert!(Pin::new(&mut listener).poll(&mut cx).is_ready());
+        });
+    });
+}
+
+/// Runs `f` on another thread until the returned guard is dropped.
+fn contend(f: impl Fn() + Send + 'static) -> impl Drop {
+    struct Stop(Arc<AtomicBool>, Option<thread::JoinHandle<()>>);
+
+    impl Drop for Stop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Relaxed);
+            self.1.take().unwrap().join().unwrap();
+        }
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = thread::spawn({
+        let stop = stop.clone();
+        move || {
+            while !stop.load(Ordering::Relaxed) {
+                f();
+            }
+        }
+    });
+
+    Stop(stop, Some(handle))
+}
+
+// The spin budget only applies to the `no_std` lock, so run these with `--no-default-features`.
+fn bench_spin_budget(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contended_spin_budget");
+
+    for &spins in &[0, 100, 1000] {
+        group.bench_function(spins.to_string(), |b| {
+            let ev = Arc::new(Event::with_spin_budget(spins));
+            let _contender = contend({
+                let ev = ev.clone();
+                move || {
+                    ev.notify_additional(1);
+                }
+            });
+            let waker = waker_fn(|| ());
+            let mut cx = Context::from_waker(&waker);
+
+            b.iter(|| {
+                let mut listener = ev.listen();
+                let _ = listener.as_mut().poll(&mut cx);
+            });
+        });
+    }
+
+    group.finish();
+}
+
+// The lock backs off while spinning, which should leave the other thread alone under contention
+// without slowing down the uncontended path.
+fn bench_lock(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lock");
+
+    for &contended in &[false, true] {
+        let name = if contended {
+            "contended"
+        } else {
+            "uncontended"
+        };
+
+        group.bench_function(name, |b| {
+            let ev = Arc::new(Event::new());
+            let _contender = if contended {
+                let ev = ev.clone();
+                Some(contend(move || {
+                    let listener = ev.listen();
+                    ev.notify_additional(1);
+                    drop(listener);
+                }))
+            } else {
+                None
+            };
+            let waker = waker_fn(|| ());
+            let mut cx = Context::from_waker(&waker);
+
+            b.iter(|| {
+                let mut listener = ev.listen();
+                ev.notify_additional(1);
+                let _ = listener.as_mut().poll(&mut cx);
+            });
+        });
+    }
+
+    group.finish();
+}
+
+// Several threads notifying at once queue their notifications behind the holder of the lock,
+// which applies them before releasing it. Run with `--no-default-features` to use the queue,
+// which is bounded so that the notifiers can't outpace the holder forever.
+fn bench_notify_storm(c: &mut Criterion) {
+    let mut group = c.benchmark_group("notify_storm");
+
+    for &threads in &[1, 3] {
+        group.bench_function(threads.to_string(), |b| {
+            let ev = Arc::new(Event::with_queue_capacity(64));
+            let _contenders = (0..threads)
+                .map(|_| {
+                    let ev = ev.clone();
+                    contend(move || {
+                        ev.notify_additional(1);
+                    })
+                })
+                .collect::<Vec<_>>();
+            let waker = waker_fn(|| ());
+            let mut cx = Context::from_waker(&waker);
+
+            b.iter(|| {
+                let mut listener = ev.listen();
+                let _ = listener.as_mut().poll(&mut cx);
+            });
+        });
+    }
+
+    group.finish();
 }
 
-criterion_group!(benches, bench_events);
+criterion_group!(
+    benches,
+    bench_events,
+    bench_spin_budget,
+    bench_lock,
+    bench_notify_storm
+);
 criterion_main!(benches);
@@ -0,0 +1,124 @@
+//! A thread parker built directly on Linux futexes.
+//!
+//! With the `linux-futex` feature, blocking waits register a waker that wakes the thread through
+//! a futex word, instead of going through the `parking` crate.
+
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::{RawWaker, RawWakerVTable, Waker};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+/// No wakeup is pending.
+const EMPTY: u32 = 0;
+
+/// A wakeup is pending.
+const NOTIFIED: u32 = 1;
+
+/// A futex word that a single thread waits on.
+pub(crate) struct Futex {
+    /// Either `EMPTY` or `NOTIFIED`.
+    word: AtomicU32,
+}
+
+impl Futex {
+    /// Create a new futex with no pending wakeup.
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            word: AtomicU32::new(EMPTY),
+        })
+    }
+
+    /// Blocks until a wakeup is pending or the deadline is reached, and consumes the wakeup.
+    ///
+    /// Spurious futex wakeups are masked by checking the word again. A wakeup left over from an
+    /// earlier wait may still make this return early, so callers have to check their own state.
+    pub(crate) fn wait(&self, deadline: Option<Instant>) {
+        loop {
+            if self.word.swap(EMPTY, Ordering::Acquire) == NOTIFIED {
+                return;
+            }
+
+            let timeout = match deadline {
+                None => None,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+
+                    let timeout = deadline - now;
+                    Some(libc::timespec {
+                        tv_sec: timeout.as_secs().min(libc::time_t::max_value() as u64)
+                            as libc::time_t,
+                        tv_nsec: timeout.subsec_nanos() as _,
+                    })
+                }
+            };
+
+            // The kernel only puts us to sleep if the word is still `EMPTY`, so a wakeup that
+            // came in after the swap above can't be lost.
+            unsafe {
+                libc::syscall(
+                    libc::SYS_futex,
+                    self.word_ptr(),
+                    libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                    EMPTY,
+                    timeout
+                        .as_ref()
+                        .map_or(ptr::null(), |timeout| timeout as *const libc::timespec),
+                );
+            }
+        }
+    }
+
+    /// Makes a wakeup pending and wakes up the waiting thread, if any.
+    pub(crate) fn wake(&self) {
+        // If a wakeup was already pending, the thread was already woken up.
+        if self.word.swap(NOTIFIED, Ordering::Release) == EMPTY {
+            unsafe {
+                libc::syscall(
+                    libc::SYS_futex,
+                    self.word_ptr(),
+                    libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                    1,
+                );
+            }
+        }
+    }
+
+    /// The address of the futex word.
+    fn word_ptr(&self) -> *const u32 {
+        &self.word as *const AtomicU32 as *const u32
+    }
+}
+
+/// Create a waker that wakes up the thread waiting on `futex`.
+pub(crate) fn waker(futex: &Arc<Futex>) -> Waker {
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        let futex = ManuallyDrop::new(Arc::from_raw(data as *const Futex));
+        let futex = Arc::clone(&futex);
+        RawWaker::new(Arc::into_raw(futex) as *const (), &VTABLE)
+    }
+
+    unsafe fn wake(data: *const ()) {
+        Arc::from_raw(data as *const Futex).wake();
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        (*(data as *const Futex)).wake();
+    }
+
+    unsafe fn drop(data: *const ()) {
+        core::mem::drop(Arc::from_raw(data as *const Futex));
+    }
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+    let data = Arc::into_raw(futex.clone()) as *const ();
+
+    // SAFETY: The vtable functions treat the data pointer as an `Arc<Futex>`.
+    unsafe { Waker::from_raw(RawWaker::new(data, &VTABLE)) }
+}
@@ -64,71 +64,232 @@
 //!
 //! - The `portable-atomic` feature enables the use of the [`portable-atomic`] crate to provide
 //!   atomic operations on platforms that don't support them.
+//! - The `linux-futex` feature makes blocking waits on Linux sleep on a futex directly, instead
+//!   of going through [`std::thread::park()`]. It has no effect on other platforms.
+//! - The `into-future` feature implements `IntoFuture` for `&Event`, so that `(&event).await`
+//!   waits for a notification. It requires Rust 1.64 or later.
+//! - The `blocking-mutex` feature makes the `no_std` implementation park threads on a contended
+//!   lock, instead of spinning and then queueing the operation. It requires the standard library
+//!   and only has an effect when the `std` feature is disabled.
+//! - The `debug-stats` feature counts how the `no_std` implementation fills the slots of its
+//!   list, see [`Event::slab_stats()`].
+//! - The `no-alloc` feature adds the [`no_alloc`] module, with an event that holds a fixed number
+//!   of listeners and never allocates. It requires Rust 1.51 or later.
+//! - The `debug` feature adds [`Event::listener_states()`], which dumps the state of every
+//!   registered listener.
+//!
+//! # Single-threaded events
+//!
+//! If an event never leaves its thread, [`LocalEvent`] can be used instead of [`Event`]. It keeps
+//! its listeners in a `RefCell` rather than behind a lock, and doesn't use atomics at all.
 //!
 //! [`portable-atomic`]: https://crates.io/crates/portable-atomic
 
-#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+#![cfg_attr(
+    all(not(feature = "std"), not(feature = "blocking-mutex"), not(test)),
+    no_std
+)]
 #![warn(missing_docs, missing_debug_implementations, rust_2018_idioms)]
 
 extern crate alloc;
 
+/// 1.39-compatible replacement for `matches!`.
+///
+/// This is defined before the modules so that they use it too.
+macro_rules! matches {
+    ($expr:expr, $($pattern:pat)|+ $(if $guard: expr)?) => {
+        match $expr {
+            $($pattern)|+ $(if $guard)? => true,
+            _ => false,
+        }
+    };
+}
+
+/// Defines a `const fn` that isn't `const` under loom, whose atomics can't be created in
+/// constants.
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis const fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])*
+        $vis const fn $($rest)*
+
+        #[cfg(loom)]
+        $(#[$attr])*
+        $vis fn $($rest)*
+    };
+}
+
 #[cfg_attr(feature = "std", path = "std.rs")]
 #[cfg_attr(not(feature = "std"), path = "no_std.rs")]
 mod sys;
 
+#[cfg(all(feature = "linux-futex", target_os = "linux"))]
+mod futex;
+
+mod local;
+
+#[cfg(feature = "no-alloc")]
+pub mod no_alloc;
+
+pub use local::{LocalEvent, LocalEventListener};
+
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 use core::fmt;
 use core::future::Future;
 use core::marker::PhantomPinned;
-use core::mem::ManuallyDrop;
+use core::mem::{self, ManuallyDrop};
 use core::ops::Deref;
 use core::pin::Pin;
 use core::ptr;
-use core::task::{Context, Poll, Waker};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
 
+#[cfg(all(
+    feature = "std",
+    not(all(feature = "linux-futex", target_os = "linux"))
+))]
+use parking::Parker;
 #[cfg(feature = "std")]
-use parking::{Parker, Unparker};
+use parking::Unparker;
 #[cfg(feature = "std")]
 use std::time::{Duration, Instant};
 
-use sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
-use sync::{Arc, WithMut};
-
-/// 1.39-compatible replacement for `matches!`
-macro_rules! matches {
-    ($expr:expr, $($pattern:pat)|+ $(if $guard: expr)?) => {
-        match $expr {
-            $($pattern)|+ $(if $guard)? => true,
-            _ => false,
-        }
-    };
-}
+use sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+#[cfg(not(loom))]
+use sync::WithMut;
+use sync::{Arc, Weak};
 
 /// Inner state of [`Event`].
-struct Inner {
+struct Inner<T = ()> {
     /// The number of notified entries, or `usize::MAX` if all of them have been notified.
     ///
     /// If there are no entries, this value is set to `usize::MAX`.
     notified: AtomicUsize,
 
+    /// The number of entries, updated whenever the list is unlocked.
+    len: AtomicUsize,
+
+    /// The number of notified entries, updated whenever the list is unlocked.
+    ///
+    /// Unlike `notified`, this is the count itself even if every entry is notified.
+    notified_count: AtomicUsize,
+
+    /// Totals of operations on the list, updated whenever the list is unlocked.
+    metrics: Metrics,
+
+    /// Set once the list was latched, while it is locked.
+    latched: AtomicBool,
+
+    /// Notified whenever `len` changes.
+    #[cfg(feature = "futures")]
+    len_changed: Event,
+
+    /// Notified whenever a notified listener receives or gives up its notification.
+    #[cfg(feature = "std")]
+    released: Event,
+
+    /// Notified when this state is dropped, waking up the weak listeners.
+    dropped: Event,
+
     /// Inner queue of event listeners.
     ///
     /// On `std` platforms, this is an intrusive linked list. On `no_std` platforms, this is a
     /// more traditional `Vec` of listeners, with an atomic queue used as a backup for high
     /// contention.
-    list: sys::List,
+    list: sys::List<T>,
 }
 
-impl Inner {
-    fn new() -> Self {
+impl<T> Inner<T> {
+    fn new(spin_budget: u32, queue_capacity: usize, capacity: usize) -> Self {
         Self {
             notified: AtomicUsize::new(core::usize::MAX),
-            list: sys::List::new(),
+            len: AtomicUsize::new(0),
+            notified_count: AtomicUsize::new(0),
+            metrics: Metrics::default(),
+            latched: AtomicBool::new(false),
+            #[cfg(feature = "futures")]
+            len_changed: Event::new(),
+            #[cfg(feature = "std")]
+            released: Event::new(),
+            dropped: Event::new(),
+            list: sys::List::new(spin_budget, queue_capacity, capacity),
+        }
+    }
+
+    /// Publishes the totals of operations on the list.
+    ///
+    /// This must only be called while the list is locked.
+    fn publish_counters(&self, counters: &Counters) {
+        self.metrics
+            .listens
+            .store(counters.listens, Ordering::Relaxed);
+        self.metrics
+            .removes
+            .store(counters.removes, Ordering::Relaxed);
+        self.metrics
+            .notifies
+            .store(counters.notifies, Ordering::Relaxed);
+    }
+
+    /// Updates the number of entries, returning `true` if it changed.
+    ///
+    /// This must only be called while the list is locked.
+    fn set_len(&self, len: usize) -> bool {
+        // Only the holder of the lock writes the count, so it can't change in between.
+        let changed = self.len.load(Ordering::Relaxed) != len;
+        if changed {
+            self.len.store(len, Ordering::Relaxed);
+        }
+        changed
+    }
+
+    /// Wakes up the streams of listener counts after `len` changed.
+    #[cfg(feature = "futures")]
+    fn len_changed(&self) {
+        if let Some(inner) = self.len_changed.try_inner() {
+            // Make sure the new count is visible to the streams that start listening.
+            full_fence();
+
+            if inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                inner.notify(core::usize::MAX, false);
+            }
+        }
+    }
+
+    /// Wakes up the streams of listener counts after `len` changed.
+    #[cfg(not(feature = "futures"))]
+    #[inline]
+    fn len_changed(&self) {}
+
+    /// Wakes up the rendezvous waiting for a notification to be received.
+    #[cfg(feature = "std")]
+    fn released(&self) {
+        // Nobody ever waited for a rendezvous if the event is uninitialized.
+        if let Some(inner) = self.released.try_inner() {
+            // Make sure the released notification is visible to the rendezvous that start waiting.
+            full_fence();
+
+            if inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                inner.notify(core::usize::MAX, false);
+            }
         }
     }
 }
 
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        // Weak listeners don't keep the state alive, so tell them that it's gone.
+        self.dropped.notify(core::usize::MAX);
+    }
+}
+
+/// The number of times the `no_std` list spins on its lock before deferring to the queue.
+const DEFAULT_SPIN_BUDGET: u32 = 100;
+
+/// A callback stored in the inner state.
+type Callback = Arc<Box<dyn Fn() + Send + Sync>>;
+
 /// A synchronization primitive for notifying async tasks and threads.
 ///
 /// Listeners can be registered using [`Event::listen()`]. There are two ways to notify listeners:
@@ -148,24 +309,32 @@ impl Inner {
 /// kind of notification was delivered.
 ///
 /// Listeners are registered and notified in the first-in first-out fashion, ensuring fairness.
-pub struct Event {
+pub struct Event<T = ()> {
     /// A pointer to heap-allocated inner state.
     ///
     /// This pointer is initially null and gets lazily initialized on first use. Semantically, it
     /// is an `Arc<Inner>` so it's important to keep in mind that it contributes to the [`Arc`]'s
     /// reference count.
-    inner: AtomicPtr<Inner>,
+    inner: AtomicPtr<Inner<T>>,
+
+    /// The number of times to spin on the list lock before deferring to the queue.
+    #[cfg(not(feature = "std"))]
+    spin_budget: u32,
+
+    /// The maximum number of operations deferred to the queue.
+    #[cfg(not(feature = "std"))]
+    queue_capacity: usize,
 }
 
-unsafe impl Send for Event {}
-unsafe impl Sync for Event {}
+unsafe impl<T: Send> Send for Event<T> {}
+unsafe impl<T: Send> Sync for Event<T> {}
 
 #[cfg(feature = "std")]
-impl std::panic::UnwindSafe for Event {}
+impl<T> std::panic::UnwindSafe for Event<T> {}
 #[cfg(feature = "std")]
-impl std::panic::RefUnwindSafe for Event {}
+impl<T> std::panic::RefUnwindSafe for Event<T> {}
 
-impl fmt::Debug for Event {
+impl<T> fmt::Debug for Event<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("Pad { .. }")
     }
@@ -179,22 +348,263 @@ impl Default for Event {
 }
 
 impl Event {
-    /// Creates a new [`Event`].
+    const_fn! {
+        /// Creates a new [`Event`].
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use event_listener::Event;
+        ///
+        /// let event = Event::new();
+        /// ```
+        #[inline]
+        pub const fn new() -> Self {
+            Self::with_spin_budget(DEFAULT_SPIN_BUDGET)
+        }
+    }
+
+    const_fn! {
+        /// Creates a new [`Event`] whose list lock spins at most `spins` times under contention.
+        ///
+        /// Without the `std` feature, the list of listeners is protected by a spinlock. When the
+        /// lock is contended, operations spin for a while before falling back to a queue of
+        /// deferred operations. A lower budget wastes fewer cycles on oversubscribed targets,
+        /// while a higher budget makes the fallback path less likely. The default is 100 spins.
+        ///
+        /// With the `std` feature, the list is protected by a blocking mutex and the budget is
+        /// ignored.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use event_listener::Event;
+        ///
+        /// let event = Event::with_spin_budget(10);
+        /// ```
+        #[inline]
+        #[cfg_attr(feature = "std", allow(unused_variables))]
+        pub const fn with_spin_budget(spins: u32) -> Self {
+            Self {
+                inner: AtomicPtr::new(ptr::null_mut()),
+                #[cfg(not(feature = "std"))]
+                spin_budget: spins,
+                #[cfg(not(feature = "std"))]
+                queue_capacity: core::usize::MAX,
+            }
+        }
+    }
+
+    const_fn! {
+        /// Creates a new [`Event`] that defers at most `capacity` operations under contention.
+        ///
+        /// Without the `std` feature, operations that can't take the list lock are pushed to a
+        /// queue that is applied by the holder of the lock. By default the queue is unbounded.
+        /// With a capacity, operations that find the queue full wait for the lock instead, which
+        /// bounds the memory used under sustained contention. Use [`Event::queue_saturated()`]
+        /// to find out if this happened.
+        ///
+        /// With the `std` feature, there is no queue and the capacity is ignored.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use event_listener::Event;
+        ///
+        /// let event = Event::with_queue_capacity(64);
+        /// ```
+        #[inline]
+        #[cfg_attr(feature = "std", allow(unused_variables))]
+        pub const fn with_queue_capacity(capacity: usize) -> Self {
+            Self {
+                inner: AtomicPtr::new(ptr::null_mut()),
+                #[cfg(not(feature = "std"))]
+                spin_budget: DEFAULT_SPIN_BUDGET,
+                #[cfg(not(feature = "std"))]
+                queue_capacity: capacity,
+            }
+        }
+    }
+
+    /// Compares two snapshots of listener states, such as those returned by
+    /// [`Event::drain_all()`].
+    ///
+    /// Listeners that are only in `before` are reported as removed first, in the order of
+    /// `before`. Then listeners whose state changed and listeners that are only in `after` are
+    /// reported in the order of `after`. Since handles are never reused, a listener that took
+    /// over the slot of another one is reported as removed and added.
     ///
     /// # Examples
     ///
     /// ```
-    /// use event_listener::Event;
+    /// use event_listener::{Event, ListenerState, StateChange};
     ///
     /// let event = Event::new();
+    /// let before = event.drain_all();
+    ///
+    /// let listener = event.listen();
+    /// let after = event.drain_all();
+    ///
+    /// assert_eq!(
+    ///     Event::diff_states(&before, &after),
+    ///     [StateChange::Added(after[0].0, ListenerState::Created)]
+    /// );
     /// ```
-    #[inline]
-    pub const fn new() -> Self {
+    pub fn diff_states(
+        before: &[(ListenerHandle, ListenerState)],
+        after: &[(ListenerHandle, ListenerState)],
+    ) -> Vec<StateChange> {
+        let find = |states: &[(ListenerHandle, ListenerState)], handle: ListenerHandle| {
+            states
+                .iter()
+                .find(|(other, _)| *other == handle)
+                .map(|(_, state)| *state)
+        };
+
+        let mut changes = Vec::new();
+
+        for &(handle, state) in before {
+            if find(after, handle).is_none() {
+                changes.push(StateChange::Removed(handle, state));
+            }
+        }
+
+        for &(handle, state) in after {
+            match find(before, handle) {
+                None => changes.push(StateChange::Added(handle, state)),
+                Some(old) if old != state => changes.push(StateChange::Changed {
+                    handle,
+                    before: old,
+                    after: state,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        changes
+    }
+
+    /// Creates a new `Event` with a listener in each of the given states.
+    ///
+    /// This reconstructs an event from the states returned by [`Event::drain_all()`], so that a
+    /// notification scenario can be reproduced deterministically. The returned listeners own the
+    /// entries, in the order of `states`, and the entries are removed once they are dropped.
+    ///
+    /// A waker can't be reconstructed, so listeners in the [`ListenerState::Task`] state are
+    /// given a waker that does nothing. Listeners in the [`ListenerState::NotifiedTaken`] state
+    /// are restored as [`ListenerState::Notified`], so they complete once they are polled.
+    /// Notified listeners are placed ahead of the unnotified ones, as if they had been notified
+    /// in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Event, ListenerState};
+    ///
+    /// let (event, _listeners) = Event::from_state(&[
+    ///     ListenerState::Created,
+    ///     ListenerState::Notified(true),
+    ///     ListenerState::Created,
+    /// ]);
+    ///
+    /// // The notified listener already counts towards `notify()`.
+    /// event.notify(2);
+    ///
+    /// let states = event
+    ///     .drain_all()
+    ///     .into_iter()
+    ///     .map(|(_, state)| state)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     states,
+    ///     [
+    ///         ListenerState::Notified(true),
+    ///         ListenerState::Notified(false),
+    ///         ListenerState::Created,
+    ///     ]
+    /// );
+    /// ```
+    pub fn from_state(states: &[ListenerState]) -> (Event, Vec<Pin<Box<EventListener>>>) {
+        let event = Event::new();
+        let listeners = states
+            .iter()
+            .map(|&state| {
+                let mut listener = event.listen();
+                listener
+                    .as_mut()
+                    .listener()
+                    .restore(State::from_kind(state));
+                listener
+            })
+            .collect();
+
+        (event, listeners)
+    }
+}
+
+impl<T> Event<T> {
+    const_fn! {
+        /// Creates a new [`Event`] whose notifications can carry a tag of type `T`.
+        ///
+        /// Tags are sent with [`Event::notify_tagged()`] and [`Event::notify_additional_tagged()`],
+        /// and a listener that received a tagged notification can read the tag with
+        /// [`EventListener::tag()`]. Notifications sent with the other methods carry no tag.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use event_listener::Event;
+        ///
+        /// let event = Event::<u32>::with_tag();
+        /// ```
+        #[inline]
+        pub const fn with_tag() -> Self {
+            Self {
+                inner: AtomicPtr::new(ptr::null_mut()),
+                #[cfg(not(feature = "std"))]
+                spin_budget: DEFAULT_SPIN_BUDGET,
+                #[cfg(not(feature = "std"))]
+                queue_capacity: core::usize::MAX,
+            }
+        }
+    }
+
+    /// Creates a new [`Event`] with room for `capacity` listeners.
+    ///
+    /// Without the `std` feature, listeners are stored in a `Vec` that grows as listeners are
+    /// added. This preallocates it, so that the first `capacity` listeners are inserted without
+    /// reallocating, and the list never shrinks below that capacity once listeners are removed.
+    /// Unlike the other constructors, the state of the event is allocated right away.
+    ///
+    /// With the `std` feature, listeners are stored in an intrusive linked list and the capacity
+    /// is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event: Event = Event::with_capacity(1024);
+    /// let tagged = Event::<u32>::with_capacity(1024);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        let inner = Arc::new(Inner::<T>::new(
+            DEFAULT_SPIN_BUDGET,
+            core::usize::MAX,
+            capacity,
+        ));
+
         Self {
-            inner: AtomicPtr::new(ptr::null_mut()),
+            inner: AtomicPtr::new(Arc::into_raw(inner) as *mut Inner<T>),
+            #[cfg(not(feature = "std"))]
+            spin_budget: DEFAULT_SPIN_BUDGET,
+            #[cfg(not(feature = "std"))]
+            queue_capacity: core::usize::MAX,
         }
     }
+}
 
+impl<T: Clone> Event<T> {
     /// Returns a guard listening for a notification.
     ///
     /// This method emits a `SeqCst` fence after registering a listener. For now, this method
@@ -210,290 +620,291 @@ impl Event {
     /// let listener = event.listen();
     /// ```
     #[cold]
-    pub fn listen(&self) -> Pin<Box<EventListener>> {
+    pub fn listen(&self) -> Pin<Box<EventListener<T>>> {
         let mut listener = Box::pin(EventListener::new(self));
         listener.as_mut().listen();
         listener
     }
 
-    /// Notifies a number of active listeners.
-    ///
-    /// The number is allowed to be zero or exceed the current number of listeners.
-    ///
-    /// In contrast to [`Event::notify_additional()`], this method only makes sure *at least* `n`
-    /// listeners among the active ones are notified.
+    /// Returns a guard listening for a notification, or an error if the event was shut down.
     ///
-    /// This method emits a `SeqCst` fence before notifying listeners.
+    /// Unlike [`Event::listen()`], this doesn't return a listener that completes right away once
+    /// [`Event::shutdown()`] was called. The returned listener resolves to `Err(Shutdown)` if the
+    /// event is shut down before it is notified, so a notification can be told apart from a
+    /// teardown.
     ///
     /// # Examples
     ///
     /// ```
-    /// use event_listener::Event;
+    /// use event_listener::{Event, Shutdown};
     ///
     /// let event = Event::new();
+    /// let mut listener = event.try_listen().unwrap();
     ///
-    /// // This notification gets lost because there are no listeners.
-    /// event.notify(1);
-    ///
-    /// let listener1 = event.listen();
-    /// let listener2 = event.listen();
-    /// let listener3 = event.listen();
-    ///
-    /// // Notifies two listeners.
-    /// //
-    /// // Listener queueing is fair, which means `listener1` and `listener2`
-    /// // get notified here since they start listening before `listener3`.
-    /// event.notify(2);
+    /// event.shutdown();
+    /// assert_eq!(listener.wait(), Err(Shutdown));
+    /// assert_eq!(event.try_listen().err(), Some(Shutdown));
     /// ```
-    #[inline]
-    pub fn notify(&self, n: usize) {
-        // Make sure the notification comes after whatever triggered it.
-        full_fence();
+    pub fn try_listen(&self) -> Result<TryEventListener<T>, Shutdown> {
+        let listener = self.listen();
 
-        if let Some(inner) = self.try_inner() {
-            // Notify if there is at least one unnotified listener and the number of notified
-            // listeners is less than `n`.
-            if inner.notified.load(Ordering::Acquire) < n {
-                inner.notify(n, false);
-            }
+        // A listener registered after the shutdown starts out shut down.
+        if unsafe { &*self.inner() }.is_shutdown() {
+            return Err(Shutdown);
         }
+
+        Ok(TryEventListener { listener })
     }
 
-    /// Notifies a number of active listeners without emitting a `SeqCst` fence.
-    ///
-    /// The number is allowed to be zero or exceed the current number of listeners.
-    ///
-    /// In contrast to [`Event::notify_additional()`], this method only makes sure *at least* `n`
-    /// listeners among the active ones are notified.
+    /// Returns a guard listening for a notification, which is delivered ahead of listeners with a
+    /// lower priority.
     ///
-    /// Unlike [`Event::notify()`], this method does not emit a `SeqCst` fence.
+    /// Notifications go to the unnotified listeners with the highest priority first. Listeners
+    /// with the same priority are notified in the order they started listening, and listeners
+    /// created with [`Event::listen()`] have a priority of zero.
     ///
     /// # Examples
     ///
     /// ```
     /// use event_listener::Event;
-    /// use std::sync::atomic::{self, Ordering};
     ///
     /// let event = Event::new();
+    /// let mut data = event.listen();
+    /// let mut control = event.listen_with_priority(1);
     ///
-    /// // This notification gets lost because there are no listeners.
+    /// // The control listener is notified first, even though it started listening later.
     /// event.notify(1);
-    ///
-    /// let listener1 = event.listen();
-    /// let listener2 = event.listen();
-    /// let listener3 = event.listen();
-    ///
-    /// // We should emit a fence manually when using relaxed notifications.
-    /// atomic::fence(Ordering::SeqCst);
-    ///
-    /// // Notifies two listeners.
-    /// //
-    /// // Listener queueing is fair, which means `listener1` and `listener2`
-    /// // get notified here since they start listening before `listener3`.
-    /// event.notify(2);
+    /// assert!(control.as_mut().discard());
+    /// assert!(!data.as_mut().discard());
     /// ```
-    #[inline]
-    pub fn notify_relaxed(&self, n: usize) {
-        if let Some(inner) = self.try_inner() {
-            // Notify if there is at least one unnotified listener and the number of notified
-            // listeners is less than `n`.
-            if inner.notified.load(Ordering::Acquire) < n {
-                inner.notify(n, true);
-            }
-        }
+    #[cold]
+    pub fn listen_with_priority(&self, priority: u8) -> Pin<Box<EventListener<T>>> {
+        let mut listener = Box::pin(EventListener::new(self));
+        listener.as_mut().listen_with_priority(priority);
+        listener
     }
 
-    /// Notifies a number of active and still unnotified listeners.
-    ///
-    /// The number is allowed to be zero or exceed the current number of listeners.
+    /// Returns a guard listening for a notification, which doesn't keep the event alive.
     ///
-    /// In contrast to [`Event::notify()`], this method will notify `n` *additional* listeners that
-    /// were previously unnotified.
+    /// Unlike an [`EventListener`], a [`WeakEventListener`] holds a weak reference to the event,
+    /// so a listener that is kept around, for example in a cache, doesn't leak the event once
+    /// every [`Event`] and strong listener is gone. Polling or waiting on the listener then
+    /// returns [`EventGone`], and a task that was waiting on it is woken up when the event is
+    /// dropped.
     ///
-    /// This method emits a `SeqCst` fence before notifying listeners.
+    /// This method emits a `SeqCst` fence after registering a listener.
     ///
     /// # Examples
     ///
     /// ```
-    /// use event_listener::Event;
+    /// use event_listener::{Event, EventGone};
     ///
     /// let event = Event::new();
+    /// let mut listener = event.listen_weak();
     ///
-    /// // This notification gets lost because there are no listeners.
     /// event.notify(1);
+    /// assert_eq!(listener.as_mut().wait(), Ok(()));
     ///
-    /// let listener1 = event.listen();
-    /// let listener2 = event.listen();
-    /// let listener3 = event.listen();
-    ///
-    /// // Notifies two listeners.
-    /// //
-    /// // Listener queueing is fair, which means `listener1` and `listener2`
-    /// // get notified here since they start listening before `listener3`.
-    /// event.notify_additional(1);
-    /// event.notify_additional(1);
+    /// let mut listener = event.listen_weak();
+    /// drop(event);
+    /// assert_eq!(listener.as_mut().wait(), Err(EventGone));
     /// ```
-    #[inline]
-    pub fn notify_additional(&self, n: usize) {
-        // Make sure the notification comes after whatever triggered it.
+    pub fn listen_weak(&self) -> Pin<Box<WeakEventListener<T>>> {
+        let inner = unsafe { ManuallyDrop::new(Arc::from_raw(self.inner())) };
+
+        let mut listener = Box::pin(WeakEventListener {
+            listener: ManuallyDrop::new(Listener {
+                event: WeakRef(self.inner()),
+                listener: None,
+                trace_id: TraceId::default(),
+                tag: None,
+                shutdown: false,
+                _pin: PhantomPinned,
+            }),
+            event: Arc::downgrade(&inner),
+            dropped: inner.dropped.listen(),
+        });
+        listener.as_mut().listener().insert(0);
+
+        // Make sure the listener is registered before whatever happens next.
         full_fence();
 
-        if let Some(inner) = self.try_inner() {
-            // Notify if there is at least one unnotified listener.
-            if inner.notified.load(Ordering::Acquire) < core::usize::MAX {
-                inner.notify(n, true);
-            }
-        }
+        listener
     }
 
-    /// Notifies a number of active and still unnotified listeners without emitting a `SeqCst`
-    /// fence.
+    /// Returns a guard listening for a notification, which is delivered ahead of every listener
+    /// that is still waiting.
     ///
-    /// The number is allowed to be zero or exceed the current number of listeners.
-    ///
-    /// In contrast to [`Event::notify()`], this method will notify `n` *additional* listeners that
-    /// were previously unnotified.
+    /// The new listener goes to the front of the line of unnotified listeners, so repeated calls
+    /// notify listeners in last-in first-out order. It takes the priority of the listener it goes
+    /// ahead of, so a later listener with a higher priority still overtakes it. If a permit or a
+    /// deferred notification is waiting, the listener takes it instead, like with
+    /// [`Event::listen()`].
     ///
-    /// Unlike [`Event::notify_additional()`], this method does not emit a `SeqCst` fence.
+    /// On `no_std`, this method waits for the lock instead of deferring the registration.
     ///
     /// # Examples
     ///
     /// ```
     /// use event_listener::Event;
-    /// use std::sync::atomic::{self, Ordering};
     ///
     /// let event = Event::new();
+    /// let mut older = event.listen();
+    /// let mut newer = event.listen_front();
     ///
-    /// // This notification gets lost because there are no listeners.
+    /// // The newer listener is notified first.
     /// event.notify(1);
+    /// assert!(newer.as_mut().discard());
+    /// assert!(!older.as_mut().discard());
+    /// ```
+    #[cold]
+    pub fn listen_front(&self) -> Pin<Box<EventListener<T>>> {
+        let mut listener = Box::pin(EventListener::new(self));
+        listener.as_mut().listener().insert_front();
+
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+        listener
+    }
+
+    /// Returns a guard listening for a notification, along with the number of listeners that
+    /// were registered before it.
     ///
-    /// let listener1 = event.listen();
-    /// let listener2 = event.listen();
-    /// let listener3 = event.listen();
+    /// The number is the position of the new listener in line, counted from the front, at the
+    /// time it was registered. Listeners ahead of it that are removed or notified later don't
+    /// change the returned number, so it is only a snapshot. Listeners that were already notified
+    /// but are still registered are counted too.
     ///
-    /// // We should emit a fence manually when using relaxed notifications.
-    /// atomic::fence(Ordering::SeqCst);
+    /// On `no_std`, this method waits for the lock instead of deferring the registration.
+    ///
+    /// # Examples
     ///
-    /// // Notifies two listeners.
-    /// //
-    /// // Listener queueing is fair, which means `listener1` and `listener2`
-    /// // get notified here since they start listening before `listener3`.
-    /// event.notify_additional_relaxed(1);
-    /// event.notify_additional_relaxed(1);
     /// ```
-    #[inline]
-    pub fn notify_additional_relaxed(&self, n: usize) {
-        if let Some(inner) = self.try_inner() {
-            // Notify if there is at least one unnotified listener.
-            if inner.notified.load(Ordering::Acquire) < core::usize::MAX {
-                inner.notify(n, true);
-            }
-        }
-    }
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let (_listener1, position1) = event.listen_positioned();
+    /// let (_listener2, position2) = event.listen_positioned();
+    ///
+    /// assert_eq!(position1, 0);
+    /// assert_eq!(position2, 1);
+    /// ```
+    #[cold]
+    pub fn listen_positioned(&self) -> (Pin<Box<EventListener<T>>>, usize) {
+        let mut listener = Box::pin(EventListener::new(self));
+        let position = listener.as_mut().listener().insert_positioned();
 
-    /// Return a reference to the inner state if it has been initialized.
-    #[inline]
-    fn try_inner(&self) -> Option<&Inner> {
-        let inner = self.inner.load(Ordering::Acquire);
-        unsafe { inner.as_ref() }
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+        (listener, position)
     }
 
-    /// Returns a raw, initialized pointer to the inner state.
+    /// Returns a guard listening for a notification, which can take `capacity` items.
     ///
-    /// This returns a raw pointer instead of reference because `from_raw`
-    /// requires raw/mut provenance: <https://github.com/rust-lang/rust/pull/67339>.
-    fn inner(&self) -> *const Inner {
-        let mut inner = self.inner.load(Ordering::Acquire);
-
-        // If this is the first use, initialize the state.
-        if inner.is_null() {
-            // Allocate the state on the heap.
-            let new = Arc::new(Inner::new());
-
-            // Convert the state to a raw pointer.
-            let new = Arc::into_raw(new) as *mut Inner;
-
-            // Replace the null pointer with the new state pointer.
-            inner = self
-                .inner
-                .compare_exchange(inner, new, Ordering::AcqRel, Ordering::Acquire)
-                .unwrap_or_else(|x| x);
-
-            // Check if the old pointer value was indeed null.
-            if inner.is_null() {
-                // If yes, then use the new state pointer.
-                inner = new;
-            } else {
-                // If not, that means a concurrent operation has initialized the state.
-                // In that case, use the old pointer and deallocate the new one.
-                unsafe {
-                    drop(Arc::from_raw(new));
-                }
-            }
-        }
-
-        inner
-    }
-}
+    /// The capacity is used by [`Event::notify_to_capacity()`] to decide how many listeners to
+    /// notify. Listeners registered with [`Event::listen()`] can take a single item.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener = event.listen_with_capacity(8);
+    /// ```
+    pub fn listen_with_capacity(&self, capacity: usize) -> Pin<Box<EventListener<T>>> {
+        let mut listener = Box::pin(EventListener::new(self));
+        listener.as_mut().listener().insert_with_capacity(capacity);
 
-impl Drop for Event {
-    #[inline]
-    fn drop(&mut self) {
-        self.inner.with_mut(|&mut inner| {
-            // If the state pointer has been initialized, drop it.
-            if !inner.is_null() {
-                unsafe {
-                    drop(Arc::from_raw(inner));
-                }
-            }
-        })
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+        listener
     }
-}
-
-/// A guard waiting for a notification from an [`Event`].
-///
-/// There are two ways for a listener to wait for a notification:
-///
-/// 1. In an asynchronous manner using `.await`.
-/// 2. In a blocking manner by calling [`EventListener::wait()`] on it.
-///
-/// If a notified listener is dropped without receiving a notification, dropping will notify
-/// another active listener. Whether one *additional* listener will be notified depends on what
-/// kind of notification was delivered.
-pub struct EventListener(Listener<Arc<Inner>>);
 
-impl fmt::Debug for EventListener {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("EventListener { .. }")
+    /// Returns a guard listening for a notification, which passes the notification on if it is
+    /// dropped before receiving it.
+    ///
+    /// This is the same contract as dropping an [`EventListener`], made explicit: if the listener
+    /// was notified but the notification wasn't consumed by polling or waiting on the guard,
+    /// dropping the guard notifies the next active listener instead, so that notifications aren't
+    /// lost when a waiter gives up. [`ListenGuard::release()`] does this early and reports whether
+    /// a notification was passed on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let guard = event.listen_guarded();
+    /// let mut listener = event.listen();
+    ///
+    /// event.notify(1);
+    ///
+    /// // The guard gives up, so the notification goes to `listener`.
+    /// assert!(guard.release());
+    /// assert!(listener.as_mut().discard());
+    /// ```
+    pub fn listen_guarded(&self) -> ListenGuard<T> {
+        ListenGuard {
+            listener: Some(self.listen()),
+        }
     }
-}
-
-impl EventListener {
-    /// Create a new `EventListener` that will wait for a notification from the given [`Event`].
-    pub fn new(event: &Event) -> Self {
-        let inner = event.inner();
-
-        let listener = Listener {
-            event: unsafe { Arc::clone(&ManuallyDrop::new(Arc::from_raw(inner))) },
-            listener: None,
-            _pin: PhantomPinned,
-        };
 
-        Self(listener)
+    /// Returns a guard listening for a notification, which belongs to `group`.
+    ///
+    /// Groups are used by [`Event::notify_per_group()`] to notify at most one listener of every
+    /// group. The group of a listener is unrelated to the tag of the notification it receives.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::with_tag();
+    /// let listener = event.listen_in_group("tenant-a");
+    /// ```
+    pub fn listen_in_group(&self, group: T) -> Pin<Box<EventListener<T>>> {
+        let mut listener = self.listen();
+        listener.as_mut().listener().set_group(group);
+        listener
     }
 
-    /// Register this listener into the given [`Event`].
+    /// Creates a listener for the duration of a closure.
     ///
-    /// This method can only be called after the listener has been pinned, and must be called before
-    /// the listener is polled.
-    pub fn listen(self: Pin<&mut Self>) {
-        self.listener().insert();
+    /// The listener is registered before `f` is called and removed once `f` returns or unwinds.
+    /// If it was notified but didn't receive the notification, the notification is passed on to
+    /// another listener, like when an [`EventListener`] is dropped. The listener is only lent to
+    /// `f`, so it can't escape the closure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    ///
+    /// let value = event.with_listener(|listener| {
+    ///     event.notify(1);
+    ///     listener.wait();
+    ///     42
+    /// });
+    ///
+    /// assert_eq!(value, 42);
+    /// assert_eq!(event.total_listeners(), 0);
+    /// ```
+    pub fn with_listener<R>(&self, f: impl FnOnce(Pin<&mut EventListener<T>>) -> R) -> R {
+        let mut listener = self.listen();
 
-        // Make sure the listener is registered before whatever happens next.
-        full_fence();
+        // The listener is dropped when `f` returns or unwinds.
+        f(listener.as_mut())
     }
 
-    /// Blocks until a notification is received.
+    /// Registers a listener, then runs `act` and returns the listener along with its result.
+    ///
+    /// The listener is registered before `act` is called, so a notification caused by `act`,
+    /// such as a reply to a request it sends, can't be missed. If `act` panics, the listener is
+    /// removed while unwinding, and a notification it received is passed on to another listener.
     ///
     /// # Examples
     ///
@@ -501,85 +912,213 @@ impl EventListener {
     /// use event_listener::Event;
     ///
     /// let event = Event::new();
-    /// let mut listener = event.listen();
     ///
-    /// // Notify `listener`.
-    /// event.notify(1);
+    /// let (mut listener, sent) = event.arm(|| {
+    ///     // Something that leads to a notification.
+    ///     event.notify(1);
+    ///     true
+    /// });
     ///
-    /// // Receive the notification.
+    /// assert!(sent);
     /// listener.as_mut().wait();
     /// ```
-    #[cfg(feature = "std")]
-    pub fn wait(self: Pin<&mut Self>) {
-        self.listener().wait_internal(None);
+    pub fn arm<R>(&self, act: impl FnOnce() -> R) -> (Pin<Box<EventListener<T>>>, R) {
+        // The listener is fully registered once `listen` returns.
+        let listener = self.listen();
+
+        // The listener is dropped if `act` unwinds.
+        let result = act();
+        (listener, result)
     }
 
-    /// Blocks until a notification is received or a timeout is reached.
+    /// Blocks until `pred` returns `true` or the deadline is reached.
     ///
-    /// Returns `true` if a notification was received.
+    /// This is the condition variable pattern: `pred` is checked, and if it returns `false`, the
+    /// current thread waits for a notification and checks `pred` again. Notifications that leave
+    /// `pred` unsatisfied are treated as spurious wakeups, and waiting resumes until the same
+    /// deadline. Because the deadline is absolute, several waits can share it.
+    ///
+    /// Returns `true` if `pred` was satisfied, and `false` if the deadline was reached first.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::time::Duration;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::time::{Duration, Instant};
     /// use event_listener::Event;
     ///
     /// let event = Event::new();
-    /// let mut listener = event.listen();
+    /// let flag = AtomicBool::new(false);
     ///
-    /// // There are no notification so this times out.
-    /// assert!(!listener.as_mut().wait_timeout(Duration::from_secs(1)));
+    /// // Nobody sets the flag, so this times out.
+    /// let deadline = Instant::now() + Duration::from_millis(10);
+    /// assert!(!event.wait_deadline_while(deadline, || flag.load(Ordering::SeqCst)));
     /// ```
     #[cfg(feature = "std")]
-    pub fn wait_timeout(self: Pin<&mut Self>, timeout: Duration) -> bool {
-        self.listener()
-            .wait_internal(Instant::now().checked_add(timeout))
+    pub fn wait_deadline_while(&self, deadline: Instant, mut pred: impl FnMut() -> bool) -> bool {
+        loop {
+            if pred() {
+                return true;
+            }
+
+            let mut listener = self.listen();
+
+            // Check again, in case the condition changed before the listener was registered.
+            if pred() {
+                return true;
+            }
+
+            if !listener.as_mut().wait_deadline(deadline) {
+                // The condition may still have changed right before the deadline.
+                return pred();
+            }
+        }
     }
 
-    /// Blocks until a notification is received or a deadline is reached.
+    /// Listens for a notification, reusing the listener in `slot` if possible.
     ///
-    /// Returns `true` if a notification was received.
+    /// If `slot` holds a listener for this `Event` that is still waiting for a notification, that
+    /// listener is reused. Otherwise a new listener is registered and stored in `slot`. This
+    /// guarantees that an owner calling this method repeatedly has at most one listener
+    /// registered at a time.
+    ///
+    /// If the previous listener was already notified, it is replaced and dropped after the new
+    /// listener is registered. As with any dropped listener, its notification is passed on.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::time::{Duration, Instant};
     /// use event_listener::Event;
     ///
     /// let event = Event::new();
-    /// let mut listener = event.listen();
+    /// let mut slot = None;
     ///
-    /// // There are no notification so this times out.
-    /// assert!(!listener.as_mut().wait_deadline(Instant::now() + Duration::from_secs(1)));
+    /// event.listen_coalesced(&mut slot);
+    /// event.listen_coalesced(&mut slot);
+    /// assert_eq!(event.total_listeners(), 1);
     /// ```
-    #[cfg(feature = "std")]
-    pub fn wait_deadline(self: Pin<&mut Self>, deadline: Instant) -> bool {
-        self.listener().wait_internal(Some(deadline))
+    pub fn listen_coalesced<'a>(
+        &self,
+        slot: &'a mut Option<Pin<Box<EventListener<T>>>>,
+    ) -> Pin<&'a mut EventListener<T>> {
+        let reuse = match slot {
+            Some(listener) => listener.listens_to(self) && listener.as_mut().is_waiting(),
+            None => false,
+        };
+
+        if !reuse {
+            *slot = Some(self.listen());
+        }
+
+        slot.as_mut().unwrap().as_mut()
     }
 
-    /// Drops this listener and discards its notification (if any) without notifying another
-    /// active listener.
+    /// Notifies a number of active listeners.
     ///
-    /// Returns `true` if a notification was discarded.
+    /// The number is allowed to be zero or exceed the current number of listeners.
+    ///
+    /// In contrast to [`Event::notify_additional()`], this method only makes sure *at least* `n`
+    /// listeners among the active ones are notified.
+    ///
+    /// Returns the number of listeners that were notified by this call, which doesn't include
+    /// listeners that were already notified. On `no_std`, a notification sent while the list of
+    /// listeners is contended is queued and applied later by the holder of the lock. It isn't
+    /// known yet how many listeners it will notify, so `0` is returned in that case.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
     ///
     /// # Examples
+    ///
     /// ```
     /// use event_listener::Event;
     ///
     /// let event = Event::new();
+    ///
+    /// // This notification gets lost because there are no listeners.
+    /// event.notify(1);
+    ///
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    /// let listener3 = event.listen();
+    ///
+    /// // Notifies two listeners.
+    /// //
+    /// // Listener queueing is fair, which means `listener1` and `listener2`
+    /// // get notified here since they start listening before `listener3`.
+    /// event.notify(2);
+    /// ```
+    ///
+    /// Other strategies can be plugged in through the [`Notification`] trait, for example
+    /// [`Additional`] to notify a number of *additional* listeners:
+    ///
+    /// ```
+    /// use event_listener::{Additional, Event};
+    ///
+    /// let event = Event::new();
     /// let mut listener1 = event.listen();
     /// let mut listener2 = event.listen();
     ///
     /// event.notify(1);
+    /// event.notify(Additional(1));
     ///
     /// assert!(listener1.as_mut().discard());
-    /// assert!(!listener2.as_mut().discard());
+    /// assert!(listener2.as_mut().discard());
     /// ```
-    pub fn discard(self: Pin<&mut Self>) -> bool {
-        self.listener().discard()
+    #[inline]
+    pub fn notify(&self, notify: impl IntoNotification) -> usize {
+        let mut notify = notify.into_notification();
+
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            let notified = inner.notified.load(Ordering::Acquire);
+
+            // Only load the number of listeners if it's needed.
+            let len = if notify.uses_len() || notified == core::usize::MAX {
+                inner.len.load(Ordering::Relaxed)
+            } else {
+                0
+            };
+            let n = notify.next_count(
+                match notified {
+                    core::usize::MAX => len,
+                    notified => notified,
+                },
+                len,
+            );
+
+            if notify.is_additional() {
+                // Notify if `n` is non-zero and there is at least one unnotified listener.
+                if n > 0 && notified < core::usize::MAX {
+                    return inner.notify(n, true);
+                }
+            } else if notified < n {
+                // Notify if there is at least one unnotified listener and the number of notified
+                // listeners is less than `n`.
+                return inner.notify(n, false);
+            }
+        }
+
+        0
     }
 
-    /// Returns `true` if this listener listens to the given `Event`.
+    /// Notifies a number of active listeners, returning `false` if no listener was registered.
+    ///
+    /// This behaves like [`Event::notify()`] with a number of listeners, and also tells apart an
+    /// event nobody listens to from one whose listeners are all notified already. Both checks
+    /// read the counters published when the list of listeners was last unlocked, so an empty
+    /// event is detected without taking the lock.
+    ///
+    /// The result is only as current as those counters: a listener registered or removed after
+    /// they were read isn't seen, just like with [`Event::total_listeners()`] followed by
+    /// [`Event::notify()`]. On `no_std`, a notification that finds the list contended is queued,
+    /// so `true` means that a listener was registered, not that it has been notified yet.
+    ///
+    /// Returns `true` if at least one listener was registered, even if it didn't need another
+    /// notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
     ///
     /// # Examples
     ///
@@ -587,16 +1126,39 @@ impl EventListener {
     /// use event_listener::Event;
     ///
     /// let event = Event::new();
-    /// let listener = event.listen();
+    /// assert!(!event.notify_present(1));
     ///
-    /// assert!(listener.listens_to(&event));
+    /// let mut listener = event.listen();
+    /// assert!(event.notify_present(1));
+    /// listener.as_mut().wait();
     /// ```
     #[inline]
-    pub fn listens_to(&self, event: &Event) -> bool {
-        ptr::eq::<Inner>(&**self.inner(), event.inner.load(Ordering::Acquire))
+    pub fn notify_present(&self, n: usize) -> bool {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            let notified = inner.notified.load(Ordering::Acquire);
+
+            if notified < n {
+                // There is at least one unnotified listener.
+                inner.notify(n, false);
+                return true;
+            }
+
+            // Either every listener is notified or there are none.
+            return inner.len.load(Ordering::Relaxed) > 0;
+        }
+
+        false
     }
 
-    /// Returns `true` if both listeners listen to the same `Event`.
+    /// Notifies a single active listener.
+    ///
+    /// This is equivalent to `notify(1)`, but takes a shorter path when the list of listeners is
+    /// not contended.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
     ///
     /// # Examples
     ///
@@ -604,68 +1166,4271 @@ impl EventListener {
     /// use event_listener::Event;
     ///
     /// let event = Event::new();
-    /// let listener1 = event.listen();
+    ///
+    /// let mut listener1 = event.listen();
     /// let listener2 = event.listen();
     ///
-    /// assert!(listener1.same_event(&listener2));
-    /// ```
-    pub fn same_event(&self, other: &EventListener) -> bool {
-        ptr::eq::<Inner>(&**self.inner(), &**other.inner())
-    }
+    /// // Notifies `listener1`.
+    /// event.notify_one();
+    /// listener1.as_mut().wait();
+    /// ```
+    #[inline]
+    pub fn notify_one(&self) {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if there are no notified listeners yet.
+            if inner.notified.load(Ordering::Acquire) == 0 {
+                inner.notify_one();
+            }
+        }
+    }
+
+    /// Notifies a number of active listeners without emitting a `SeqCst` fence.
+    ///
+    /// The number is allowed to be zero or exceed the current number of listeners.
+    ///
+    /// In contrast to [`Event::notify_additional()`], this method only makes sure *at least* `n`
+    /// listeners among the active ones are notified.
+    ///
+    /// Unlike [`Event::notify()`], this method does not emit a `SeqCst` fence, and the internal
+    /// count of notified listeners is updated with `Relaxed` ordering. This is only correct if
+    /// the state the listeners are waiting for has already been published through some other
+    /// synchronization edge, such as another atomic operation or a fence.
+    ///
+    /// Returns the number of listeners that were notified by this call, as in [`Event::notify()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use std::sync::atomic::{self, Ordering};
+    ///
+    /// let event = Event::new();
+    ///
+    /// // This notification gets lost because there are no listeners.
+    /// event.notify_relaxed(1);
+    ///
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    /// let listener3 = event.listen();
+    ///
+    /// // We should emit a fence manually when using relaxed notifications.
+    /// atomic::fence(Ordering::SeqCst);
+    ///
+    /// // Notifies two listeners.
+    /// //
+    /// // Listener queueing is fair, which means `listener1` and `listener2`
+    /// // get notified here since they start listening before `listener3`.
+    /// event.notify_relaxed(2);
+    /// ```
+    #[inline]
+    pub fn notify_relaxed(&self, n: usize) -> usize {
+        if let Some(inner) = self.try_inner() {
+            // Notify if there is at least one unnotified listener and the number of notified
+            // listeners is less than `n`.
+            if inner.notified.load(Ordering::Acquire) < n {
+                return inner
+                    .notify_with(n, false, TraceId::default(), None, Ordering::Relaxed)
+                    .0;
+            }
+        }
+
+        0
+    }
+
+    /// Notifies one listener of this `Event` and returns a listener for the `reply` event.
+    ///
+    /// The listener on `reply` is registered before the notification is sent, so a reply sent by
+    /// the notified side in response can't be missed. This makes it easy to hand control back
+    /// and forth between two tasks or threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let ping = Event::new();
+    /// let pong = Event::new();
+    ///
+    /// let mut ping_listener = ping.listen();
+    /// let mut pong_listener = ping.notify_then_listen(&pong);
+    ///
+    /// // The other side receives the ping and replies.
+    /// ping_listener.as_mut().wait();
+    /// pong.notify(1);
+    ///
+    /// pong_listener.as_mut().wait();
+    /// ```
+    pub fn notify_then_listen<U: Clone>(&self, reply: &Event<U>) -> Pin<Box<EventListener<U>>> {
+        // Register the listener first, so the reply can't be lost.
+        let listener = reply.listen();
+        self.notify(1);
+        listener
+    }
+
+    /// Notifies a number of active and still unnotified listeners.
+    ///
+    /// The number is allowed to be zero or exceed the current number of listeners.
+    ///
+    /// In contrast to [`Event::notify()`], this method will notify `n` *additional* listeners that
+    /// were previously unnotified.
+    ///
+    /// Returns the number of listeners that were notified, which is less than `n` if there weren't
+    /// enough unnotified listeners. See [`Event::notify()`] for the contended case on `no_std`.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    ///
+    /// // This notification gets lost because there are no listeners.
+    /// event.notify(1);
+    ///
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    /// let listener3 = event.listen();
+    ///
+    /// // Notifies two listeners.
+    /// //
+    /// // Listener queueing is fair, which means `listener1` and `listener2`
+    /// // get notified here since they start listening before `listener3`.
+    /// event.notify_additional(1);
+    /// event.notify_additional(1);
+    /// ```
+    #[inline]
+    pub fn notify_additional(&self, n: usize) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                return inner.notify(n, true);
+            }
+        }
+
+        0
+    }
+
+    /// Notifies a number of active and still unnotified listeners without emitting a `SeqCst`
+    /// fence.
+    ///
+    /// The number is allowed to be zero or exceed the current number of listeners.
+    ///
+    /// In contrast to [`Event::notify()`], this method will notify `n` *additional* listeners that
+    /// were previously unnotified.
+    ///
+    /// Unlike [`Event::notify_additional()`], this method does not emit a `SeqCst` fence, and the
+    /// internal count of notified listeners is updated with `Relaxed` ordering. This is only
+    /// correct if the state the listeners are waiting for has already been published through
+    /// some other synchronization edge, such as the atomics of a channel or a fence.
+    ///
+    /// Returns the number of listeners that were notified, as in [`Event::notify_additional()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use std::sync::atomic::{self, Ordering};
+    ///
+    /// let event = Event::new();
+    ///
+    /// // This notification gets lost because there are no listeners.
+    /// event.notify(1);
+    ///
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    /// let listener3 = event.listen();
+    ///
+    /// // We should emit a fence manually when using relaxed notifications.
+    /// atomic::fence(Ordering::SeqCst);
+    ///
+    /// // Notifies two listeners.
+    /// //
+    /// // Listener queueing is fair, which means `listener1` and `listener2`
+    /// // get notified here since they start listening before `listener3`.
+    /// event.notify_additional_relaxed(1);
+    /// event.notify_additional_relaxed(1);
+    /// ```
+    #[inline]
+    pub fn notify_additional_relaxed(&self, n: usize) -> usize {
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                return inner
+                    .notify_with(n, true, TraceId::default(), None, Ordering::Relaxed)
+                    .0;
+            }
+        }
+
+        0
+    }
+
+    /// Notifies a number of active listeners and delivers `tag` to them.
+    ///
+    /// This behaves like [`Event::notify()`], and every listener notified by this call receives
+    /// a clone of `tag`, which it can read with [`EventListener::tag()`] once it completes.
+    /// Listeners that were already notified count towards `n` and keep the tag they were
+    /// notified with. If a notified listener is dropped without receiving its notification, the
+    /// tag is passed on along with the notification.
+    ///
+    /// Returns the number of listeners that received `tag`, as in [`Event::notify()`].
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::with_tag();
+    /// let mut listener = event.listen();
+    ///
+    /// event.notify_tagged(1, "ready");
+    ///
+    /// listener.as_mut().wait();
+    /// assert_eq!(listener.tag(), Some(&"ready"));
+    /// ```
+    #[inline]
+    pub fn notify_tagged(&self, n: usize, tag: T) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if there is at least one unnotified listener and the number of notified
+            // listeners is less than `n`.
+            if inner.notified.load(Ordering::Acquire) < n {
+                return inner
+                    .notify_with(n, false, TraceId::default(), Some(tag), Ordering::Release)
+                    .0;
+            }
+        }
+
+        0
+    }
+
+    /// Notifies a number of active listeners and delivers a separate tag to each of them.
+    ///
+    /// This behaves like [`Event::notify_tagged()`], except that `f` is called once for every
+    /// listener notified by this call, in notification order, and the listener receives the
+    /// returned tag. This can be used to hand out sequential tickets, for example.
+    ///
+    /// `f` is called while the list of listeners is locked, so it must not use this `Event`. On
+    /// `no_std`, this method waits for the lock instead of deferring the notification.
+    ///
+    /// Returns the number of listeners that were notified, which is the number of times `f` was
+    /// called.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::with_tag();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// let mut ticket = 0;
+    /// event.notify_each(2, || {
+    ///     ticket += 1;
+    ///     ticket
+    /// });
+    ///
+    /// listener1.as_mut().wait();
+    /// listener2.as_mut().wait();
+    /// assert_eq!(listener1.tag(), Some(&1));
+    /// assert_eq!(listener2.tag(), Some(&2));
+    /// ```
+    pub fn notify_each<F>(&self, n: usize, mut f: F) -> usize
+    where
+        F: FnMut() -> T,
+    {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if there is at least one unnotified listener and the number of notified
+            // listeners is less than `n`.
+            if inner.notified.load(Ordering::Acquire) < n {
+                return inner.notify_each(n, &mut f);
+            }
+        }
+
+        0
+    }
+
+    /// Notifies a number of active and still unnotified listeners and delivers `tag` to them.
+    ///
+    /// This behaves like [`Event::notify_additional()`], and every listener notified by this
+    /// call receives a clone of `tag`, which it can read with [`EventListener::tag()`] once it
+    /// completes.
+    ///
+    /// Returns the number of listeners that received `tag`, as in [`Event::notify_additional()`].
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::with_tag();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// event.notify_additional_tagged(1, 1);
+    /// event.notify_additional_tagged(1, 2);
+    ///
+    /// listener1.as_mut().wait();
+    /// listener2.as_mut().wait();
+    /// assert_eq!(listener1.tag(), Some(&1));
+    /// assert_eq!(listener2.tag(), Some(&2));
+    /// ```
+    #[inline]
+    pub fn notify_additional_tagged(&self, n: usize, tag: T) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                return inner
+                    .notify_with(n, true, TraceId::default(), Some(tag), Ordering::Release)
+                    .0;
+            }
+        }
+
+        0
+    }
+
+    /// Notifies a number of listeners and attaches a trace id to the notifications.
+    ///
+    /// If `additional` is `false`, this behaves like [`Event::notify()`], otherwise like
+    /// [`Event::notify_additional()`]. Listeners that receive the notification can read the
+    /// trace id with [`EventListener::trace_id()`] once they complete, which allows the consumer
+    /// to continue the trace of the notifier.
+    ///
+    /// If `additional` is `false`, the listeners that were already notified but haven't received
+    /// their notification yet count towards `n`, and their trace id is replaced with `trace_id`.
+    ///
+    /// Returns the number of listeners that were newly notified. Listeners that were already
+    /// notified and only had their trace id replaced don't count.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// event.notify_traced(1, false, 99);
+    ///
+    /// listener.as_mut().wait();
+    /// assert_eq!(listener.trace_id(), Some(99));
+    /// ```
+    #[cfg(feature = "trace-id")]
+    pub fn notify_traced(&self, n: usize, additional: bool, trace_id: u64) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Always take the lock, since already notified listeners may need a new trace id.
+            return inner
+                .notify_with(
+                    n,
+                    additional,
+                    TraceId(Some(trace_id)),
+                    None,
+                    Ordering::Release,
+                )
+                .0;
+        }
+
+        0
+    }
+
+    /// Notifies a number of listeners without letting the number of notified listeners exceed
+    /// `cap`.
+    ///
+    /// If `additional` is `false`, this behaves like [`Event::notify()`], otherwise like
+    /// [`Event::notify_additional()`], except that notifications that would push the number of
+    /// notified listeners above `cap` are discarded. This keeps a slow consumer from building up
+    /// an unbounded backlog of notified listeners. Listeners that are already notified are never
+    /// un-notified, even if there are more than `cap` of them.
+    ///
+    /// On `no_std`, this method waits for the lock instead of deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listeners = (0..10).map(|_| event.listen()).collect::<Vec<_>>();
+    ///
+    /// event.notify_capped(2, true, 3);
+    /// event.notify_capped(2, true, 3);
+    /// assert_eq!(event.debug_stats().notified, 3);
+    /// ```
+    pub fn notify_capped(&self, n: usize, additional: bool, cap: usize) {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            let notified = inner.notified.load(Ordering::Acquire);
+
+            // Notify if there is at least one unnotified listener and there is room below `cap`.
+            let limit = if additional { cap } else { n.min(cap) };
+            if n > 0 && notified < core::usize::MAX && notified < limit {
+                inner.notify_capped(n, additional, cap);
+            }
+        }
+    }
+
+    /// Notifies a number of listeners if the list of listeners can be locked right away.
+    ///
+    /// If `additional` is `false`, this behaves like [`Event::notify()`], otherwise like
+    /// [`Event::notify_additional()`]. If the list is locked by someone else, this returns
+    /// `false` without notifying anyone, leaving it to the caller to try again later.
+    ///
+    /// Unlike the other notification methods, this never spins on the lock and never queues the
+    /// notification, so on `no_std` it doesn't allocate a queue node. It is not bounded work,
+    /// though: on `no_std`, releasing the lock also applies every operation that others queued
+    /// while it was held and wakes the tasks those operations notified. Only with
+    /// [`Event::with_queue_capacity()`] is that extra work bounded, by the queue capacity. Waking
+    /// more than a few tasks at once may also allocate, so keep `n` small in interrupt handlers.
+    ///
+    /// Returns `true` if the notification was delivered, or if there was nobody to notify.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// assert!(event.try_notify(1, false));
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn try_notify(&self, n: usize, additional: bool) -> bool {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            let notified = inner.notified.load(Ordering::Acquire);
+
+            // Notify under the same conditions as `notify()` and `notify_additional()`.
+            let needed = if additional {
+                n > 0 && notified < core::usize::MAX
+            } else {
+                notified < n
+            };
+
+            if needed {
+                return inner.try_notify(n, additional);
+            }
+        }
+
+        true
+    }
+
+    /// Notifies a number of listeners, retrying a contended lock before deferring the
+    /// notification.
+    ///
+    /// If `additional` is `false`, this behaves like [`Event::notify()`], otherwise like
+    /// [`Event::notify_additional()`].
+    ///
+    /// On `no_std` platforms, a notification that can't take the list lock within the spin
+    /// budget of the event is queued, which allocates and leaves the work to the holder of the
+    /// lock. This method first spins up to `retry_spins` more times on the lock, so that brief
+    /// contention doesn't send the notification to the queue. On `std` platforms the list is
+    /// protected by a blocking mutex and `retry_spins` is ignored.
+    ///
+    /// Returns the number of listeners that were notified, or `0` if the notification ended up
+    /// queued anyway.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// assert_eq!(event.notify_with_fallback_poll(1, false, 1000), 1);
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn notify_with_fallback_poll(&self, n: usize, additional: bool, retry_spins: u32) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            let notified = inner.notified.load(Ordering::Acquire);
+
+            // Notify under the same conditions as `notify()` and `notify_additional()`.
+            let needed = if additional {
+                n > 0 && notified < core::usize::MAX
+            } else {
+                notified < n
+            };
+
+            if needed {
+                return inner.notify_with_retry(n, additional, retry_spins);
+            }
+        }
+
+        0
+    }
+
+    /// Notifies a number of listeners and reports how the notification was delivered.
+    ///
+    /// If `additional` is `false`, this behaves like [`Event::notify()`], otherwise like
+    /// [`Event::notify_additional()`].
+    ///
+    /// On `no_std` platforms a notification is queued if the list of listeners is contended, and
+    /// applied later by the holder of the lock. In that case this returns
+    /// [`NotifyPath::Queued`] with the number of queued operations right after the notification
+    /// was queued. Otherwise, including when there is nobody to notify, this returns
+    /// [`NotifyPath::Fast`]. On `std` platforms this always returns [`NotifyPath::Fast`].
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Event, NotifyPath};
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// assert_eq!(event.notify_with_path_info(1, false), NotifyPath::Fast);
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn notify_with_path_info(&self, n: usize, additional: bool) -> NotifyPath {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            let notified = inner.notified.load(Ordering::Acquire);
+
+            // Notify under the same conditions as `notify()` and `notify_additional()`.
+            let needed = if additional {
+                n > 0 && notified < core::usize::MAX
+            } else {
+                notified < n
+            };
+
+            if needed {
+                return inner
+                    .notify_with(n, additional, TraceId::default(), None, Ordering::Release)
+                    .1;
+            }
+        }
+
+        NotifyPath::Fast
+    }
+
+    /// Notifies unnotified listeners until the items they can take cover `needed` items.
+    ///
+    /// Listeners are notified in order, and each one counts for the capacity it was registered
+    /// with in [`Event::listen_with_capacity()`], or one item otherwise. Notification stops once
+    /// the sum reaches `needed` or there are no unnotified listeners left, so a single listener
+    /// that can take more than `needed` items is notified on its own. The notifications are
+    /// *additional*, as in [`Event::notify_additional()`].
+    ///
+    /// Returns the number of items the notified listeners can take, which may be more or less
+    /// than `needed`.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener1 = event.listen_with_capacity(2);
+    /// let listener2 = event.listen_with_capacity(3);
+    /// let listener3 = event.listen_with_capacity(5);
+    ///
+    /// // Notifies `listener1` and `listener2`.
+    /// assert_eq!(event.notify_to_capacity(4), 5);
+    /// ```
+    pub fn notify_to_capacity(&self, needed: usize) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        match self.try_inner() {
+            // Notify if `needed` is non-zero and there is at least one unnotified listener.
+            Some(inner)
+                if needed > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX =>
+            {
+                inner.notify_to_capacity(needed)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Notifies a number of active and still unnotified listeners, newest first.
+    ///
+    /// [`Event::notify()`] wakes listeners in the order of the list, so the listener that was
+    /// registered first is woken first. This walks the list from the other end instead, which
+    /// suits a work-stealing executor: the worker that parked last is the most likely to still
+    /// have its data in cache. Listeners with a higher priority are still ahead of the others in
+    /// the list, so they are woken last.
+    ///
+    /// Like [`Event::notify_additional()`], this notifies `n` *additional* listeners, and it can
+    /// be freely mixed with the other notification methods. On `no_std`, this method waits for
+    /// the lock instead of deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// event.notify_lifo(1);
+    /// assert!(listener2.as_mut().discard());
+    /// assert!(!listener1.as_mut().discard());
+    /// ```
+    pub fn notify_lifo(&self, n: usize) {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                inner.notify_lifo(n);
+            }
+        }
+    }
+
+    /// Notifies a number of active and still unnotified listeners, preferring the ones that have
+    /// been waiting the longest.
+    ///
+    /// Listeners are ordered by when a task first started waiting on them, rather than by when
+    /// they were registered: a listener that was created early but only polled late is notified
+    /// after listeners that were polled before it. Once no unnotified listener is waiting, the
+    /// remaining notifications go to the other listeners in the order they were registered.
+    ///
+    /// Like [`Event::notify_additional()`], this notifies `n` *additional* listeners. On
+    /// `no_std`, this method waits for the lock instead of deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use futures_lite::future;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// // `listener2` starts waiting first.
+    /// assert!(future::block_on(future::poll_once(listener2.as_mut())).is_none());
+    /// assert!(future::block_on(future::poll_once(listener1.as_mut())).is_none());
+    ///
+    /// event.notify_oldest(1);
+    /// assert!(future::block_on(future::poll_once(listener2.as_mut())).is_some());
+    /// assert!(future::block_on(future::poll_once(listener1.as_mut())).is_none());
+    /// ```
+    pub fn notify_oldest(&self, n: usize) {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                inner.notify_oldest(n);
+            }
+        }
+    }
+
+    /// Notifies a number of active and still unnotified listeners, taking turns between them.
+    ///
+    /// The event remembers the listener notified last by this method, and each call continues
+    /// with the listeners after it, wrapping around at the end. This spreads notifications evenly
+    /// among equivalent listeners, such as workers that listen again after every notification.
+    /// On `std`, listeners take turns in the order they were registered. On `no_std`, they take
+    /// turns by their slot in the list, which a listener that is dropped and registered again
+    /// usually gets back.
+    ///
+    /// Like [`Event::notify_additional()`], this notifies `n` *additional* listeners, and it
+    /// returns the number of listeners that were notified. Finding the next listener takes time
+    /// linear in the number of listeners. On `no_std`, this method waits for the lock instead of
+    /// deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// event.notify_round_robin(1);
+    /// assert!(listener1.as_mut().discard());
+    /// let mut listener1 = event.listen();
+    ///
+    /// // The next turn goes to `listener2`, even though `listener1` is listening again.
+    /// event.notify_round_robin(1);
+    /// assert!(listener2.as_mut().discard());
+    /// assert!(!listener1.as_mut().discard());
+    /// ```
+    pub fn notify_round_robin(&self, n: usize) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                return inner.notify_round_robin(n);
+            }
+        }
+
+        0
+    }
+
+    /// Notifies a number of active and still unnotified listeners, never passing over a listener
+    /// for one registered after it.
+    ///
+    /// Every call to this method starts a new round, and listeners registered in an earlier round
+    /// are notified before any listener registered in a later one, in the order of the list. This
+    /// bounds how long a listener waits, even if listeners with a higher priority or registered
+    /// with [`Event::listen_front()`] keep going ahead of it: once every listener that was
+    /// registered before its round is notified, it is next.
+    ///
+    /// Like [`Event::notify_additional()`], this notifies `n` *additional* listeners, and it
+    /// returns the number of listeners that were notified. Finding the next listener takes time
+    /// linear in the number of listeners. On `no_std`, this method waits for the lock instead of
+    /// deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    /// event.notify_fair(1);
+    /// assert!(listener1.as_mut().discard());
+    ///
+    /// // `listener3` goes ahead of `listener2`, but was registered in a later round.
+    /// let mut listener3 = event.listen_front();
+    /// event.notify_fair(1);
+    /// assert!(listener2.as_mut().discard());
+    /// assert!(!listener3.as_mut().discard());
+    /// ```
+    pub fn notify_fair(&self, n: usize) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                return inner.notify_fair(n);
+            }
+        }
+
+        0
+    }
+
+    /// Notifies active and still unnotified listeners until `target` listeners hold a
+    /// notification.
+    ///
+    /// This is similar to [`Event::notify()`] with a non-additional notification, but the
+    /// listeners holding a notification are counted again by walking the list, after applying
+    /// any removals that were deferred on `no_std`, rather than relying on the running count.
+    /// The listeners are notified with *additional* notifications, so a listener that is dropped
+    /// without taking its notification passes it on instead of discarding it. If `target`
+    /// listeners already hold a notification, nothing happens.
+    ///
+    /// Returns the number of listeners that were notified. Counting takes time linear in the
+    /// number of listeners. On `no_std`, this method waits for the lock instead of deferring the
+    /// notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    /// let mut listener3 = event.listen();
+    ///
+    /// assert_eq!(event.notify_saturating(1), 1);
+    /// assert_eq!(event.notify_saturating(2), 1);
+    ///
+    /// // Two listeners are notified already, so lower targets do nothing.
+    /// assert_eq!(event.notify_saturating(1), 0);
+    /// assert!(listener1.as_mut().discard());
+    /// assert!(listener2.as_mut().discard());
+    /// assert!(!listener3.as_mut().discard());
+    /// ```
+    pub fn notify_saturating(&self, target: usize) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `target` is non-zero and there is at least one unnotified listener.
+            if target > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                return inner.notify_saturating(target);
+            }
+        }
+
+        0
+    }
+
+    /// Notifies the listener with `handle`, unless it is already notified.
+    ///
+    /// The listener receives an *additional* notification, even if other listeners are waiting
+    /// in front of it, and no other listener is notified. The handle is obtained from
+    /// [`EventListener::handle()`].
+    ///
+    /// Returns `false` if the listener is no longer registered, or if `handle` belongs to a
+    /// listener of another event. On `no_std`, the notification is deferred if the list is
+    /// contended, in which case this returns `true`.
+    ///
+    /// This method emits a `SeqCst` fence before notifying the listener.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    /// let handle = listener2.as_mut().handle().unwrap();
+    ///
+    /// assert!(event.notify_handle(handle));
+    /// assert!(!listener1.as_mut().discard());
+    /// assert!(listener2.as_mut().discard());
+    ///
+    /// // The listener is gone.
+    /// assert!(!event.notify_handle(handle));
+    /// ```
+    pub fn notify_handle(&self, handle: ListenerHandle) -> bool {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        match self.try_inner() {
+            Some(inner) => match handle.within(inner) {
+                Some(handle) => inner.notify_handle_deferred(handle),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Notifies an active and still unnotified listener, then blocks until it has received the
+    /// notification.
+    ///
+    /// This forms a rendezvous with the listener: once this method returns `true`, the listener
+    /// has picked up the notification, for example by being polled or waited on. The
+    /// notification is an *additional* one, as in [`Event::notify_additional()`]. The rendezvous
+    /// also completes if the listener gives up the notification by being dropped, which passes
+    /// it on to another listener.
+    ///
+    /// Returns `false` if there was no unnotified listener, or if `timeout` elapsed before the
+    /// listener received the notification. The listener keeps the notification in that case.
+    ///
+    /// The calling thread blocks without holding any lock, so the listener can always receive
+    /// the notification. It can't do that while it is waited on by the calling thread itself,
+    /// though, or while its task is blocked on something the caller holds. Without a `timeout`,
+    /// this method never returns in those cases.
+    ///
+    /// This method emits a `SeqCst` fence before notifying the listener.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let idle = event.listen();
+    ///
+    /// // Nobody waits on the listener, so the rendezvous times out.
+    /// assert!(!event.notify_rendezvous(Some(Duration::from_millis(10))));
+    ///
+    /// let mut listener = event.listen();
+    /// let handle = thread::spawn(move || listener.as_mut().wait());
+    /// assert!(event.notify_rendezvous(None));
+    /// handle.join().unwrap();
+    /// drop(idle);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn notify_rendezvous(&self, timeout: Option<Duration>) -> bool {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        let inner = match self.try_inner() {
+            Some(inner) => inner,
+            None => return false,
+        };
+
+        // Notify only if there is at least one unnotified listener.
+        if inner.notified.load(Ordering::Acquire) == core::usize::MAX {
+            return false;
+        }
+
+        let handle = match inner.notify_rendezvous() {
+            Some(handle) => handle,
+            None => return false,
+        };
+
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut listener = inner.released.listen();
+
+        loop {
+            // The listener is registered before checking, so a release in between isn't missed.
+            if !inner.holds_notification(handle) {
+                return true;
+            }
+
+            if !listener.as_mut().listener().wait_internal(deadline, true) {
+                return !inner.holds_notification(handle);
+            }
+
+            // Make sure the listener is registered before the handle is checked again.
+            full_fence();
+        }
+    }
+
+    /// Notifies a number of active and still unnotified listeners that match a predicate.
+    ///
+    /// The predicate is called with the state of each unnotified listener, in the order they
+    /// would normally be notified, until `n` listeners matched. Matching listeners receive an
+    /// *additional* notification, while skipped listeners stay in line for later notifications.
+    ///
+    /// The predicate is called while the list of listeners is locked, so it must not use this
+    /// `Event`. On `no_std`, this method waits for the lock instead of deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Event, ListenerState};
+    /// use futures_lite::future;
+    ///
+    /// let event = Event::new();
+    /// let mut idle = event.listen();
+    /// let mut waiting = event.listen();
+    ///
+    /// // Only `waiting` has a task registered.
+    /// assert!(future::block_on(future::poll_once(waiting.as_mut())).is_none());
+    ///
+    /// event.notify_filtered(1, |state| *state == ListenerState::Task);
+    /// assert!(waiting.as_mut().discard());
+    /// assert!(!idle.as_mut().discard());
+    /// ```
+    pub fn notify_filtered<F>(&self, n: usize, mut pred: F)
+    where
+        F: FnMut(&ListenerState) -> bool,
+    {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if let Some(inner) = self.try_inner() {
+            // Notify if `n` is non-zero and there is at least one unnotified listener.
+            if n > 0 && inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+                inner.notify_filtered(n, &mut pred);
+            }
+        }
+    }
+
+    /// Notifies a number of listeners and returns the listeners that have no task yet.
+    ///
+    /// This notifies `n` listeners like [`Event::notify()`], or `n` *additional* listeners like
+    /// [`Event::notify_additional()`] if `additional` is `true`. It returns how many listeners
+    /// this call notified, along with the handles of the listeners that are still unnotified and
+    /// were never polled or waited on, in the order they would be notified. This lets another
+    /// reactor take over the listeners that nobody is waiting on yet.
+    ///
+    /// On `no_std`, this method waits for the lock instead of deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use futures_lite::future;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let listener2 = event.listen();
+    /// let listener3 = event.listen();
+    ///
+    /// // Only `listener1` has a task registered.
+    /// assert!(future::block_on(future::poll_once(listener1.as_mut())).is_none());
+    ///
+    /// let (woken, created) = event.notify_and_harvest(1, false);
+    /// assert_eq!(woken, 1);
+    /// assert_eq!(created.len(), 2);
+    /// ```
+    pub fn notify_and_harvest(&self, n: usize, additional: bool) -> (usize, Vec<ListenerHandle>) {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        match self.try_inner() {
+            // Notify if there is at least one unnotified listener.
+            Some(inner) if inner.notified.load(Ordering::Acquire) < core::usize::MAX => {
+                inner.notify_and_harvest(n, additional)
+            }
+            _ => (0, Vec::new()),
+        }
+    }
+
+    /// Notifies the first unnotified listener of every group.
+    ///
+    /// Listeners registered with [`Event::listen_in_group()`] are grouped by the key `by` returns
+    /// for their group. In a single call, the first unnotified listener of every group receives an
+    /// *additional* notification, so no group can take all the notifications of a batch. Groups
+    /// whose listeners are all notified already, and listeners without a group, are skipped.
+    /// Returns the number of notified listeners.
+    ///
+    /// The keys are collected in a list, so this takes time quadratic in the number of groups.
+    /// `by` is called while the list of listeners is locked, so it must not use this `Event`.
+    /// On `no_std`, this method waits for the lock instead of deferring the notification.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::with_tag();
+    /// let mut a1 = event.listen_in_group("a");
+    /// let mut a2 = event.listen_in_group("a");
+    /// let mut b = event.listen_in_group("b");
+    ///
+    /// assert_eq!(event.notify_per_group(|group| *group), 2);
+    /// assert!(a1.as_mut().discard());
+    /// assert!(!a2.as_mut().discard());
+    /// assert!(b.as_mut().discard());
+    /// ```
+    pub fn notify_per_group<K: PartialEq>(&self, by: impl Fn(&T) -> K) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        match self.try_inner() {
+            // Notify if there is at least one unnotified listener.
+            Some(inner) if inner.notified.load(Ordering::Acquire) < core::usize::MAX => {
+                let mut seen = Vec::new();
+                inner.notify_per_group(&mut |group| {
+                    let key = by(group);
+                    if seen.contains(&key) {
+                        false
+                    } else {
+                        seen.push(key);
+                        true
+                    }
+                })
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns the state of every registered listener, without removing them.
+    ///
+    /// This is a snapshot for debugging, for example to find out why listeners never wake up.
+    /// The states are returned in the order the listeners are linked in the list. Listeners
+    /// removed by [`Event::drain_all()`] or [`Event::shutdown()`] are no longer registered and
+    /// aren't included.
+    ///
+    /// This method takes the lock and requires the `debug` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Event, ListenerStateKind};
+    ///
+    /// let event = Event::new();
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    ///
+    /// assert_eq!(
+    ///     event.listener_states(),
+    ///     [ListenerStateKind::Notified, ListenerStateKind::Created]
+    /// );
+    /// ```
+    #[cfg(feature = "debug")]
+    pub fn listener_states(&self) -> Vec<ListenerStateKind> {
+        match self.try_inner() {
+            Some(inner) => inner
+                .listener_states()
+                .into_iter()
+                .map(|(_, state)| state.into())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Removes every registered listener and returns their handles and final states.
+    ///
+    /// Tasks waiting on the removed listeners are woken up. The removed listeners are treated
+    /// as notified: they complete when they are next polled or waited on, and dropping them does
+    /// not affect the event. The states are returned in the order the listeners were registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Event, ListenerState};
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    ///
+    /// let states = event
+    ///     .drain_all()
+    ///     .into_iter()
+    ///     .map(|(_, state)| state)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(states, [ListenerState::Notified(false), ListenerState::Created]);
+    /// ```
+    pub fn drain_all(&self) -> Vec<(ListenerHandle, ListenerState)> {
+        match self.try_inner() {
+            Some(inner) => inner.drain_all(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Notifies every registered listener and removes them, so that new listeners only see
+    /// later notifications.
+    ///
+    /// This is like [`Event::drain_all()`] without collecting the states. The removed listeners
+    /// complete when they are next polled or waited on, and tasks waiting on them are woken up.
+    /// Unlike `notify(usize::MAX)`, the notified listeners don't stay registered, so they don't
+    /// count towards later calls to [`Event::notify()`].
+    ///
+    /// Returns the number of listeners that weren't notified yet. On `no_std`, this method waits
+    /// for the lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// assert_eq!(event.notify_drain(), 2);
+    /// assert_eq!(event.total_listeners(), 0);
+    ///
+    /// listener1.as_mut().wait();
+    /// listener2.as_mut().wait();
+    /// ```
+    pub fn notify_drain(&self) -> usize {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        match self.try_inner() {
+            Some(inner) => inner.notify_drain(),
+            None => 0,
+        }
+    }
+
+    /// Removes every registered listener, as if the event was shut down, but keeps the event
+    /// usable.
+    ///
+    /// The listeners that weren't notified yet complete as shut down when they are next polled
+    /// or waited on, so [`EventListener::is_shutdown()`] returns `true` for them. Notified
+    /// listeners keep their notification. New listeners can be registered right away.
+    ///
+    /// If `wake` is `true`, tasks waiting on the removed listeners are woken up after the list
+    /// is unlocked. Otherwise their wakers are dropped, and the tasks only observe the shutdown
+    /// once something else polls them again.
+    ///
+    /// The removed listeners still own their memory, so on `no_std` platforms their slots are
+    /// only freed once the listeners are dropped. On `no_std`, this method waits for the lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// event.clear(true);
+    /// listener.as_mut().wait();
+    /// assert!(listener.is_shutdown());
+    ///
+    /// // The event can still be used.
+    /// assert!(!event.is_shutdown());
+    /// ```
+    pub fn clear(&self, wake: bool) {
+        if let Some(inner) = self.try_inner() {
+            inner.clear(wake);
+        }
+    }
+
+    /// Shuts the event down, waking up every listener.
+    ///
+    /// Every registered listener is removed from the event and completes when it is next polled
+    /// or waited on, without receiving a notification. [`EventListener::is_shutdown()`] tells
+    /// these listeners apart from notified ones, and listeners returned by
+    /// [`Event::try_listen()`] resolve to `Err(Shutdown)`. Listeners registered after the
+    /// shutdown complete right away, and [`Event::try_listen()`] fails with [`Shutdown`].
+    ///
+    /// Shutting an event down can't be undone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// event.shutdown();
+    ///
+    /// listener.as_mut().wait();
+    /// assert!(listener.is_shutdown());
+    /// ```
+    pub fn shutdown(&self) {
+        unsafe { &*self.inner() }.shutdown();
+    }
+
+    /// Returns `true` if [`Event::shutdown()`] was called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// assert!(!event.is_shutdown());
+    ///
+    /// event.shutdown();
+    /// assert!(event.is_shutdown());
+    /// ```
+    pub fn is_shutdown(&self) -> bool {
+        match self.try_inner() {
+            Some(inner) => inner.is_shutdown(),
+            None => false,
+        }
+    }
+
+    /// Notifies every listener and makes every future listener complete right away.
+    ///
+    /// This turns the event into a one-shot latch that stays signaled: the current listeners are
+    /// notified, and listeners registered afterwards start out notified. Unlike after
+    /// [`Event::shutdown()`], the listeners complete normally, so
+    /// [`EventListener::is_shutdown()`] stays `false`. Registration and latching take the same
+    /// lock, so a listener registered concurrently is either notified by the latch or starts out
+    /// notified. Latching an event that was shut down has no visible effect.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// event.latch();
+    /// listener.as_mut().wait();
+    ///
+    /// // The event stays signaled.
+    /// let mut listener = event.listen();
+    /// listener.as_mut().wait();
+    /// assert!(!listener.is_shutdown());
+    /// ```
+    pub fn latch(&self) {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        unsafe { &*self.inner() }.latch();
+    }
+
+    /// Returns `true` if [`Event::latch()`] was called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// assert!(!event.is_latched());
+    ///
+    /// event.latch();
+    /// assert!(event.is_latched());
+    /// ```
+    pub fn is_latched(&self) -> bool {
+        match self.try_inner() {
+            // Pairs with the store in `latch()`, so whatever happened before the latch is visible.
+            Some(inner) => inner.latched.load(Ordering::Acquire),
+            None => false,
+        }
+    }
+
+    /// Returns the number of listeners currently registered to this `Event`.
+    ///
+    /// Notified listeners that haven't been dropped yet are counted too.
+    ///
+    /// The returned number is only a snapshot: listeners may be registered or removed
+    /// concurrently, so it may already be stale by the time this method returns. On `std`, this
+    /// reads the count without locking. On `no_std`, it applies the deferred operations if the
+    /// list isn't contended, and otherwise returns the count as of the last time the list was
+    /// unlocked, like [`Event::listener_count()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// assert_eq!(event.total_listeners(), 0);
+    ///
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    /// assert_eq!(event.total_listeners(), 2);
+    ///
+    /// drop(listener1);
+    /// assert_eq!(event.total_listeners(), 1);
+    /// ```
+    #[inline]
+    pub fn total_listeners(&self) -> usize {
+        match self.try_inner() {
+            Some(inner) => inner.total_listeners(),
+            None => 0,
+        }
+    }
+
+    /// Returns the number of listeners registered to this `Event` without locking.
+    ///
+    /// The count is updated whenever the list of listeners is unlocked, so it may lag behind
+    /// [`Event::total_listeners()`], for example while operations are deferred under contention
+    /// on `no_std`. It never blocks, which makes it suitable for frequent sampling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// assert_eq!(event.listener_count(), 0);
+    ///
+    /// let listener = event.listen();
+    /// assert_eq!(event.listener_count(), 1);
+    /// ```
+    #[inline]
+    pub fn listener_count(&self) -> usize {
+        match self.try_inner() {
+            Some(inner) => inner.len.load(Ordering::Relaxed),
+            None => 0,
+        }
+    }
+
+    /// Returns the number of registered listeners that were notified, without locking.
+    ///
+    /// These listeners hold a notification they haven't received yet. Like
+    /// [`Event::listener_count()`], the count is updated whenever the list of listeners is
+    /// unlocked and never blocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    /// assert_eq!(event.notified_count(), 1);
+    ///
+    /// event.notify(2);
+    /// assert_eq!(event.notified_count(), 2);
+    /// ```
+    #[inline]
+    pub fn notified_count(&self) -> usize {
+        match self.try_inner() {
+            Some(inner) => inner.notified_count.load(Ordering::Relaxed),
+            None => 0,
+        }
+    }
+
+    /// Returns a stream of the number of listeners registered to this `Event`.
+    ///
+    /// The stream yields the current [`Event::listener_count()`] when it is first polled, and
+    /// then the new count whenever it changes. Rapid changes may be coalesced, so the stream is
+    /// only guaranteed to yield the latest count, not every intermediate one. The stream never
+    /// ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use futures_lite::{future, StreamExt};
+    /// use std::sync::Arc;
+    ///
+    /// let event = Arc::new(Event::new());
+    /// let mut counts = Box::pin(event.clone().count_change_stream());
+    /// assert_eq!(future::block_on(counts.next()), Some(0));
+    ///
+    /// let listener = event.listen();
+    /// assert_eq!(future::block_on(counts.next()), Some(1));
+    /// ```
+    #[cfg(feature = "futures")]
+    pub fn count_change_stream(
+        self: alloc::sync::Arc<Self>,
+    ) -> impl futures_core::Stream<Item = usize> {
+        CountChanges {
+            event: self,
+            listener: None,
+            last: None,
+        }
+    }
+
+    /// Returns a stream of the tags of notifications that match `filter`.
+    ///
+    /// The stream starts listening right away and listens again after every notification it
+    /// receives. Notifications whose tag doesn't match `filter`, and notifications without a tag,
+    /// leave the stream pending and are passed on to the next listener in line, as with
+    /// [`Event::notify_additional()`]. Notifications that arrive while the stream isn't
+    /// listening, for example because it was handed more notifications at once than it has
+    /// listeners, are missed. The stream ends once the event is shut down.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use futures_lite::{future, StreamExt};
+    /// use std::sync::Arc;
+    ///
+    /// let event = Arc::new(Event::with_tag());
+    /// let mut evens = Box::pin(event.clone().filtered_stream(|n: &u32| n % 2 == 0));
+    ///
+    /// event.notify_tagged(1, 2);
+    /// assert_eq!(future::block_on(evens.next()), Some(2));
+    /// ```
+    #[cfg(feature = "futures")]
+    pub fn filtered_stream(
+        self: alloc::sync::Arc<Self>,
+        filter: impl Fn(&T) -> bool,
+    ) -> impl futures_core::Stream<Item = T> {
+        FilteredTags {
+            listener: self.listen(),
+            event: self,
+            filter,
+        }
+    }
+
+    /// Returns a stream that yields once per notification.
+    ///
+    /// The stream starts listening right away and keeps a single listener registered. Each item
+    /// consumes the notification of the listener and registers it again at the back of the line
+    /// with the polling task, the same way as [`EventListener::poll_renew()`], so a notification
+    /// sent after an item was yielded is never missed.
+    ///
+    /// The stream is edge-triggered and coalescing: it yields one item for however many
+    /// notifications arrived since it was last polled, and notifications that don't go to its
+    /// listener, for example because [`Event::notify()`] finds it already notified, aren't
+    /// yielded. The stream ends once the event is shut down.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use futures_lite::{future, StreamExt};
+    ///
+    /// let event = Event::new();
+    /// let mut wakeups = event.stream();
+    ///
+    /// event.notify(1);
+    /// assert_eq!(future::block_on(wakeups.next()), Some(()));
+    ///
+    /// event.shutdown();
+    /// assert_eq!(future::block_on(wakeups.next()), None);
+    /// ```
+    #[cfg(feature = "futures")]
+    pub fn stream(&self) -> EventStream<T> {
+        EventStream {
+            listener: Some(self.listen()),
+        }
+    }
+
+    /// Sets a callback that is called when the last registered listener is removed.
+    ///
+    /// The callback is called without holding any locks, after the removal that left the event
+    /// without listeners. It is not called if the event had no listeners to begin with. New
+    /// listeners may be registered concurrently before or while the callback runs.
+    ///
+    /// Setting a new callback replaces the previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let empty = Arc::new(AtomicBool::new(false));
+    /// let event = Event::new();
+    /// event.set_on_empty({
+    ///     let empty = empty.clone();
+    ///     move || empty.store(true, Ordering::SeqCst)
+    /// });
+    ///
+    /// let listener = event.listen();
+    /// assert!(!empty.load(Ordering::SeqCst));
+    ///
+    /// drop(listener);
+    /// assert!(empty.load(Ordering::SeqCst));
+    /// ```
+    pub fn set_on_empty(&self, callback: impl Fn() + Send + Sync + 'static) {
+        unsafe { &*self.inner() }.set_on_empty(Arc::new(Box::new(callback)));
+    }
+
+    /// Sets how tasks are woken up when their listener is notified.
+    ///
+    /// By default, notifying a listener consumes its task with [`Waker::wake()`]. With
+    /// [`WakeStrategy::ByRef`], the task is woken with [`Waker::wake_by_ref()`] instead and kept
+    /// with the notified listener, which can save a clone for listeners that re-arm right away.
+    /// Either way, the listener is marked as notified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Event, WakeStrategy};
+    ///
+    /// let event = Event::new();
+    /// event.set_wake_strategy(WakeStrategy::ByRef);
+    ///
+    /// let mut listener = event.listen();
+    /// event.notify(1);
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn set_wake_strategy(&self, strategy: WakeStrategy) {
+        unsafe { &*self.inner() }.set_wake_strategy(strategy);
+    }
+
+    /// Sets whether listeners that share a task wake it only once per notification.
+    ///
+    /// When several listeners of this event are polled by the same task, notifying all of them
+    /// wakes that task once for every listener. With deduplication enabled, the tasks of the
+    /// notified listeners are collected while the list is locked and woken once it is unlocked,
+    /// skipping tasks for which [`Waker::will_wake()`] matches a task that is already collected.
+    /// Threads blocked in [`EventListener::wait()`] are never deduplicated.
+    ///
+    /// Comparing the tasks takes time quadratic in the number of distinct tasks woken at once, so
+    /// this is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// event.set_wake_deduplication(true);
+    ///
+    /// let mut listener = event.listen();
+    /// event.notify(1);
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn set_wake_deduplication(&self, enabled: bool) {
+        unsafe { &*self.inner() }.set_wake_deduplication(enabled);
+    }
+
+    /// Performs housekeeping on the list of listeners and reports what was done.
+    ///
+    /// Under a single lock acquisition, this applies any operations that were deferred due to
+    /// contention, trims empty slots from the end of the listener list, releases excess memory
+    /// and validates the consistency of the list.
+    ///
+    /// This is safe to call at any time, even while listeners are registered. On `std` platforms
+    /// listeners are stored in an intrusive linked list, so there is nothing to reclaim and only
+    /// the validation is performed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener = event.listen();
+    /// drop(listener);
+    ///
+    /// let report = event.maintain();
+    /// assert!(report.validation.is_ok());
+    /// ```
+    pub fn maintain(&self) -> MaintenanceReport {
+        unsafe { &*self.inner() }.maintain()
+    }
+
+    /// Notifies a number of active and still unnotified listeners, deferring the notifications
+    /// that can't be delivered.
+    ///
+    /// Like [`Event::notify_additional()`], this notifies `n` *additional* listeners. If there
+    /// are fewer than `n` unnotified listeners, the remaining notifications are kept in the event
+    /// instead of being lost, and the next listeners to be registered start out notified.
+    ///
+    /// This method emits a `SeqCst` fence before notifying listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    ///
+    /// // Nobody is waiting, so the notifications are deferred.
+    /// event.coalesce_additional(2);
+    ///
+    /// // The next two listeners start out notified.
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    /// listener1.as_mut().wait();
+    /// listener2.as_mut().wait();
+    /// ```
+    pub fn coalesce_additional(&self, n: usize) {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        // Zero notifications are a no-op, so they don't need the lock.
+        if n > 0 {
+            unsafe { &*self.inner() }.coalesce_additional(n);
+        }
+    }
+
+    /// Returns `true` if an operation found the queue of deferred operations full since the last
+    /// call.
+    ///
+    /// See [`Event::with_queue_capacity()`]. This can be used to detect sustained contention and
+    /// apply backpressure. With the `std` feature, there is no queue and this always returns
+    /// `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::with_queue_capacity(64);
+    /// let listener = event.listen();
+    ///
+    /// assert!(!event.queue_saturated());
+    /// ```
+    pub fn queue_saturated(&self) -> bool {
+        match self.try_inner() {
+            Some(inner) => inner.take_saturated(),
+            None => false,
+        }
+    }
+
+    /// Returns statistics about the listeners and the queue of deferred operations.
+    ///
+    /// The counts are read from atomics, and the statistics of the slab are only collected if
+    /// its lock is free, so this never blocks. See [`EventStats`] for what is available on which
+    /// platform.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener = event.listen();
+    /// event.notify(1);
+    ///
+    /// let stats = event.debug_stats();
+    /// assert_eq!(stats.len, 1);
+    /// assert_eq!(stats.notified, 1);
+    /// assert_eq!(stats.queue_len, 0);
+    /// ```
+    pub fn debug_stats(&self) -> EventStats {
+        let mut stats = EventStats::default();
+
+        if let Some(inner) = self.try_inner() {
+            stats.len = inner.len.load(Ordering::Relaxed);
+            stats.notified = match inner.notified.load(Ordering::Acquire) {
+                core::usize::MAX => stats.len,
+                notified => notified,
+            };
+            inner.fill_stats(&mut stats);
+        }
+
+        stats
+    }
+
+    /// Returns how often the slab of listeners reused an empty slot instead of growing.
+    ///
+    /// Removing a listener frees its slot for the next one, so a workload that keeps listening
+    /// and removing listeners should mostly reuse slots. This waits for the lock of the list. On
+    /// `std` platforms, which have no slab, every count is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// for _ in 0..10 {
+    ///     drop(event.listen());
+    /// }
+    ///
+    /// let stats = event.slab_stats();
+    /// assert_eq!(stats.inserts, stats.reuses + stats.pushes);
+    /// ```
+    #[cfg(feature = "debug-stats")]
+    pub fn slab_stats(&self) -> SlabStats {
+        match self.try_inner() {
+            Some(inner) => inner.slab_stats(),
+            None => SlabStats::default(),
+        }
+    }
+
+    /// Returns a snapshot of the operation counters of this event.
+    ///
+    /// The counters are read with relaxed loads and without taking the lock, so this is cheap
+    /// enough to call from a metrics exporter. The totals are updated whenever the list of
+    /// listeners is unlocked, so operations still in progress on another thread, or still
+    /// waiting in the queue on `no_std` platforms, may be missing. The counters are read one by
+    /// one and may not be consistent with each other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    /// drop(listener1);
+    ///
+    /// let metrics = event.metrics();
+    /// assert_eq!(metrics.listens, 2);
+    /// assert_eq!(metrics.notifies, 2);
+    /// assert_eq!(metrics.removes, 1);
+    /// assert_eq!(metrics.len, 1);
+    /// assert_eq!(metrics.notified, 1);
+    /// ```
+    pub fn metrics(&self) -> EventMetrics {
+        let mut metrics = EventMetrics::default();
+
+        if let Some(inner) = self.try_inner() {
+            metrics.notifies = inner.metrics.notifies.load(Ordering::Relaxed);
+            metrics.listens = inner.metrics.listens.load(Ordering::Relaxed);
+            metrics.removes = inner.metrics.removes.load(Ordering::Relaxed);
+            metrics.fallbacks = inner.metrics.fallbacks.load(Ordering::Relaxed);
+            metrics.len = inner.len.load(Ordering::Relaxed);
+            metrics.notified = inner.notified_count.load(Ordering::Relaxed);
+        }
+
+        metrics
+    }
+
+    /// Releases the memory held for listeners that are no longer registered.
+    ///
+    /// Returns the number of bytes that were released. A slab that becomes empty after holding
+    /// many listeners is shrunk automatically, so this is only needed to reclaim memory while
+    /// some listeners are still registered.
+    ///
+    /// On `std` platforms listeners are stored in an intrusive linked list, so there is nothing
+    /// to release and this always returns zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listeners = (0..100).map(|_| event.listen()).collect::<Vec<_>>();
+    /// drop(listeners);
+    ///
+    /// event.shrink_to_fit();
+    /// ```
+    pub fn shrink_to_fit(&self) -> usize {
+        match self.try_inner() {
+            Some(inner) => inner.shrink_to_fit(),
+            None => 0,
+        }
+    }
+
+    /// Moves registered listeners into the slots freed by removed ones.
+    ///
+    /// Returns the number of listeners that were moved. After many listeners come and go, the
+    /// remaining ones can end up scattered over the slab, so notifying them touches more memory.
+    /// Compaction fills the lowest free slots with listeners in the order they are notified.
+    ///
+    /// A moved listener keeps its old slot reserved until it is next polled, waited on or
+    /// dropped, at which point it picks up its new slot and the old one is freed. Call
+    /// [`Event::shrink_to_fit()`] after that to release the memory.
+    ///
+    /// On `std` platforms listeners are stored in an intrusive linked list, so there is nothing
+    /// to move and this always returns zero. On `no_std`, this method waits for the lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listeners = (0..100).map(|_| event.listen()).collect::<Vec<_>>();
+    /// listeners.truncate(1);
+    /// let mut listener = event.listen();
+    ///
+    /// event.compact();
+    /// event.notify(2);
+    /// listeners[0].as_mut().wait();
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn compact(&self) -> usize {
+        match self.try_inner() {
+            Some(inner) => inner.compact(),
+            None => 0,
+        }
+    }
+
+    /// Detaches the notified listeners that no task is waiting on and returns how many there were.
+    ///
+    /// A listener that was notified but never polled afterwards, for example one that was leaked,
+    /// keeps counting towards [`Event::notify()`]. Pruning removes such listeners from the event
+    /// without propagating their notifications: they still complete when they are next polled or
+    /// waited on, but no longer count as notified listeners. As with [`Event::drain_all()`], they
+    /// are left with a plain notification, without a trace id or tag.
+    ///
+    /// On `no_std`, the pruning is deferred if the list of listeners is contended, in which case
+    /// this returns zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    /// assert_eq!(event.prune_notified(), 1);
+    ///
+    /// // `listener1` no longer counts towards `notify()`.
+    /// event.notify(1);
+    /// assert!(listener1.as_mut().discard());
+    /// assert!(listener2.as_mut().discard());
+    /// ```
+    pub fn prune_notified(&self) -> usize {
+        match self.try_inner() {
+            Some(inner) => inner.prune_notified(),
+            None => 0,
+        }
+    }
+
+    /// Issues a permit, handing it directly to the longest-waiting listener if there is one.
+    ///
+    /// If there is an unnotified listener, it is notified with the permit in the same way as an
+    /// additional notification and `true` is returned. Otherwise, the permit is stored in the
+    /// event and the next listener to be registered starts out notified; `false` is returned.
+    ///
+    /// Unlike notifications, permits are never lost: if a listener holding a permit is dropped
+    /// without receiving it, the permit is handed to the next waiting listener or returned to
+    /// the event.
+    ///
+    /// On `no_std` platforms the permit may be deferred if the event is under contention, in
+    /// which case `false` is returned.
+    ///
+    /// This method emits a `SeqCst` fence before issuing the permit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    ///
+    /// // Nobody is waiting, so the permit is stored.
+    /// assert!(!event.notify_permit());
+    ///
+    /// // The next listener takes the stored permit.
+    /// let listener1 = event.listen();
+    ///
+    /// // This permit is handed directly to a waiting listener.
+    /// let listener2 = event.listen();
+    /// assert!(event.notify_permit());
+    /// ```
+    pub fn notify_permit(&self) -> bool {
+        // Make sure the permit comes after whatever triggered it.
+        full_fence();
+
+        unsafe { &*self.inner() }.notify_permit()
+    }
+
+    /// Notifies one waiting listener, or marks the event so that the next listener to be
+    /// registered starts out notified.
+    ///
+    /// If there is an unnotified listener, it is given an additional notification and `true` is
+    /// returned. Otherwise, the event is marked and `false` is returned. Under a single lock
+    /// acquisition, this is what the unlock path of a lock wants: wake up a waiter, or make sure
+    /// that the next one doesn't go to sleep.
+    ///
+    /// Unlike [`Event::coalesce_additional()`], marks don't add up: marking an event that is
+    /// already marked does nothing, and the mark is cleared by the first listener that takes it.
+    /// That listener holds an ordinary additional notification, so if it is dropped without
+    /// receiving it, the notification is passed on to the next unnotified listener, but the mark
+    /// isn't restored.
+    ///
+    /// On `no_std` platforms the operation may be deferred if the event is under contention, in
+    /// which case `false` is returned.
+    ///
+    /// This method emits a `SeqCst` fence before notifying the listener.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    ///
+    /// // Nobody is waiting, so the event is marked, once.
+    /// assert!(!event.notify_or_mark());
+    /// assert!(!event.notify_or_mark());
+    ///
+    /// // The next listener takes the mark, the one after it doesn't.
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    /// assert!(listener1.as_mut().discard());
+    ///
+    /// // This one wakes up the waiting listener.
+    /// assert!(event.notify_or_mark());
+    /// listener2.as_mut().wait();
+    /// ```
+    pub fn notify_or_mark(&self) -> bool {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        unsafe { &*self.inner() }.notify_or_mark()
+    }
+
+    /// Return a reference to the inner state if it has been initialized.
+    #[inline]
+    fn try_inner(&self) -> Option<&Inner<T>> {
+        let inner = self.inner.load(Ordering::Acquire);
+        unsafe { inner.as_ref() }
+    }
+
+    /// Returns a raw, initialized pointer to the inner state.
+    ///
+    /// This returns a raw pointer instead of reference because `from_raw`
+    /// requires raw/mut provenance: <https://github.com/rust-lang/rust/pull/67339>.
+    fn inner(&self) -> *const Inner<T> {
+        let mut inner = self.inner.load(Ordering::Acquire);
+
+        // If this is the first use, initialize the state.
+        if inner.is_null() {
+            // Allocate the state on the heap.
+            #[cfg(feature = "std")]
+            let new = Arc::new(Inner::<T>::new(DEFAULT_SPIN_BUDGET, core::usize::MAX, 0));
+            #[cfg(not(feature = "std"))]
+            let new = Arc::new(Inner::<T>::new(self.spin_budget, self.queue_capacity, 0));
+
+            // Convert the state to a raw pointer.
+            let new = Arc::into_raw(new) as *mut Inner<T>;
+
+            // Replace the null pointer with the new state pointer.
+            inner = self
+                .inner
+                .compare_exchange(inner, new, Ordering::AcqRel, Ordering::Acquire)
+                .unwrap_or_else(|x| x);
+
+            // Check if the old pointer value was indeed null.
+            if inner.is_null() {
+                // If yes, then use the new state pointer.
+                inner = new;
+            } else {
+                // If not, that means a concurrent operation has initialized the state.
+                // In that case, use the old pointer and deallocate the new one.
+                unsafe {
+                    drop(Arc::from_raw(new));
+                }
+            }
+        }
+
+        inner
+    }
+
+    /// Creates a [`WeakEvent`] that refers to this event without keeping it alive.
+    ///
+    /// The weak handle can't notify or listen by itself. Use [`WeakEvent::upgrade()`] to get a
+    /// handle to the event again, as long as any [`Event`] or [`EventListener`] still refers to
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let weak = event.downgrade();
+    ///
+    /// let mut listener = event.listen();
+    /// weak.upgrade().unwrap().notify(1);
+    /// listener.as_mut().wait();
+    ///
+    /// drop(event);
+    /// drop(listener);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn downgrade(&self) -> WeakEvent<T> {
+        let inner = unsafe { ManuallyDrop::new(Arc::from_raw(self.inner())) };
+
+        WeakEvent {
+            inner: Arc::downgrade(&inner),
+            #[cfg(not(feature = "std"))]
+            spin_budget: self.spin_budget,
+            #[cfg(not(feature = "std"))]
+            queue_capacity: self.queue_capacity,
+        }
+    }
+
+    /// Creates a [`Waker`] that notifies this event when woken.
+    ///
+    /// Waking the waker notifies one *additional* listener, as in
+    /// [`Event::notify_additional()`]. This lets the event receive wakeups from APIs that only
+    /// take a [`Waker`]. The waker keeps the event alive, like an [`EventListener`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let waker = event.waker();
+    ///
+    /// let mut listener = event.listen();
+    /// waker.wake();
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn waker(&self) -> Waker
+    where
+        T: Send + 'static,
+    {
+        let inner = unsafe { Arc::clone(&ManuallyDrop::new(Arc::from_raw(self.inner()))) };
+        event_waker(inner)
+    }
+}
+
+impl<T> Drop for Event<T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.inner.with_mut(|&mut inner| {
+            // If the state pointer has been initialized, drop it.
+            if !inner.is_null() {
+                unsafe {
+                    drop(Arc::from_raw(inner));
+                }
+            }
+        })
+    }
+}
+
+/// A weak handle to an [`Event`], created by [`Event::downgrade()`].
+///
+/// A `WeakEvent` doesn't keep the event alive, which makes it possible to break ownership cycles
+/// between events and the values that notify them. The event is freed once every [`Event`] and
+/// [`EventListener`] referring to it is dropped, after which [`WeakEvent::upgrade()`] returns
+/// `None`.
+pub struct WeakEvent<T = ()> {
+    /// The inner state of the event.
+    inner: Weak<Inner<T>>,
+
+    /// The spin budget of the event, passed on to upgraded handles.
+    #[cfg(not(feature = "std"))]
+    spin_budget: u32,
+
+    /// The queue capacity of the event, passed on to upgraded handles.
+    #[cfg(not(feature = "std"))]
+    queue_capacity: usize,
+}
+
+unsafe impl<T: Send> Send for WeakEvent<T> {}
+unsafe impl<T: Send> Sync for WeakEvent<T> {}
+
+impl<T> fmt::Debug for WeakEvent<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WeakEvent { .. }")
+    }
+}
+
+impl<T> Clone for WeakEvent<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            #[cfg(not(feature = "std"))]
+            spin_budget: self.spin_budget,
+            #[cfg(not(feature = "std"))]
+            queue_capacity: self.queue_capacity,
+        }
+    }
+}
+
+impl<T> WeakEvent<T> {
+    /// Returns a handle to the event, or `None` if the event was already freed.
+    ///
+    /// The returned [`Event`] shares its listeners with the original one: notifying through it
+    /// notifies the listeners of the original event and vice versa.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let weak = event.downgrade();
+    /// assert!(weak.upgrade().is_some());
+    ///
+    /// drop(event);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn upgrade(&self) -> Option<Event<T>> {
+        let inner = self.inner.upgrade()?;
+
+        Some(Event {
+            inner: AtomicPtr::new(Arc::into_raw(inner) as *mut Inner<T>),
+            #[cfg(not(feature = "std"))]
+            spin_budget: self.spin_budget,
+            #[cfg(not(feature = "std"))]
+            queue_capacity: self.queue_capacity,
+        })
+    }
+}
+
+/// A guard waiting for a notification from an [`Event`].
+///
+/// There are two ways for a listener to wait for a notification:
+///
+/// 1. In an asynchronous manner using `.await`.
+/// 2. In a blocking manner by calling [`EventListener::wait()`] on it.
+///
+/// If a notified listener is dropped without receiving a notification, dropping will notify
+/// another active listener. Whether one *additional* listener will be notified depends on what
+/// kind of notification was delivered.
+///
+/// # Leaking
+///
+/// Forgetting a listener, for example with [`core::mem::forget()`], is memory-safe. The listener
+/// owns a reference to the shared state of the event, so its entry stays valid and the shared
+/// state is leaked along with it, even after the [`Event`] is dropped. Until then, a forgotten
+/// listener still counts as registered, and a notification it received is never passed on to
+/// another listener.
+pub struct EventListener<T: Clone = ()>(Listener<Arc<Inner<T>>, T>);
+
+impl<T: Clone> fmt::Debug for EventListener<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventListener { .. }")
+    }
+}
+
+impl<T: Clone> EventListener<T> {
+    /// Create a new `EventListener` that will wait for a notification from the given [`Event`].
+    pub fn new(event: &Event<T>) -> Self {
+        let inner = event.inner();
+
+        let listener = Listener {
+            event: unsafe { Arc::clone(&ManuallyDrop::new(Arc::from_raw(inner))) },
+            listener: None,
+            trace_id: TraceId::default(),
+            tag: None,
+            shutdown: false,
+            _pin: PhantomPinned,
+        };
+
+        Self(listener)
+    }
+
+    /// Register this listener into the given [`Event`].
+    ///
+    /// This method can only be called after the listener has been pinned, and must be called before
+    /// the listener is polled.
+    pub fn listen(self: Pin<&mut Self>) {
+        self.listen_with_priority(0);
+    }
+
+    /// Register this listener into the given [`Event`] with a priority.
+    ///
+    /// The listener is notified before any unnotified listener with a lower priority. Listeners
+    /// with the same priority are notified in the order they started listening. Listeners
+    /// registered with [`EventListener::listen()`] have a priority of zero.
+    ///
+    /// This method can only be called after the listener has been pinned, and must be called before
+    /// the listener is polled.
+    pub fn listen_with_priority(self: Pin<&mut Self>, priority: u8) {
+        self.listener().insert(priority);
+
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+    }
+
+    /// Register this listener into the given [`Event`] if the list of listeners can be locked
+    /// right away.
+    ///
+    /// If the list is locked by someone else, this returns `false` without registering the
+    /// listener, leaving it to the caller to try again later, e.g. from a context where
+    /// [`EventListener::listen()`] is acceptable. Like [`Event::try_notify()`], this never spins
+    /// on the lock and never queues the listener, so on `no_std` it doesn't allocate a queue
+    /// node. Growing the list of listeners may still allocate.
+    ///
+    /// Returns `true` if the listener is registered, including if it already was.
+    ///
+    /// This method can only be called after the listener has been pinned, and must be called before
+    /// the listener is polled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Event, EventListener};
+    ///
+    /// let event = Event::new();
+    /// let mut listener = Box::pin(EventListener::new(&event));
+    /// assert!(listener.as_mut().try_listen());
+    ///
+    /// event.notify(1);
+    /// listener.as_mut().wait();
+    /// ```
+    pub fn try_listen(self: Pin<&mut Self>) -> bool {
+        let registered = self.listener().try_insert(0);
+
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+        registered
+    }
+
+    /// Blocks until a notification is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// // Notify `listener`.
+    /// event.notify(1);
+    ///
+    /// // Receive the notification.
+    /// listener.as_mut().wait();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn wait(self: Pin<&mut Self>) {
+        self.listener().wait_internal(None, false);
+    }
+
+    /// Blocks until a notification is received or a timeout is reached.
+    ///
+    /// Returns `true` if a notification was received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// // There are no notification so this times out.
+    /// assert!(!listener.as_mut().wait_timeout(Duration::from_secs(1)));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn wait_timeout(self: Pin<&mut Self>, timeout: Duration) -> bool {
+        self.listener()
+            .wait_internal(Instant::now().checked_add(timeout), false)
+    }
+
+    /// Blocks until a notification is received or a deadline is reached.
+    ///
+    /// Returns `true` if a notification was received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// // There are no notification so this times out.
+    /// assert!(!listener.as_mut().wait_deadline(Instant::now() + Duration::from_secs(1)));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn wait_deadline(self: Pin<&mut Self>, deadline: Instant) -> bool {
+        self.listener().wait_internal(Some(deadline), false)
+    }
+
+    /// Blocks until `check` returns `Some`, waiting for a notification between checks.
+    ///
+    /// This is the condition variable pattern without lost wakeups: a listener is registered
+    /// before `check` is first called, and it stays registered between checks. A notification
+    /// is consumed in place, the same way as with [`EventListener::poll_renew()`], so a
+    /// notification sent while `check` runs makes the next wait return right away. Notifications
+    /// after which `check` still returns `None` are treated as spurious wakeups.
+    ///
+    /// Returns the value returned by `check`, or an error if the event was shut down before
+    /// `check` returned `Some`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use event_listener::{Event, EventListener};
+    ///
+    /// let event = Arc::new(Event::new());
+    /// let value = Arc::new(AtomicUsize::new(0));
+    ///
+    /// thread::spawn({
+    ///     let event = event.clone();
+    ///     let value = value.clone();
+    ///     move || {
+    ///         value.store(42, Ordering::SeqCst);
+    ///         event.notify(1);
+    ///     }
+    /// });
+    ///
+    /// let result = EventListener::wait_until(&event, || match value.load(Ordering::SeqCst) {
+    ///     0 => None,
+    ///     n => Some(n),
+    /// });
+    /// assert_eq!(result, Ok(42));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn wait_until<R>(
+        event: &Event<T>,
+        mut check: impl FnMut() -> Option<R>,
+    ) -> Result<R, Shutdown> {
+        let mut listener = event.listen();
+
+        loop {
+            if let Some(result) = check() {
+                return Ok(result);
+            }
+
+            if listener.is_shutdown() {
+                return Err(Shutdown);
+            }
+
+            listener.as_mut().listener().wait_internal(None, true);
+
+            // Make sure the listener is registered before the condition is checked again.
+            full_fence();
+        }
+    }
+
+    /// Spins waiting for a notification, then blocks if none arrives.
+    ///
+    /// The notification is checked up to `spin_budget` times before the thread is parked as in
+    /// [`EventListener::wait()`]. This avoids the latency of parking and unparking the thread
+    /// when the notification usually arrives shortly after waiting starts, at the cost of
+    /// burning CPU while spinning.
+    ///
+    /// Returns `true` if the notification arrived while spinning, and `false` if the thread had
+    /// to be parked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// event.notify(1);
+    /// assert!(listener.as_mut().wait_spin(100));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn wait_spin(self: Pin<&mut Self>, spin_budget: u32) -> bool {
+        self.listener().wait_spin(spin_budget)
+    }
+
+    /// Drops this listener and discards its notification (if any) without notifying another
+    /// active listener.
+    ///
+    /// Returns `true` if a notification was discarded.
+    ///
+    /// # Examples
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    ///
+    /// assert!(listener1.as_mut().discard());
+    /// assert!(!listener2.as_mut().discard());
+    /// ```
+    pub fn discard(self: Pin<&mut Self>) -> bool {
+        self.listener().discard()
+    }
+
+    /// Drops this listener like [`EventListener::discard()`], and returns the waker of the task
+    /// that was waiting on it, if any.
+    ///
+    /// The waker is neither woken nor dropped while the event is locked, so it can be used to
+    /// pass the notification on in a custom way, e.g. by waking the task elsewhere. On `no_std`,
+    /// this method waits for the lock instead of deferring the removal.
+    ///
+    /// Returns `true` along with the waker if a notification was discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use futures_lite::future;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    /// assert!(future::block_on(future::poll_once(listener.as_mut())).is_none());
+    ///
+    /// let (notified, waker) = listener.as_mut().discard_taking_waker();
+    /// assert!(!notified);
+    /// assert!(waker.is_some());
+    /// ```
+    pub fn discard_taking_waker(self: Pin<&mut Self>) -> (bool, Option<Waker>) {
+        let (notified, task) = self.listener().discard_taking_task();
+        (notified, task.map(Task::into_waker))
+    }
+
+    /// Polls for a notification, consuming it only if this returns [`Poll::Ready`].
+    ///
+    /// This is the same as polling the listener as a future, with the guarantees that make it
+    /// safe to use in a `select`:
+    ///
+    /// - Returning [`Poll::Pending`] never consumes a notification. The task of `cx` is
+    ///   registered instead, replacing the task registered by a previous poll.
+    /// - If the listener is dropped after returning [`Poll::Pending`], a notification it received
+    ///   in the meantime is passed on to another active listener, so it is never lost. Use
+    ///   [`EventListener::take_notification()`] before dropping the listener to keep it instead.
+    /// - Once this returned [`Poll::Ready`], the notification was received and dropping the
+    ///   listener doesn't affect the event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use std::task::{Context, Poll};
+    /// use waker_fn::waker_fn;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    /// let waker = waker_fn(|| ());
+    /// let mut cx = Context::from_waker(&waker);
+    ///
+    /// assert_eq!(listener.as_mut().poll_consume(&mut cx), Poll::Pending);
+    /// event.notify(1);
+    /// assert_eq!(listener.as_mut().poll_consume(&mut cx), Poll::Ready(()));
+    /// ```
+    pub fn poll_consume(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.listener().poll_internal(cx)
+    }
+
+    /// Consumes the notification of this listener if it has one, without registering a task.
+    ///
+    /// Returns `true` if a notification was taken. The listener then completes as if it was
+    /// polled to completion, and dropping it no longer passes the notification on. Otherwise the
+    /// listener stays registered, and the task registered by the last poll, if any, is kept.
+    ///
+    /// This lets the loser of a `select` salvage a notification that arrived right before it was
+    /// cancelled, instead of passing it on when it is dropped. On `no_std`, this method waits for
+    /// the lock rather than queueing the removal, since a queued removal couldn't report whether a
+    /// notification was taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let listener2 = event.listen();
+    /// assert!(!listener1.as_mut().take_notification());
+    ///
+    /// event.notify(1);
+    /// assert!(listener1.as_mut().take_notification());
+    ///
+    /// // The notification isn't passed on to `listener2`.
+    /// drop(listener1);
+    /// assert!(!listener2.is_notified());
+    /// ```
+    pub fn take_notification(self: Pin<&mut Self>) -> bool {
+        self.listener().take_notification()
+    }
+
+    /// Returns the handle of this listener, or `None` if it isn't registered.
+    ///
+    /// The handle identifies this listener in [`Event::notify_handle()`], which notifies it in
+    /// particular. A listener that was notified is still registered until it receives the
+    /// notification. On `no_std`, this method waits for the lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    /// assert!(listener.as_mut().handle().is_some());
+    ///
+    /// event.notify(1);
+    /// listener.as_mut().wait();
+    /// assert!(listener.as_mut().handle().is_none());
+    /// ```
+    pub fn handle(self: Pin<&mut Self>) -> Option<ListenerHandle> {
+        self.listener().handle()
+    }
+
+    /// Returns the id of this listener, or `None` if it isn't registered.
+    ///
+    /// The id is [`Hash`] and [`Eq`], and tells apart the listeners of different events, so it
+    /// can key a map of listeners. It is only meaningful while this listener is registered, see
+    /// [`ListenerId`]. On `no_std`, this method waits for the lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use std::collections::HashMap;
+    ///
+    /// let (event1, event2) = (Event::new(), Event::new());
+    /// let mut listener1 = event1.listen();
+    /// let mut listener2 = event2.listen();
+    ///
+    /// let mut connections = HashMap::new();
+    /// connections.insert(listener1.as_mut().id().unwrap(), "first");
+    /// connections.insert(listener2.as_mut().id().unwrap(), "second");
+    ///
+    /// assert_eq!(connections[&listener2.as_mut().id().unwrap()], "second");
+    /// ```
+    pub fn id(self: Pin<&mut Self>) -> Option<ListenerId> {
+        self.handle()
+            .map(|ListenerHandle { event, handle }| ListenerId { event, handle })
+    }
+
+    /// Polls for a notification, giving up once a deadline is reached.
+    ///
+    /// Returns `Poll::Ready(true)` if a notification was received, and `Poll::Ready(false)` if
+    /// the deadline was reached first. The deadline is only checked when the listener is polled:
+    /// the task is registered to be woken by a notification, but nothing wakes it at the
+    /// deadline. Callers must arm a timer of their own that wakes the task at `deadline`.
+    ///
+    /// Once this returned `Poll::Ready`, the listener is no longer registered and must not be
+    /// polled again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::task::Poll;
+    /// use std::time::Instant;
+    /// use event_listener::Event;
+    /// use futures_lite::future;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// // The deadline has already passed.
+    /// let deadline = Instant::now();
+    /// let poll = future::block_on(future::poll_fn(|cx| {
+    ///     Poll::Ready(listener.as_mut().poll_timeout(cx, deadline))
+    /// }));
+    /// assert_eq!(poll, Poll::Ready(false));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn poll_timeout(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        deadline: Instant,
+    ) -> Poll<bool> {
+        self.listener().poll_deadline(cx, deadline)
+    }
+
+    /// Registers this listener again, so it can wait for another notification.
+    ///
+    /// A notification that was received but not consumed yet is discarded without notifying
+    /// another listener, like [`EventListener::discard()`]. The listener is then placed at the
+    /// back of the line, as if it just started listening, but keeps its place in memory: on
+    /// `no_std` platforms this reuses its slot in the list instead of freeing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    ///
+    /// for _ in 0..3 {
+    ///     event.notify(1);
+    ///     listener.as_mut().wait();
+    ///
+    ///     // Wait for the next notification with the same listener.
+    ///     listener.as_mut().reset();
+    /// }
+    /// ```
+    pub fn reset(self: Pin<&mut Self>) {
+        self.listener().reset();
+
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+    }
+
+    /// Polls for a notification and keeps the listener registered for the next one.
+    ///
+    /// If the listener was notified, the notification is consumed and this returns
+    /// [`Poll::Ready`], as with polling the listener. Unlike polling, the listener is not removed
+    /// but placed at the back of the line with the task of `cx` registered, so the next call
+    /// waits for the next notification. This saves a [`EventListener::reset()`] per
+    /// notification, and keeps the slot of the listener on `no_std` platforms.
+    ///
+    /// A listener that was drained or shut down is registered again, the same way as with
+    /// [`EventListener::reset()`].
+    ///
+    /// On `no_std`, this method waits for the lock instead of deferring the registration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    /// use std::task::{Context, Poll};
+    /// use waker_fn::waker_fn;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    /// let waker = waker_fn(|| ());
+    /// let mut cx = Context::from_waker(&waker);
+    ///
+    /// event.notify(1);
+    /// assert_eq!(listener.as_mut().poll_renew(&mut cx), Poll::Ready(()));
+    ///
+    /// // The listener is waiting for the next notification.
+    /// assert_eq!(listener.as_mut().poll_renew(&mut cx), Poll::Pending);
+    /// event.notify(1);
+    /// assert_eq!(listener.as_mut().poll_renew(&mut cx), Poll::Ready(()));
+    /// ```
+    pub fn poll_renew(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let poll = self.listener().poll_renew(cx);
+
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+        poll
+    }
+
+    /// Polls for a notification, registering the task handle of an executor instead of a
+    /// [`Waker`].
+    ///
+    /// This behaves like polling the listener, except that a waker is only created from `handle`
+    /// if the listener doesn't already hold one for the same task. See [`TaskHandle`].
+    ///
+    /// # Examples
+    ///
+    /// See [`TaskHandle`].
+    pub fn poll_with_handle(self: Pin<&mut Self>, handle: &dyn TaskHandle) -> Poll<()> {
+        self.listener().poll_task(TaskRef::Handle(handle))
+    }
+
+    /// Returns the trace id of the notification received by this listener.
+    ///
+    /// Returns `None` if no notification was received yet, or if it wasn't sent with
+    /// [`Event::notify_traced()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    /// assert_eq!(listener.trace_id(), None);
+    ///
+    /// event.notify_traced(1, false, 7);
+    /// listener.as_mut().wait();
+    /// assert_eq!(listener.trace_id(), Some(7));
+    /// ```
+    #[cfg(feature = "trace-id")]
+    pub fn trace_id(&self) -> Option<u64> {
+        self.0.trace_id.0
+    }
+
+    /// Returns the tag of the notification received by this listener.
+    ///
+    /// Returns `None` if no notification was received yet, or if it wasn't sent with
+    /// [`Event::notify_tagged()`] or [`Event::notify_additional_tagged()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::with_tag();
+    /// let mut listener = event.listen();
+    /// assert_eq!(listener.tag(), None);
+    ///
+    /// event.notify_tagged(1, 42);
+    /// listener.as_mut().wait();
+    /// assert_eq!(listener.tag(), Some(&42));
+    /// ```
+    pub fn tag(&self) -> Option<&T> {
+        self.0.tag.as_ref()
+    }
+
+    /// Returns `true` if this listener listens to the given `Event`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener = event.listen();
+    ///
+    /// assert!(listener.listens_to(&event));
+    /// ```
+    #[inline]
+    pub fn listens_to(&self, event: &Event<T>) -> bool {
+        ptr::eq::<Inner<T>>(&**self.inner(), event.inner.load(Ordering::Acquire))
+    }
+
+    /// Returns `true` if both listeners listen to the same `Event`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    ///
+    /// assert!(listener1.same_event(&listener2));
+    /// ```
+    pub fn same_event(&self, other: &EventListener<T>) -> bool {
+        ptr::eq::<Inner<T>>(&**self.inner(), &**other.inner())
+    }
+
+    /// Returns an opaque identifier of the `Event` this listener listens to.
+    ///
+    /// Two listeners return the same identifier if and only if [`EventListener::same_event()`]
+    /// returns `true` for them, which makes it usable as a key in a map. The identifier is only
+    /// unique as long as the listener is alive: once every listener and handle to an event is
+    /// gone, a new event may reuse it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event1 = Event::new();
+    /// let event2 = Event::new();
+    /// let listener1 = event1.listen();
+    /// let listener2 = event1.listen();
+    /// let listener3 = event2.listen();
+    ///
+    /// assert_eq!(listener1.event_id(), listener2.event_id());
+    /// assert_ne!(listener1.event_id(), listener3.event_id());
+    /// ```
+    #[inline]
+    pub fn event_id(&self) -> usize {
+        &**self.inner() as *const Inner<T> as usize
+    }
+
+    /// Returns `true` if this listener completed because the event was shut down.
+    ///
+    /// See [`Event::shutdown()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    /// event.shutdown();
+    ///
+    /// listener1.as_mut().wait();
+    /// listener2.as_mut().wait();
+    /// assert!(!listener1.is_shutdown());
+    /// assert!(listener2.is_shutdown());
+    /// ```
+    pub fn is_shutdown(&self) -> bool {
+        self.0.shutdown
+    }
+
+    /// Returns `true` if this listener was notified, without consuming the notification.
+    ///
+    /// Unlike polling or waiting on the listener, this leaves the notification in place, so the
+    /// listener can be checked repeatedly and only receive the notification once the caller is
+    /// ready to act on it. Returns `false` once the notification has been received.
+    ///
+    /// On `no_std` platforms, this returns `false` if the list of listeners is contended.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen();
+    /// assert!(!listener.is_notified());
+    ///
+    /// event.notify(1);
+    /// assert!(listener.is_notified());
+    /// assert!(listener.is_notified());
+    ///
+    /// // Receive the notification.
+    /// listener.as_mut().wait();
+    /// assert!(!listener.is_notified());
+    /// ```
+    pub fn is_notified(&self) -> bool {
+        self.0.is_notified()
+    }
+
+    /// Returns `true` if this listener is registered and hasn't been notified yet.
+    fn is_waiting(self: Pin<&mut Self>) -> bool {
+        matches!(
+            self.listener().state(),
+            Some(ListenerState::Created) | Some(ListenerState::Task)
+        )
+    }
+
+    fn listener(self: Pin<&mut Self>) -> Pin<&mut Listener<Arc<Inner<T>>, T>> {
+        unsafe { self.map_unchecked_mut(|this| &mut this.0) }
+    }
+
+    fn inner(&self) -> &Arc<Inner<T>> {
+        &self.0.event
+    }
+}
+
+impl<T: Clone> Future for EventListener<T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.listener().poll_internal(cx)
+    }
+}
+
+/// Awaiting an event waits for the next notification.
+///
+/// The listener is registered by [`Event::listen()`] in `into_future()`, which `.await` calls
+/// before polling. Notifications sent after that point are received even if they arrive before
+/// the first poll, while notifications sent before the `.await` expression is evaluated are
+/// missed. Call [`Event::listen()`] ahead of time to also catch those.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::Event;
+/// use std::future::IntoFuture;
+///
+/// let event = Event::new();
+///
+/// // The listener is registered here, so the notification isn't lost.
+/// let notified = (&event).into_future();
+/// event.notify(1);
+///
+/// futures_lite::future::block_on(async { notified.await });
+/// ```
+#[cfg(feature = "into-future")]
+impl<T: Clone> core::future::IntoFuture for &Event<T> {
+    type Output = ();
+    type IntoFuture = Pin<Box<EventListener<T>>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.listen()
+    }
+}
+
+/// Creates a [`StackListener`] pinned to the stack and registers it into an [`Event`].
+///
+/// Unlike [`Event::listen()`], this doesn't allocate: the listener lives in the current stack
+/// frame and borrows the event instead of holding a reference count. The listener is bound to
+/// the given identifier as a `Pin<&mut StackListener<'_>>`, shadowing the unpinned value so it
+/// can't be moved afterwards, like [`core::pin::pin!`].
+///
+/// The listener borrows the event, so the event can't be dropped or moved while the listener is
+/// alive. The listener can't outlive the scope it was created in, so it can't be returned or
+/// sent to another thread that outlives the current function.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::{listen_pinned, Event};
+///
+/// let event = Event::new();
+/// listen_pinned!(event => listener);
+///
+/// // Notify `listener`.
+/// event.notify(1);
+///
+/// // Receive the notification.
+/// listener.as_mut().wait();
+/// ```
+#[macro_export]
+macro_rules! listen_pinned {
+    ($event:expr => $listener:ident) => {
+        let mut $listener = $crate::StackListener::new(&$event);
+        // SAFETY: The unpinned listener is shadowed, so it can never be moved again.
+        #[allow(unused_mut)]
+        let mut $listener = unsafe { ::core::pin::Pin::new_unchecked(&mut $listener) };
+        $listener.as_mut().listen();
+    };
+}
+
+/// A guard waiting for a notification from an [`Event`], which lives on the stack.
+///
+/// This is usually created and pinned with [`listen_pinned!`]. It behaves like an
+/// [`EventListener`], but borrows the [`Event`] instead of keeping it alive.
+pub struct StackListener<'a, T: Clone = ()>(Listener<&'a Inner<T>, T>);
+
+impl<T: Clone> fmt::Debug for StackListener<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StackListener { .. }")
+    }
+}
+
+impl<'a, T: Clone> StackListener<'a, T> {
+    /// Create a new `StackListener` that will wait for a notification from the given [`Event`].
+    ///
+    /// The listener must be pinned and registered with [`StackListener::listen()`] before it is
+    /// polled.
+    pub fn new(event: &'a Event<T>) -> Self {
+        Self(Listener {
+            event: unsafe { &*event.inner() },
+            listener: None,
+            trace_id: TraceId::default(),
+            tag: None,
+            shutdown: false,
+            _pin: PhantomPinned,
+        })
+    }
+
+    /// Register this listener into the given [`Event`].
+    ///
+    /// This method can only be called after the listener has been pinned, and must be called before
+    /// the listener is polled.
+    pub fn listen(self: Pin<&mut Self>) {
+        self.listener().insert(0);
+
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+    }
+
+    /// Register this listener into the given [`Event`] if the list of listeners can be locked
+    /// right away.
+    ///
+    /// This is the allocation-free counterpart of [`EventListener::try_listen()`]: the listener
+    /// lives on the stack and is never queued. Returns `true` if the listener is registered.
+    pub fn try_listen(self: Pin<&mut Self>) -> bool {
+        let registered = self.listener().try_insert(0);
+
+        // Make sure the listener is registered before whatever happens next.
+        full_fence();
+        registered
+    }
+
+    /// Blocks until a notification is received.
+    #[cfg(feature = "std")]
+    pub fn wait(self: Pin<&mut Self>) {
+        self.listener().wait_internal(None, false);
+    }
+
+    /// Blocks until a notification is received or a timeout is reached.
+    ///
+    /// Returns `true` if a notification was received.
+    #[cfg(feature = "std")]
+    pub fn wait_timeout(self: Pin<&mut Self>, timeout: Duration) -> bool {
+        self.listener()
+            .wait_internal(Instant::now().checked_add(timeout), false)
+    }
+
+    /// Blocks until a notification is received or a deadline is reached.
+    ///
+    /// Returns `true` if a notification was received.
+    #[cfg(feature = "std")]
+    pub fn wait_deadline(self: Pin<&mut Self>, deadline: Instant) -> bool {
+        self.listener().wait_internal(Some(deadline), false)
+    }
+
+    /// Drops this listener and discards its notification (if any) without notifying another
+    /// active listener.
+    ///
+    /// Returns `true` if a notification was discarded.
+    pub fn discard(self: Pin<&mut Self>) -> bool {
+        self.listener().discard()
+    }
+
+    fn listener(self: Pin<&mut Self>) -> Pin<&mut Listener<&'a Inner<T>, T>> {
+        unsafe { self.map_unchecked_mut(|this| &mut this.0) }
+    }
+}
+
+impl<T: Clone> Future for StackListener<'_, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.listener().poll_internal(cx)
+    }
+}
+
+/// A listener that passes its notification on if it is dropped before receiving it.
+///
+/// See [`Event::listen_guarded()`].
+pub struct ListenGuard<T: Clone = ()> {
+    /// The guarded listener, taken out when the guard is released or converted.
+    listener: Option<Pin<Box<EventListener<T>>>>,
+}
+
+impl<T: Clone> fmt::Debug for ListenGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ListenGuard { .. }")
+    }
+}
+
+impl<T: Clone> ListenGuard<T> {
+    /// Returns the guarded listener.
+    pub fn listener(&mut self) -> Pin<&mut EventListener<T>> {
+        self.listener.as_mut().unwrap().as_mut()
+    }
+
+    /// Removes the listener, passing an unconsumed notification on to the next active listener.
+    ///
+    /// Returns `true` if the listener held a notification it hadn't received yet.
+    pub fn release(mut self) -> bool {
+        let mut listener = self.listener.take().unwrap();
+        listener.as_mut().listener().propagate()
+    }
+
+    /// Converts the guard back into a plain listener.
+    pub fn into_listener(mut self) -> Pin<Box<EventListener<T>>> {
+        self.listener.take().unwrap()
+    }
+}
+
+impl<T: Clone> Future for ListenGuard<T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.listener().poll(cx)
+    }
+}
+
+impl<T: Clone> Drop for ListenGuard<T> {
+    fn drop(&mut self) {
+        if let Some(mut listener) = self.listener.take() {
+            listener.as_mut().listener().propagate();
+        }
+    }
+}
+
+/// A listener that resolves to `Err(Shutdown)` if its [`Event`] is shut down before it is
+/// notified.
+///
+/// See [`Event::try_listen()`].
+pub struct TryEventListener<T: Clone = ()> {
+    /// The registered listener.
+    listener: Pin<Box<EventListener<T>>>,
+}
+
+impl<T: Clone> fmt::Debug for TryEventListener<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TryEventListener { .. }")
+    }
+}
+
+impl<T: Clone> TryEventListener<T> {
+    /// Blocks until a notification is received, or until the event is shut down.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.try_listen().unwrap();
+    ///
+    /// event.notify(1);
+    /// assert!(listener.wait().is_ok());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn wait(&mut self) -> Result<(), Shutdown> {
+        self.listener.as_mut().wait();
+        self.result()
+    }
+
+    /// Returns the underlying listener.
+    pub fn listener(&mut self) -> Pin<&mut EventListener<T>> {
+        self.listener.as_mut()
+    }
+
+    /// Converts this back into a plain listener.
+    pub fn into_listener(self) -> Pin<Box<EventListener<T>>> {
+        self.listener
+    }
+
+    /// Returns the outcome of the completed listener.
+    fn result(&self) -> Result<(), Shutdown> {
+        if self.listener.is_shutdown() {
+            Err(Shutdown)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: Clone> Future for TryEventListener<T> {
+    type Output = Result<(), Shutdown>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.listener.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(self.result()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A listener that doesn't keep its [`Event`] alive.
+///
+/// See [`Event::listen_weak()`].
+pub struct WeakEventListener<T: Clone = ()> {
+    /// The registered listener, which is only used while `event` can be upgraded.
+    listener: ManuallyDrop<Listener<WeakRef<T>, T>>,
+
+    /// The inner state of the event.
+    event: Weak<Inner<T>>,
+
+    /// Listens for the inner state being dropped.
+    dropped: Pin<Box<EventListener>>,
+}
+
+impl<T: Clone> fmt::Debug for WeakEventListener<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WeakEventListener { .. }")
+    }
+}
+
+impl<T: Clone> WeakEventListener<T> {
+    /// Blocks until a notification is received or the event is dropped.
+    ///
+    /// The event isn't kept alive while blocking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::Event;
+    ///
+    /// let event = Event::new();
+    /// let mut listener = event.listen_weak();
+    ///
+    /// event.notify(1);
+    /// assert!(listener.as_mut().wait().is_ok());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn wait(mut self: Pin<&mut Self>) -> Result<(), EventGone> {
+        let (parker, unparker) = parking::pair();
+
+        loop {
+            if let Poll::Ready(result) = self.as_mut().poll_task(TaskRef::Unparker(&unparker)) {
+                return result;
+            }
+
+            parker.park();
+        }
+    }
+
+    /// Returns `true` if the event was dropped.
+    pub fn is_gone(&self) -> bool {
+        self.event.upgrade().is_none()
+    }
+
+    fn listener(self: Pin<&mut Self>) -> Pin<&mut Listener<WeakRef<T>, T>> {
+        unsafe { self.map_unchecked_mut(|this| &mut *this.listener) }
+    }
+
+    /// Poll this listener, registering `task` to be woken up by a notification or by the event
+    /// being dropped.
+    fn poll_task(mut self: Pin<&mut Self>, task: TaskRef<'_>) -> Poll<Result<(), EventGone>> {
+        // Keep the event alive while the listener is used.
+        let _event = match self.event.upgrade() {
+            Some(event) => event,
+            None => return Poll::Ready(Err(EventGone)),
+        };
+
+        if self.as_mut().listener().poll_task(task).is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+
+        // The event is alive, so this only registers the task.
+        let this = unsafe { self.get_unchecked_mut() };
+        let _ = this.dropped.as_mut().listener().poll_task(task);
+        Poll::Pending
+    }
+}
+
+impl<T: Clone> Future for WeakEventListener<T> {
+    type Output = Result<(), EventGone>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_task(TaskRef::Waker(cx.waker()))
+    }
+}
+
+impl<T: Clone> Drop for WeakEventListener<T> {
+    fn drop(&mut self) {
+        match self.event.upgrade() {
+            Some(_event) => {
+                // Remove the listener while the event is kept alive.
+                unsafe { ManuallyDrop::drop(&mut self.listener) }
+            }
+
+            None => {
+                // The list was dropped along with the event, so only drop the listener's own
+                // state.
+                unsafe {
+                    ptr::drop_in_place(&mut self.listener.listener);
+                    ptr::drop_in_place(&mut self.listener.tag);
+                }
+            }
+        }
+    }
+}
+
+/// A reference to the inner state of an [`Event`] that doesn't keep it alive.
+///
+/// This must only be dereferenced while the state is kept alive by another reference.
+struct WeakRef<T>(*const Inner<T>);
+
+unsafe impl<T: Send> Send for WeakRef<T> {}
+unsafe impl<T: Send> Sync for WeakRef<T> {}
+
+impl<T> Deref for WeakRef<T> {
+    type Target = Inner<T>;
+
+    fn deref(&self) -> &Inner<T> {
+        unsafe { &*self.0 }
+    }
+}
+
+/// The stream returned by [`Event::count_change_stream()`].
+#[cfg(feature = "futures")]
+struct CountChanges<T> {
+    /// The event whose listeners are counted.
+    event: alloc::sync::Arc<Event<T>>,
+
+    /// Listens for the next change of the count.
+    listener: Option<Pin<Box<EventListener>>>,
+
+    /// The last count that was yielded.
+    last: Option<usize>,
+}
+
+#[cfg(feature = "futures")]
+impl<T: Clone> futures_core::Stream for CountChanges<T> {
+    type Item = usize;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<usize>> {
+        loop {
+            let len = self.event.listener_count();
+            if self.last != Some(len) {
+                self.last = Some(len);
+                return Poll::Ready(Some(len));
+            }
+
+            match self.listener.as_mut() {
+                None => {
+                    // Start listening, then check the count again so no change is missed.
+                    let inner = unsafe { &*self.event.inner() };
+                    self.listener = Some(inner.len_changed.listen());
+                    full_fence();
+                }
+                Some(listener) => {
+                    if listener.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.listener = None;
+                }
+            }
+        }
+    }
+}
+
+/// The stream returned by [`Event::filtered_stream()`].
+#[cfg(feature = "futures")]
+struct FilteredTags<T: Clone, F> {
+    /// The event whose notifications are filtered.
+    event: alloc::sync::Arc<Event<T>>,
+
+    /// Listens for the next notification.
+    listener: Pin<Box<EventListener<T>>>,
+
+    /// Decides which tags are yielded.
+    filter: F,
+}
+
+// None of the fields are pinned.
+#[cfg(feature = "futures")]
+impl<T: Clone, F> Unpin for FilteredTags<T, F> {}
+
+#[cfg(feature = "futures")]
+impl<T: Clone, F: Fn(&T) -> bool> futures_core::Stream for FilteredTags<T, F> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            if self.listener.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            if self.listener.is_shutdown() {
+                return Poll::Ready(None);
+            }
+
+            let tag = self.listener.tag().cloned();
+            match tag {
+                Some(tag) if (self.filter)(&tag) => {
+                    self.listener = self.event.listen();
+                    return Poll::Ready(Some(tag));
+                }
+
+                // Pass rejected notifications on to another listener. This happens before
+                // listening again, so that they don't come straight back to this stream.
+                Some(tag) => {
+                    self.event.notify_additional_tagged(1, tag);
+                }
+                None => {
+                    self.event.notify_additional(1);
+                }
+            }
+
+            self.listener = self.event.listen();
+        }
+    }
+}
+
+/// A stream that yields once per notification of an [`Event`].
+///
+/// See [`Event::stream()`].
+#[cfg(feature = "futures")]
+pub struct EventStream<T: Clone = ()> {
+    /// Listens for the next notification, or `None` once the event was shut down.
+    listener: Option<Pin<Box<EventListener<T>>>>,
+}
+
+#[cfg(feature = "futures")]
+impl<T: Clone> fmt::Debug for EventStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventStream { .. }")
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T: Clone> futures_core::Stream for EventStream<T> {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<()>> {
+        let listener = match self.listener.as_mut() {
+            Some(listener) => listener,
+            None => return Poll::Ready(None),
+        };
+
+        // The listener stays registered, so there's no window in which a notification is missed.
+        if listener.as_mut().poll_renew(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        if listener.is_shutdown() {
+            self.listener = None;
+            return Poll::Ready(None);
+        }
+
+        Poll::Ready(Some(()))
+    }
+}
+
+/// An opaque identifier of a listener registered in an [`Event`].
+///
+/// Handles are never reused while the [`Event`] is alive, even if the slot of the listener is
+/// reused by another listener. A handle also records its event, so the handles of listeners of
+/// different events are never equal, and [`Event::notify_handle()`] rejects a handle of another
+/// event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerHandle {
+    /// The address of the shared state of the event.
+    event: usize,
+
+    /// The identity of the listener within the event.
+    handle: sys::Handle,
+}
+
+impl ListenerHandle {
+    /// Creates the handle of a listener of `inner`.
+    pub(crate) fn new<T>(inner: &Inner<T>, handle: sys::Handle) -> Self {
+        ListenerHandle {
+            event: inner as *const Inner<T> as usize,
+            handle,
+        }
+    }
+
+    /// Returns the identity of the listener within `inner`, or `None` if the handle belongs to a
+    /// listener of another event.
+    pub(crate) fn within<T>(&self, inner: &Inner<T>) -> Option<sys::Handle> {
+        if self.event == inner as *const Inner<T> as usize {
+            Some(self.handle)
+        } else {
+            None
+        }
+    }
+
+    /// Creates the handles of the listeners of `inner`, keeping their states.
+    pub(crate) fn with_states<T>(
+        inner: &Inner<T>,
+        states: Vec<(sys::Handle, ListenerState)>,
+    ) -> Vec<(ListenerHandle, ListenerState)> {
+        states
+            .into_iter()
+            .map(|(handle, state)| (ListenerHandle::new(inner, handle), state))
+            .collect()
+    }
+}
+
+/// An identifier of a registered listener, which tells apart the listeners of different events.
+///
+/// The ids of two registered listeners are only equal if they are the same listener, so they can
+/// key a map that holds listeners of several events. See [`EventListener::id()`]. An id tells
+/// apart the same listeners as the [`ListenerHandle`] of the listener does.
+///
+/// An id is only meaningful for as long as its listener is registered. Once the listener is
+/// removed, its slot and, after the event is dropped, the memory of the event are reused, so a
+/// listener registered later may get an equal id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId {
+    /// The address of the shared state of the event.
+    event: usize,
+
+    /// The identity of the listener within the event.
+    handle: sys::Handle,
+}
+
+/// Notifies the listeners of a fixed set of handles in turn.
+///
+/// Each call to [`BalancedNotifier::notify_next()`] notifies the listener of the next handle in
+/// the set, wrapping around at the end. Handles whose listeners are no longer registered are
+/// dropped from the set when they come up.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::{BalancedNotifier, Event};
+///
+/// let event = Event::new();
+/// let mut listener1 = event.listen();
+/// let mut listener2 = event.listen();
+///
+/// // Take over the listeners without notifying any of them.
+/// let (_, handles) = event.notify_and_harvest(0, true);
+/// let mut notifier = BalancedNotifier::new(handles);
+///
+/// notifier.notify_next(&event);
+/// assert!(listener1.as_mut().discard());
+/// assert!(!listener2.as_mut().discard());
+/// ```
+#[derive(Debug, Clone)]
+pub struct BalancedNotifier {
+    /// The handles of the listeners to notify.
+    handles: Vec<ListenerHandle>,
+
+    /// The index of the next handle to notify.
+    next: usize,
+}
+
+impl BalancedNotifier {
+    /// Creates a notifier that rotates through `handles`, starting with the first one.
+    pub fn new(handles: Vec<ListenerHandle>) -> Self {
+        Self { handles, next: 0 }
+    }
+
+    /// Notifies the listener of the next handle in the set and returns its handle.
+    ///
+    /// The listener receives an *additional* notification, unless it was already notified.
+    /// Handles whose listeners are no longer registered in `event`, or belong to another event,
+    /// are removed from the set and skipped. Returns `None` once no handle is left.
+    ///
+    /// This method emits a `SeqCst` fence before notifying the listener.
+    pub fn notify_next<T: Clone>(&mut self, event: &Event<T>) -> Option<ListenerHandle> {
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        let inner = event.try_inner();
+        while !self.handles.is_empty() {
+            if self.next >= self.handles.len() {
+                self.next = 0;
+            }
+
+            let handle = self.handles[self.next];
+            let notified = inner.and_then(|inner| Some(inner.notify_handle(handle.within(inner)?)));
+            if notified.unwrap_or(false) {
+                self.next += 1;
+                return Some(handle);
+            }
+
+            // The listener is gone, so drop its handle.
+            self.handles.remove(self.next);
+        }
+
+        None
+    }
+
+    /// Returns the handles that are left in the set.
+    pub fn handles(&self) -> &[ListenerHandle] {
+        &self.handles
+    }
+}
+
+/// A strategy for choosing how many listeners [`Event::notify()`] notifies.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::{Event, Notification};
+///
+/// /// Notifies half of the listeners.
+/// struct Half;
+///
+/// impl Notification for Half {
+///     fn next_count(&mut self, _notified: usize, len: usize) -> usize {
+///         len / 2
+///     }
+/// }
+///
+/// let event = Event::new();
+/// let mut listeners = (0..4).map(|_| event.listen()).collect::<Vec<_>>();
+///
+/// event.notify(Half);
+/// let notified = listeners.iter_mut().map(|l| l.as_mut().discard());
+/// assert_eq!(notified.filter(|&n| n).count(), 2);
+/// ```
+pub trait Notification {
+    /// Returns the number of listeners to notify.
+    ///
+    /// `notified` and `len` are the number of notified and registered listeners, as of the last
+    /// time the list was unlocked.
+    fn next_count(&mut self, notified: usize, len: usize) -> usize;
+
+    /// Returns `true` if the count is a number of *additional* listeners to notify.
+    ///
+    /// Otherwise, listeners are only notified until the count of notified listeners reaches the
+    /// number returned by [`Notification::next_count()`]. The default implementation returns
+    /// `false`.
+    fn is_additional(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if [`Notification::next_count()`] looks at the number of registered
+    /// listeners.
+    ///
+    /// If this returns `false`, [`Event::notify()`] skips loading that number and may pass zero
+    /// instead. The default implementation returns `true`.
+    fn uses_len(&self) -> bool {
+        true
+    }
+}
+
+/// A task handle of an executor, which can be registered with a listener instead of a
+/// [`Waker`].
+///
+/// Polling a listener with a [`Waker`] clones the waker unless the listener already holds one
+/// that wakes the same task. With [`EventListener::poll_with_handle()`], the listener compares
+/// [`TaskHandle::task_id()`] with that of the handle it holds instead, and only creates a waker
+/// with [`TaskHandle::to_waker()`] once a different task is registered.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::{Event, TaskHandle};
+/// use std::task::{Poll, Waker};
+/// use waker_fn::waker_fn;
+///
+/// struct Handle(usize);
+///
+/// impl TaskHandle for Handle {
+///     fn task_id(&self) -> usize {
+///         self.0
+///     }
+///
+///     fn to_waker(&self) -> Waker {
+///         waker_fn(|| ())
+///     }
+/// }
+///
+/// let event = Event::new();
+/// let mut listener = event.listen();
+///
+/// assert_eq!(listener.as_mut().poll_with_handle(&Handle(1)), Poll::Pending);
+/// event.notify(1);
+/// assert_eq!(listener.as_mut().poll_with_handle(&Handle(1)), Poll::Ready(()));
+/// ```
+pub trait TaskHandle {
+    /// Returns an identifier of the task.
+    ///
+    /// Handles with the same identifier must wake up the same task, for example because the
+    /// identifier is the address of the task.
+    fn task_id(&self) -> usize;
+
+    /// Creates a waker that wakes up the task, to be stored in the listener.
+    fn to_waker(&self) -> Waker;
+}
+
+/// A value that can be turned into a [`Notification`].
+///
+/// This is implemented for every [`Notification`] and for `usize`, which notifies listeners
+/// until that many of them are notified.
+pub trait IntoNotification {
+    /// The notification this value turns into.
+    type Notify: Notification;
+
+    /// Turns this value into a notification.
+    fn into_notification(self) -> Self::Notify;
+}
+
+impl<N: Notification> IntoNotification for N {
+    type Notify = N;
+
+    fn into_notification(self) -> Self::Notify {
+        self
+    }
+}
+
+impl Notification for usize {
+    fn next_count(&mut self, _notified: usize, _len: usize) -> usize {
+        *self
+    }
+
+    fn uses_len(&self) -> bool {
+        false
+    }
+}
+
+/// Notifies a number of *additional* listeners that were previously unnotified.
+///
+/// `event.notify(Additional(n))` is equivalent to `event.notify_additional(n)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Additional(pub usize);
+
+impl Notification for Additional {
+    fn next_count(&mut self, _notified: usize, _len: usize) -> usize {
+        self.0
+    }
+
+    fn is_additional(&self) -> bool {
+        true
+    }
+
+    fn uses_len(&self) -> bool {
+        false
+    }
+}
+
+/// A set of [`Event`]s that are notified together.
+///
+/// [`EventGroup::notify_all()`] emits a single `SeqCst` fence and then notifies every event in
+/// the group. Everything that happened before the call, in particular the store that triggered
+/// the notifications, is visible to every listener that receives one of them, just as if each
+/// event had been notified with [`Event::notify()`]. Only the fence is shared: each event is
+/// still locked and notified on its own, one after the other, so a listener of one event may
+/// wake up before the later events of the group are notified.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::{Event, EventGroup};
+///
+/// let readable = Event::new();
+/// let closed = Event::new();
+///
+/// let mut listener1 = readable.listen();
+/// let mut listener2 = closed.listen();
+///
+/// // Wake up everyone waiting on the state machine.
+/// EventGroup::new(&[&readable, &closed]).notify_all(1);
+///
+/// listener1.as_mut().wait();
+/// listener2.as_mut().wait();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct EventGroup<'a, T = ()> {
+    /// The events in the group.
+    events: &'a [&'a Event<T>],
+}
+
+impl<'a, T: Clone> EventGroup<'a, T> {
+    /// Creates a group of `events`.
+    pub fn new(events: &'a [&'a Event<T>]) -> Self {
+        Self { events }
+    }
+
+    /// Notifies a number of active listeners of every event in the group.
+    ///
+    /// This behaves like calling [`Event::notify()`] on each event in turn, but emits only one
+    /// `SeqCst` fence before the first notification.
+    pub fn notify_all(&self, n: usize) {
+        // Make sure the notifications come after whatever triggered them.
+        full_fence();
+
+        for event in self.events {
+            if let Some(inner) = event.try_inner() {
+                // Notify if there is at least one unnotified listener and the number of notified
+                // listeners is less than `n`.
+                if inner.notified.load(Ordering::Acquire) < n {
+                    inner.notify(n, false);
+                }
+            }
+        }
+    }
+
+    /// Returns the events in the group.
+    pub fn events(&self) -> &'a [&'a Event<T>] {
+        self.events
+    }
+}
+
+/// The state of a listener registered in an [`Event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListenerState {
+    /// The listener has not been polled or waited on yet.
+    Created,
+
+    /// A task is waiting for a notification.
+    Task,
+
+    /// The listener has received a notification.
+    ///
+    /// The `bool` is `true` if this was an "additional" notification.
+    Notified(bool),
+
+    /// The notification of the listener is being received.
+    NotifiedTaken,
+
+    /// The listener has been handed a permit.
+    Permit,
+
+    /// The event was shut down before the listener received a notification.
+    Shutdown,
+}
+
+impl ListenerState {
+    fn is_notified(&self) -> bool {
+        match self {
+            Self::Notified(_) | Self::NotifiedTaken | Self::Permit => true,
+            _ => false,
+        }
+    }
+}
+
+/// The kind of state a registered listener is in.
+///
+/// See [`Event::listener_states()`].
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListenerStateKind {
+    /// The listener has not been polled or waited on yet.
+    Created,
+
+    /// A task is waiting for a notification.
+    Task,
+
+    /// The listener has received a notification, a permit, or the event was shut down.
+    Notified,
+
+    /// The notification of the listener is being received.
+    NotifiedTaken,
+}
+
+#[cfg(feature = "debug")]
+impl From<ListenerState> for ListenerStateKind {
+    fn from(state: ListenerState) -> Self {
+        match state {
+            ListenerState::Created => Self::Created,
+            ListenerState::Task => Self::Task,
+            ListenerState::Notified(_) | ListenerState::Permit | ListenerState::Shutdown => {
+                Self::Notified
+            }
+            ListenerState::NotifiedTaken => Self::NotifiedTaken,
+        }
+    }
+}
+
+/// A difference between two snapshots of listener states.
+///
+/// See [`Event::diff_states()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateChange {
+    /// The listener is only in the second snapshot.
+    Added(ListenerHandle, ListenerState),
+
+    /// The listener is only in the first snapshot.
+    Removed(ListenerHandle, ListenerState),
+
+    /// The state of the listener differs between the snapshots.
+    Changed {
+        /// The handle of the listener.
+        handle: ListenerHandle,
+
+        /// The state in the first snapshot.
+        before: ListenerState,
+
+        /// The state in the second snapshot.
+        after: ListenerState,
+    },
+}
+
+/// How tasks are woken up when their listener is notified.
+///
+/// See [`Event::set_wake_strategy()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WakeStrategy {
+    /// Consume the task with [`Waker::wake()`].
+    Consume,
+
+    /// Wake the task with [`Waker::wake_by_ref()`] and keep it with the listener.
+    ByRef,
+}
+
+impl Default for WakeStrategy {
+    fn default() -> Self {
+        Self::Consume
+    }
+}
+
+/// How a notification was delivered.
+///
+/// See [`Event::notify_with_path_info()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotifyPath {
+    /// The listeners were notified right away, or there was nobody to notify.
+    Fast,
+
+    /// The list was contended, so the notification was queued for the holder of the lock.
+    Queued {
+        /// The number of operations in the queue right after the notification was queued.
+        depth: usize,
+    },
+}
+
+/// The result of [`Event::maintain()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// The number of deferred operations that were applied to the list.
+    pub nodes_applied: usize,
+
+    /// The number of empty slots that were removed from the list.
+    pub slots_compacted: usize,
+
+    /// The number of bytes of memory that were released.
+    pub bytes_reclaimed: usize,
+
+    /// The result of validating the consistency of the list.
+    pub validation: Result<(), SlabError>,
+}
+
+/// The result of [`Event::metrics()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventMetrics {
+    /// The number of times a listener was notified.
+    pub notifies: usize,
+
+    /// The number of times a listener was registered.
+    pub listens: usize,
+
+    /// The number of times a listener was removed from the list.
+    pub removes: usize,
+
+    /// The number of operations that were deferred to the queue because the list was locked.
+    ///
+    /// This is always zero on `std` platforms.
+    pub fallbacks: usize,
+
+    /// The number of registered listeners.
+    pub len: usize,
+
+    /// The number of notified listeners.
+    pub notified: usize,
+}
+
+/// The result of [`Event::slab_stats()`].
+///
+/// Every insertion into the slab either reuses an empty slot or grows the slab by one slot, so
+/// `inserts` is the sum of `reuses` and `pushes`. Slots that listeners are moved into by
+/// [`Event::compact()`] count as reused.
+#[cfg(feature = "debug-stats")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlabStats {
+    /// The number of entries inserted into the slab.
+    pub inserts: usize,
+
+    /// The number of insertions that took an empty slot.
+    pub reuses: usize,
+
+    /// The number of insertions that grew the slab.
+    pub pushes: usize,
+}
+
+#[cfg(feature = "debug-stats")]
+impl SlabStats {
+    /// Counts an insertion, which grew the slab if `pushed` is `true`.
+    #[cfg_attr(feature = "std", allow(dead_code))]
+    fn record(&mut self, pushed: bool) {
+        self.inserts += 1;
+        if pushed {
+            self.pushes += 1;
+        } else {
+            self.reuses += 1;
+        }
+    }
+}
+
+/// Totals of operations on the list, kept while holding its lock.
+#[derive(Default)]
+struct Counters {
+    /// The number of listeners inserted into the list.
+    listens: usize,
+
+    /// The number of listeners removed from the list.
+    removes: usize,
+
+    /// The number of listeners that were notified.
+    notifies: usize,
+}
+
+/// The published totals of operations on the list, see [`Counters`].
+#[derive(Default)]
+struct Metrics {
+    /// Mirrors [`Counters::listens`].
+    listens: AtomicUsize,
+
+    /// Mirrors [`Counters::removes`].
+    removes: AtomicUsize,
+
+    /// Mirrors [`Counters::notifies`].
+    notifies: AtomicUsize,
+
+    /// The number of operations pushed to the queue.
+    fallbacks: AtomicUsize,
+}
+
+/// The result of [`Event::debug_stats()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventStats {
+    /// The number of registered listeners, as of the last time the list was unlocked.
+    pub len: usize,
+
+    /// The number of notified listeners, as of the last time the list was unlocked.
+    pub notified: usize,
+
+    /// The number of slots the slab can hold without reallocating.
+    ///
+    /// This is `None` on `std` platforms, which have no slab, or if the slab was locked.
+    pub slab_capacity: Option<usize>,
+
+    /// The number of empty slots in between the occupied slots of the slab.
+    ///
+    /// This is `None` on `std` platforms, which have no slab, or if the slab was locked.
+    pub empty_slots: Option<usize>,
 
-    fn listener(self: Pin<&mut Self>) -> Pin<&mut Listener<Arc<Inner>>> {
-        unsafe { self.map_unchecked_mut(|this| &mut this.0) }
+    /// The number of operations waiting in the queue for the lock of the slab.
+    ///
+    /// This is always zero on `std` platforms.
+    pub queue_len: usize,
+}
+
+/// The error returned when an [`Event`] was shut down.
+///
+/// See [`Event::shutdown()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shutdown;
+
+impl fmt::Display for Shutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("event was shut down")
     }
+}
 
-    fn inner(&self) -> &Arc<Inner> {
-        &self.0.event
+#[cfg(feature = "std")]
+impl std::error::Error for Shutdown {}
+
+/// The error returned when the [`Event`] of a [`WeakEventListener`] was dropped.
+///
+/// See [`Event::listen_weak()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventGone;
+
+impl fmt::Display for EventGone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("event was dropped")
     }
 }
 
-impl Future for EventListener {
-    type Output = ();
+#[cfg(feature = "std")]
+impl std::error::Error for EventGone {}
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.listener().poll_internal(cx)
+/// An inconsistency found while validating the list of listeners.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlabError {
+    /// The links between two neighboring listeners do not point at each other.
+    BrokenLink,
+
+    /// The last listener reached from the head is not the tail.
+    TailMismatch,
+
+    /// The first unnotified listener is not part of the list.
+    DanglingStart,
+
+    /// The number of listeners in the list does not match the recorded length.
+    LengthMismatch {
+        /// The recorded length.
+        expected: usize,
+
+        /// The number of listeners found in the list.
+        found: usize,
+    },
+
+    /// The number of notified listeners does not match the recorded count.
+    NotifiedMismatch {
+        /// The recorded count.
+        expected: usize,
+
+        /// The number of notified listeners found in the list.
+        found: usize,
+    },
+
+    /// The chain of empty slots is broken or does not cover every empty slot.
+    FreeListCorrupted,
+}
+
+impl fmt::Display for SlabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BrokenLink => f.write_str("listener links are inconsistent"),
+            Self::TailMismatch => f.write_str("tail is not the last listener in the list"),
+            Self::DanglingStart => f.write_str("first unnotified listener is not in the list"),
+            Self::LengthMismatch { expected, found } => write!(
+                f,
+                "expected {} listeners in the list, found {}",
+                expected, found
+            ),
+            Self::NotifiedMismatch { expected, found } => write!(
+                f,
+                "expected {} notified listeners in the list, found {}",
+                expected, found
+            ),
+            Self::FreeListCorrupted => f.write_str("list of empty slots is corrupted"),
+        }
     }
 }
 
-struct Listener<B: Deref<Target = Inner> + Unpin> {
+#[cfg(feature = "std")]
+impl std::error::Error for SlabError {}
+
+struct Listener<B: Deref<Target = Inner<T>> + Unpin, T: Clone> {
     /// The reference to the original event.
     event: B,
 
     /// The inner state of the listener.
+    #[cfg(feature = "std")]
+    listener: Option<sys::Listener<T>>,
+
+    /// The inner state of the listener.
+    #[cfg(not(feature = "std"))]
     listener: Option<sys::Listener>,
 
+    /// The trace id of the received notification.
+    trace_id: TraceId,
+
+    /// The tag of the received notification.
+    tag: Option<T>,
+
+    /// Whether the listener completed because the event was shut down.
+    shutdown: bool,
+
     /// Enforce pinning.
     _pin: PhantomPinned,
 }
 
-unsafe impl<B: Deref<Target = Inner> + Unpin + Send> Send for Listener<B> {}
-unsafe impl<B: Deref<Target = Inner> + Unpin + Sync> Sync for Listener<B> {}
+unsafe impl<B: Deref<Target = Inner<T>> + Unpin + Send, T: Clone + Send> Send for Listener<B, T> {}
+unsafe impl<B: Deref<Target = Inner<T>> + Unpin + Sync, T: Clone + Sync> Sync for Listener<B, T> {}
+
+/// The pin-projected fields of a [`Listener`].
+#[cfg(feature = "std")]
+type Projection<'a, T> = (
+    &'a Inner<T>,
+    Pin<&'a mut Option<sys::Listener<T>>>,
+    &'a mut TraceId,
+    &'a mut Option<T>,
+    &'a mut bool,
+);
+
+/// The pin-projected fields of a [`Listener`].
+#[cfg(not(feature = "std"))]
+type Projection<'a, T> = (
+    &'a Inner<T>,
+    Pin<&'a mut Option<sys::Listener>>,
+    &'a mut TraceId,
+    &'a mut Option<T>,
+    &'a mut bool,
+);
 
-impl<B: Deref<Target = Inner> + Unpin> Listener<B> {
+impl<B: Deref<Target = Inner<T>> + Unpin, T: Clone> Listener<B, T> {
     /// Pin-project this listener.
-    fn project(self: Pin<&mut Self>) -> (&Inner, Pin<&mut Option<sys::Listener>>) {
+    fn project(self: Pin<&mut Self>) -> Projection<'_, T> {
         // SAFETY: `event` is `Unpin`, and `listener`'s pin status is preserved
         unsafe {
             let Listener {
-                event, listener, ..
+                event,
+                listener,
+                trace_id,
+                tag,
+                shutdown,
+                ..
             } = self.get_unchecked_mut();
 
-            (&*event, Pin::new_unchecked(listener))
+            (
+                &*event,
+                Pin::new_unchecked(listener),
+                trace_id,
+                tag,
+                shutdown,
+            )
         }
     }
 
     /// Register this listener with the event.
-    fn insert(self: Pin<&mut Self>) {
-        let (inner, listener) = self.project();
-        inner.insert(listener);
+    fn insert(self: Pin<&mut Self>, priority: u8) {
+        let (inner, listener, ..) = self.project();
+        inner.insert(listener, priority);
+    }
+
+    /// Register this listener with the event, taking `capacity` items.
+    fn insert_with_capacity(self: Pin<&mut Self>, capacity: usize) {
+        let (inner, listener, ..) = self.project();
+        inner.insert_with_capacity(listener, capacity);
+    }
+
+    /// Register this listener with the event if the list isn't locked.
+    fn try_insert(self: Pin<&mut Self>, priority: u8) -> bool {
+        let (inner, listener, ..) = self.project();
+        inner.try_insert(listener, priority)
+    }
+
+    /// Get the handle of this listener, or `None` if it isn't in the list.
+    fn handle(self: Pin<&mut Self>) -> Option<ListenerHandle> {
+        let (inner, listener, ..) = self.project();
+        let handle = inner.handle(listener)?;
+        Some(ListenerHandle::new(inner, handle))
+    }
+
+    /// Register this listener with the event, ahead of every unnotified listener.
+    fn insert_front(self: Pin<&mut Self>) {
+        let (inner, listener, ..) = self.project();
+        inner.insert_front(listener);
+    }
+
+    /// Register this listener with the event and return the number of listeners ahead of it.
+    fn insert_positioned(self: Pin<&mut Self>) -> usize {
+        let (inner, listener, ..) = self.project();
+        inner.insert_positioned(listener)
+    }
+
+    /// Get the state of this listener without consuming its notification.
+    fn state(self: Pin<&mut Self>) -> Option<ListenerState> {
+        let (inner, listener, ..) = self.project();
+        inner.state(listener)
+    }
+
+    /// Check whether this listener was notified without consuming its notification.
+    fn is_notified(&self) -> bool {
+        self.event.is_notified(&self.listener)
+    }
+
+    /// Discard the notification of this listener and register it again.
+    fn reset(self: Pin<&mut Self>) {
+        let (inner, listener, trace_id, tag, shutdown) = self.project();
+        *trace_id = TraceId::default();
+        *tag = None;
+        *shutdown = false;
+        inner.reset(listener);
+    }
+
+    /// Set the group of this listener.
+    fn set_group(self: Pin<&mut Self>, group: T) {
+        let (inner, listener, ..) = self.project();
+        inner.set_group(listener, group);
+    }
+
+    /// Replace the state of this newly inserted listener.
+    fn restore(self: Pin<&mut Self>, state: State<T>) {
+        let (inner, listener, ..) = self.project();
+        inner.restore(listener, state);
     }
 
     /// Wait until the provided deadline.
-    #[cfg(feature = "std")]
-    fn wait_internal(mut self: Pin<&mut Self>, deadline: Option<Instant>) -> bool {
+    #[cfg(all(
+        feature = "std",
+        not(all(feature = "linux-futex", target_os = "linux"))
+    ))]
+    fn wait_internal(mut self: Pin<&mut Self>, deadline: Option<Instant>, renew: bool) -> bool {
         use std::cell::RefCell;
 
         std::thread_local! {
@@ -686,39 +5451,111 @@ impl<B: Deref<Target = Inner> + Unpin> Listener<B> {
                         (parker, Task::Unparker(unparker))
                     });
 
-                    this.wait_with_parker(deadline, parker, unparker.as_task_ref())
+                    this.wait_with(deadline, park_with(parker), unparker.as_task_ref(), renew)
                 }
             })
             .unwrap_or_else(|_| {
                 // If the pair isn't accessible, we may be being called in a destructor.
                 // Just create a new pair.
                 let (parker, unparker) = parking::pair();
-                self.wait_with_parker(deadline, &parker, TaskRef::Unparker(&unparker))
+                self.wait_with(
+                    deadline,
+                    park_with(&parker),
+                    TaskRef::Unparker(&unparker),
+                    renew,
+                )
+            })
+    }
+
+    /// Wait until the provided deadline, sleeping on a futex.
+    #[cfg(all(feature = "linux-futex", target_os = "linux"))]
+    fn wait_internal(mut self: Pin<&mut Self>, deadline: Option<Instant>, renew: bool) -> bool {
+        use std::cell::RefCell;
+
+        std::thread_local! {
+            /// Cached thread-local futex and the waker that wakes it.
+            static FUTEX: RefCell<Option<(Arc<futex::Futex>, Waker)>> = RefCell::new(None);
+        }
+
+        // Try to borrow the thread-local futex.
+        FUTEX
+            .try_with({
+                let this = self.as_mut();
+                |cached| {
+                    let mut pair = cached
+                        .try_borrow_mut()
+                        .expect("Shouldn't be able to borrow futex reentrantly");
+                    let (futex, waker) = pair.get_or_insert_with(|| {
+                        let futex = futex::Futex::new();
+                        let waker = futex::waker(&futex);
+                        (futex, waker)
+                    });
+
+                    this.wait_with(
+                        deadline,
+                        |deadline| futex.wait(deadline),
+                        TaskRef::Waker(waker),
+                        renew,
+                    )
+                }
+            })
+            .unwrap_or_else(|_| {
+                // If the futex isn't accessible, we may be being called in a destructor.
+                // Just create a new one.
+                let futex = futex::Futex::new();
+                let waker = futex::waker(&futex);
+                self.wait_with(
+                    deadline,
+                    |deadline| futex.wait(deadline),
+                    TaskRef::Waker(&waker),
+                    renew,
+                )
             })
     }
 
-    /// Wait until the provided deadline using the specified parker/unparker pair.
+    /// Wait until the provided deadline, blocking with `park` and waking up through `unparker`.
+    ///
+    /// `park` blocks until the thread is woken up or the deadline passes, and may return
+    /// spuriously. If `renew` is set, the notification is consumed without removing the
+    /// listener, as in `poll_renew`.
     #[cfg(feature = "std")]
-    fn wait_with_parker(
+    fn wait_with(
         self: Pin<&mut Self>,
         deadline: Option<Instant>,
-        parker: &Parker,
+        park: impl Fn(Option<Instant>),
         unparker: TaskRef<'_>,
+        renew: bool,
     ) -> bool {
-        let (inner, mut listener) = self.project();
+        let (inner, mut listener, trace_id, tag, shutdown) = self.project();
+
+        let register = |mut listener: Pin<&mut Option<_>>| {
+            if !renew {
+                return inner.register(listener, unparker);
+            }
+
+            inner.register_renew(listener.as_mut(), unparker)
+        };
 
         // Set the listener's state to `Task`.
-        match inner.register(listener.as_mut(), unparker) {
-            Some(true) => {
+        match register(listener.as_mut()) {
+            RegisterResult::Notified(id, received) => {
                 // We were already notified, so we don't need to park.
+                *trace_id = id;
+                *tag = received;
                 return true;
             }
 
-            Some(false) => {
+            RegisterResult::Shutdown => {
+                // The event was shut down, so we don't need to park.
+                *shutdown = true;
+                return true;
+            }
+
+            RegisterResult::Registered => {
                 // We're now waiting for a notification.
             }
 
-            None => {
+            RegisterResult::NeverInserted => {
                 // We were never inserted into the list.
                 panic!("listener was never inserted into the list");
             }
@@ -727,58 +5564,196 @@ impl<B: Deref<Target = Inner> + Unpin> Listener<B> {
         // Wait until a notification is received or the timeout is reached.
         loop {
             match deadline {
-                None => parker.park(),
+                None => park(None),
 
                 Some(deadline) => {
                     // Make sure we're not timed out already.
                     let now = Instant::now();
                     if now >= deadline {
                         // Remove our entry and check if we were notified.
-                        return inner
+                        let state = inner
                             .remove(listener, false)
-                            .expect("We never removed ourself from the list")
-                            .is_notified();
+                            .expect("We never removed ourself from the list");
+                        *shutdown = matches!(state, State::Shutdown);
+                        let notified = state.is_notified() || *shutdown;
+                        *trace_id = state.trace_id();
+                        *tag = state.into_tag();
+                        return notified;
                     }
+
+                    // Park until the deadline.
+                    park(Some(deadline));
                 }
             }
 
             // See if we were notified.
-            if inner
-                .register(listener.as_mut(), unparker)
-                .expect("We never removed ourself from the list")
-            {
-                return true;
+            match register(listener.as_mut()) {
+                RegisterResult::Notified(id, received) => {
+                    *trace_id = id;
+                    *tag = received;
+                    return true;
+                }
+                RegisterResult::Shutdown => {
+                    *shutdown = true;
+                    return true;
+                }
+                RegisterResult::Registered => {}
+                RegisterResult::NeverInserted => {
+                    panic!("We never removed ourself from the list");
+                }
+            }
+        }
+    }
+
+    /// Consumes the notification of this listener if it has one, without registering a task.
+    fn take_notification(self: Pin<&mut Self>) -> bool {
+        let (inner, listener, trace_id, tag, _) = self.project();
+
+        match inner.take_notification(listener) {
+            Some(state) => {
+                *trace_id = state.trace_id();
+                *tag = state.into_tag();
+                true
             }
+            None => false,
         }
     }
 
     /// Drops this listener and discards its notification (if any) without notifying another
     /// active listener.
     fn discard(self: Pin<&mut Self>) -> bool {
-        let (inner, listener) = self.project();
+        let (inner, listener, ..) = self.project();
+
+        inner
+            .remove(listener, false)
+            .map_or(false, |state| state.is_notified())
+    }
+
+    /// Drops this listener like [`Listener::discard`], handing back the task it held.
+    fn discard_taking_task(self: Pin<&mut Self>) -> (bool, Option<Task>) {
+        let (inner, listener, ..) = self.project();
 
         inner
+            .remove_taking_task(listener, false)
+            .map_or((false, None), |(state, task)| (state.is_notified(), task))
+    }
+
+    /// Spin up to `spin_budget` times waiting for a notification, then park the thread.
+    ///
+    /// Returns `true` if the notification arrived while spinning.
+    #[cfg(feature = "std")]
+    fn wait_spin(mut self: Pin<&mut Self>, spin_budget: u32) -> bool {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        for _ in 0..spin_budget {
+            if self.as_mut().poll_internal(&mut cx).is_ready() {
+                return true;
+            }
+
+            #[allow(deprecated)]
+            core::sync::atomic::spin_loop_hint();
+        }
+
+        // Registering the parker replaces the no-op waker, and reports a notification that came
+        // in after the last poll, so it can't be lost.
+        self.wait_internal(None, false);
+        false
+    }
+
+    /// Poll this listener for a notification, giving up once `deadline` is reached.
+    #[cfg(feature = "std")]
+    fn poll_deadline(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        deadline: Instant,
+    ) -> Poll<bool> {
+        if self.as_mut().poll_internal(cx).is_ready() {
+            return Poll::Ready(true);
+        }
+
+        if Instant::now() < deadline {
+            return Poll::Pending;
+        }
+
+        // Remove our entry and check if we were notified in the meantime.
+        let (inner, listener, trace_id, tag, shutdown) = self.project();
+        let state = inner
             .remove(listener, false)
+            .expect("We never removed ourself from the list");
+        *shutdown = matches!(state, State::Shutdown);
+        let notified = state.is_notified() || *shutdown;
+        *trace_id = state.trace_id();
+        *tag = state.into_tag();
+        Poll::Ready(notified)
+    }
+
+    /// Drops this listener and passes its notification (if any) on to another active listener.
+    ///
+    /// Returns `true` if a notification was held.
+    fn propagate(self: Pin<&mut Self>) -> bool {
+        let (inner, listener, ..) = self.project();
+
+        inner
+            .remove(listener, true)
             .map_or(false, |state| state.is_notified())
     }
 
+    /// Poll this listener for a notification, staying registered for the next one.
+    fn poll_renew(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let (inner, mut listener, trace_id, tag, shutdown) = self.project();
+
+        match inner.register_renew(listener.as_mut(), TaskRef::Waker(cx.waker())) {
+            RegisterResult::Notified(id, received) => {
+                *trace_id = id;
+                *tag = received;
+                *shutdown = false;
+                Poll::Ready(())
+            }
+
+            RegisterResult::Shutdown => {
+                *shutdown = true;
+                Poll::Ready(())
+            }
+
+            RegisterResult::Registered => Poll::Pending,
+
+            RegisterResult::NeverInserted => {
+                panic!("listener was never inserted into the list");
+            }
+        }
+    }
+
     /// Poll this listener for a notification.
     fn poll_internal(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
-        let (inner, mut listener) = self.project();
+        self.poll_task(TaskRef::Waker(cx.waker()))
+    }
+
+    /// Poll this listener for a notification, registering `task` if it wasn't notified yet.
+    fn poll_task(self: Pin<&mut Self>, task: TaskRef<'_>) -> Poll<()> {
+        let (inner, mut listener, trace_id, tag, shutdown) = self.project();
 
         // Try to register the listener.
-        match inner.register(listener.as_mut(), TaskRef::Waker(cx.waker())) {
-            Some(true) => {
+        match inner.register(listener.as_mut(), task) {
+            RegisterResult::Notified(id, received) => {
                 // We were already notified, so we don't need to park.
+                *trace_id = id;
+                *tag = received;
+                Poll::Ready(())
+            }
+
+            RegisterResult::Shutdown => {
+                // The event was shut down.
+                *shutdown = true;
                 Poll::Ready(())
             }
 
-            Some(false) => {
+            RegisterResult::Registered => {
                 // We're now waiting for a notification.
                 Poll::Pending
             }
 
-            None => {
+            RegisterResult::NeverInserted => {
                 // We were never inserted into the list.
                 panic!("listener was never inserted into the list");
             }
@@ -786,10 +5761,10 @@ impl<B: Deref<Target = Inner> + Unpin> Listener<B> {
     }
 }
 
-impl<B: Deref<Target = Inner> + Unpin> Drop for Listener<B> {
+impl<B: Deref<Target = Inner<T>> + Unpin, T: Clone> Drop for Listener<B, T> {
     fn drop(&mut self) {
         // If we're being dropped, we need to remove ourself from the list.
-        let (inner, listener) = unsafe { Pin::new_unchecked(self).project() };
+        let (inner, listener, ..) = unsafe { Pin::new_unchecked(self).project() };
 
         inner.remove(listener, true);
     }
@@ -797,34 +5772,163 @@ impl<B: Deref<Target = Inner> + Unpin> Drop for Listener<B> {
 
 /// The state of a listener.
 #[derive(Debug, PartialEq)]
-enum State {
+enum State<T = ()> {
     /// The listener was just created.
     Created,
 
     /// The listener has received a notification.
     ///
-    /// The `bool` is `true` if this was an "additional" notification.
-    Notified(bool),
+    /// The `bool` is `true` if this was an "additional" notification. The notification carries
+    /// a tag if it was sent with one.
+    Notified(bool, TraceId, Option<T>),
 
     /// A task is waiting for a notification.
     Task(Task),
 
+    /// The listener has received a notification, and its task was kept after waking it.
+    ///
+    /// The `bool` is `true` if this was an "additional" notification.
+    NotifiedWithTask(bool, TraceId, Option<T>, Task),
+
     /// Empty hole used to replace a notified listener.
     NotifiedTaken,
+
+    /// The listener has been handed a permit.
+    ///
+    /// This behaves like an additional notification, except that the permit is returned to the
+    /// event if the listener is dropped and there is nobody to hand it to.
+    Permit,
+
+    /// The event was shut down.
+    Shutdown,
 }
 
-impl State {
+impl<T> State<T> {
     fn is_notified(&self) -> bool {
-        matches!(self, Self::Notified(_) | Self::NotifiedTaken)
+        matches!(
+            self,
+            Self::Notified(..) | Self::NotifiedWithTask(..) | Self::NotifiedTaken | Self::Permit
+        )
+    }
+
+    /// Get the trace id of the notification, if any.
+    fn trace_id(&self) -> TraceId {
+        match self {
+            Self::Notified(_, trace_id, _) | Self::NotifiedWithTask(_, trace_id, ..) => *trace_id,
+            _ => TraceId::default(),
+        }
+    }
+
+    /// Take the task out of this state, if it holds one.
+    ///
+    /// A waiting listener goes back to `Created`, and a notified one keeps its notification.
+    fn take_task(&mut self) -> Option<Task> {
+        match mem::replace(self, Self::NotifiedTaken) {
+            Self::Task(task) => {
+                *self = Self::Created;
+                Some(task)
+            }
+            Self::NotifiedWithTask(additional, trace_id, tag, task) => {
+                *self = Self::Notified(additional, trace_id, tag);
+                Some(task)
+            }
+            state => {
+                *self = state;
+                None
+            }
+        }
+    }
+
+    /// Take the tag of the notification, if any.
+    fn into_tag(self) -> Option<T> {
+        match self {
+            Self::Notified(_, _, tag) | Self::NotifiedWithTask(_, _, tag, _) => tag,
+            _ => None,
+        }
+    }
+
+    /// Replace the trace id of a received notification.
+    #[cfg(feature = "trace-id")]
+    fn with_trace_id(self, trace_id: TraceId) -> Self {
+        match self {
+            Self::Notified(additional, _, tag) => Self::Notified(additional, trace_id, tag),
+            Self::NotifiedWithTask(additional, _, tag, task) => {
+                Self::NotifiedWithTask(additional, trace_id, tag, task)
+            }
+            state => state,
+        }
+    }
+
+    /// Create a state from its public view.
+    ///
+    /// `Task` states are given a waker that does nothing.
+    fn from_kind(kind: ListenerState) -> Self {
+        match kind {
+            ListenerState::Created => Self::Created,
+            ListenerState::Task => Self::Task(Task::Waker(noop_waker())),
+            ListenerState::Notified(additional) => {
+                Self::Notified(additional, TraceId::default(), None)
+            }
+            // The listener hasn't picked up a restored notification yet, so it's still pending.
+            ListenerState::NotifiedTaken => Self::Notified(false, TraceId::default(), None),
+            ListenerState::Permit => Self::Permit,
+            ListenerState::Shutdown => Self::Shutdown,
+        }
+    }
+
+    /// Get the public view of this state.
+    fn kind(&self) -> ListenerState {
+        match self {
+            Self::Created => ListenerState::Created,
+            Self::Notified(additional, ..) | Self::NotifiedWithTask(additional, ..) => {
+                ListenerState::Notified(*additional)
+            }
+            Self::Task(_) => ListenerState::Task,
+            Self::NotifiedTaken => ListenerState::NotifiedTaken,
+            Self::Permit => ListenerState::Permit,
+            Self::Shutdown => ListenerState::Shutdown,
+        }
     }
 }
 
+/// Returns the kind of the state in `slot`, without taking the state out.
+fn state_kind<T>(slot: &sync::cell::Cell<State<T>>) -> ListenerState {
+    // SAFETY: `Cell` isn't `Sync`, so only this thread can access the slot, and nothing writes to
+    // it while the reference is alive.
+    unsafe { &*slot.as_ptr() }.kind()
+}
+
+/// The trace id passed along with a notification.
+///
+/// This is zero-sized unless the `trace-id` feature is enabled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct TraceId(#[cfg(feature = "trace-id")] Option<u64>);
+
+/// The result of registering a task with a listener.
+#[derive(Debug, PartialEq)]
+enum RegisterResult<T> {
+    /// The listener was already notified.
+    Notified(TraceId, Option<T>),
+
+    /// The task was registered and is waiting for a notification.
+    Registered,
+
+    /// The event was shut down.
+    Shutdown,
+
+    /// The listener was never inserted into the list.
+    NeverInserted,
+}
+
 /// A task that can be woken up.
 #[derive(Debug, Clone)]
 enum Task {
     /// A waker that wakes up a future.
     Waker(Waker),
 
+    /// A waker created from the task handle of an executor.
+    Handle(OwnedHandle),
+
     /// An unparker that wakes up a thread.
     #[cfg(feature = "std")]
     Unparker(Unparker),
@@ -834,6 +5938,7 @@ impl Task {
     fn as_task_ref(&self) -> TaskRef<'_> {
         match self {
             Self::Waker(waker) => TaskRef::Waker(waker),
+            Self::Handle(handle) => TaskRef::Handle(handle),
             #[cfg(feature = "std")]
             Self::Unparker(unparker) => TaskRef::Unparker(unparker),
         }
@@ -842,12 +5947,115 @@ impl Task {
     fn wake(self) {
         match self {
             Self::Waker(waker) => waker.wake(),
+            Self::Handle(handle) => handle.waker.wake(),
+            #[cfg(feature = "std")]
+            Self::Unparker(unparker) => {
+                unparker.unpark();
+            }
+        }
+    }
+
+    /// Converts this task into a waker that wakes it up.
+    fn into_waker(self) -> Waker {
+        match self {
+            Self::Waker(waker) => waker,
+            Self::Handle(handle) => handle.waker,
+            #[cfg(feature = "std")]
+            Self::Unparker(unparker) => unparker.into(),
+        }
+    }
+
+    fn wake_by_ref(&self) {
+        match self {
+            Self::Waker(waker) => waker.wake_by_ref(),
+            Self::Handle(handle) => handle.waker.wake_by_ref(),
             #[cfg(feature = "std")]
             Self::Unparker(unparker) => {
                 unparker.unpark();
             }
         }
     }
+
+    /// Adds this task to `tasks`, unless one of them already wakes the same task.
+    fn push_deduplicated(self, tasks: &mut Vec<Task>) {
+        if !tasks
+            .iter()
+            .any(|task| task.as_task_ref().will_wake(self.as_task_ref()))
+        {
+            tasks.push(self);
+        }
+    }
+}
+
+/// Blocks the thread with `parker` until it is unparked or the deadline passes.
+#[cfg(all(
+    feature = "std",
+    not(all(feature = "linux-futex", target_os = "linux"))
+))]
+fn park_with(parker: &Parker) -> impl Fn(Option<Instant>) + '_ {
+    move |deadline| match deadline {
+        None => parker.park(),
+        #[cfg(not(loom))]
+        Some(deadline) => {
+            parker.park_deadline(deadline);
+        }
+        #[cfg(loom)]
+        Some(_) => panic!("timeouts are not supported under loom"),
+    }
+}
+
+/// Create a waker that does nothing.
+fn noop_waker() -> Waker {
+    unsafe fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &NOOP_VTABLE)
+    }
+
+    unsafe fn noop(_: *const ()) {}
+
+    static NOOP_VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // SAFETY: The vtable functions do nothing with the data pointer.
+    unsafe { Waker::from_raw(clone(ptr::null())) }
+}
+
+/// Create a waker that notifies an additional listener of `inner`.
+fn event_waker<T: Clone + Send + 'static>(inner: Arc<Inner<T>>) -> Waker {
+    fn raw<T: Clone + Send + 'static>(inner: Arc<Inner<T>>) -> RawWaker {
+        RawWaker::new(
+            Arc::into_raw(inner) as *const (),
+            &RawWakerVTable::new(clone::<T>, wake::<T>, wake_by_ref::<T>, drop::<T>),
+        )
+    }
+
+    unsafe fn clone<T: Clone + Send + 'static>(data: *const ()) -> RawWaker {
+        // Take another reference, leaving the one of the cloned waker in place.
+        let inner = ManuallyDrop::new(Arc::from_raw(data as *const Inner<T>));
+        raw(Arc::clone(&inner))
+    }
+
+    unsafe fn wake<T: Clone + Send + 'static>(data: *const ()) {
+        wake_by_ref::<T>(data);
+        drop::<T>(data);
+    }
+
+    unsafe fn wake_by_ref<T: Clone + Send + 'static>(data: *const ()) {
+        let inner = &*(data as *const Inner<T>);
+
+        // Make sure the notification comes after whatever triggered it.
+        full_fence();
+
+        if inner.notified.load(Ordering::Acquire) < core::usize::MAX {
+            inner.notify(1, true);
+        }
+    }
+
+    unsafe fn drop<T: Clone + Send + 'static>(data: *const ()) {
+        core::mem::drop(Arc::from_raw(data as *const Inner<T>));
+    }
+
+    // SAFETY: Every waker owns one reference to `inner`, which is released when it's dropped or
+    // woken by value.
+    unsafe { Waker::from_raw(raw(inner)) }
 }
 
 impl PartialEq for Task {
@@ -856,12 +6064,35 @@ impl PartialEq for Task {
     }
 }
 
+/// The waker of a [`TaskHandle`], stored along with the identifier of its task.
+#[derive(Debug, Clone)]
+struct OwnedHandle {
+    /// The identifier of the task.
+    task_id: usize,
+
+    /// The waker created from the handle.
+    waker: Waker,
+}
+
+impl TaskHandle for OwnedHandle {
+    fn task_id(&self) -> usize {
+        self.task_id
+    }
+
+    fn to_waker(&self) -> Waker {
+        self.waker.clone()
+    }
+}
+
 /// A reference to a task.
 #[derive(Clone, Copy)]
 enum TaskRef<'a> {
     /// A waker that wakes up a future.
     Waker(&'a Waker),
 
+    /// The task handle of an executor.
+    Handle(&'a dyn TaskHandle),
+
     /// An unparker that wakes up a thread.
     #[cfg(feature = "std")]
     Unparker(&'a Unparker),
@@ -873,6 +6104,7 @@ impl TaskRef<'_> {
     fn will_wake(self, other: Self) -> bool {
         match (self, other) {
             (Self::Waker(a), Self::Waker(b)) => a.will_wake(b),
+            (Self::Handle(a), Self::Handle(b)) => a.task_id() == b.task_id(),
             #[cfg(feature = "std")]
             (Self::Unparker(_), Self::Unparker(_)) => {
                 // TODO: Use unreleased will_unpark API.
@@ -886,6 +6118,10 @@ impl TaskRef<'_> {
     fn into_task(self) -> Task {
         match self {
             Self::Waker(waker) => Task::Waker(waker.clone()),
+            Self::Handle(handle) => Task::Handle(OwnedHandle {
+                task_id: handle.task_id(),
+                waker: handle.to_waker(),
+            }),
             #[cfg(feature = "std")]
             Self::Unparker(unparker) => Task::Unparker(unparker.clone()),
         }
@@ -897,7 +6133,8 @@ impl TaskRef<'_> {
 fn full_fence() {
     if cfg!(all(
         any(target_arch = "x86", target_arch = "x86_64"),
-        not(miri)
+        not(miri),
+        not(loom)
     )) {
         // HACK(stjepang): On x86 architectures there are two different ways of executing
         // a `SeqCst` fence.
@@ -911,10 +6148,10 @@ fn full_fence() {
         // The ideal solution here would be to use inline assembly, but we're instead creating a
         // temporary atomic variable and compare-and-exchanging its value. No sane compiler to
         // x86 platforms is going to optimize this away.
-        sync::atomic::compiler_fence(Ordering::SeqCst);
+        core::sync::atomic::compiler_fence(Ordering::SeqCst);
         let a = AtomicUsize::new(0);
         let _ = a.compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst);
-        sync::atomic::compiler_fence(Ordering::SeqCst);
+        core::sync::atomic::compiler_fence(Ordering::SeqCst);
     } else {
         sync::atomic::fence(Ordering::SeqCst);
     }
@@ -925,18 +6162,28 @@ mod sync {
     pub(super) use core::cell;
 
     #[cfg(not(feature = "portable-atomic"))]
-    pub(super) use alloc::sync::Arc;
-    #[cfg(not(feature = "portable-atomic"))]
+    pub(super) use alloc::sync::{Arc, Weak};
+    #[cfg(all(not(feature = "portable-atomic"), not(loom)))]
     pub(super) use core::sync::atomic;
 
     #[cfg(feature = "portable-atomic")]
     pub(super) use portable_atomic_crate as atomic;
     #[cfg(feature = "portable-atomic")]
-    pub(super) use portable_atomic_util::Arc;
+    pub(super) use portable_atomic_util::{Arc, Weak};
 
-    #[cfg(feature = "std")]
-    pub(super) use std::sync::{Mutex, MutexGuard};
+    // Loom checks the orderings of the atomics and the lock. Reference counting and the cells
+    // behind the lock are left alone.
+    #[cfg(loom)]
+    pub(super) use loom::sync::atomic;
+
+    #[cfg(all(feature = "std", loom))]
+    pub(super) use loom::sync::{Mutex, MutexGuard};
+    #[cfg(all(feature = "std", loom))]
+    pub(super) use std::sync::TryLockError;
+    #[cfg(all(feature = "std", not(loom)))]
+    pub(super) use std::sync::{Mutex, MutexGuard, TryLockError};
 
+    #[cfg(not(loom))]
     pub(super) trait WithMut {
         type Output;
 
@@ -945,6 +6192,7 @@ mod sync {
             F: FnOnce(&mut Self::Output) -> R;
     }
 
+    #[cfg(not(loom))]
     impl<T> WithMut for atomic::AtomicPtr<T> {
         type Output = *mut T;
 
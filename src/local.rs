@@ -0,0 +1,490 @@
+//! A single-threaded variant of [`Event`](crate::Event).
+//!
+//! An [`Event`](crate::Event) can be shared between threads, so its list of listeners sits behind
+//! a lock, and the counts that allow notifying without locking are kept in atomics. On `no_std`,
+//! operations that find the lock contended are queued as well. None of this is needed if the
+//! event never leaves its thread, as on the executor of a thread-per-core runtime.
+//!
+//! [`LocalEvent`] is neither `Send` nor `Sync`, so it keeps its listeners in a plain slab behind a
+//! `RefCell`. Every operation is a single borrow of the slab, with no atomics, no lock and no
+//! queue.
+
+use crate::{IntoNotification, Notification};
+
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use core::cell::RefCell;
+use core::fmt;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+/// A synchronization primitive for notifying async tasks on the same thread.
+///
+/// This behaves like an [`Event`](crate::Event), but can't be shared with or sent to another
+/// thread. In exchange, notifying and listening don't use atomics or a lock, which makes them
+/// considerably faster.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::LocalEvent;
+/// use futures_lite::future;
+///
+/// let event = LocalEvent::new();
+/// let listener = event.listen();
+///
+/// event.notify(1);
+/// future::block_on(listener);
+/// ```
+pub struct LocalEvent {
+    /// The listeners, shared with every [`LocalEventListener`].
+    list: Rc<RefCell<Slab>>,
+}
+
+impl fmt::Debug for LocalEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.list.try_borrow() {
+            Ok(list) => f
+                .debug_struct("LocalEvent")
+                .field("listeners_notified", &list.notified)
+                .field("listeners_total", &list.len)
+                .finish(),
+            Err(_) => f.write_str("LocalEvent { <borrowed> }"),
+        }
+    }
+}
+
+impl Default for LocalEvent {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalEvent {
+    /// Creates a new [`LocalEvent`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::LocalEvent;
+    ///
+    /// let event = LocalEvent::new();
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            list: Rc::new(RefCell::new(Slab::new())),
+        }
+    }
+
+    /// Returns a guard listening for a notification.
+    ///
+    /// Unlike [`Event::listen()`](crate::Event::listen), the listener doesn't need to be pinned,
+    /// since it only refers to its slot in the list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::LocalEvent;
+    ///
+    /// let event = LocalEvent::new();
+    /// let listener = event.listen();
+    /// assert_eq!(event.total_listeners(), 1);
+    /// ```
+    pub fn listen(&self) -> LocalEventListener {
+        let key = self.list.borrow_mut().insert();
+
+        LocalEventListener {
+            list: self.list.clone(),
+            key: Some(key),
+        }
+    }
+
+    /// Notifies a number of active listeners.
+    ///
+    /// This takes the same notifications as [`Event::notify()`](crate::Event::notify). Returns
+    /// the number of listeners that were notified by this call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::{Additional, LocalEvent};
+    ///
+    /// let event = LocalEvent::new();
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    ///
+    /// assert_eq!(event.notify(1), 1);
+    /// assert_eq!(event.notify(1), 0);
+    /// assert_eq!(event.notify(Additional(1)), 1);
+    /// ```
+    pub fn notify(&self, notify: impl IntoNotification) -> usize {
+        let mut notify = notify.into_notification();
+        let mut tasks = Vec::new();
+        let count = {
+            let mut list = self.list.borrow_mut();
+            let n = notify.next_count(list.notified, list.len);
+            list.notify(n, notify.is_additional(), &mut tasks)
+        };
+
+        // Wake the tasks once the list isn't borrowed anymore, in case they notify again.
+        for task in tasks {
+            task.wake();
+        }
+
+        count
+    }
+
+    /// Notifies a number of active and still unnotified listeners.
+    ///
+    /// This is the same as [`LocalEvent::notify()`] with an additional notification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::LocalEvent;
+    ///
+    /// let event = LocalEvent::new();
+    /// let listener1 = event.listen();
+    /// let listener2 = event.listen();
+    ///
+    /// assert_eq!(event.notify_additional(1), 1);
+    /// assert_eq!(event.notify_additional(1), 1);
+    /// ```
+    pub fn notify_additional(&self, n: usize) -> usize {
+        let mut tasks = Vec::new();
+        let count = self.list.borrow_mut().notify(n, true, &mut tasks);
+
+        // Wake the tasks once the list isn't borrowed anymore, in case they notify again.
+        for task in tasks {
+            task.wake();
+        }
+
+        count
+    }
+
+    /// Returns the number of listeners that are currently registered.
+    pub fn total_listeners(&self) -> usize {
+        self.list.borrow().len
+    }
+}
+
+/// A guard waiting for a notification from a [`LocalEvent`].
+///
+/// The listener is a future that completes once it is notified. If it is dropped after being
+/// notified without having received the notification, the notification is passed on to another
+/// listener.
+pub struct LocalEventListener {
+    /// The listeners of the event.
+    list: Rc<RefCell<Slab>>,
+
+    /// The slot of this listener, or `None` once it received its notification.
+    key: Option<usize>,
+}
+
+impl fmt::Debug for LocalEventListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LocalEventListener { .. }")
+    }
+}
+
+impl LocalEventListener {
+    /// Returns `true` if this listener was notified, without consuming the notification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::LocalEvent;
+    ///
+    /// let event = LocalEvent::new();
+    /// let listener = event.listen();
+    ///
+    /// event.notify(1);
+    /// assert!(listener.is_notified());
+    /// ```
+    pub fn is_notified(&self) -> bool {
+        match self.key {
+            Some(key) => self.list.borrow().entry(key).state.is_notified(),
+            None => true,
+        }
+    }
+
+    /// Drops this listener and discards its notification (if any) without notifying another
+    /// active listener.
+    ///
+    /// Returns `true` if a notification was discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::LocalEvent;
+    ///
+    /// let event = LocalEvent::new();
+    /// let mut listener1 = event.listen();
+    /// let mut listener2 = event.listen();
+    ///
+    /// event.notify(1);
+    ///
+    /// assert!(listener1.discard());
+    /// assert!(!listener2.discard());
+    /// ```
+    pub fn discard(&mut self) -> bool {
+        match self.key.take() {
+            Some(key) => self.list.borrow_mut().remove(key).is_notified(),
+            None => false,
+        }
+    }
+}
+
+impl Future for LocalEventListener {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let key = match self.key {
+            Some(key) => key,
+            None => return Poll::Ready(()),
+        };
+
+        let mut list = self.list.borrow_mut();
+        let entry = list.entry_mut(key);
+
+        if entry.state.is_notified() {
+            // Take the notification and leave the list.
+            list.remove(key);
+            drop(list);
+            self.key = None;
+            return Poll::Ready(());
+        }
+
+        match &mut entry.state {
+            State::Task(waker) if waker.will_wake(cx.waker()) => {}
+            state => *state = State::Task(cx.waker().clone()),
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for LocalEventListener {
+    fn drop(&mut self) {
+        let key = match self.key.take() {
+            Some(key) => key,
+            None => return,
+        };
+
+        let mut tasks = Vec::new();
+        {
+            let mut list = self.list.borrow_mut();
+
+            // Pass an unreceived notification on to the next listener.
+            if let State::Notified(additional) = list.remove(key) {
+                list.notify(1, additional, &mut tasks);
+            }
+        }
+
+        for task in tasks {
+            task.wake();
+        }
+    }
+}
+
+/// The state of a listener in a [`LocalEvent`].
+enum State {
+    /// The listener was just created.
+    Created,
+
+    /// The listener was notified.
+    ///
+    /// The `bool` is `true` if this was an "additional" notification.
+    Notified(bool),
+
+    /// A task is waiting for a notification.
+    Task(Waker),
+}
+
+impl State {
+    fn is_notified(&self) -> bool {
+        match self {
+            Self::Notified(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A listener in the slab.
+struct Entry {
+    /// The state of the listener.
+    state: State,
+
+    /// The previous listener in the list.
+    prev: Option<usize>,
+
+    /// The next listener in the list.
+    next: Option<usize>,
+}
+
+/// A slot of the slab.
+enum Slot {
+    /// A slot holding a listener.
+    Occupied(Entry),
+
+    /// An empty slot that contains the index of the next empty slot.
+    Empty(Option<usize>),
+}
+
+/// The list of listeners of a [`LocalEvent`].
+///
+/// Notified listeners are kept in front of the unnotified ones, so notifying means walking the
+/// list from `start`.
+struct Slab {
+    /// The slots of the listeners.
+    slots: Vec<Slot>,
+
+    /// The first empty slot.
+    first_empty: Option<usize>,
+
+    /// First listener in the list.
+    head: Option<usize>,
+
+    /// Last listener in the list.
+    tail: Option<usize>,
+
+    /// The first unnotified listener in the list.
+    start: Option<usize>,
+
+    /// The number of notified listeners.
+    notified: usize,
+
+    /// The total number of listeners.
+    len: usize,
+}
+
+impl Slab {
+    fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            first_empty: None,
+            head: None,
+            tail: None,
+            start: None,
+            notified: 0,
+            len: 0,
+        }
+    }
+
+    fn entry(&self, key: usize) -> &Entry {
+        match &self.slots[key] {
+            Slot::Occupied(entry) => entry,
+            Slot::Empty(_) => unreachable!(),
+        }
+    }
+
+    fn entry_mut(&mut self, key: usize) -> &mut Entry {
+        match &mut self.slots[key] {
+            Slot::Occupied(entry) => entry,
+            Slot::Empty(_) => unreachable!(),
+        }
+    }
+
+    /// Inserts a new listener at the back of the list and returns its key.
+    fn insert(&mut self) -> usize {
+        let entry = Slot::Occupied(Entry {
+            state: State::Created,
+            prev: self.tail,
+            next: None,
+        });
+
+        let key = match self.first_empty {
+            Some(key) => {
+                self.first_empty = match mem::replace(&mut self.slots[key], entry) {
+                    Slot::Empty(next) => next,
+                    Slot::Occupied(_) => unreachable!(),
+                };
+                key
+            }
+            None => {
+                self.slots.push(entry);
+                self.slots.len() - 1
+            }
+        };
+
+        match self.tail {
+            Some(tail) => self.entry_mut(tail).next = Some(key),
+            None => self.head = Some(key),
+        }
+        self.tail = Some(key);
+
+        if self.start.is_none() {
+            self.start = Some(key);
+        }
+        self.len += 1;
+
+        key
+    }
+
+    /// Removes a listener from the list and returns its state.
+    fn remove(&mut self, key: usize) -> State {
+        let entry = match mem::replace(&mut self.slots[key], Slot::Empty(self.first_empty)) {
+            Slot::Occupied(entry) => entry,
+            Slot::Empty(_) => unreachable!(),
+        };
+        self.first_empty = Some(key);
+
+        match entry.prev {
+            Some(prev) => self.entry_mut(prev).next = entry.next,
+            None => self.head = entry.next,
+        }
+        match entry.next {
+            Some(next) => self.entry_mut(next).prev = entry.prev,
+            None => self.tail = entry.prev,
+        }
+        if self.start == Some(key) {
+            self.start = entry.next;
+        }
+
+        if entry.state.is_notified() {
+            self.notified -= 1;
+        }
+        self.len -= 1;
+
+        entry.state
+    }
+
+    /// Notifies a number of listeners, collecting the tasks to wake in `tasks`.
+    ///
+    /// Returns the number of listeners that were notified.
+    fn notify(&mut self, mut n: usize, additional: bool, tasks: &mut Vec<Waker>) -> usize {
+        if !additional {
+            // Make sure we're not notifying more than we have.
+            if n <= self.notified {
+                return 0;
+            }
+            n -= self.notified;
+        }
+
+        let mut count = 0;
+        while n > 0 {
+            let key = match self.start {
+                Some(key) => key,
+                None => break,
+            };
+
+            let entry = self.entry_mut(key);
+            let next = entry.next;
+            if let State::Task(waker) = mem::replace(&mut entry.state, State::Notified(additional))
+            {
+                tasks.push(waker);
+            }
+
+            self.start = next;
+            self.notified += 1;
+            count += 1;
+            n -= 1;
+        }
+
+        count
+    }
+}
@@ -0,0 +1,667 @@
+//! A variant of [`Event`](crate::Event) that never allocates.
+//!
+//! An [`Event`](crate::Event) allocates its list of listeners on first use, grows it as needed,
+//! and on `no_std` allocates a queue node for every operation that finds the list locked. None of
+//! this is possible without an allocator.
+//!
+//! The [`Event`] in this module instead keeps at most `N` listeners in an array that lives inside
+//! the event itself, so it can be placed in a `static`. There is no queue: every operation waits
+//! for the lock, except for [`Event::listen()`] on an event created with [`Contention::Fail`],
+//! which fails instead. Listening fails as well once all `N` slots are taken.
+//!
+//! Listeners borrow the event rather than sharing ownership of it, and don't need to be pinned,
+//! since they only refer to their slot in the array.
+//!
+//! Nothing in this module allocates. This requires the `no-alloc` feature and Rust 1.51 or
+//! later.
+
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::cell::UnsafeCell;
+use crate::{IntoNotification, Notification};
+
+use core::fmt;
+use core::future::Future;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+/// What [`Event::listen()`] does when the list of listeners is locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Contention {
+    /// Spin until the lock is released.
+    Spin,
+
+    /// Fail with [`ListenError::Contended`].
+    Fail,
+}
+
+/// The error returned by [`Event::listen()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListenError {
+    /// All slots of the event are taken by other listeners.
+    Full,
+
+    /// The list of listeners was locked and the event was created with [`Contention::Fail`].
+    Contended,
+}
+
+impl fmt::Display for ListenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => f.write_str("event has no free listener slot"),
+            Self::Contended => f.write_str("event is locked"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ListenError {}
+
+/// A synchronization primitive for notifying async tasks, holding at most `N` listeners.
+///
+/// This behaves like an [`Event`](crate::Event), but doesn't allocate. See the
+/// [module documentation](self) for the differences.
+///
+/// # Examples
+///
+/// ```
+/// use event_listener::no_alloc::Event;
+/// use futures_lite::future;
+///
+/// static EVENT: Event<4> = Event::new();
+///
+/// let listener = EVENT.listen().unwrap();
+///
+/// EVENT.notify(1);
+/// future::block_on(listener);
+/// ```
+pub struct Event<const N: usize> {
+    /// Whether the list is locked.
+    locked: AtomicBool,
+
+    /// What to do when listening finds the list locked.
+    contention: Contention,
+
+    /// The listeners, protected by `locked`.
+    list: UnsafeCell<Slab<N>>,
+}
+
+unsafe impl<const N: usize> Send for Event<N> {}
+unsafe impl<const N: usize> Sync for Event<N> {}
+
+impl<const N: usize> fmt::Debug for Event<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(list) => f
+                .debug_struct("Event")
+                .field("listeners_notified", &list.notified)
+                .field("listeners_total", &list.len)
+                .field("capacity", &N)
+                .finish(),
+            None => f.write_str("Event { <locked> }"),
+        }
+    }
+}
+
+impl<const N: usize> Default for Event<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Event<N> {
+    /// Creates a new [`Event`] that spins on a contended lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::no_alloc::Event;
+    ///
+    /// let event = Event::<4>::new();
+    /// ```
+    pub const fn new() -> Self {
+        Self::with_contention(Contention::Spin)
+    }
+
+    /// Creates a new [`Event`] that handles a contended lock in [`Event::listen()`] as
+    /// `contention` says.
+    ///
+    /// Notifying and dropping listeners always spin on a contended lock, since neither can fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::no_alloc::{Contention, Event};
+    ///
+    /// static EVENT: Event<4> = Event::with_contention(Contention::Fail);
+    /// ```
+    pub const fn with_contention(contention: Contention) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            contention,
+            list: UnsafeCell::new(Slab::new()),
+        }
+    }
+
+    /// Returns a guard listening for a notification.
+    ///
+    /// Fails with [`ListenError::Full`] if `N` listeners are already registered, or with
+    /// [`ListenError::Contended`] if the list is locked and the event was created with
+    /// [`Contention::Fail`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::no_alloc::{Event, ListenError};
+    ///
+    /// let event = Event::<1>::new();
+    /// let listener = event.listen().unwrap();
+    ///
+    /// assert_eq!(event.listen().unwrap_err(), ListenError::Full);
+    /// drop(listener);
+    /// assert!(event.listen().is_ok());
+    /// ```
+    pub fn listen(&self) -> Result<EventListener<'_, N>, ListenError> {
+        let mut list = match self.contention {
+            Contention::Spin => self.lock(),
+            Contention::Fail => self.try_lock().ok_or(ListenError::Contended)?,
+        };
+        let key = list.insert().ok_or(ListenError::Full)?;
+
+        Ok(EventListener {
+            event: self,
+            key: Some(key),
+        })
+    }
+
+    /// Notifies a number of active listeners.
+    ///
+    /// This takes the same notifications as [`Event::notify()`](crate::Event::notify) and spins
+    /// on a contended lock. Returns the number of listeners that were notified by this call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::no_alloc::Event;
+    /// use event_listener::Additional;
+    ///
+    /// let event = Event::<2>::new();
+    /// let listener1 = event.listen().unwrap();
+    /// let listener2 = event.listen().unwrap();
+    ///
+    /// assert_eq!(event.notify(1), 1);
+    /// assert_eq!(event.notify(1), 0);
+    /// assert_eq!(event.notify(Additional(1)), 1);
+    /// ```
+    pub fn notify(&self, notify: impl IntoNotification) -> usize {
+        let mut notify = notify.into_notification();
+        let mut tasks = Tasks::<N>::new();
+        let count = {
+            let mut list = self.lock();
+            let n = notify.next_count(list.notified, list.len);
+            list.notify(n, notify.is_additional(), &mut tasks)
+        };
+
+        // Wake the tasks once the lock is released, in case they notify again.
+        tasks.wake();
+
+        count
+    }
+
+    /// Notifies a number of active and still unnotified listeners.
+    ///
+    /// This is the same as [`Event::notify()`] with an additional notification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::no_alloc::Event;
+    ///
+    /// let event = Event::<2>::new();
+    /// let listener1 = event.listen().unwrap();
+    /// let listener2 = event.listen().unwrap();
+    ///
+    /// assert_eq!(event.notify_additional(1), 1);
+    /// assert_eq!(event.notify_additional(1), 1);
+    /// ```
+    pub fn notify_additional(&self, n: usize) -> usize {
+        let mut tasks = Tasks::<N>::new();
+        let count = self.lock().notify(n, true, &mut tasks);
+
+        // Wake the tasks once the lock is released, in case they notify again.
+        tasks.wake();
+
+        count
+    }
+
+    /// Returns the number of listeners that are currently registered.
+    pub fn total_listeners(&self) -> usize {
+        self.lock().len
+    }
+
+    /// Locks the list if it isn't locked already.
+    fn try_lock(&self) -> Option<Guard<'_, N>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| Guard { event: self })
+    }
+
+    /// Locks the list, spinning until it is released.
+    fn lock(&self) -> Guard<'_, N> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+
+            while self.locked.load(Ordering::Relaxed) {
+                #[allow(deprecated)]
+                core::sync::atomic::spin_loop_hint();
+            }
+        }
+    }
+}
+
+/// Access to the list of an [`Event`], releasing the lock once dropped.
+struct Guard<'a, const N: usize> {
+    event: &'a Event<N>,
+}
+
+impl<const N: usize> Deref for Guard<'_, N> {
+    type Target = Slab<N>;
+
+    fn deref(&self) -> &Slab<N> {
+        // SAFETY: The lock is held.
+        unsafe { &*self.event.list.get() }
+    }
+}
+
+impl<const N: usize> DerefMut for Guard<'_, N> {
+    fn deref_mut(&mut self) -> &mut Slab<N> {
+        // SAFETY: The lock is held.
+        unsafe { &mut *self.event.list.get() }
+    }
+}
+
+impl<const N: usize> Drop for Guard<'_, N> {
+    fn drop(&mut self) {
+        self.event.locked.store(false, Ordering::Release);
+    }
+}
+
+/// A guard waiting for a notification from an [`Event`].
+///
+/// The listener is a future that completes once it is notified. If it is dropped after being
+/// notified without having received the notification, the notification is passed on to another
+/// listener.
+pub struct EventListener<'a, const N: usize> {
+    /// The event this listener is registered in.
+    event: &'a Event<N>,
+
+    /// The slot of this listener, or `None` once it received its notification.
+    key: Option<usize>,
+}
+
+impl<const N: usize> fmt::Debug for EventListener<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventListener { .. }")
+    }
+}
+
+impl<const N: usize> EventListener<'_, N> {
+    /// Returns `true` if this listener was notified, without consuming the notification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::no_alloc::Event;
+    ///
+    /// let event = Event::<1>::new();
+    /// let listener = event.listen().unwrap();
+    ///
+    /// event.notify(1);
+    /// assert!(listener.is_notified());
+    /// ```
+    pub fn is_notified(&self) -> bool {
+        match self.key {
+            Some(key) => self.event.lock().entry(key).state.is_notified(),
+            None => true,
+        }
+    }
+
+    /// Drops this listener and discards its notification (if any) without notifying another
+    /// active listener.
+    ///
+    /// Returns `true` if a notification was discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use event_listener::no_alloc::Event;
+    ///
+    /// let event = Event::<2>::new();
+    /// let mut listener1 = event.listen().unwrap();
+    /// let mut listener2 = event.listen().unwrap();
+    ///
+    /// event.notify(1);
+    ///
+    /// assert!(listener1.discard());
+    /// assert!(!listener2.discard());
+    /// ```
+    pub fn discard(&mut self) -> bool {
+        match self.key.take() {
+            Some(key) => self.event.lock().remove(key).is_notified(),
+            None => false,
+        }
+    }
+}
+
+impl<const N: usize> Future for EventListener<'_, N> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let key = match self.key {
+            Some(key) => key,
+            None => return Poll::Ready(()),
+        };
+
+        let mut list = self.event.lock();
+        let entry = list.entry_mut(key);
+
+        if entry.state.is_notified() {
+            // Take the notification and leave the list.
+            list.remove(key);
+            drop(list);
+            self.key = None;
+            return Poll::Ready(());
+        }
+
+        match &mut entry.state {
+            State::Task(waker) if waker.will_wake(cx.waker()) => {}
+            state => *state = State::Task(cx.waker().clone()),
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<const N: usize> Drop for EventListener<'_, N> {
+    fn drop(&mut self) {
+        let key = match self.key.take() {
+            Some(key) => key,
+            None => return,
+        };
+
+        let mut tasks = Tasks::<N>::new();
+        {
+            let mut list = self.event.lock();
+
+            // Pass an unreceived notification on to the next listener.
+            if let State::Notified(additional) = list.remove(key) {
+                list.notify(1, additional, &mut tasks);
+            }
+        }
+
+        tasks.wake();
+    }
+}
+
+/// The state of a listener in an [`Event`].
+enum State {
+    /// The listener was just created.
+    Created,
+
+    /// The listener was notified.
+    ///
+    /// The `bool` is `true` if this was an "additional" notification.
+    Notified(bool),
+
+    /// A task is waiting for a notification.
+    Task(Waker),
+}
+
+impl State {
+    fn is_notified(&self) -> bool {
+        match self {
+            Self::Notified(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A listener in the slab.
+struct Entry {
+    /// The state of the listener.
+    state: State,
+
+    /// The previous listener in the list.
+    prev: Option<usize>,
+
+    /// The next listener in the list.
+    next: Option<usize>,
+}
+
+/// A slot of the slab.
+enum Slot {
+    /// A slot holding a listener.
+    Occupied(Entry),
+
+    /// An empty slot that contains the index of the next empty slot.
+    Empty(Option<usize>),
+}
+
+/// The list of listeners of an [`Event`].
+///
+/// Like a `Vec`, the slots past `used` have never been occupied. Notified listeners are kept in
+/// front of the unnotified ones, so notifying means walking the list from `start`.
+struct Slab<const N: usize> {
+    /// The slots of the listeners.
+    slots: [Slot; N],
+
+    /// The number of slots that have been occupied at some point.
+    used: usize,
+
+    /// The first empty slot below `used`.
+    first_empty: Option<usize>,
+
+    /// First listener in the list.
+    head: Option<usize>,
+
+    /// Last listener in the list.
+    tail: Option<usize>,
+
+    /// The first unnotified listener in the list.
+    start: Option<usize>,
+
+    /// The number of notified listeners.
+    notified: usize,
+
+    /// The total number of listeners.
+    len: usize,
+}
+
+/// A slot that was never occupied.
+const UNUSED: Slot = Slot::Empty(None);
+
+impl<const N: usize> Slab<N> {
+    const fn new() -> Self {
+        Self {
+            slots: [UNUSED; N],
+            used: 0,
+            first_empty: None,
+            head: None,
+            tail: None,
+            start: None,
+            notified: 0,
+            len: 0,
+        }
+    }
+
+    fn entry(&self, key: usize) -> &Entry {
+        match &self.slots[key] {
+            Slot::Occupied(entry) => entry,
+            Slot::Empty(_) => unreachable!(),
+        }
+    }
+
+    fn entry_mut(&mut self, key: usize) -> &mut Entry {
+        match &mut self.slots[key] {
+            Slot::Occupied(entry) => entry,
+            Slot::Empty(_) => unreachable!(),
+        }
+    }
+
+    /// Inserts a new listener at the back of the list and returns its key.
+    ///
+    /// Returns `None` if every slot is taken.
+    fn insert(&mut self) -> Option<usize> {
+        let key = match self.first_empty {
+            Some(key) => key,
+            None if self.used < N => {
+                self.used += 1;
+                self.used - 1
+            }
+            None => return None,
+        };
+
+        let entry = Slot::Occupied(Entry {
+            state: State::Created,
+            prev: self.tail,
+            next: None,
+        });
+        if let Slot::Empty(next) = mem::replace(&mut self.slots[key], entry) {
+            if self.first_empty == Some(key) {
+                self.first_empty = next;
+            }
+        }
+
+        match self.tail {
+            Some(tail) => self.entry_mut(tail).next = Some(key),
+            None => self.head = Some(key),
+        }
+        self.tail = Some(key);
+
+        if self.start.is_none() {
+            self.start = Some(key);
+        }
+        self.len += 1;
+
+        Some(key)
+    }
+
+    /// Removes a listener from the list and returns its state.
+    fn remove(&mut self, key: usize) -> State {
+        let entry = match mem::replace(&mut self.slots[key], Slot::Empty(self.first_empty)) {
+            Slot::Occupied(entry) => entry,
+            Slot::Empty(_) => unreachable!(),
+        };
+        self.first_empty = Some(key);
+
+        match entry.prev {
+            Some(prev) => self.entry_mut(prev).next = entry.next,
+            None => self.head = entry.next,
+        }
+        match entry.next {
+            Some(next) => self.entry_mut(next).prev = entry.prev,
+            None => self.tail = entry.prev,
+        }
+        if self.start == Some(key) {
+            self.start = entry.next;
+        }
+
+        if entry.state.is_notified() {
+            self.notified -= 1;
+        }
+        self.len -= 1;
+
+        entry.state
+    }
+
+    /// Notifies a number of listeners, collecting the tasks to wake in `tasks`.
+    ///
+    /// Returns the number of listeners that were notified.
+    fn notify(&mut self, mut n: usize, additional: bool, tasks: &mut Tasks<N>) -> usize {
+        if !additional {
+            // Make sure we're not notifying more than we have.
+            if n <= self.notified {
+                return 0;
+            }
+            n -= self.notified;
+        }
+
+        let mut count = 0;
+        while n > 0 {
+            let key = match self.start {
+                Some(key) => key,
+                None => break,
+            };
+
+            let entry = self.entry_mut(key);
+            let next = entry.next;
+            if let State::Task(waker) = mem::replace(&mut entry.state, State::Notified(additional))
+            {
+                tasks.push(waker);
+            }
+
+            self.start = next;
+            self.notified += 1;
+            count += 1;
+            n -= 1;
+        }
+
+        count
+    }
+}
+
+/// The tasks to wake after releasing the lock.
+///
+/// An event holds at most `N` listeners, so at most `N` tasks are woken at once.
+struct Tasks<const N: usize> {
+    tasks: [Option<Waker>; N],
+    len: usize,
+}
+
+/// An empty task slot.
+const NO_TASK: Option<Waker> = None;
+
+impl<const N: usize> Tasks<N> {
+    fn new() -> Self {
+        Self {
+            tasks: [NO_TASK; N],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, waker: Waker) {
+        self.tasks[self.len] = Some(waker);
+        self.len += 1;
+    }
+
+    fn wake(mut self) {
+        for waker in self.tasks.iter_mut().take(self.len) {
+            if let Some(waker) = waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listen_contended() {
+        let event = Event::<2>::with_contention(Contention::Fail);
+
+        // Listening fails instead of spinning while the lock is held.
+        let guard = event.lock();
+        assert_eq!(event.listen().unwrap_err(), ListenError::Contended);
+        drop(guard);
+
+        let _listener = event.listen().unwrap();
+        assert_eq!(event.total_listeners(), 1);
+    }
+}
@@ -8,6 +8,9 @@
 //! cases. Therefore, we use a spinlock that protects a linked list of listeners, and fall back to an
 //! atomic queue if the lock is contended. Benchmarks show that this is about 20% slower than the std
 //! implementation, but still much faster than using a queue.
+//!
+//! Where the standard library is available, the `blocking-mutex` feature swaps the spinlock for a
+//! lock that parks contended threads, so operations only fall back to the queue on reentrancy.
 
 #[path = "no_std/node.rs"]
 mod node;
@@ -18,32 +21,108 @@ mod queue;
 use node::{Node, TaskWaiting};
 use queue::Queue;
 
+#[cfg(feature = "blocking-mutex")]
+use crate::sync::atomic::AtomicUsize;
 use crate::sync::atomic::{AtomicBool, Ordering};
 use crate::sync::cell::{Cell, UnsafeCell};
 use crate::sync::Arc;
-use crate::{State, Task, TaskRef};
+use crate::{
+    state_kind, Callback, Counters, EventStats, ListenerHandle, ListenerState, MaintenanceReport,
+    NotifyPath, RegisterResult, SlabError, State, Task, TaskRef, TraceId, WakeStrategy,
+};
 
 use core::fmt;
 use core::mem;
 use core::num::NonZeroUsize;
 use core::ops;
 use core::pin::Pin;
+use core::ptr;
 
 use alloc::vec::Vec;
 
-impl crate::Inner {
+impl<T: Clone> crate::Inner<T> {
     /// Locks the list.
-    fn try_lock(&self) -> Option<ListGuard<'_>> {
-        self.list.inner.try_lock().map(|guard| ListGuard {
+    ///
+    /// The operations queued while the list was contended are applied before the guard is
+    /// returned, so the list is up to date at the start of the locked operation.
+    fn try_lock(&self) -> Option<ListGuard<'_, T>> {
+        self.list
+            .inner
+            .try_lock()
+            .map(|guard| self.fresh_guard(guard))
+    }
+
+    /// Wraps a guard of the list, applying the queued operations.
+    ///
+    /// Applying the queue drains it, so the nodes applied here aren't applied again when the
+    /// guard is dropped. Only nodes pushed while the guard is held are left to the drop.
+    fn fresh_guard<'a>(&'a self, guard: MutexGuard<'a, ListenerSlab<T>>) -> ListGuard<'a, T> {
+        let mut list = ListGuard {
             inner: self,
             guard: Some(guard),
-        })
+            tasks: TaskBuffer::new(),
+            notified_ordering: Ordering::Release,
+        };
+        list.apply_queue();
+        list
+    }
+
+    /// Locks the list, spinning until it becomes available.
+    fn lock(&self) -> ListGuard<'_, T> {
+        ListGuard {
+            inner: self,
+            guard: Some(self.list.inner.lock()),
+            tasks: TaskBuffer::new(),
+            notified_ordering: Ordering::Release,
+        }
+    }
+
+    /// Pushes an operation to the queue.
+    ///
+    /// If the queue is full, this waits for the lock and applies the operation instead.
+    ///
+    /// Returns the number of operations in the queue after the push, or `None` if the operation
+    /// was applied directly.
+    fn push(&self, node: Node<T>) -> Option<usize> {
+        match self.list.queue.push(node) {
+            Ok(depth) => {
+                self.metrics.fallbacks.fetch_add(1, Ordering::Relaxed);
+
+                // The holder may have drained the queue and released the lock before our node
+                // was linked, in which case nobody else is going to apply it.
+                crate::full_fence();
+                if let Some(guard) = self.list.inner.try_lock_once() {
+                    drop(self.fresh_guard(guard));
+                }
+
+                Some(depth)
+            }
+
+            Err(node) => {
+                self.list.saturated.store(true, Ordering::Relaxed);
+
+                let mut list = self.lock();
+
+                // Keep the operations in order.
+                list.apply_queue();
+                if let Some(task) = node.apply(&mut list) {
+                    list.tasks.push(task);
+                }
+
+                None
+            }
+        }
+    }
+
+    /// Returns `true` if the queue was full since the last call.
+    pub(crate) fn take_saturated(&self) -> bool {
+        self.list.saturated.swap(false, Ordering::Relaxed)
     }
 
     /// Add a new listener to the list.
     ///
     /// Does nothing if the list is already registered.
-    pub(crate) fn insert(&self, mut listener: Pin<&mut Option<Listener>>) {
+    pub(crate) fn insert(&self, mut listener: Pin<&mut Option<Listener>>, priority: u8) {
         if listener.as_ref().as_pin_ref().is_some() {
             // Already inserted.
             return;
@@ -51,29 +130,155 @@ impl crate::Inner {
 
         match self.try_lock() {
             Some(mut lock) => {
-                let key = lock.insert(State::Created);
+                let key = lock.listen(priority);
                 *listener = Some(Listener::HasNode(key));
             }
 
             None => {
                 // Push it to the queue.
-                let (node, task_waiting) = Node::listener();
-                self.list.queue.push(node);
+                let (node, task_waiting) = Node::listener(priority);
+                self.push(node);
                 *listener = Some(Listener::Queued(task_waiting));
             }
         }
     }
 
+    /// Add a new listener to the list if it isn't locked, returning `false` otherwise.
+    ///
+    /// This neither spins on the lock nor queues the listener. Returns `true` if the listener is
+    /// already registered.
+    pub(crate) fn try_insert(
+        &self,
+        mut listener: Pin<&mut Option<Listener>>,
+        priority: u8,
+    ) -> bool {
+        if listener.as_ref().as_pin_ref().is_some() {
+            // Already inserted.
+            return true;
+        }
+
+        match self.list.inner.try_lock_once() {
+            Some(guard) => {
+                let mut list = self.fresh_guard(guard);
+                let key = list.listen(priority);
+                *listener = Some(Listener::HasNode(key));
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    /// Add a new listener to the back of the list, which can take `capacity` items.
+    ///
+    /// This waits for the lock, so that no notification sees the listener before its capacity is
+    /// set. Does nothing if the listener is already registered.
+    pub(crate) fn insert_with_capacity(
+        &self,
+        mut listener: Pin<&mut Option<Listener>>,
+        capacity: usize,
+    ) {
+        if listener.as_ref().as_pin_ref().is_some() {
+            // Already inserted.
+            return;
+        }
+
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners end up ahead of this one.
+        list.apply_queue();
+        let key = list.listen(0);
+        if let Entry::Listener { .. } = list.listeners[key.get()] {
+            list.listeners[key.get()].capacity().set(capacity);
+        }
+        *listener = Some(Listener::HasNode(key));
+    }
+
+    /// Add a new listener to the list, ahead of every unnotified listener.
+    ///
+    /// This waits for the lock, since the front of the list depends on the queued operations.
+    /// Does nothing if the listener is already registered.
+    pub(crate) fn insert_front(&self, mut listener: Pin<&mut Option<Listener>>) {
+        if listener.as_ref().as_pin_ref().is_some() {
+            // Already inserted.
+            return;
+        }
+
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners end up behind this one.
+        list.apply_queue();
+        let key = list.listen_front();
+        *listener = Some(Listener::HasNode(key));
+    }
+
+    /// Add a new listener to the back of the list and return the number of listeners that were
+    /// registered before it.
+    ///
+    /// This waits for the lock, since the position depends on the queued operations.
+    pub(crate) fn insert_positioned(&self, mut listener: Pin<&mut Option<Listener>>) -> usize {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are counted.
+        list.apply_queue();
+        let position = list.len;
+        let key = list.listen(0);
+        *listener = Some(Listener::HasNode(key));
+        position
+    }
+
+    /// Discard the notification of a listener and move it back in line.
+    ///
+    /// A listener that is still part of the list keeps its slot. Otherwise, it is inserted
+    /// again.
+    ///
+    /// This waits for the lock, so that the listener keeps its slot and priority.
+    pub(crate) fn reset(&self, mut listener: Pin<&mut Option<Listener>>) {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so a queued listener is inserted.
+        let priority = match list.key(listener.as_mut()) {
+            Some(key) => match list.listeners[key.get()] {
+                Entry::Listener { .. } => {
+                    list.reset(key);
+                    return;
+                }
+
+                // The listener was drained, so take it out.
+                Entry::Orphan(priority) => {
+                    list.remove(key, false);
+                    priority
+                }
+                _ => {
+                    list.remove(key, false);
+                    0
+                }
+            },
+            None => 0,
+        };
+
+        let key = list.listen(priority);
+        *listener = Some(Listener::HasNode(key));
+    }
+
     /// Remove a listener from the list.
     pub(crate) fn remove(
         &self,
         mut listener: Pin<&mut Option<Listener>>,
         propogate: bool,
-    ) -> Option<State> {
+    ) -> Option<State<T>> {
         let state = match listener.as_mut().take() {
             Some(Listener::HasNode(key)) => {
                 match self.try_lock() {
                     Some(mut list) => {
+                        if propogate {
+                            // Apply the operations queued since the list was locked as well. A
+                            // notification passed on to a listener whose removal is still queued
+                            // would be lost if that removal discards it, so keep going until no
+                            // removal is pending and every listener in the list is live.
+                            while list.apply_queue() > 0 {}
+                        }
+
                         // Fast path removal.
                         list.remove(key, propogate)
                     }
@@ -86,7 +291,7 @@ impl crate::Inner {
                             propagate: propogate,
                         };
 
-                        self.list.queue.push(node);
+                        self.push(node);
 
                         None
                     }
@@ -104,13 +309,84 @@ impl crate::Inner {
         state
     }
 
-    /// Notifies a number of entries.
+    /// Remove a listener from the list, taking the task out of its state.
+    ///
+    /// A queued removal couldn't hand the task back, so this waits for the lock.
+    pub(crate) fn remove_taking_task(
+        &self,
+        mut listener: Pin<&mut Option<Listener>>,
+        propogate: bool,
+    ) -> Option<(State<T>, Option<Task>)> {
+        let mut state = {
+            let mut list = self.lock();
+
+            // Apply pending operations first, so a queued listener is inserted.
+            let key = list.key(listener.as_mut());
+            listener.set(None);
+            list.remove(key?, propogate)?
+        };
+
+        let task = state.take_task();
+        Some((state, task))
+    }
+
+    /// Remove a listener from the list if it was notified, leaving it in place otherwise.
+    ///
+    /// A queued removal couldn't hand the state back, so this waits for the lock.
+    pub(crate) fn take_notification(
+        &self,
+        mut listener: Pin<&mut Option<Listener>>,
+    ) -> Option<State<T>> {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so a queued listener is inserted.
+        let key = list.key(listener.as_mut())?;
+
+        let notified = match &list.listeners[key.get()] {
+            Entry::Orphan(_) => true,
+            Entry::Shutdown => false,
+            entry => {
+                let state = entry.state().replace(State::NotifiedTaken);
+                let notified = matches!(state.kind(), ListenerState::Notified(_));
+                entry.state().set(state);
+                notified
+            }
+        };
+        if !notified {
+            return None;
+        }
+
+        listener.set(None);
+        list.remove(key, false)
+    }
+
+    /// Notifies a number of entries and returns how many were notified.
     #[cold]
-    pub(crate) fn notify(&self, n: usize, additional: bool) {
+    pub(crate) fn notify(&self, n: usize, additional: bool) -> usize {
+        self.notify_with(n, additional, TraceId::default(), None, Ordering::Release)
+            .0
+    }
+
+    /// Notifies a number of listeners with a trace id and a tag, publishing the new `notified`
+    /// count with `ordering`.
+    ///
+    /// Returns the number of listeners that were notified. If the list is contended, the
+    /// notification is queued, the count is published by the holder of the lock instead and `0`
+    /// is returned.
+    pub(crate) fn notify_with(
+        &self,
+        n: usize,
+        additional: bool,
+        trace_id: TraceId,
+        tag: Option<T>,
+        ordering: Ordering,
+    ) -> (usize, NotifyPath) {
         match self.try_lock() {
             Some(mut guard) => {
                 // Notify the listeners.
-                guard.notify(n, additional);
+                guard.notified_ordering = ordering;
+                let count = guard.notify(n, additional, trace_id, tag);
+                (count, NotifyPath::Fast)
             }
 
             None => {
@@ -118,472 +394,2571 @@ impl crate::Inner {
                 let node = Node::Notify {
                     count: n,
                     additional,
+                    trace_id,
+                    tag,
                 };
 
-                self.list.queue.push(node);
+                match self.push(node) {
+                    Some(depth) => (0, NotifyPath::Queued { depth }),
+                    None => (0, NotifyPath::Fast),
+                }
             }
         }
     }
 
-    /// Register a task to be notified when the event is triggered.
+    /// Notifies a number of listeners, spinning up to `retry_spins` more times on a contended
+    /// lock before queueing the notification.
     ///
-    /// Returns `true` if the listener was already notified, and `false` otherwise. If the listener
-    /// isn't inserted, returns `None`.
-    pub(crate) fn register(
-        &self,
-        mut listener: Pin<&mut Option<Listener>>,
-        task: TaskRef<'_>,
-    ) -> Option<bool> {
-        loop {
-            match listener.as_mut().take() {
-                Some(Listener::HasNode(key)) => {
-                    *listener = Some(Listener::HasNode(key));
-                    match self.try_lock() {
-                        Some(mut guard) => {
-                            // Fast path registration.
-                            return guard.register(listener, task);
-                        }
-
-                        None => {
-                            // Wait for the lock.
-                            let node = Node::Waiting(task.into_task());
-                            self.list.queue.push(node);
-                            return Some(false);
-                        }
-                    }
-                }
-
-                Some(Listener::Queued(task_waiting)) => {
-                    // Are we done yet?
-                    match task_waiting.status() {
-                        Some(key) => {
-                            // We're inserted now, adjust state.
-                            *listener = Some(Listener::HasNode(key));
-                        }
+    /// Returns the number of listeners that were notified, or `0` if the notification was queued.
+    pub(crate) fn notify_with_retry(&self, n: usize, additional: bool, retry_spins: u32) -> usize {
+        let guard = self.try_lock().or_else(|| {
+            self.list
+                .inner
+                .try_lock_with_budget(retry_spins)
+                .map(|guard| ListGuard {
+                    inner: self,
+                    guard: Some(guard),
+                    tasks: TaskBuffer::new(),
+                    notified_ordering: Ordering::Release,
+                })
+        });
 
-                        None => {
-                            // We're still queued, so register the task.
-                            task_waiting.register(task.into_task());
-                            *listener = Some(Listener::Queued(task_waiting));
-                            return None;
-                        }
-                    }
-                }
+        match guard {
+            Some(mut guard) => guard.notify(n, additional, TraceId::default(), None),
 
-                _ => return None,
+            None => {
+                // Push it to the queue.
+                self.push(Node::Notify {
+                    count: n,
+                    additional,
+                    trace_id: TraceId::default(),
+                    tag: None,
+                });
+                0
             }
         }
     }
-}
-
-pub(crate) struct List {
-    /// The inner list.
-    inner: Mutex<ListenerSlab>,
 
-    /// The queue of pending operations.
-    queue: Queue,
-}
+    /// Notifies a number of listeners if the list isn't locked, returning `false` otherwise.
+    ///
+    /// This neither spins on the lock nor queues the notification. Releasing the lock still
+    /// applies the queued operations of others and wakes their tasks.
+    pub(crate) fn try_notify(&self, n: usize, additional: bool) -> bool {
+        match self.list.inner.try_lock_once() {
+            Some(guard) => {
+                let mut list = ListGuard {
+                    inner: self,
+                    guard: Some(guard),
+                    tasks: TaskBuffer::new(),
+                    notified_ordering: Ordering::Release,
+                };
+                list.notify(n, additional, TraceId::default(), None);
+                true
+            }
 
-impl List {
-    pub(super) fn new() -> List {
-        List {
-            inner: Mutex::new(ListenerSlab::new()),
-            queue: Queue::new(),
+            None => false,
         }
     }
-}
-
-/// The guard returned by [`Inner::lock`].
-pub(crate) struct ListGuard<'a> {
-    /// Reference to the inner state.
-    pub(crate) inner: &'a crate::Inner,
 
-    /// The locked list.
-    pub(crate) guard: Option<MutexGuard<'a, ListenerSlab>>,
-}
+    /// Notifies a single listener.
+    ///
+    /// If the list is contended, this falls back to queueing a regular notification.
+    pub(crate) fn notify_one(&self) {
+        match self.try_lock() {
+            Some(mut guard) => guard.notify_one(),
 
-impl ListGuard<'_> {
-    #[cold]
-    fn process_nodes_slow(
-        &mut self,
-        start_node: Node,
-        tasks: &mut Vec<Task>,
-        guard: &mut MutexGuard<'_, ListenerSlab>,
-    ) {
-        // Process the start node.
-        tasks.extend(start_node.apply(guard));
+            None => {
+                // Push it to the queue.
+                let node = Node::Notify {
+                    count: 1,
+                    additional: false,
+                    trace_id: TraceId::default(),
+                    tag: None,
+                };
 
-        // Process all remaining nodes.
-        while let Some(node) = self.inner.list.queue.pop() {
-            tasks.extend(node.apply(guard));
+                self.push(node);
+            }
         }
     }
-}
 
-impl ops::Deref for ListGuard<'_> {
-    type Target = ListenerSlab;
+    /// Notifies a number of listeners without raising the number of notified ones above `cap`.
+    ///
+    /// This waits for the lock, since the number to notify depends on the current count.
+    pub(crate) fn notify_capped(&self, n: usize, additional: bool, cap: usize) {
+        let mut list = self.lock();
 
-    fn deref(&self) -> &Self::Target {
-        self.guard.as_ref().unwrap()
+        // Apply pending operations first, so the count is up to date.
+        list.apply_queue();
+        list.notify_capped(n, additional, cap);
     }
-}
 
-impl ops::DerefMut for ListGuard<'_> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.guard.as_mut().unwrap()
-    }
-}
+    /// Notifies a number of listeners, preferring the ones that have been waiting the longest.
+    ///
+    /// This waits for the lock, since the choice depends on the whole list.
+    pub(crate) fn notify_oldest(&self, n: usize) {
+        let mut list = self.lock();
 
-impl Drop for ListGuard<'_> {
-    fn drop(&mut self) {
-        let Self { inner, guard } = self;
-        let mut list = guard.take().unwrap();
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_oldest(n);
+    }
 
-        // Tasks to wakeup after releasing the lock.
-        let mut tasks = alloc::vec![];
+    /// Notifies a number of additional listeners, newest first.
+    ///
+    /// The newest listener may still be queued, so this waits for the lock.
+    pub(crate) fn notify_lifo(&self, n: usize) {
+        let mut list = self.lock();
 
-        // Process every node left in the queue.
-        if let Some(start_node) = inner.list.queue.pop() {
-            self.process_nodes_slow(start_node, &mut tasks, &mut list);
-        }
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_lifo(n);
+    }
 
-        // Update the atomic `notified` counter.
-        let notified = if list.notified < list.len {
-            list.notified
-        } else {
-            core::usize::MAX
-        };
+    /// Notifies a number of additional listeners, taking turns.
+    ///
+    /// The cursor lives in the list, so this waits for the lock.
+    pub(crate) fn notify_round_robin(&self, n: usize) -> usize {
+        let mut list = self.lock();
 
-        self.inner.notified.store(notified, Ordering::Release);
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_round_robin(n)
+    }
 
-        // Drop the actual lock.
-        drop(list);
+    /// Notifies listeners until a number of them hold a notification.
+    ///
+    /// The count depends on the whole list, so this waits for the lock.
+    pub(crate) fn notify_saturating(&self, target: usize) -> usize {
+        let mut list = self.lock();
 
-        // Wakeup all tasks.
-        for task in tasks {
-            task.wake();
-        }
+        // Apply pending operations first, so queued removals and listeners are considered too.
+        list.apply_queue();
+        list.notify_saturating(target)
     }
-}
-
-/// An entry representing a registered listener.
-enum Entry {
-    /// Contains the listener state.
-    Listener {
-        /// The state of the listener.
-        state: Cell<State>,
 
-        /// The previous listener in the list.
-        prev: Cell<Option<NonZeroUsize>>,
+    /// Notifies a number of additional listeners, preferring the ones inserted the longest ago.
+    ///
+    /// The round counter lives in the list, so this waits for the lock.
+    pub(crate) fn notify_fair(&self, n: usize) -> usize {
+        let mut list = self.lock();
 
-        /// The next listener in the list.
-        next: Cell<Option<NonZeroUsize>>,
-    },
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_fair(n)
+    }
 
-    /// An empty slot that contains the index of the next empty slot.
-    Empty(NonZeroUsize),
+    /// Notifies a number of listeners, giving each of them the tag returned by `f`.
+    ///
+    /// The closure can't be queued, so this waits for the lock.
+    pub(crate) fn notify_each(&self, n: usize, f: &mut dyn FnMut() -> T) -> usize {
+        let mut list = self.lock();
 
-    /// Sentinel value.
-    Sentinel,
-}
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_each(n, false, TraceId::default(), || Some(f()))
+    }
 
-struct TakenState<'a> {
-    slot: &'a Cell<State>,
-    state: State,
-}
+    /// Notifies a number of listeners that match a predicate.
+    ///
+    /// The predicate can't be queued, so this waits for the lock.
+    pub(crate) fn notify_filtered(&self, n: usize, pred: &mut dyn FnMut(&ListenerState) -> bool) {
+        let mut list = self.lock();
 
-impl Drop for TakenState<'_> {
-    fn drop(&mut self) {
-        self.slot
-            .set(mem::replace(&mut self.state, State::NotifiedTaken));
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_filtered(n, pred);
     }
-}
 
-impl fmt::Debug for TakenState<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(&self.state, f)
+    /// Hands a permit to the next unnotified listener, or stores it.
+    pub(crate) fn notify_permit(&self) -> bool {
+        match self.try_lock() {
+            Some(mut guard) => guard.notify_permit(),
+
+            None => {
+                // Push it to the queue.
+                self.push(Node::NotifyPermit);
+                false
+            }
+        }
     }
-}
 
-impl PartialEq for TakenState<'_> {
-    fn eq(&self, other: &Self) -> bool {
-        self.state == other.state
+    /// Notifies the next unnotified listener, or marks the list for the next listener.
+    pub(crate) fn notify_or_mark(&self) -> bool {
+        match self.try_lock() {
+            Some(mut guard) => guard.notify_or_mark(),
+
+            None => {
+                // Push it to the queue.
+                self.push(Node::NotifyOrMark);
+                false
+            }
+        }
     }
-}
 
-impl<'a> TakenState<'a> {
-    fn new(slot: &'a Cell<State>) -> Self {
-        let state = slot.replace(State::NotifiedTaken);
-        Self { slot, state }
+    /// Notifies a number of additional listeners, deferring the rest to future listeners.
+    pub(crate) fn coalesce_additional(&self, n: usize) {
+        match self.try_lock() {
+            Some(mut guard) => guard.coalesce_additional(n),
+
+            None => {
+                // Push it to the queue.
+                self.push(Node::CoalesceAdditional { count: n });
+            }
+        }
     }
-}
 
-impl fmt::Debug for Entry {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Entry::Listener { state, next, prev } => f
-                .debug_struct("Listener")
-                .field("state", &TakenState::new(state))
-                .field("prev", prev)
-                .field("next", next)
-                .finish(),
-            Entry::Empty(next) => f.debug_tuple("Empty").field(next).finish(),
-            Entry::Sentinel => f.debug_tuple("Sentinel").finish(),
+    /// Fill in the statistics of the slab and the queue, without blocking.
+    pub(crate) fn fill_stats(&self, stats: &mut EventStats) {
+        stats.queue_len = self.list.queue.len();
+
+        if let Some(list) = self.try_lock() {
+            // Don't count the sentinel.
+            stats.slab_capacity = Some(list.listeners.capacity() - 1);
+            stats.empty_slots = Some(list.listeners.len() - 1 - list.len - list.orphans);
         }
     }
-}
 
-impl PartialEq for Entry {
-    fn eq(&self, other: &Entry) -> bool {
+    /// Get the slot statistics of the slab.
+    #[cfg(feature = "debug-stats")]
+    pub(crate) fn slab_stats(&self) -> crate::SlabStats {
+        self.lock().slab_stats
+    }
+
+    /// Notify the listener with `handle`, deferring the notification if the list is contended.
+    ///
+    /// Returns `false` if the listener isn't registered. A deferred notification is assumed to
+    /// find its listener.
+    pub(crate) fn notify_handle_deferred(&self, handle: Handle) -> bool {
+        match self.try_lock() {
+            Some(mut guard) => guard.notify_handle(handle),
+
+            None => {
+                // Push it to the queue.
+                self.push(Node::NotifyHandle(handle));
+                true
+            }
+        }
+    }
+
+    /// Get the handle of a listener, or `None` if it isn't in the list.
+    ///
+    /// This waits for the lock, so that a queued listener is inserted.
+    pub(crate) fn handle(&self, listener: Pin<&mut Option<Listener>>) -> Option<Handle> {
+        let mut list = self.lock();
+        let key = list.key(listener)?;
+
+        match &list.listeners[key.get()] {
+            entry @ Entry::Listener { .. } => Some(Handle {
+                key,
+                id: entry.id(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Notify the listener with `handle`, returning `false` if it isn't registered.
+    pub(crate) fn notify_handle(&self, handle: Handle) -> bool {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are found too.
+        list.apply_queue();
+        list.notify_handle(handle)
+    }
+
+    /// Detach the notified listeners that no task is waiting on.
+    ///
+    /// Returns zero if the list is contended and the operation was deferred.
+    pub(crate) fn prune_notified(&self) -> usize {
+        match self.try_lock() {
+            Some(mut guard) => guard.prune_notified(),
+
+            None => {
+                // Push it to the queue.
+                self.push(Node::PruneNotified);
+                0
+            }
+        }
+    }
+
+    /// Return the handles and states of every listener in the list.
+    #[cfg(feature = "debug")]
+    pub(crate) fn listener_states(&self) -> Vec<(ListenerHandle, ListenerState)> {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are included.
+        list.apply_queue();
+        let states = list.listener_states();
+        ListenerHandle::with_states(self, states)
+    }
+
+    /// Remove every listener from the list and return their final states.
+    pub(crate) fn drain_all(&self) -> Vec<(ListenerHandle, ListenerState)> {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are drained too.
+        list.apply_queue();
+
+        let ListGuard { guard, tasks, .. } = &mut list;
+        let drained = guard.as_mut().unwrap().drain_all(tasks, false);
+        ListenerHandle::with_states(self, drained)
+    }
+
+    /// Notify every listener and remove them from the list.
+    ///
+    /// Returns the number of listeners that weren't notified yet.
+    pub(crate) fn notify_drain(&self) -> usize {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are notified too.
+        list.apply_queue();
+
+        let ListGuard { guard, tasks, .. } = &mut list;
+        let guard = guard.as_mut().unwrap();
+        let count = guard.len - guard.notified;
+        guard.counters.notifies += count;
+        guard.drain_all(tasks, false);
+        count
+    }
+
+    /// Remove every listener from the list, waking up their tasks if `wake` is set.
+    pub(crate) fn clear(&self, wake: bool) {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are removed too.
+        list.apply_queue();
+
+        let ListGuard { guard, tasks, .. } = &mut list;
+        if wake {
+            guard.as_mut().unwrap().drain_all(tasks, true);
+        } else {
+            // Drop the tasks instead of waking them up once the lock is released.
+            guard
+                .as_mut()
+                .unwrap()
+                .drain_all(&mut TaskBuffer::new(), true);
+        }
+    }
+
+    /// Remove every listener from the list and refuse new ones.
+    pub(crate) fn shutdown(&self) {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are shut down too.
+        list.apply_queue();
+
+        let ListGuard { guard, tasks, .. } = &mut list;
+        let guard = guard.as_mut().unwrap();
+        guard.shutdown = true;
+        guard.drain_all(tasks, true);
+    }
+
+    /// Returns `true` if the list was shut down.
+    pub(crate) fn is_shutdown(&self) -> bool {
+        self.lock().shutdown
+    }
+
+    /// Notify every listener and make new ones start out notified.
+    pub(crate) fn latch(&self) {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are notified too.
+        list.apply_queue();
+
+        let ListGuard { guard, tasks, .. } = &mut list;
+        let guard = guard.as_mut().unwrap();
+        guard.latched = true;
+        self.latched.store(true, Ordering::Release);
+        guard.drain_all(tasks, false);
+    }
+
+    /// Set the callback that is called when the last listener is removed.
+    pub(crate) fn set_on_empty(&self, callback: Callback) {
+        self.lock().on_empty = Some(callback);
+    }
+
+    /// Set how tasks are woken up when their listener is notified.
+    pub(crate) fn set_wake_strategy(&self, strategy: WakeStrategy) {
+        self.lock().wake_strategy = strategy;
+    }
+
+    /// Set whether tasks that wake the same task are only woken once.
+    pub(crate) fn set_wake_deduplication(&self, enabled: bool) {
+        self.lock().dedup_wakes = enabled;
+    }
+
+    /// Apply pending operations, compact and shrink the slab and validate it.
+    pub(crate) fn maintain(&self) -> MaintenanceReport {
+        let mut list = self.lock();
+
+        // Apply every operation that was deferred to the queue.
+        let nodes_applied = list.apply_queue();
+
+        let slots_compacted = list.compact_free_list();
+        let bytes_reclaimed = list.shrink();
+
+        MaintenanceReport {
+            nodes_applied,
+            slots_compacted,
+            bytes_reclaimed,
+            validation: list.validate(),
+        }
+    }
+
+    /// Move the listeners into the lowest empty slots.
+    pub(crate) fn compact(&self) -> usize {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are moved too.
+        list.apply_queue();
+        list.compact()
+    }
+
+    /// Release the memory of unused slots.
+    pub(crate) fn shrink_to_fit(&self) -> usize {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued removals free their slots.
+        list.apply_queue();
+        list.shrink_to_fit()
+    }
+
+    /// Get the number of listeners in the list.
+    ///
+    /// Locking the list applies the queue, so queued listeners are counted. If the list is
+    /// contended, this returns the count published by the last holder of the lock instead.
+    pub(crate) fn total_listeners(&self) -> usize {
+        match self.try_lock() {
+            Some(list) => list.len,
+            None => self.len.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Register a task to be notified when the event is triggered.
+    pub(crate) fn register(
+        &self,
+        mut listener: Pin<&mut Option<Listener>>,
+        task: TaskRef<'_>,
+    ) -> RegisterResult<T> {
+        loop {
+            match listener.as_mut().take() {
+                Some(Listener::HasNode(key)) => {
+                    *listener = Some(Listener::HasNode(key));
+                    match self.try_lock() {
+                        Some(mut guard) => {
+                            // Fast path registration.
+                            return guard.register(listener, task);
+                        }
+
+                        None => {
+                            // Wait for the lock.
+                            let node = Node::Waiting(task.into_task());
+                            self.push(node);
+                            return RegisterResult::Registered;
+                        }
+                    }
+                }
+
+                Some(Listener::Queued(task_waiting)) => {
+                    // Are we done yet?
+                    match task_waiting.status() {
+                        Some(key) => {
+                            // We're inserted now, adjust state.
+                            *listener = Some(Listener::HasNode(key));
+                        }
+
+                        None => {
+                            // We're still queued, so register the task.
+                            task_waiting.register(task.into_task());
+                            *listener = Some(Listener::Queued(task_waiting));
+                            return RegisterResult::Registered;
+                        }
+                    }
+                }
+
+                _ => return RegisterResult::NeverInserted,
+            }
+        }
+    }
+
+    /// Register a task to be notified when the event is triggered, keeping a notified listener
+    /// registered.
+    ///
+    /// This waits for the lock, so that a notified listener can be renewed in place.
+    pub(crate) fn register_renew(
+        &self,
+        mut listener: Pin<&mut Option<Listener>>,
+        task: TaskRef<'_>,
+    ) -> RegisterResult<T> {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so a queued listener is inserted.
+        if list.key(listener.as_mut()).is_none() {
+            return RegisterResult::NeverInserted;
+        }
+        list.register_renew(listener, task)
+    }
+
+    /// Get the state of a listener without consuming its notification.
+    ///
+    /// Returns `None` if the listener isn't inserted.
+    pub(crate) fn state(&self, listener: Pin<&mut Option<Listener>>) -> Option<ListenerState> {
+        let mut list = self.lock();
+        let key = list.key(listener)?;
+
+        match &list.listeners[key.get()] {
+            Entry::Orphan(_) => Some(ListenerState::Notified(false)),
+            Entry::Shutdown => Some(ListenerState::Shutdown),
+            entry => {
+                let state = entry.state().replace(State::NotifiedTaken);
+                let kind = state.kind();
+                entry.state().set(state);
+                Some(kind)
+            }
+        }
+    }
+
+    /// Returns `true` if a listener was notified, without consuming the notification.
+    ///
+    /// If the list is contended, this conservatively returns `false`.
+    pub(crate) fn is_notified(&self, listener: &Option<Listener>) -> bool {
+        let mut list = match self.try_lock() {
+            Some(list) => list,
+            None => return false,
+        };
+
+        // Apply pending operations, so queued notifications are seen.
+        list.apply_queue();
+
+        let key = match listener {
+            Some(Listener::HasNode(key)) => *key,
+            Some(Listener::Queued(task_waiting)) => match task_waiting.status() {
+                Some(key) => key,
+                None => return false,
+            },
+            None => return false,
+        };
+
+        match &list.listeners[list.follow(key).get()] {
+            Entry::Orphan(_) => true,
+            Entry::Shutdown => false,
+            entry => TakenState::new(entry.state()).state.is_notified(),
+        }
+    }
+
+    /// Replace the state of a listener that was just inserted.
+    pub(crate) fn restore(&self, listener: Pin<&mut Option<Listener>>, state: State<T>) {
+        let mut list = self.lock();
+        if let Some(key) = list.key(listener) {
+            list.restore(key, state);
+        }
+    }
+
+    /// Set the group of a listener.
+    pub(crate) fn set_group(&self, listener: Pin<&mut Option<Listener>>, group: T) {
+        let mut list = self.lock();
+        if let Some(key) = list.key(listener) {
+            if let Entry::Listener { .. } = list.listeners[key.get()] {
+                list.listeners[key.get()].group().set(Some(group));
+            }
+        }
+    }
+
+    /// Notify the first unnotified listener of every group.
+    pub(crate) fn notify_per_group(&self, first: &mut dyn FnMut(&T) -> bool) -> usize {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_per_group(first)
+    }
+
+    /// Notifies listeners until the items they can take add up to `needed`.
+    pub(crate) fn notify_to_capacity(&self, needed: usize) -> usize {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        list.notify_to_capacity(needed)
+    }
+
+    /// Notifies a number of listeners and collects the listeners that are left without a task.
+    pub(crate) fn notify_and_harvest(
+        &self,
+        n: usize,
+        additional: bool,
+    ) -> (usize, Vec<ListenerHandle>) {
+        let mut list = self.lock();
+
+        // Apply pending operations first, so queued listeners are considered too.
+        list.apply_queue();
+        let (notified, created) = list.notify_and_harvest(n, additional);
+        let created = created
+            .into_iter()
+            .map(|handle| ListenerHandle::new(self, handle))
+            .collect();
+        (notified, created)
+    }
+}
+
+pub(crate) struct List<T> {
+    /// The inner list.
+    inner: Mutex<ListenerSlab<T>>,
+
+    /// The queue of pending operations.
+    queue: Queue<T>,
+
+    /// Set when an operation found the queue full.
+    saturated: AtomicBool,
+}
+
+impl<T> List<T> {
+    pub(super) fn new(spin_budget: u32, queue_capacity: usize, capacity: usize) -> List<T> {
+        List {
+            inner: Mutex::new(ListenerSlab::with_capacity(capacity), spin_budget),
+            queue: Queue::new(queue_capacity),
+            saturated: AtomicBool::new(false),
+        }
+    }
+}
+
+/// The guard returned by [`Inner::lock`].
+pub(crate) struct ListGuard<'a, T: Clone> {
+    /// Reference to the inner state.
+    pub(crate) inner: &'a crate::Inner<T>,
+
+    /// The locked list.
+    pub(crate) guard: Option<MutexGuard<'a, ListenerSlab<T>>>,
+
+    /// Tasks to wake up after releasing the lock.
+    tasks: TaskBuffer,
+
+    /// The ordering used to publish the `notified` count.
+    notified_ordering: Ordering,
+}
+
+impl<T: Clone> ListGuard<'_, T> {
+    /// Get the key of a listener, applying pending operations so a queued listener is inserted.
+    ///
+    /// Returns `None` if the listener isn't inserted.
+    fn key(&mut self, mut listener: Pin<&mut Option<Listener>>) -> Option<NonZeroUsize> {
+        self.apply_queue();
+
+        let key = match listener.as_ref().get_ref() {
+            Some(Listener::HasNode(key)) => *key,
+            Some(Listener::Queued(task_waiting)) => task_waiting.status()?,
+            None => return None,
+        };
+        let key = self.forward(key);
+        *listener = Some(Listener::HasNode(key));
+
+        Some(key)
+    }
+
+    /// Apply every node in the queue to the list.
+    ///
+    /// Only the nodes pushed before the call are applied, so that a storm of pushers can't keep
+    /// the lock holder busy forever. Returns the number of nodes applied.
+    pub(crate) fn apply_queue(&mut self) -> usize {
+        let mut applied = 0;
+        let list = self.guard.as_mut().unwrap();
+        let queued = self.inner.list.queue.len();
+
+        while applied < queued {
+            // SAFETY: We hold the lock.
+            match unsafe { self.inner.list.queue.pop() } {
+                Some(node) => self.tasks.extend(node.apply(list)),
+                None => break,
+            }
+            applied += 1;
+        }
+
+        applied
+    }
+
+    #[cold]
+    fn process_nodes_slow(
+        &mut self,
+        start_node: Node<T>,
+        tasks: &mut TaskBuffer,
+        guard: &mut MutexGuard<'_, ListenerSlab<T>>,
+    ) {
+        // Process the start node.
+        tasks.extend(start_node.apply(guard));
+
+        // Detach the remaining nodes at once, rather than taking them one at a time. Nodes pushed
+        // in the meantime are left to the next round, see `Drop`.
+        let mut nodes = Vec::new();
+
+        // SAFETY: We hold the lock.
+        unsafe { self.inner.list.queue.drain_into(&mut nodes) };
+        for node in nodes {
+            tasks.extend(node.apply(guard));
+        }
+    }
+}
+
+impl<T: Clone> ops::Deref for ListGuard<'_, T> {
+    type Target = ListenerSlab<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<T: Clone> ops::DerefMut for ListGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl<T: Clone> Drop for ListGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut list = self.guard.take().unwrap();
+
+        // Tasks to wakeup after releasing the lock.
+        let mut tasks = mem::replace(&mut self.tasks, TaskBuffer::new());
+
+        loop {
+            // Process every node left in the queue.
+            // SAFETY: We hold the lock.
+            if let Some(start_node) = unsafe { self.inner.list.queue.pop() } {
+                self.process_nodes_slow(start_node, &mut tasks, &mut list);
+            }
+
+            // Update the atomic `notified` counter.
+            let notified = if list.notified < list.len {
+                list.notified
+            } else {
+                core::usize::MAX
+            };
+
+            self.inner.notified.store(notified, self.notified_ordering);
+            self.inner
+                .notified_count
+                .store(list.notified, Ordering::Relaxed);
+            let len_changed = self.inner.set_len(list.len);
+            self.inner.publish_counters(&list.counters);
+
+            // Check if the list was emptied.
+            let on_empty = if list.emptied {
+                list.emptied = false;
+                list.on_empty.clone()
+            } else {
+                None
+            };
+
+            // Wake the deferred tasks along with the others.
+            tasks.extend(list.woken.take());
+
+            // Drop the actual lock.
+            drop(list);
+
+            // Wakeup all tasks.
+            mem::replace(&mut tasks, TaskBuffer::new()).wake();
+
+            if len_changed {
+                self.inner.len_changed();
+            }
+            if let Some(on_empty) = on_empty {
+                on_empty();
+            }
+
+            // Apply the nodes pushed since the queue was drained, if their pushers saw the lock
+            // held. This loops rather than recursing, so a storm of pushers can't overflow the
+            // stack, and the lock is released between the rounds.
+            crate::full_fence();
+            if self.inner.list.queue.is_empty() {
+                break;
+            }
+            list = match self.inner.list.inner.try_lock_once() {
+                Some(guard) => guard,
+                None => break,
+            };
+            self.notified_ordering = Ordering::Release;
+        }
+    }
+}
+
+/// The number of slots above which a slab releases its memory once it becomes empty.
+const SHRINK_THRESHOLD: usize = 64;
+
+/// The number of tasks a [`TaskBuffer`] holds before allocating.
+const INLINE_TASKS: usize = 4;
+
+/// A buffer of tasks to wake up, which only allocates once it holds more than a few tasks.
+pub(crate) struct TaskBuffer {
+    /// The tasks stored inline.
+    inline: [Option<Task>; INLINE_TASKS],
+
+    /// The number of tasks stored inline.
+    len: usize,
+
+    /// The tasks that didn't fit inline.
+    spilled: Vec<Task>,
+}
+
+impl TaskBuffer {
+    /// Create a new, empty buffer.
+    pub(crate) fn new() -> Self {
+        Self {
+            inline: [None, None, None, None],
+            len: 0,
+            spilled: Vec::new(),
+        }
+    }
+
+    /// Add a task to the buffer.
+    pub(crate) fn push(&mut self, task: Task) {
+        if self.len < INLINE_TASKS {
+            self.inline[self.len] = Some(task);
+            self.len += 1;
+        } else {
+            self.spilled.push(task);
+        }
+    }
+
+    /// Wake up every task in the buffer.
+    pub(crate) fn wake(self) {
+        let Self {
+            mut inline,
+            spilled,
+            ..
+        } = self;
+
+        for task in inline.iter_mut().filter_map(Option::take) {
+            task.wake();
+        }
+
+        for task in spilled {
+            task.wake();
+        }
+    }
+}
+
+impl Extend<Task> for TaskBuffer {
+    fn extend<I: IntoIterator<Item = Task>>(&mut self, iter: I) {
+        for task in iter {
+            self.push(task);
+        }
+    }
+}
+
+/// An entry representing a registered listener.
+enum Entry<T> {
+    /// Contains the listener state.
+    Listener {
+        /// The unique identifier of the listener.
+        id: usize,
+
+        /// The priority of the listener.
+        priority: u8,
+
+        /// When the task of the listener started waiting, relative to other listeners.
+        waiting_since: Cell<usize>,
+
+        /// The `notify_fair` round in which the listener was inserted.
+        epoch: usize,
+
+        /// The number of items the listener can take.
+        capacity: Cell<usize>,
+
+        /// The group of the listener.
+        group: Cell<Option<T>>,
+
+        /// The state of the listener.
+        state: Cell<State<T>>,
+
+        /// The previous listener in the list.
+        prev: Cell<Option<NonZeroUsize>>,
+
+        /// The next listener in the list.
+        next: Cell<Option<NonZeroUsize>>,
+    },
+
+    /// An empty slot that contains the index of the next empty slot.
+    Empty(NonZeroUsize),
+
+    /// A slot whose listener was drained from the list, but is still owned by an
+    /// `EventListener`.
+    ///
+    /// The slot is freed once the owner is removed or polled. It keeps the priority of the
+    /// listener, in case the owner is reset.
+    Orphan(u8),
+
+    /// A slot whose listener was removed by a shutdown before it was notified.
+    ///
+    /// Like `Orphan`, the slot is freed once the owner is removed or polled.
+    Shutdown,
+
+    /// A slot whose listener was moved to another slot by compaction.
+    ///
+    /// The slot is freed once the owner looks up the new slot.
+    Moved(NonZeroUsize),
+
+    /// Sentinel value.
+    Sentinel,
+}
+
+struct TakenState<'a, T> {
+    slot: &'a Cell<State<T>>,
+    state: State<T>,
+}
+
+impl<T> Drop for TakenState<'_, T> {
+    fn drop(&mut self) {
+        self.slot
+            .set(mem::replace(&mut self.state, State::NotifiedTaken));
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TakenState<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.state, f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for TakenState<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state
+    }
+}
+
+impl<'a, T> TakenState<'a, T> {
+    fn new(slot: &'a Cell<State<T>>) -> Self {
+        let state = slot.replace(State::NotifiedTaken);
+        Self { slot, state }
+    }
+}
+
+/// Compares two states, only taking them out if their kinds match and they carry a task, a trace
+/// id or a tag.
+fn states_eq<T: PartialEq>(state1: &Cell<State<T>>, state2: &Cell<State<T>>) -> bool {
+    if ptr::eq(state1, state2) {
+        return true;
+    }
+
+    let kind = state_kind(state1);
+    if kind != state_kind(state2) {
+        return false;
+    }
+
+    match kind {
+        ListenerState::Task | ListenerState::Notified(_) => {
+            TakenState::new(state1) == TakenState::new(state2)
+        }
+        _ => true,
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Entry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Listener {
+                id,
+                priority,
+                waiting_since,
+                capacity,
+                state,
+                next,
+                prev,
+                ..
+            } => f
+                .debug_struct("Listener")
+                .field("id", id)
+                .field("priority", priority)
+                .field("waiting_since", waiting_since)
+                .field("capacity", capacity)
+                .field("state", &state_kind(state))
+                .field("prev", prev)
+                .field("next", next)
+                .finish(),
+            Entry::Empty(next) => f.debug_tuple("Empty").field(next).finish(),
+            Entry::Orphan(priority) => f.debug_tuple("Orphan").field(priority).finish(),
+            Entry::Shutdown => f.debug_tuple("Shutdown").finish(),
+            Entry::Moved(key) => f.debug_tuple("Moved").field(key).finish(),
+            Entry::Sentinel => f.debug_tuple("Sentinel").finish(),
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Entry<T> {
+    fn eq(&self, other: &Entry<T>) -> bool {
         match (self, other) {
             (
-                Self::Listener {
-                    state: state1,
-                    prev: prev1,
-                    next: next1,
-                },
-                Self::Listener {
-                    state: state2,
-                    prev: prev2,
-                    next: next2,
-                },
-            ) => {
-                if TakenState::new(state1) != TakenState::new(state2) {
-                    return false;
+                Self::Listener {
+                    id: id1,
+                    priority: priority1,
+                    state: state1,
+                    prev: prev1,
+                    next: next1,
+                    ..
+                },
+                Self::Listener {
+                    id: id2,
+                    priority: priority2,
+                    state: state2,
+                    prev: prev2,
+                    next: next2,
+                    ..
+                },
+            ) => {
+                if id1 != id2 || priority1 != priority2 || !states_eq(state1, state2) {
+                    return false;
+                }
+
+                prev1.get() == prev2.get() && next1.get() == next2.get()
+            }
+            (Self::Empty(next1), Self::Empty(next2)) => next1 == next2,
+            (Self::Orphan(priority1), Self::Orphan(priority2)) => priority1 == priority2,
+            (Self::Shutdown, Self::Shutdown) => true,
+            (Self::Moved(key1), Self::Moved(key2)) => key1 == key2,
+            (Self::Sentinel, Self::Sentinel) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T> Entry<T> {
+    fn id(&self) -> usize {
+        match self {
+            Entry::Listener { id, .. } => *id,
+            _ => unreachable!(),
+        }
+    }
+
+    fn priority(&self) -> u8 {
+        match self {
+            Entry::Listener { priority, .. } => *priority,
+            _ => unreachable!(),
+        }
+    }
+
+    fn state(&self) -> &Cell<State<T>> {
+        match self {
+            Entry::Listener { state, .. } => state,
+            _ => unreachable!(),
+        }
+    }
+
+    fn waiting_since(&self) -> &Cell<usize> {
+        match self {
+            Entry::Listener { waiting_since, .. } => waiting_since,
+            _ => unreachable!(),
+        }
+    }
+
+    fn epoch(&self) -> usize {
+        match self {
+            Entry::Listener { epoch, .. } => *epoch,
+            _ => unreachable!(),
+        }
+    }
+
+    fn group(&self) -> &Cell<Option<T>> {
+        match self {
+            Entry::Listener { group, .. } => group,
+            _ => unreachable!(),
+        }
+    }
+
+    fn capacity(&self) -> &Cell<usize> {
+        match self {
+            Entry::Listener { capacity, .. } => capacity,
+            _ => unreachable!(),
+        }
+    }
+
+    fn prev(&self) -> &Cell<Option<NonZeroUsize>> {
+        match self {
+            Entry::Listener { prev, .. } => prev,
+            _ => unreachable!(),
+        }
+    }
+
+    fn next(&self) -> &Cell<Option<NonZeroUsize>> {
+        match self {
+            Entry::Listener { next, .. } => next,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A linked list of entries.
+pub(crate) struct ListenerSlab<T> {
+    /// The raw list of entries.
+    listeners: Vec<Entry<T>>,
+
+    /// First entry in the list.
+    head: Option<NonZeroUsize>,
+
+    /// Last entry in the list.
+    tail: Option<NonZeroUsize>,
+
+    /// The first unnotified entry in the list.
+    start: Option<NonZeroUsize>,
+
+    /// The number of notified entries in the list.
+    notified: usize,
+
+    /// The total number of listeners.
+    len: usize,
+
+    /// The number of `Orphan`, `Shutdown` and `Moved` entries.
+    orphans: usize,
+
+    /// The index of the first `Empty` entry, or the length of the list plus one if there
+    /// are no empty entries.
+    first_empty: NonZeroUsize,
+
+    /// Called when the last listener is removed.
+    on_empty: Option<Callback>,
+
+    /// Set when a removal has emptied the list.
+    emptied: bool,
+
+    /// The number of slots to keep when shrinking, see `Event::with_capacity()`.
+    min_capacity: usize,
+
+    /// The number of permits waiting for a listener.
+    permits: usize,
+
+    /// The number of additional notifications waiting for a listener.
+    deferred: usize,
+
+    /// Set when the next inserted listener starts out notified, see `notify_or_mark`.
+    marked: bool,
+
+    /// The identifier given to the next inserted listener.
+    next_id: usize,
+
+    /// The counter used to order listeners by when their tasks started waiting.
+    next_wait: usize,
+
+    /// The slot of the listener notified last by `notify_round_robin`.
+    cursor: Option<NonZeroUsize>,
+
+    /// The current round of `notify_fair`, which new listeners are inserted in.
+    epoch: usize,
+
+    /// How tasks are woken up when their listener is notified.
+    pub(crate) wake_strategy: WakeStrategy,
+
+    /// Set once the list was shut down.
+    shutdown: bool,
+
+    /// Set once the list was latched, so that new listeners start out notified.
+    latched: bool,
+
+    /// Whether tasks that wake the same task are only woken once.
+    dedup_wakes: bool,
+
+    /// The tasks to wake once the list is unlocked, if wakes are deduplicated.
+    woken: Cell<Vec<Task>>,
+
+    /// Totals of operations on the list.
+    counters: Counters,
+
+    /// How the slots of the slab were filled.
+    #[cfg(feature = "debug-stats")]
+    slab_stats: crate::SlabStats,
+}
+
+impl<T> ListenerSlab<T> {
+    /// Create a new, empty list.
+    #[cfg(test)]
+    pub(crate) fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Create a new, empty list with room for `capacity` entries.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        // Leave room for the sentinel as well.
+        let min_capacity = capacity.saturating_add(1);
+        let mut listeners = Vec::with_capacity(min_capacity);
+        listeners.push(Entry::Sentinel);
+
+        Self {
+            listeners,
+            head: None,
+            tail: None,
+            start: None,
+            notified: 0,
+            len: 0,
+            orphans: 0,
+            first_empty: unsafe { NonZeroUsize::new_unchecked(1) },
+            on_empty: None,
+            emptied: false,
+            min_capacity,
+            permits: 0,
+            deferred: 0,
+            marked: false,
+            next_id: 1,
+            next_wait: 0,
+            cursor: None,
+            epoch: 0,
+            wake_strategy: WakeStrategy::Consume,
+            shutdown: false,
+            latched: false,
+            dedup_wakes: false,
+            woken: Cell::new(Vec::new()),
+            counters: Counters::default(),
+            #[cfg(feature = "debug-stats")]
+            slab_stats: crate::SlabStats::default(),
+        }
+    }
+}
+
+impl<T: Clone> ListenerSlab<T> {
+    /// Inserts a new entry into the list.
+    #[cfg(test)]
+    pub(crate) fn insert(&mut self, state: State<T>) -> NonZeroUsize {
+        self.insert_with_priority(state, 0)
+    }
+
+    /// Inserts a new entry into the list, ahead of unnotified entries with a lower priority.
+    pub(crate) fn insert_with_priority(&mut self, state: State<T>, priority: u8) -> NonZeroUsize {
+        self.insert_at(state, priority, false)
+    }
+
+    /// Inserts a new entry into the list, ahead of every unnotified entry.
+    ///
+    /// The entry takes the priority of the entry it goes ahead of, so the unnotified entries stay
+    /// sorted by priority.
+    pub(crate) fn insert_front(&mut self, state: State<T>) -> NonZeroUsize {
+        self.insert_at(state, 0, true)
+    }
+
+    /// Inserts a new entry into the list, either in line by its `priority` or at the `front`.
+    fn insert_at(&mut self, state: State<T>, priority: u8, front: bool) -> NonZeroUsize {
+        let notified = state.is_notified();
+
+        // Notified entries go at the end.
+        let (before, priority) = if notified {
+            (None, priority)
+        } else if front {
+            let start = self.start;
+            (
+                start,
+                start.map_or(0, |e| self.listeners[e.get()].priority()),
+            )
+        } else {
+            (self.position(priority), priority)
+        };
+
+        // Add the new entry into the list.
+        let key = self.occupy(Entry::Listener {
+            id: self.next_id,
+            priority,
+            waiting_since: Cell::new(0),
+            epoch: self.epoch,
+            capacity: Cell::new(1),
+            group: Cell::new(None),
+            state: Cell::new(state),
+            prev: Cell::new(None),
+            next: Cell::new(None),
+        });
+
+        self.next_id = self.next_id.wrapping_add(1);
+        self.link(key, before);
+
+        if notified {
+            // The new listener starts out notified.
+            self.notified += 1;
+            self.counters.notifies += 1;
+        } else if self.start.is_none() {
+            // If there are no listeners that have been notified, then the new listener is the next
+            // listener to be notified.
+            self.start = Some(key);
+        }
+
+        // Increment the length.
+        self.len += 1;
+        self.counters.listens += 1;
+
+        key
+    }
+
+    /// Puts an entry into the first empty slot and returns its key.
+    fn occupy(&mut self, entry: Entry<T>) -> NonZeroUsize {
+        let key = self.first_empty;
+        let push = self.first_empty.get() == self.listeners.len();
+
+        #[cfg(feature = "debug-stats")]
+        self.slab_stats.record(push);
+
+        if push {
+            // No empty entries, so add a new entry.
+            self.listeners.push(entry);
+
+            // SAFETY: Guaranteed to not overflow, since the Vec would have panicked already.
+            self.first_empty = unsafe { NonZeroUsize::new_unchecked(self.listeners.len()) };
+        } else {
+            // There is an empty entry, so replace it.
+            let slot = &mut self.listeners[key.get()];
+            let next = match mem::replace(slot, entry) {
+                Entry::Empty(next) => next,
+                _ => unreachable!(),
+            };
+
+            self.first_empty = next;
+        }
+
+        key
+    }
+
+    /// Finds the entry that a new unnotified entry with `priority` goes in front of.
+    ///
+    /// Returns `None` if the entry goes at the end of the list.
+    fn position(&self, priority: u8) -> Option<NonZeroUsize> {
+        match self.tail {
+            Some(tail) if self.listeners[tail.get()].priority() < priority => {
+                let mut current = self.start;
+                while let Some(e) = current {
+                    let entry = &self.listeners[e.get()];
+                    if entry.priority() < priority {
+                        break;
+                    }
+                    current = entry.next().get();
+                }
+                current
+            }
+            _ => None,
+        }
+    }
+
+    /// Links an entry in front of `before`, or at the end of the list.
+    fn link(&mut self, key: NonZeroUsize, before: Option<NonZeroUsize>) {
+        let prev = match before {
+            Some(before) => {
+                // Link the entry in front of `before`.
+                let prev = self.listeners[before.get()].prev().replace(Some(key));
+                match prev {
+                    None => self.head = Some(key),
+                    Some(prev) => self.listeners[prev.get()].next().set(Some(key)),
+                }
+
+                if self.start == Some(before) {
+                    self.start = Some(key);
+                }
+
+                prev
+            }
+
+            None => {
+                // Replace the tail with the entry.
+                let prev = self.tail.replace(key);
+                match prev {
+                    None => self.head = Some(key),
+                    Some(tail) => self.listeners[tail.get()].next().set(Some(key)),
+                }
+
+                prev
+            }
+        };
+
+        let entry = &self.listeners[key.get()];
+        entry.prev().set(prev);
+        entry.next().set(before);
+    }
+
+    /// Unlinks an entry from its neighbors.
+    fn unlink(&mut self, key: NonZeroUsize) {
+        let entry = &self.listeners[key.get()];
+        let prev = entry.prev().get();
+        let next = entry.next().get();
+
+        // Unlink from the previous entry.
+        match prev {
+            None => self.head = next,
+            Some(p) => self.listeners[p.get()].next().set(next),
+        }
+
+        // Unlink from the next entry.
+        match next {
+            None => self.tail = prev,
+            Some(n) => self.listeners[n.get()].prev().set(prev),
+        }
+
+        // If this was the first unnotified entry, move the pointer to the next one.
+        if self.start == Some(key) {
+            self.start = next;
+        }
+    }
+
+    /// Discards the notification of an entry and moves it to where a new entry would go.
+    ///
+    /// The entry keeps its slot. Like a new entry, it takes a waiting permit, deferred
+    /// notification or mark.
+    pub(crate) fn reset(&mut self, key: NonZeroUsize) {
+        self.unlink(key);
+
+        if self.listeners[key.get()]
+            .state()
+            .replace(State::Created)
+            .is_notified()
+        {
+            self.notified -= 1;
+        }
+
+        let state = self.admit();
+        let entry = &self.listeners[key.get()];
+        if state.is_notified() {
+            // The entry starts out notified, so it goes at the end.
+            entry.state().set(state);
+            self.link(key, None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            return;
+        }
+
+        let before = self.position(entry.priority());
+        self.link(key, before);
+
+        if self.start.is_none() {
+            self.start = Some(key);
+        }
+    }
+
+    /// Inserts a new listener, which takes a waiting permit, deferred notification or mark if
+    /// there is one.
+    pub(crate) fn listen(&mut self, priority: u8) -> NonZeroUsize {
+        if self.shutdown {
+            // The list was shut down, so the listener only gets a slot.
+            self.orphans += 1;
+            self.occupy(Entry::Shutdown)
+        } else if self.latched {
+            // The list was latched, so the listener gets a slot that reads as notified.
+            self.orphans += 1;
+            self.occupy(Entry::Orphan(priority))
+        } else {
+            let state = self.admit();
+            self.insert_with_priority(state, priority)
+        }
+    }
+
+    /// Inserts a new listener ahead of every unnotified listener, unless it takes a waiting
+    /// permit, deferred notification or mark.
+    pub(crate) fn listen_front(&mut self) -> NonZeroUsize {
+        if self.shutdown {
+            // The list was shut down, so the listener only gets a slot.
+            self.orphans += 1;
+            self.occupy(Entry::Shutdown)
+        } else if self.latched {
+            // The list was latched, so the listener gets a slot that reads as notified.
+            self.orphans += 1;
+            self.occupy(Entry::Orphan(0))
+        } else {
+            // A notified entry still goes at the end.
+            let state = self.admit();
+            self.insert_front(state)
+        }
+    }
+
+    /// Takes a waiting permit, deferred notification or mark for a new entry, and returns the
+    /// state the entry starts out in.
+    fn admit(&mut self) -> State<T> {
+        if self.permits > 0 {
+            self.permits -= 1;
+            State::Permit
+        } else if self.deferred > 0 {
+            self.deferred -= 1;
+            State::Notified(true, TraceId::default(), None)
+        } else if self.marked {
+            self.marked = false;
+            State::Notified(true, TraceId::default(), None)
+        } else {
+            State::Created
+        }
+    }
+
+    /// Removes an entry from the list and returns its state.
+    pub(crate) fn remove(&mut self, key: NonZeroUsize, propogate: bool) -> Option<State<T>> {
+        let key = self.forward(key);
+
+        if let Entry::Orphan(_) | Entry::Shutdown = self.listeners[key.get()] {
+            // The listener was drained, so just free the slot.
+            let entry = mem::replace(
+                &mut self.listeners[key.get()],
+                Entry::Empty(self.first_empty),
+            );
+            self.first_empty = key;
+            self.orphans -= 1;
+            self.shrink_if_empty();
+            return Some(match entry {
+                Entry::Shutdown => State::Shutdown,
+                _ => State::Notified(false, TraceId::default(), None),
+            });
+        }
+
+        self.unlink(key);
+
+        // Extract the state.
+        let entry = mem::replace(
+            &mut self.listeners[key.get()],
+            Entry::Empty(self.first_empty),
+        );
+        self.first_empty = key;
+
+        let state = match entry {
+            Entry::Listener { state, .. } => state.into_inner(),
+            _ => unreachable!(),
+        };
+
+        // Update the counters.
+        if state.is_notified() {
+            self.notified = self.notified.saturating_sub(1);
+
+            if propogate {
+                // Propogate the notification to the next entry.
+                match &state {
+                    State::Notified(additional, trace_id, tag)
+                    | State::NotifiedWithTask(additional, trace_id, tag, _) => {
+                        self.notify(1, *additional, *trace_id, tag.clone());
+                    }
+                    State::Permit => {
+                        self.notify_permit();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.len -= 1;
+        self.counters.removes += 1;
+        if self.len == 0 {
+            self.emptied = true;
+            self.shrink_if_empty();
+        }
+
+        Some(state)
+    }
+
+    /// Notifies a number of listeners, attaching a trace id and a tag to the notifications.
+    ///
+    /// Returns the number of listeners that were notified by this call.
+    #[cold]
+    pub(crate) fn notify(
+        &mut self,
+        n: usize,
+        additional: bool,
+        trace_id: TraceId,
+        tag: Option<T>,
+    ) -> usize {
+        self.notify_each(n, additional, trace_id, || tag.clone())
+    }
+
+    /// Notifies a number of listeners, giving each of them the tag returned by `tag`.
+    ///
+    /// `tag` is called once per notified listener, in notification order. Returns the number of
+    /// listeners that were notified by this call.
+    pub(crate) fn notify_each(
+        &mut self,
+        mut n: usize,
+        additional: bool,
+        trace_id: TraceId,
+        mut tag: impl FnMut() -> Option<T>,
+    ) -> usize {
+        if !additional {
+            #[cfg(feature = "trace-id")]
+            {
+                if trace_id.0.is_some() {
+                    self.retrace(n, trace_id);
+                }
+            }
+
+            // Make sure we're not notifying more than we have.
+            if n <= self.notified {
+                return 0;
+            }
+            n -= self.notified;
+        }
+
+        let mut count = 0;
+        while n > 0 {
+            n -= 1;
+
+            // Notify the next entry.
+            match self.start {
+                None => break,
+
+                Some(e) => {
+                    // Get the tag before touching the list, in case `tag` panics.
+                    let tag = tag();
+
+                    // Get the entry and move the pointer forwards.
+                    let entry = &self.listeners[e.get()];
+                    self.start = entry.next().get();
+
+                    // Notified listeners, including those whose notification is being consumed,
+                    // are kept in front of `start`, so they are never notified twice.
+                    debug_assert!(!TakenState::new(entry.state()).state.is_notified());
+
+                    // Set the state to `Notified` and notify.
+                    self.notify_entry(entry, additional, trace_id, tag);
+
+                    // Bump the notified count.
+                    self.notified += 1;
+                    self.counters.notifies += 1;
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Notifies a number of listeners without raising the number of notified ones above `cap`.
+    ///
+    /// The excess is discarded. Listeners that are already notified stay notified, even if
+    /// there are more than `cap` of them.
+    pub(crate) fn notify_capped(&mut self, n: usize, additional: bool, cap: usize) {
+        let n = if additional {
+            n.min(cap.saturating_sub(self.notified))
+        } else {
+            n.min(cap)
+        };
+
+        self.notify(n, additional, TraceId::default(), None);
+    }
+
+    /// Notifies the first unnotified entry, unless an entry is already notified.
+    pub(crate) fn notify_one(&mut self) {
+        if self.notified > 0 {
+            return;
+        }
+
+        if let Some(e) = self.start {
+            // Get the entry and move the pointer forwards.
+            let entry = &self.listeners[e.get()];
+            self.start = entry.next().get();
+
+            self.notify_entry(entry, false, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+        }
+    }
+
+    /// Notifies `n` additional listeners for which `pred` returns `true`.
+    ///
+    /// Notified listeners are moved in front of `start`, so that skipped listeners stay
+    /// eligible for later notifications.
+    pub(crate) fn notify_filtered(
+        &mut self,
+        mut n: usize,
+        pred: &mut dyn FnMut(&ListenerState) -> bool,
+    ) {
+        let mut current = self.start;
+
+        while n > 0 {
+            let e = match current {
+                Some(e) => e,
+                None => break,
+            };
+
+            let entry = &self.listeners[e.get()];
+            current = entry.next().get();
+
+            // Skip listeners that are already notified or don't match.
+            let kind = state_kind(entry.state());
+            let matched = !kind.is_notified() && pred(&kind);
+
+            if !matched {
+                continue;
+            }
+
+            if self.start == Some(e) {
+                self.start = current;
+            } else {
+                self.move_before_start(e);
+            }
+
+            self.notify_entry(&self.listeners[e.get()], true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            n -= 1;
+        }
+    }
+
+    /// Replace the state of an entry that was just inserted.
+    ///
+    /// A notified entry is moved in front of `start`.
+    pub(crate) fn restore(&mut self, key: NonZeroUsize, state: State<T>) {
+        let notified = state.is_notified();
+        let entry = &self.listeners[key.get()];
+        let old = entry.state().replace(state);
+        debug_assert!(!old.is_notified());
+
+        if notified {
+            if self.start == Some(key) {
+                self.start = entry.next().get();
+            } else {
+                self.move_before_start(key);
+            }
+
+            self.notified += 1;
+            self.counters.notifies += 1;
+        }
+    }
+
+    /// Notifies unnotified listeners in order until the items they can take add up to `needed`.
+    ///
+    /// Returns the number of items the notified listeners can take.
+    pub(crate) fn notify_to_capacity(&mut self, needed: usize) -> usize {
+        let mut notified = 0;
+
+        while notified < needed {
+            let e = match self.start {
+                Some(e) => e,
+                None => break,
+            };
+
+            // Get the entry and move the pointer forwards.
+            let entry = &self.listeners[e.get()];
+            self.start = entry.next().get();
+
+            notified = notified.saturating_add(entry.capacity().get());
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+        }
+
+        notified
+    }
+
+    /// Notifies a number of listeners and returns how many were notified, along with the handles
+    /// of the unnotified listeners that don't have a task yet.
+    pub(crate) fn notify_and_harvest(
+        &mut self,
+        n: usize,
+        additional: bool,
+    ) -> (usize, Vec<Handle>) {
+        let before = self.notified;
+        self.notify(n, additional, TraceId::default(), None);
+
+        let mut created = Vec::new();
+        let mut current = self.start;
+        while let Some(key) = current {
+            let entry = &self.listeners[key.get()];
+            current = entry.next().get();
+
+            if let State::Created = TakenState::new(entry.state()).state {
+                created.push(Handle {
+                    key,
+                    id: entry.id(),
+                });
+            }
+        }
+
+        (self.notified - before, created)
+    }
+
+    /// Notifies the first unnotified listener of every group for which `first` returns `true`.
+    ///
+    /// `first` is called with the group of every unnotified listener that has one, in order.
+    /// Notified listeners are moved in front of `start`. Returns the number of notified listeners.
+    pub(crate) fn notify_per_group(&mut self, first: &mut dyn FnMut(&T) -> bool) -> usize {
+        let mut notified = 0;
+        let mut current = self.start;
+
+        while let Some(e) = current {
+            let entry = &self.listeners[e.get()];
+            current = entry.next().get();
+
+            // Skip listeners that are already notified, have no group or aren't the first of it.
+            // SAFETY: We are locked, so nothing writes to the group while `first` looks at it.
+            let group = unsafe { &*entry.group().as_ptr() };
+            let matched = !state_kind(entry.state()).is_notified()
+                && group.as_ref().map_or(false, &mut *first);
+
+            if !matched {
+                continue;
+            }
+
+            if self.start == Some(e) {
+                self.start = current;
+            } else {
+                self.move_before_start(e);
+            }
+
+            self.notify_entry(&self.listeners[e.get()], true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            notified += 1;
+        }
+
+        notified
+    }
+
+    /// Notifies `n` additional listeners, preferring the ones whose tasks have been waiting the
+    /// longest.
+    ///
+    /// Once no unnotified listener has a waiting task, the rest are notified in order.
+    pub(crate) fn notify_oldest(&mut self, mut n: usize) {
+        while n > 0 {
+            // Find the unnotified entry whose task has been waiting the longest.
+            let mut oldest = None;
+            let mut current = self.start;
+            while let Some(e) = current {
+                let entry = &self.listeners[e.get()];
+                current = entry.next().get();
+
+                let state = entry.state().replace(State::NotifiedTaken);
+                let waiting = matches!(state, State::Task(_));
+                entry.state().set(state);
+
+                let since = entry.waiting_since().get();
+                if waiting && oldest.map_or(true, |(_, oldest)| since < oldest) {
+                    oldest = Some((e, since));
                 }
+            }
 
-                prev1.get() == prev2.get() && next1.get() == next2.get()
+            let e = match oldest {
+                Some((e, _)) => e,
+                None => break,
+            };
+
+            if self.start == Some(e) {
+                self.start = self.listeners[e.get()].next().get();
+            } else {
+                self.move_before_start(e);
             }
-            (Self::Empty(next1), Self::Empty(next2)) => next1 == next2,
-            (Self::Sentinel, Self::Sentinel) => true,
-            _ => false,
+
+            self.notify_entry(&self.listeners[e.get()], true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            n -= 1;
         }
+
+        self.notify(n, true, TraceId::default(), None);
     }
-}
 
-impl Entry {
-    fn state(&self) -> &Cell<State> {
-        match self {
-            Entry::Listener { state, .. } => state,
-            _ => unreachable!(),
+    /// Notifies `n` additional listeners, starting from the end of the list.
+    ///
+    /// Notified listeners are moved in front of `start`, so the notified listeners stay in front
+    /// of the unnotified ones.
+    pub(crate) fn notify_lifo(&mut self, mut n: usize) {
+        while n > 0 {
+            // The last entry is unnotified as long as any entry is.
+            if self.start.is_none() {
+                break;
+            }
+            let e = self.tail.unwrap();
+
+            if self.start == Some(e) {
+                self.start = None;
+            } else {
+                self.move_before_start(e);
+            }
+
+            self.notify_entry(&self.listeners[e.get()], true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            n -= 1;
         }
     }
 
-    fn prev(&self) -> &Cell<Option<NonZeroUsize>> {
-        match self {
-            Entry::Listener { prev, .. } => prev,
-            _ => unreachable!(),
+    /// Notifies `n` additional listeners, taking turns by slot.
+    ///
+    /// The slots after the one notified last by this method are scanned first, wrapping around
+    /// to the start of the list. Slots that don't hold an unnotified listener, including the
+    /// slot of the cursor itself once it was removed, are skipped.
+    ///
+    /// Returns the number of listeners that were notified.
+    pub(crate) fn notify_round_robin(&mut self, mut n: usize) -> usize {
+        let len = self.listeners.len();
+        let cursor = match self.cursor {
+            // The list may have shrunk since.
+            Some(key) if key.get() < len => key.get(),
+            _ => 0,
+        };
+
+        let mut count = 0;
+        for index in (cursor + 1..len).chain(1..=cursor) {
+            if n == 0 || self.start.is_none() {
+                break;
+            }
+
+            let unnotified = match &self.listeners[index] {
+                entry @ Entry::Listener { .. } => {
+                    !TakenState::new(entry.state()).state.is_notified()
+                }
+                _ => false,
+            };
+            if !unnotified {
+                continue;
+            }
+
+            let key = NonZeroUsize::new(index).unwrap();
+            if self.start == Some(key) {
+                self.start = self.listeners[index].next().get();
+            } else {
+                self.move_before_start(key);
+            }
+
+            self.notify_entry(&self.listeners[index], true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            self.cursor = Some(key);
+            count += 1;
+            n -= 1;
         }
+
+        count
     }
 
-    fn next(&self) -> &Cell<Option<NonZeroUsize>> {
-        match self {
-            Entry::Listener { next, .. } => next,
-            _ => unreachable!(),
+    /// Notifies listeners until `target` of them hold a notification.
+    ///
+    /// The notified listeners are counted again instead of relying on `notified`. Returns the
+    /// number of listeners that were notified, which is zero if `target` is already reached.
+    pub(crate) fn notify_saturating(&mut self, target: usize) -> usize {
+        let mut live = 0;
+        let mut current = self.head;
+        while current != self.start {
+            let entry = &self.listeners[current.unwrap().get()];
+            current = entry.next().get();
+
+            if TakenState::new(entry.state()).state.is_notified() {
+                live += 1;
+            }
         }
+
+        self.notify(target.saturating_sub(live), true, TraceId::default(), None)
     }
-}
 
-/// A linked list of entries.
-pub(crate) struct ListenerSlab {
-    /// The raw list of entries.
-    listeners: Vec<Entry>,
+    /// Notifies `n` additional listeners, preferring the ones inserted in the oldest round.
+    ///
+    /// Listeners inserted in the same round are notified in order. Every call starts a new round,
+    /// so a listener is never passed over for one inserted after it, regardless of priorities.
+    ///
+    /// Returns the number of listeners that were notified.
+    pub(crate) fn notify_fair(&mut self, mut n: usize) -> usize {
+        let mut count = 0;
+        while n > 0 {
+            // Find the first unnotified entry from the oldest round.
+            let mut oldest = None;
+            let mut current = self.start;
+            while let Some(e) = current {
+                let entry = &self.listeners[e.get()];
+                current = entry.next().get();
+
+                let epoch = entry.epoch();
+                if oldest.map_or(true, |(_, oldest)| epoch < oldest) {
+                    oldest = Some((e, epoch));
+                }
+            }
 
-    /// First entry in the list.
-    head: Option<NonZeroUsize>,
+            let e = match oldest {
+                Some((e, _)) => e,
+                None => break,
+            };
 
-    /// Last entry in the list.
-    tail: Option<NonZeroUsize>,
+            if self.start == Some(e) {
+                self.start = self.listeners[e.get()].next().get();
+            } else {
+                self.move_before_start(e);
+            }
 
-    /// The first unnotified entry in the list.
-    start: Option<NonZeroUsize>,
+            self.notify_entry(&self.listeners[e.get()], true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            count += 1;
+            n -= 1;
+        }
 
-    /// The number of notified entries in the list.
-    notified: usize,
+        self.epoch = self.epoch.wrapping_add(1);
+        count
+    }
 
-    /// The total number of listeners.
-    len: usize,
+    /// Moves an entry after `start` to the position right in front of it.
+    fn move_before_start(&mut self, key: NonZeroUsize) {
+        let start = self.start.unwrap();
+        let (prev, next) = {
+            let entry = &self.listeners[key.get()];
+            (entry.prev().get(), entry.next().get())
+        };
 
-    /// The index of the first `Empty` entry, or the length of the list plus one if there
-    /// are no empty entries.
-    first_empty: NonZeroUsize,
-}
+        // Unlink the entry. It comes after `start`, so it always has a previous entry.
+        self.listeners[prev.unwrap().get()].next().set(next);
+        match next {
+            None => self.tail = prev,
+            Some(next) => self.listeners[next.get()].prev().set(prev),
+        }
 
-impl ListenerSlab {
-    /// Create a new, empty list.
-    pub(crate) fn new() -> Self {
-        Self {
-            listeners: alloc::vec![Entry::Sentinel],
-            head: None,
-            tail: None,
-            start: None,
-            notified: 0,
-            len: 0,
-            first_empty: unsafe { NonZeroUsize::new_unchecked(1) },
+        // Link it in front of `start`.
+        let before = self.listeners[start.get()].prev().replace(Some(key));
+        match before {
+            None => self.head = Some(key),
+            Some(before) => self.listeners[before.get()].next().set(Some(key)),
         }
+
+        let entry = &self.listeners[key.get()];
+        entry.prev().set(before);
+        entry.next().set(Some(start));
     }
 
-    /// Inserts a new entry into the list.
-    pub(crate) fn insert(&mut self, state: State) -> NonZeroUsize {
-        // Add the new entry into the list.
-        let key = {
-            let entry = Entry::Listener {
-                state: Cell::new(state),
-                prev: Cell::new(self.tail),
-                next: Cell::new(None),
+    /// Sets the state of an entry to `Notified` and wakes its task.
+    fn notify_entry(&self, entry: &Entry<T>, additional: bool, trace_id: TraceId, tag: Option<T>) {
+        let notified = State::Notified(additional, trace_id, tag);
+        if let State::Task(task) = entry.state().replace(notified) {
+            match self.wake_strategy {
+                WakeStrategy::Consume => self.wake(task),
+                WakeStrategy::ByRef => {
+                    // Keep the task around so it can be reused.
+                    self.wake_by_ref(&task);
+                    let tag = entry.state().replace(State::NotifiedTaken).into_tag();
+                    entry
+                        .state()
+                        .set(State::NotifiedWithTask(additional, trace_id, tag, task));
+                }
+            }
+        }
+    }
+
+    /// Wakes a task, or defers it until the list is unlocked if wakes are deduplicated.
+    fn wake(&self, task: Task) {
+        if self.dedup_wakes {
+            let mut woken = self.woken.take();
+            task.push_deduplicated(&mut woken);
+            self.woken.set(woken);
+        } else {
+            task.wake();
+        }
+    }
+
+    /// Wakes a task by reference, or defers a clone of it if wakes are deduplicated.
+    fn wake_by_ref(&self, task: &Task) {
+        if self.dedup_wakes {
+            self.wake(task.clone());
+        } else {
+            task.wake_by_ref();
+        }
+    }
+
+    /// Replaces the trace id of the first `n` listeners that are notified, but haven't received
+    /// their notification yet.
+    #[cfg(feature = "trace-id")]
+    fn retrace(&mut self, n: usize, trace_id: TraceId) {
+        let mut current = self.head;
+
+        for _ in 0..n {
+            match current {
+                Some(e) if current != self.start => {
+                    let entry = &self.listeners[e.get()];
+                    let state = entry.state().replace(State::NotifiedTaken);
+                    entry.state().set(state.with_trace_id(trace_id));
+                    current = entry.next().get();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Notifies `n` additional listeners, deferring the notifications that can't be delivered
+    /// to the next listeners to be inserted.
+    pub(crate) fn coalesce_additional(&mut self, n: usize) {
+        let notified = self.notified;
+        self.notify(n, true, TraceId::default(), None);
+        self.deferred = self.deferred.saturating_add(n - (self.notified - notified));
+    }
+
+    /// Notifies the next unnotified listener, or marks the list so that the next listener to be
+    /// inserted starts out notified.
+    pub(crate) fn notify_or_mark(&mut self) -> bool {
+        if self.start.is_none() {
+            self.marked = true;
+            return false;
+        }
+
+        self.notify(1, true, TraceId::default(), None);
+        true
+    }
+
+    /// Hands a permit to the next unnotified listener, or stores it if there is none.
+    pub(crate) fn notify_permit(&mut self) -> bool {
+        match self.start {
+            None => {
+                self.permits += 1;
+                false
+            }
+
+            Some(e) => {
+                // Get the entry and move the pointer forwards.
+                let entry = &self.listeners[e.get()];
+                self.start = entry.next().get();
+
+                // Set the state to `Permit` and notify.
+                if let State::Task(task) = entry.state().replace(State::Permit) {
+                    self.wake(task);
+                }
+
+                // Bump the notified count.
+                self.notified += 1;
+                self.counters.notifies += 1;
+                true
+            }
+        }
+    }
+
+    /// Returns the handles and states of the linked listeners, in list order.
+    #[cfg(feature = "debug")]
+    pub(crate) fn listener_states(&self) -> Vec<(Handle, ListenerState)> {
+        let mut states = Vec::with_capacity(self.len);
+        let mut current = self.head;
+
+        while let Some(key) = current {
+            let entry = &self.listeners[key.get()];
+            current = entry.next().get();
+
+            let handle = Handle {
+                key,
+                id: entry.id(),
             };
+            states.push((handle, state_kind(entry.state())));
+        }
 
-            let key = self.first_empty;
-            if self.first_empty.get() == self.listeners.len() {
-                // No empty entries, so add a new entry.
-                self.listeners.push(entry);
+        states
+    }
 
-                // SAFETY: Guaranteed to not overflow, since the Vec would have panicked already.
-                self.first_empty = unsafe { NonZeroUsize::new_unchecked(self.listeners.len()) };
-            } else {
-                // There is an empty entry, so replace it.
-                let slot = &mut self.listeners[key.get()];
-                let next = match mem::replace(slot, entry) {
-                    Entry::Empty(next) => next,
-                    _ => unreachable!(),
-                };
+    /// Unlinks every listener and returns their final states.
+    ///
+    /// The slots are kept as orphans until their owners are removed. If `shutdown` is set, the
+    /// listeners that weren't notified yet are marked as shut down instead. Tasks waiting on the
+    /// listeners are pushed to `tasks`.
+    pub(crate) fn drain_all(
+        &mut self,
+        tasks: &mut TaskBuffer,
+        shutdown: bool,
+    ) -> Vec<(Handle, ListenerState)> {
+        let mut drained = Vec::with_capacity(self.len);
+        let mut current = self.head.take();
+
+        while let Some(key) = current {
+            let priority = self.listeners[key.get()].priority();
+            let entry = mem::replace(&mut self.listeners[key.get()], Entry::Orphan(priority));
+            self.orphans += 1;
+            let handle = Handle {
+                key,
+                id: entry.id(),
+            };
+            current = entry.next().get();
+
+            let state = match entry {
+                Entry::Listener { state, .. } => state.into_inner(),
+                _ => unreachable!(),
+            };
+            drained.push((handle, state.kind()));
+
+            if shutdown && !state.is_notified() {
+                self.listeners[key.get()] = Entry::Shutdown;
+            }
 
-                self.first_empty = next;
+            if let State::Task(task) = state {
+                tasks.push(task);
             }
+        }
+
+        self.tail = None;
+        self.start = None;
+        self.notified = 0;
+        if self.len > 0 {
+            self.counters.removes += self.len;
+            self.len = 0;
+            self.emptied = true;
+        }
 
-            key
+        drained
+    }
+
+    /// Notifies the listener with `handle` unless it is already notified.
+    ///
+    /// The listener is moved in front of `start`. Returns `false` if the listener isn't in the
+    /// list.
+    pub(crate) fn notify_handle(&mut self, handle: Handle) -> bool {
+        let key = self.follow(handle.key);
+        let notified = match self.listeners.get(key.get()) {
+            Some(entry @ Entry::Listener { .. }) if entry.id() == handle.id => {
+                TakenState::new(entry.state()).state.is_notified()
+            }
+            _ => return false,
         };
 
-        // Replace the tail with the new entry.
-        match mem::replace(&mut self.tail, Some(key)) {
-            None => self.head = Some(key),
-            Some(tail) => {
-                let tail = &self.listeners[tail.get()];
-                tail.next().set(Some(key));
+        if !notified {
+            if self.start == Some(key) {
+                self.start = self.listeners[key.get()].next().get();
+            } else {
+                self.move_before_start(key);
             }
+
+            self.notify_entry(&self.listeners[key.get()], true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
         }
 
-        // If there are no listeners that have been notified, then the new listener is the next
-        // listener to be notified.
-        if self.start.is_none() {
-            self.start = Some(key);
+        true
+    }
+
+    /// Unlinks the notified listeners that no task is waiting on, without propagating their
+    /// notifications.
+    ///
+    /// Like drained entries, the slots are kept as orphans until their owners are removed.
+    pub(crate) fn prune_notified(&mut self) -> usize {
+        let mut pruned = 0;
+        let mut current = self.head;
+
+        while let Some(key) = current {
+            let entry = &self.listeners[key.get()];
+            current = entry.next().get();
+
+            let idle = match TakenState::new(entry.state()).state {
+                State::Notified(..) | State::NotifiedTaken => true,
+                _ => false,
+            };
+
+            if !idle {
+                continue;
+            }
+
+            let priority = entry.priority();
+            self.unlink(key);
+            self.listeners[key.get()] = Entry::Orphan(priority);
+            self.orphans += 1;
+            self.notified -= 1;
+            self.len -= 1;
+            self.counters.removes += 1;
+            pruned += 1;
         }
 
-        // Increment the length.
-        self.len += 1;
+        if pruned > 0 && self.len == 0 {
+            self.emptied = true;
+        }
+
+        pruned
+    }
+
+    /// Removes empty slots from the end of the slab and rebuilds the chain of empty slots in
+    /// ascending order.
+    ///
+    /// Returns the number of slots that were removed.
+    pub(crate) fn compact_free_list(&mut self) -> usize {
+        // Drop trailing empty slots; live keys are never moved.
+        let old_len = self.listeners.len();
+        while let Some(Entry::Empty(_)) = self.listeners.last() {
+            self.listeners.pop();
+        }
+
+        // Rebuild the free list so that the lowest empty slot is reused first.
+        let mut first_empty = unsafe { NonZeroUsize::new_unchecked(self.listeners.len()) };
+        for (i, entry) in self.listeners.iter_mut().enumerate().skip(1).rev() {
+            if let Entry::Empty(next) = entry {
+                *next = first_empty;
+                first_empty = unsafe { NonZeroUsize::new_unchecked(i) };
+            }
+        }
+        self.first_empty = first_empty;
+
+        old_len - self.listeners.len()
+    }
+
+    /// Moves listeners into the lowest empty slots, in list order.
+    ///
+    /// The old slots are kept as `Moved` entries until the owners of the listeners look up their
+    /// new slots. Returns the number of listeners that were moved.
+    pub(crate) fn compact(&mut self) -> usize {
+        // Sort the empty slots, so that the lowest ones are filled first.
+        self.compact_free_list();
+
+        let mut moved = 0;
+        let mut current = self.head;
+        while let Some(key) = current {
+            current = self.listeners[key.get()].next().get();
+
+            // There is no empty slot in front of this listener.
+            let new = self.first_empty;
+            if new >= key {
+                continue;
+            }
+
+            let entry = mem::replace(&mut self.listeners[key.get()], Entry::Moved(new));
+            let (prev, next) = (entry.prev().get(), entry.next().get());
+            self.occupy(entry);
+            self.orphans += 1;
+
+            // Point the neighbours at the new slot.
+            match prev {
+                Some(prev) => self.listeners[prev.get()].next().set(Some(new)),
+                None => self.head = Some(new),
+            }
+            match next {
+                Some(next) => self.listeners[next.get()].prev().set(Some(new)),
+                None => self.tail = Some(new),
+            }
+            if self.start == Some(key) {
+                self.start = Some(new);
+            }
+            if self.cursor == Some(key) {
+                self.cursor = Some(new);
+            }
+
+            moved += 1;
+        }
+
+        moved
+    }
+
+    /// Returns the slot that the listener of `key` was moved to, freeing the slots it was moved
+    /// out of.
+    ///
+    /// This must only be called with the key held by the owner of the listener, which then has to
+    /// use the returned key.
+    fn forward(&mut self, mut key: NonZeroUsize) -> NonZeroUsize {
+        while let Entry::Moved(next) = self.listeners[key.get()] {
+            self.listeners[key.get()] = Entry::Empty(self.first_empty);
+            self.first_empty = key;
+            self.orphans -= 1;
+            key = next;
+        }
 
         key
     }
 
-    /// Removes an entry from the list and returns its state.
-    pub(crate) fn remove(&mut self, key: NonZeroUsize, propogate: bool) -> Option<State> {
-        let entry = &self.listeners[key.get()];
-        let prev = entry.prev().get();
-        let next = entry.next().get();
+    /// Returns the slot that the listener of `key` was moved to, without freeing any slots.
+    fn follow(&self, mut key: NonZeroUsize) -> NonZeroUsize {
+        while let Some(Entry::Moved(next)) = self.listeners.get(key.get()) {
+            key = *next;
+        }
+
+        key
+    }
+
+    /// Releases excess capacity of the slab, keeping at least the capacity it was created with.
+    ///
+    /// Returns the number of bytes that were released.
+    pub(crate) fn shrink(&mut self) -> usize {
+        let old_capacity = self.listeners.capacity();
+        if old_capacity <= self.min_capacity {
+            return 0;
+        }
+
+        if self.listeners.len() < self.min_capacity {
+            // Keep the requested capacity, `Vec::shrink_to()` needs Rust 1.56.
+            let mut listeners = Vec::with_capacity(self.min_capacity);
+            listeners.append(&mut self.listeners);
+            self.listeners = listeners;
+        } else {
+            self.listeners.shrink_to_fit();
+        }
+
+        old_capacity.saturating_sub(self.listeners.capacity()) * mem::size_of::<Entry<T>>()
+    }
+
+    /// Releases the memory of unused slots.
+    ///
+    /// If the slab holds no entries, it is truncated down to the sentinel. Otherwise, empty slots
+    /// are trimmed from the end of the slab. Returns the number of bytes that were released.
+    pub(crate) fn shrink_to_fit(&mut self) -> usize {
+        if self.len == 0 && self.orphans == 0 {
+            // Every slot is empty, so the chain of empty slots can be dropped altogether.
+            self.listeners.truncate(1);
+            self.first_empty = unsafe { NonZeroUsize::new_unchecked(1) };
+        } else {
+            self.compact_free_list();
+        }
+
+        self.shrink()
+    }
+
+    /// Releases the memory of a large slab once it holds no entries.
+    fn shrink_if_empty(&mut self) {
+        if self.len == 0 && self.orphans == 0 && self.listeners.len() > SHRINK_THRESHOLD {
+            self.shrink_to_fit();
+        }
+    }
+
+    /// Check that the links, the counters and the chain of empty slots are consistent.
+    pub(crate) fn validate(&self) -> Result<(), SlabError> {
+        let mut len = 0;
+        let mut notified = 0;
+        let mut found_start = self.start.is_none();
+        let mut prev = None;
+        let mut current = self.head;
+
+        while let Some(key) = current {
+            let entry = match self.listeners.get(key.get()) {
+                Some(entry @ Entry::Listener { .. }) => entry,
+                _ => return Err(SlabError::BrokenLink),
+            };
+
+            if entry.prev().get() != prev {
+                return Err(SlabError::BrokenLink);
+            }
+
+            if self.start == Some(key) {
+                found_start = true;
+            }
 
-        // Unlink from the previous entry.
-        match prev {
-            None => self.head = next,
-            Some(p) => self.listeners[p.get()].next().set(next),
-        }
+            if TakenState::new(entry.state()).state.is_notified() {
+                notified += 1;
+            }
 
-        // Unlink from the next entry.
-        match next {
-            None => self.tail = prev,
-            Some(n) => self.listeners[n.get()].prev().set(prev),
+            // A cycle would make the list longer than the slab.
+            len += 1;
+            if len >= self.listeners.len() {
+                return Err(SlabError::BrokenLink);
+            }
+
+            prev = Some(key);
+            current = entry.next().get();
         }
 
-        // If this was the first unnotified entry, move the pointer to the next one.
-        if self.start == Some(key) {
-            self.start = next;
+        if prev != self.tail {
+            return Err(SlabError::TailMismatch);
         }
 
-        // Extract the state.
-        let entry = mem::replace(
-            &mut self.listeners[key.get()],
-            Entry::Empty(self.first_empty),
-        );
-        self.first_empty = key;
+        if !found_start {
+            return Err(SlabError::DanglingStart);
+        }
 
-        let state = match entry {
-            Entry::Listener { state, .. } => state.into_inner(),
-            _ => unreachable!(),
-        };
+        if len != self.len {
+            return Err(SlabError::LengthMismatch {
+                expected: self.len,
+                found: len,
+            });
+        }
 
-        // Update the counters.
-        if state.is_notified() {
-            self.notified = self.notified.saturating_sub(1);
+        if notified != self.notified {
+            return Err(SlabError::NotifiedMismatch {
+                expected: self.notified,
+                found: notified,
+            });
+        }
 
-            if propogate {
-                // Propogate the notification to the next entry.
-                if let State::Notified(additional) = state {
-                    self.notify(1, additional);
+        // Every empty slot must be reachable through the free list.
+        let empty = self
+            .listeners
+            .iter()
+            .filter(|entry| match entry {
+                Entry::Empty(_) => true,
+                _ => false,
+            })
+            .count();
+        let mut visited = 0;
+        let mut current = self.first_empty;
+        while current.get() != self.listeners.len() {
+            match self.listeners.get(current.get()) {
+                Some(Entry::Empty(next)) if visited < empty => {
+                    visited += 1;
+                    current = *next;
                 }
+                _ => return Err(SlabError::FreeListCorrupted),
             }
         }
-        self.len -= 1;
-
-        Some(state)
-    }
 
-    /// Notifies a number of listeners.
-    #[cold]
-    pub(crate) fn notify(&mut self, mut n: usize, additional: bool) {
-        if !additional {
-            // Make sure we're not notifying more than we have.
-            if n <= self.notified {
-                return;
-            }
-            n -= self.notified;
+        if visited != empty {
+            return Err(SlabError::FreeListCorrupted);
         }
 
-        while n > 0 {
-            n -= 1;
+        Ok(())
+    }
 
-            // Notify the next entry.
-            match self.start {
-                None => break,
+    /// Register a task to be notified when the event is triggered, keeping a notified listener
+    /// registered.
+    ///
+    /// A notified listener has its notification consumed and is moved back in line in its slot,
+    /// with the task registered. Listeners that were drained or shut down are handled like in
+    /// `register`, and then inserted again with their priority.
+    pub(crate) fn register_renew(
+        &mut self,
+        mut listener: Pin<&mut Option<Listener>>,
+        task: TaskRef<'_>,
+    ) -> RegisterResult<T> {
+        let mut priority = 0;
 
-                Some(e) => {
-                    // Get the entry and move the pointer forwards.
-                    let entry = &self.listeners[e.get()];
-                    self.start = entry.next().get();
+        if let Some(Listener::HasNode(key)) = *listener {
+            let key = self.forward(key);
+            *listener = Some(Listener::HasNode(key));
 
-                    // Set the state to `Notified` and notify.
-                    if let State::Task(task) = entry.state().replace(State::Notified(additional)) {
-                        task.wake();
-                    }
+            if let Entry::Orphan(orphan_priority) = self.listeners[key.get()] {
+                priority = orphan_priority;
+            }
 
-                    // Bump the notified count.
-                    self.notified += 1;
+            if let Entry::Listener { .. } = self.listeners[key.get()] {
+                let state = self.listeners[key.get()]
+                    .state()
+                    .replace(State::NotifiedTaken);
+
+                if state.is_notified() {
+                    // Consume the notification and wait for the next one, unless the entry was
+                    // notified again right away.
+                    self.reset(key);
+                    let entry = &self.listeners[key.get()];
+                    if state_kind(entry.state()) == ListenerState::Created {
+                        entry.state().set(State::Task(task.into_task()));
+                        entry.waiting_since().set(self.next_wait);
+                        self.next_wait = self.next_wait.wrapping_add(1);
+                    }
+                    return RegisterResult::Notified(state.trace_id(), state.into_tag());
                 }
+
+                self.listeners[key.get()].state().set(state);
             }
         }
+
+        let result = self.register(listener.as_mut(), task);
+
+        // A listener that was drained or shut down is removed instead, so insert it again.
+        if let RegisterResult::Notified(..) | RegisterResult::Shutdown = result {
+            let key = self.listen(priority);
+            *listener = Some(Listener::HasNode(key));
+        }
+
+        result
     }
 
     /// Register a task to be notified when the event is triggered.
     ///
-    /// Returns `true` if the listener was already notified, and `false` otherwise. If the listener
-    /// isn't inserted, returns `None`.
     pub(crate) fn register(
         &mut self,
         mut listener: Pin<&mut Option<Listener>>,
         task: TaskRef<'_>,
-    ) -> Option<bool> {
+    ) -> RegisterResult<T> {
         let key = match *listener {
-            Some(Listener::HasNode(key)) => key,
-            _ => return None,
+            Some(Listener::HasNode(key)) => self.forward(key),
+            _ => return RegisterResult::NeverInserted,
         };
+        *listener = Some(Listener::HasNode(key));
+
+        if let Entry::Orphan(_) = self.listeners[key.get()] {
+            // The listener was drained, which counts as a notification.
+            self.remove(key, false);
+            *listener = None;
+            return RegisterResult::Notified(TraceId::default(), None);
+        }
+
+        if let Entry::Shutdown = self.listeners[key.get()] {
+            // The event was shut down before the listener was notified.
+            self.remove(key, false);
+            *listener = None;
+            return RegisterResult::Shutdown;
+        }
 
         let entry = &self.listeners[key.get()];
 
         // Take the state out and check it.
         match entry.state().replace(State::NotifiedTaken) {
-            State::Notified(_) | State::NotifiedTaken => {
+            state @ State::Notified(..)
+            | state @ State::NotifiedWithTask(..)
+            | state @ State::NotifiedTaken
+            | state @ State::Permit => {
                 // The listener was already notified, so we don't need to do anything.
-                self.remove(key, false)?;
+                self.remove(key, false);
                 *listener = None;
-                Some(true)
+                RegisterResult::Notified(state.trace_id(), state.into_tag())
+            }
+
+            State::Shutdown => {
+                // The listener was restored as shut down.
+                self.remove(key, false);
+                *listener = None;
+                RegisterResult::Shutdown
             }
 
             State::Task(other_task) => {
@@ -594,18 +2969,30 @@ impl ListenerSlab {
                     entry.state().set(State::Task(task.into_task()));
                 }
 
-                Some(false)
+                RegisterResult::Registered
             }
 
             _ => {
                 // Register the task.
                 entry.state().set(State::Task(task.into_task()));
-                Some(false)
+                entry.waiting_since().set(self.next_wait);
+                self.next_wait = self.next_wait.wrapping_add(1);
+                RegisterResult::Registered
             }
         }
     }
 }
 
+/// The identity of a listener in the slab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Handle {
+    /// The index of the slot.
+    key: NonZeroUsize,
+
+    /// The identifier of the listener, which distinguishes reuses of the same slot.
+    id: usize,
+}
+
 #[derive(Debug)]
 pub(crate) enum Listener {
     /// The listener has a node inside of the linked list.
@@ -625,44 +3012,69 @@ impl PartialEq for Listener {
     }
 }
 
-/// A simple mutex type that optimistically assumes that the lock is uncontended.
-pub(crate) struct Mutex<T> {
-    /// The inner value.
-    value: UnsafeCell<T>,
+/// The lock used by [`Mutex`] unless another one is requested.
+#[cfg(not(feature = "blocking-mutex"))]
+pub(crate) type DefaultLock = SpinLock;
 
-    /// Whether the mutex is locked.
-    locked: AtomicBool,
+/// The lock used by [`Mutex`] unless another one is requested.
+#[cfg(feature = "blocking-mutex")]
+pub(crate) type DefaultLock = BlockingLock;
+
+/// A raw lock that can back a [`Mutex`].
+///
+/// The mutex is generic over its lock rather than using a trait object, so that the uncontended
+/// path is inlined.
+pub(crate) trait RawLock {
+    /// Create a new, unlocked lock.
+    fn new(spin_budget: u32) -> Self;
+
+    /// Lock without waiting, returning `true` if the lock was acquired.
+    fn try_lock_once(&self) -> bool;
+
+    /// Lock, waiting for a contended lock as long as the lock's own budget allows.
+    fn try_lock(&self) -> bool;
+
+    /// Lock, spinning at most `spins` times if a spinning lock is contended.
+    fn try_lock_with_budget(&self, spins: u32) -> bool;
+
+    /// Lock, waiting until the lock becomes available.
+    fn lock(&self);
+
+    /// Unlock the lock.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held by the caller.
+    unsafe fn unlock(&self);
 }
 
-impl<T> Mutex<T> {
-    /// Create a new mutex.
-    pub(crate) fn new(value: T) -> Self {
-        Self {
-            value: UnsafeCell::new(value),
-            locked: AtomicBool::new(false),
-        }
-    }
+/// The maximum number of spins between two checks of a contended lock.
+#[cfg(not(feature = "blocking-mutex"))]
+const MAX_BACKOFF: u32 = 16;
+
+/// A spinlock that optimistically assumes that it is uncontended.
+///
+/// A contended lock is given up once the spin budget is exhausted, so that the operation can be
+/// queued instead.
+#[cfg(not(feature = "blocking-mutex"))]
+pub(crate) struct SpinLock {
+    /// Whether the lock is held.
+    locked: AtomicBool,
 
-    /// Lock the mutex.
-    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
-        // Try to lock the mutex.
-        if self
-            .locked
-            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
-            .is_ok()
-        {
-            // We have successfully locked the mutex.
-            Some(MutexGuard { mutex: self })
-        } else {
-            self.try_lock_slow()
-        }
-    }
+    /// The number of times to spin on a held lock before giving up.
+    spin_budget: u32,
+}
 
+#[cfg(not(feature = "blocking-mutex"))]
+impl SpinLock {
     #[cold]
-    fn try_lock_slow(&self) -> Option<MutexGuard<'_, T>> {
+    fn try_lock_slow(&self, mut spins: u32) -> bool {
         // Assume that the contention is short-term.
         // Spin for a while to see if the mutex becomes unlocked.
-        let mut spins = 100u32;
+        //
+        // The budget bounds the total number of iterations, so that neither a long hold nor
+        // other threads racing us for the lock keep us spinning indefinitely.
+        let mut backoff = 1;
 
         loop {
             if self
@@ -671,29 +3083,267 @@ impl<T> Mutex<T> {
                 .is_ok()
             {
                 // We have successfully locked the mutex.
-                return Some(MutexGuard { mutex: self });
+                return true;
             }
 
-            // Use atomic loads instead of compare-exchange.
-            while self.locked.load(Ordering::Relaxed) {
-                // Return None once we've exhausted the number of spins.
-                spins = spins.checked_sub(1)?;
+            // Use atomic loads instead of compare-exchange, backing off exponentially between
+            // them so the holder and other spinners get a chance to run.
+            loop {
+                for _ in 0..backoff {
+                    // Give up once we've exhausted the number of spins.
+                    spins = match spins.checked_sub(1) {
+                        Some(spins) => spins,
+                        None => return false,
+                    };
+
+                    #[allow(deprecated)]
+                    core::sync::atomic::spin_loop_hint();
+                }
+
+                if backoff < MAX_BACKOFF {
+                    backoff *= 2;
+                }
+
+                if !self.locked.load(Ordering::Relaxed) {
+                    break;
+                }
             }
         }
     }
 }
 
-pub(crate) struct MutexGuard<'a, T> {
-    mutex: &'a Mutex<T>,
+#[cfg(not(feature = "blocking-mutex"))]
+impl RawLock for SpinLock {
+    fn new(spin_budget: u32) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            spin_budget,
+        }
+    }
+
+    fn try_lock_once(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    fn try_lock(&self) -> bool {
+        self.try_lock_once() || self.try_lock_slow(self.spin_budget)
+    }
+
+    fn try_lock_with_budget(&self, spins: u32) -> bool {
+        self.try_lock_once() || self.try_lock_slow(spins)
+    }
+
+    fn lock(&self) {
+        while !self.try_lock() {
+            // Loom only lets the holder run if we yield.
+            #[cfg(loom)]
+            loom::thread::yield_now();
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        // This has to be a `Release` store even if nothing was written, since the reads made
+        // under the lock must not be reordered past the unlock and race with the next owner.
+        self.locked.store(false, Ordering::Release);
+    }
+}
+
+/// A lock that parks the thread while it is contended, instead of spinning.
+///
+/// A thread that already holds the lock, e.g. because a waker woken under the lock notifies the
+/// same event, still fails to lock it, so the operation is queued rather than deadlocking.
+/// Operations that must wait for the lock, such as those finding the queue full, panic instead.
+#[cfg(feature = "blocking-mutex")]
+pub(crate) struct BlockingLock {
+    /// Whether the lock is held.
+    locked: AtomicBool,
+
+    /// The thread holding the lock, or `0` if the lock isn't held.
+    owner: AtomicUsize,
+
+    /// The number of threads parked on the lock.
+    waiters: AtomicUsize,
+
+    /// The mutex the parked threads wait on.
+    parked: std::sync::Mutex<()>,
+
+    /// Signalled when the lock is released and there are parked threads.
+    unparked: std::sync::Condvar,
+}
+
+#[cfg(feature = "blocking-mutex")]
+impl BlockingLock {
+    /// An identifier of the current thread, which is never `0`.
+    fn current_thread() -> usize {
+        std::thread_local! {
+            static ID: u8 = 0;
+        }
+
+        // The address of a thread local is unique among the running threads.
+        ID.try_with(|id| id as *const u8 as usize).unwrap_or(1)
+    }
+
+    /// Park until the lock is acquired.
+    #[cold]
+    fn park(&self) {
+        let mut parked = self
+            .parked
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        // Register as a waiter before the last check, so that an unlock in between sees us.
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        while self
+            .locked
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            parked = self
+                .unparked
+                .wait(parked)
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+
+        self.owner.store(Self::current_thread(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "blocking-mutex")]
+impl RawLock for BlockingLock {
+    fn new(_spin_budget: u32) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            owner: AtomicUsize::new(0),
+            waiters: AtomicUsize::new(0),
+            parked: std::sync::Mutex::new(()),
+            unparked: std::sync::Condvar::new(),
+        }
+    }
+
+    fn try_lock_once(&self) -> bool {
+        let locked = self
+            .locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok();
+        if locked {
+            self.owner.store(Self::current_thread(), Ordering::Relaxed);
+        }
+        locked
+    }
+
+    fn try_lock(&self) -> bool {
+        if self.try_lock_once() {
+            return true;
+        }
+
+        // Only this thread stores its own id, so it's only seen while this thread holds the lock.
+        if self.owner.load(Ordering::Relaxed) == Self::current_thread() {
+            return false;
+        }
+
+        self.park();
+        true
+    }
+
+    fn try_lock_with_budget(&self, _spins: u32) -> bool {
+        self.try_lock()
+    }
+
+    fn lock(&self) {
+        if self.try_lock_once() {
+            return;
+        }
+
+        // Parking would never return, as the lock is held by this very thread.
+        if self.owner.load(Ordering::Relaxed) == Self::current_thread() {
+            panic!("the event was locked again by the thread holding its lock, e.g. from a waker");
+        }
+
+        self.park();
+    }
+
+    unsafe fn unlock(&self) {
+        self.owner.store(0, Ordering::Relaxed);
+        self.locked.store(false, Ordering::SeqCst);
+
+        // Wake a parked thread. Taking the mutex makes sure it's either waiting or hasn't
+        // registered yet, in which case it sees the lock released.
+        if self.waiters.load(Ordering::SeqCst) != 0 {
+            drop(self.parked.lock());
+            self.unparked.notify_one();
+        }
+    }
+}
+
+/// A mutex protecting a value with a [`RawLock`].
+pub(crate) struct Mutex<T, L = DefaultLock> {
+    /// The inner value.
+    value: UnsafeCell<T>,
+
+    /// The lock protecting the value.
+    lock: L,
+}
+
+impl<T, L: RawLock> Mutex<T, L> {
+    /// Create a new mutex.
+    pub(crate) fn new(value: T, spin_budget: u32) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+            lock: L::new(spin_budget),
+        }
+    }
+
+    /// Lock the mutex.
+    #[inline]
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T, L>> {
+        self.guard(self.lock.try_lock())
+    }
+
+    /// Lock the mutex, spinning at most `spins` times if it is locked.
+    #[inline]
+    pub(crate) fn try_lock_with_budget(&self, spins: u32) -> Option<MutexGuard<'_, T, L>> {
+        self.guard(self.lock.try_lock_with_budget(spins))
+    }
+
+    /// Lock the mutex without spinning.
+    #[inline]
+    pub(crate) fn try_lock_once(&self) -> Option<MutexGuard<'_, T, L>> {
+        self.guard(self.lock.try_lock_once())
+    }
+
+    /// Lock the mutex, waiting until it becomes available.
+    #[inline]
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T, L> {
+        self.lock.lock();
+        MutexGuard { mutex: self }
+    }
+
+    /// Wrap an acquired lock in a guard.
+    #[inline]
+    fn guard(&self, locked: bool) -> Option<MutexGuard<'_, T, L>> {
+        if locked {
+            Some(MutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) struct MutexGuard<'a, T, L: RawLock = DefaultLock> {
+    mutex: &'a Mutex<T, L>,
 }
 
-impl<'a, T> Drop for MutexGuard<'a, T> {
+impl<'a, T, L: RawLock> Drop for MutexGuard<'a, T, L> {
     fn drop(&mut self) {
-        self.mutex.locked.store(false, Ordering::Release);
+        // SAFETY: The guard is only created once the lock is acquired.
+        unsafe { self.mutex.lock.unlock() }
     }
 }
 
-impl<'a, T> ops::Deref for MutexGuard<'a, T> {
+impl<'a, T, L: RawLock> ops::Deref for MutexGuard<'a, T, L> {
     type Target = T;
 
     fn deref(&self) -> &T {
@@ -701,41 +3351,179 @@ impl<'a, T> ops::Deref for MutexGuard<'a, T> {
     }
 }
 
-impl<'a, T> ops::DerefMut for MutexGuard<'a, T> {
+impl<'a, T, L: RawLock> ops::DerefMut for MutexGuard<'a, T, L> {
     fn deref_mut(&mut self) -> &mut T {
         unsafe { &mut *self.mutex.value.get() }
     }
-}
+}
+
+unsafe impl<T: Send, L: Send> Send for Mutex<T, L> {}
+unsafe impl<T: Send, L: Sync> Sync for Mutex<T, L> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Task;
+
+    #[test]
+    fn smoke_mutex() {
+        let mutex = Mutex::<_>::new(0, crate::DEFAULT_SPIN_BUDGET);
+
+        {
+            let mut guard = mutex.try_lock().unwrap();
+            *guard += 1;
+        }
+
+        {
+            let mut guard = mutex.try_lock().unwrap();
+            *guard += 1;
+        }
+
+        let guard = mutex.try_lock().unwrap();
+        assert_eq!(*guard, 2);
+    }
+
+    #[test]
+    fn mutex_spin_budget() {
+        for &spins in &[0, 100, 1000] {
+            let mutex = Mutex::<_>::new(0, spins);
+
+            // A held lock makes `try_lock` give up once the budget is exhausted.
+            let guard = mutex.try_lock().unwrap();
+            assert!(mutex.try_lock().is_none());
+            drop(guard);
+
+            assert!(mutex.try_lock().is_some());
+        }
+    }
+
+    #[test]
+    fn mutex_spin_budget_under_churn() {
+        let mutex = Arc::new(Mutex::<usize>::new(0, 10));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // Keep taking and releasing the lock, so attempts to lock it keep racing.
+        let handle = std::thread::spawn({
+            let mutex = mutex.clone();
+            let stop = stop.clone();
+            move || {
+                let mut taken = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(mut guard) = mutex.try_lock() {
+                        *guard += 1;
+                        taken += 1;
+                    }
+                }
+                taken
+            }
+        });
+
+        // Every attempt gives up after a bounded amount of work, however the race goes.
+        let mut taken = 0;
+        for _ in 0..1000 {
+            if let Some(mut guard) = mutex.try_lock() {
+                *guard += 1;
+                taken += 1;
+            }
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        let other = handle.join().unwrap();
+
+        // Every acquisition was exclusive, and the lock was released after each of them.
+        assert_eq!(*mutex.try_lock().unwrap(), taken + other);
+    }
+
+    #[test]
+    fn notify_with_held_lock() {
+        let inner = crate::Inner::<()>::new(10, 8, 0);
+
+        let mut listener = None;
+        inner.insert(Pin::new(&mut listener), 0);
+
+        // The lock is never released while notifying, so the notification is queued once the
+        // spin budget is used up.
+        let guard = inner.list.inner.lock();
+        inner.notify(1, false);
+        assert_eq!(inner.list.queue.len(), 1);
+        drop(guard);
+
+        let mut list = inner.lock();
+        list.apply_queue();
+        assert_eq!(list.notified, 1);
+        assert_eq!(
+            state_kind(list.listeners[1].state()),
+            ListenerState::Notified(false)
+        );
+    }
 
-unsafe impl<T: Send> Send for Mutex<T> {}
-unsafe impl<T: Send> Sync for Mutex<T> {}
+    #[cfg(feature = "blocking-mutex")]
+    #[test]
+    fn blocking_mutex_parks() {
+        let mutex = Arc::new(Mutex::<_>::new(0, 0));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Task;
+        // The thread holding the lock fails to lock it again instead of deadlocking.
+        let guard = mutex.try_lock().unwrap();
+        assert!(mutex.try_lock().is_none());
+
+        // Other threads park until the lock is released, whatever the spin budget.
+        let handles = (0..4)
+            .map(|_| {
+                let mutex = mutex.clone();
+                std::thread::spawn(move || *mutex.try_lock_with_budget(0).unwrap() += 1)
+            })
+            .collect::<Vec<_>>();
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        drop(guard);
+        for handle in handles {
+            handle.join().unwrap();
+        }
 
+        assert_eq!(*mutex.try_lock().unwrap(), 4);
+    }
+
+    #[cfg(feature = "blocking-mutex")]
     #[test]
-    fn smoke_mutex() {
-        let mutex = Mutex::new(0);
+    #[should_panic(expected = "locked again by the thread holding its lock")]
+    fn blocking_mutex_reentrant_lock() {
+        let mutex = Mutex::<_>::new(0, 0);
 
-        {
-            let mut guard = mutex.try_lock().unwrap();
-            *guard += 1;
-        }
+        let _guard = mutex.try_lock().unwrap();
+        let _ = mutex.lock();
+    }
 
-        {
-            let mut guard = mutex.try_lock().unwrap();
-            *guard += 1;
-        }
+    #[test]
+    fn notify_skips_taken() {
+        let mut listeners = ListenerSlab::<()>::new();
+        let key1 = listeners.insert(State::Created);
+        let key2 = listeners.insert(State::Created);
+        let key3 = listeners.insert(State::Created);
 
-        let guard = mutex.try_lock().unwrap();
-        assert_eq!(*guard, 2);
+        // The first listener is notified and its notification is being consumed.
+        assert_eq!(listeners.notify(1, false, TraceId::default(), None), 1);
+        listeners.listeners[key1.get()]
+            .state()
+            .set(State::NotifiedTaken);
+
+        // An additional notification goes to the next listener instead.
+        assert_eq!(listeners.notify(1, true, TraceId::default(), None), 1);
+        assert_eq!(
+            listeners.listeners[key1.get()]
+                .state()
+                .replace(State::Created),
+            State::NotifiedTaken
+        );
+        assert!(TakenState::new(listeners.listeners[key2.get()].state())
+            .state
+            .is_notified());
+        assert_eq!(listeners.notified, 2);
+        assert_eq!(listeners.start, Some(key3));
     }
 
     #[test]
     fn smoke_listener_slab() {
-        let mut listeners = ListenerSlab::new();
+        let mut listeners = ListenerSlab::<()>::new();
 
         // Insert a few listeners.
         let key1 = listeners.insert(State::Created);
@@ -752,6 +3540,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key2)),
@@ -760,6 +3554,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(Some(key3)),
@@ -768,6 +3568,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -787,6 +3593,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key3)),
@@ -799,6 +3611,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(None),
@@ -806,9 +3624,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn listener_slab_insert_front() {
+        let mut listeners = ListenerSlab::<()>::new();
+
+        // Insert a listener, then one at the front.
+        let key1 = listeners.insert(State::Created);
+        let key2 = listeners.insert_front(State::Created);
+
+        assert_eq!(listeners.len, 2);
+        assert_eq!(listeners.notified, 0);
+        assert_eq!(listeners.tail, Some(key1));
+        assert_eq!(listeners.head, Some(key2));
+        assert_eq!(listeners.start, Some(key2));
+        assert_eq!(
+            listeners.listeners[1],
+            Entry::Listener {
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Created),
+                prev: Cell::new(Some(key2)),
+                next: Cell::new(None),
+            }
+        );
+        assert_eq!(
+            listeners.listeners[2],
+            Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Created),
+                prev: Cell::new(None),
+                next: Cell::new(Some(key1)),
+            }
+        );
+
+        // Notify the front listener.
+        listeners.notify(1, false, TraceId::default(), None);
+        assert_eq!(listeners.notified, 1);
+        assert_eq!(listeners.start, Some(key1));
+
+        // The next listener at the front goes behind the notified one.
+        let key3 = listeners.insert_front(State::Created);
+
+        assert_eq!(listeners.len, 3);
+        assert_eq!(listeners.tail, Some(key1));
+        assert_eq!(listeners.head, Some(key2));
+        assert_eq!(listeners.start, Some(key3));
+        assert_eq!(
+            listeners.listeners[3],
+            Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Created),
+                prev: Cell::new(Some(key2)),
+                next: Cell::new(Some(key1)),
+            }
+        );
+
+        // Without unnotified listeners, the front is the end of the list.
+        listeners.notify(2, true, TraceId::default(), None);
+        assert_eq!(listeners.start, None);
+
+        let key4 = listeners.insert_front(State::Created);
+        assert_eq!(listeners.tail, Some(key4));
+        assert_eq!(listeners.start, Some(key4));
+    }
+
     #[test]
     fn listener_slab_notify() {
-        let mut listeners = ListenerSlab::new();
+        let mut listeners = ListenerSlab::<()>::new();
 
         // Insert a few listeners.
         let key1 = listeners.insert(State::Created);
@@ -816,7 +3712,7 @@ mod tests {
         let key3 = listeners.insert(State::Created);
 
         // Notify one.
-        listeners.notify(1, true);
+        listeners.notify(1, true, TraceId::default(), None);
 
         assert_eq!(listeners.len, 3);
         assert_eq!(listeners.notified, 1);
@@ -828,7 +3724,13 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
-                state: Cell::new(State::Notified(true)),
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Notified(true, TraceId::default(), None)),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key2)),
             }
@@ -836,6 +3738,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(Some(key3)),
@@ -844,6 +3752,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -851,7 +3765,10 @@ mod tests {
         );
 
         // Remove the notified listener.
-        assert_eq!(listeners.remove(key1, false), Some(State::Notified(true)));
+        assert_eq!(
+            listeners.remove(key1, false),
+            Some(State::Notified(true, TraceId::default(), None))
+        );
 
         assert_eq!(listeners.len, 2);
         assert_eq!(listeners.notified, 0);
@@ -867,6 +3784,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key3)),
@@ -875,6 +3798,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -890,7 +3819,7 @@ mod tests {
             move || woken.store(true, Ordering::SeqCst)
         });
 
-        let mut listeners = ListenerSlab::new();
+        let mut listeners = ListenerSlab::<()>::new();
 
         // Insert a few listeners.
         let key1 = listeners.insert(State::Created);
@@ -903,7 +3832,7 @@ mod tests {
                 Pin::new(&mut Some(Listener::HasNode(key2))),
                 TaskRef::Waker(&waker)
             ),
-            Some(false)
+            RegisterResult::Registered
         );
 
         assert_eq!(listeners.len, 3);
@@ -916,6 +3845,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key2)),
@@ -924,6 +3859,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Task(Task::Waker(waker.clone()))),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(Some(key3)),
@@ -932,6 +3873,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -939,7 +3886,7 @@ mod tests {
         );
 
         // Notify the listener.
-        listeners.notify(2, false);
+        listeners.notify(2, false, TraceId::default(), None);
 
         assert_eq!(listeners.len, 3);
         assert_eq!(listeners.notified, 2);
@@ -951,7 +3898,13 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
-                state: Cell::new(State::Notified(false)),
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Notified(false, TraceId::default(), None)),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key2)),
             }
@@ -959,7 +3912,13 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
-                state: Cell::new(State::Notified(false)),
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Notified(false, TraceId::default(), None)),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(Some(key3)),
             }
@@ -967,6 +3926,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -979,7 +3944,7 @@ mod tests {
                 Pin::new(&mut Some(Listener::HasNode(key2))),
                 TaskRef::Waker(&waker)
             ),
-            Some(true)
+            RegisterResult::Notified(TraceId::default(), None)
         );
     }
 
@@ -991,7 +3956,7 @@ mod tests {
             move || woken.store(true, Ordering::SeqCst)
         });
 
-        let mut listeners = ListenerSlab::new();
+        let mut listeners = ListenerSlab::<()>::new();
 
         // Insert a few listeners.
         let key1 = listeners.insert(State::Created);
@@ -1004,7 +3969,7 @@ mod tests {
                 Pin::new(&mut Some(Listener::HasNode(key2))),
                 TaskRef::Waker(&waker)
             ),
-            Some(false)
+            RegisterResult::Registered
         );
 
         assert_eq!(listeners.len, 3);
@@ -1017,6 +3982,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key2)),
@@ -1025,6 +3996,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Task(Task::Waker(waker.clone()))),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(Some(key3)),
@@ -1033,6 +4010,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -1040,7 +4023,7 @@ mod tests {
         );
 
         // Notify the first listener.
-        listeners.notify(1, false);
+        listeners.notify(1, false, TraceId::default(), None);
 
         assert_eq!(listeners.len, 3);
         assert_eq!(listeners.notified, 1);
@@ -1052,7 +4035,13 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
-                state: Cell::new(State::Notified(false)),
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Notified(false, TraceId::default(), None)),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key2)),
             }
@@ -1060,6 +4049,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Task(Task::Waker(waker.clone()))),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(Some(key3)),
@@ -1068,6 +4063,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -1075,7 +4076,7 @@ mod tests {
         );
 
         // Calling notify again should not change anything.
-        listeners.notify(1, false);
+        listeners.notify(1, false, TraceId::default(), None);
 
         assert_eq!(listeners.len, 3);
         assert_eq!(listeners.notified, 1);
@@ -1087,7 +4088,13 @@ mod tests {
         assert_eq!(
             listeners.listeners[1],
             Entry::Listener {
-                state: Cell::new(State::Notified(false)),
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Notified(false, TraceId::default(), None)),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key2)),
             }
@@ -1095,6 +4102,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Task(Task::Waker(waker.clone()))),
                 prev: Cell::new(Some(key1)),
                 next: Cell::new(Some(key3)),
@@ -1103,6 +4116,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -1110,7 +4129,10 @@ mod tests {
         );
 
         // Remove the first listener.
-        assert_eq!(listeners.remove(key1, false), Some(State::Notified(false)));
+        assert_eq!(
+            listeners.remove(key1, false),
+            Some(State::Notified(false, TraceId::default(), None))
+        );
 
         assert_eq!(listeners.len, 2);
         assert_eq!(listeners.notified, 0);
@@ -1126,6 +4148,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Task(Task::Waker(waker))),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key3)),
@@ -1134,6 +4162,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -1141,7 +4175,7 @@ mod tests {
         );
 
         // Notify the second listener.
-        listeners.notify(1, false);
+        listeners.notify(1, false, TraceId::default(), None);
         assert!(woken.load(Ordering::SeqCst));
 
         assert_eq!(listeners.len, 2);
@@ -1158,7 +4192,13 @@ mod tests {
         assert_eq!(
             listeners.listeners[2],
             Entry::Listener {
-                state: Cell::new(State::Notified(false)),
+                id: 2,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Notified(false, TraceId::default(), None)),
                 prev: Cell::new(None),
                 next: Cell::new(Some(key3)),
             }
@@ -1166,6 +4206,12 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
                 state: Cell::new(State::Created),
                 prev: Cell::new(Some(key2)),
                 next: Cell::new(None),
@@ -1173,7 +4219,10 @@ mod tests {
         );
 
         // Remove and propogate the second listener.
-        assert_eq!(listeners.remove(key2, true), Some(State::Notified(false)));
+        assert_eq!(
+            listeners.remove(key2, true),
+            Some(State::Notified(false, TraceId::default(), None))
+        );
 
         // The third listener should be notified.
         assert_eq!(listeners.len, 1);
@@ -1194,25 +4243,34 @@ mod tests {
         assert_eq!(
             listeners.listeners[3],
             Entry::Listener {
-                state: Cell::new(State::Notified(false)),
+                id: 3,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::Notified(false, TraceId::default(), None)),
                 prev: Cell::new(None),
                 next: Cell::new(None),
             }
         );
 
         // Remove the third listener.
-        assert_eq!(listeners.remove(key3, false), Some(State::Notified(false)));
+        assert_eq!(
+            listeners.remove(key3, false),
+            Some(State::Notified(false, TraceId::default(), None))
+        );
     }
 
     #[test]
     fn uncontended_inner() {
-        let inner = crate::Inner::new();
+        let inner = crate::Inner::<()>::new(crate::DEFAULT_SPIN_BUDGET, core::usize::MAX, 0);
 
         // Register two listeners.
         let (mut listener1, mut listener2, mut listener3) = (None, None, None);
-        inner.insert(Pin::new(&mut listener1));
-        inner.insert(Pin::new(&mut listener2));
-        inner.insert(Pin::new(&mut listener3));
+        inner.insert(Pin::new(&mut listener1), 0);
+        inner.insert(Pin::new(&mut listener2), 0);
+        inner.insert(Pin::new(&mut listener3), 0);
 
         assert_eq!(
             listener1,
@@ -1231,7 +4289,7 @@ mod tests {
         });
         assert_eq!(
             inner.register(Pin::new(&mut listener2), TaskRef::Waker(&waker)),
-            Some(false)
+            RegisterResult::Registered
         );
 
         // Notify the first listener.
@@ -1245,7 +4303,7 @@ mod tests {
         // Receive the notification.
         assert_eq!(
             inner.register(Pin::new(&mut listener1), TaskRef::Waker(&waker)),
-            Some(true)
+            RegisterResult::Notified(TraceId::default(), None)
         );
 
         // First listener is already removed.
@@ -1258,7 +4316,7 @@ mod tests {
         // Remove the second listener and propogate the notification.
         assert_eq!(
             inner.remove(Pin::new(&mut listener2), true),
-            Some(State::Notified(false))
+            Some(State::Notified(false, TraceId::default(), None))
         );
 
         // Second listener is already removed.
@@ -1267,7 +4325,742 @@ mod tests {
         // Third listener should be notified.
         assert_eq!(
             inner.register(Pin::new(&mut listener3), TaskRef::Waker(&waker)),
-            Some(true)
+            RegisterResult::Notified(TraceId::default(), None)
+        );
+    }
+
+    #[test]
+    fn maintain_after_churn() {
+        use core::future::Future;
+
+        let event = crate::Event::new();
+
+        // Register a burst of listeners and keep only the first one.
+        let mut listeners = (0..100).map(|_| event.listen()).collect::<Vec<_>>();
+        let mut first = listeners.remove(0);
+        event.notify(10);
+        drop(listeners);
+
+        let report = event.maintain();
+        assert_eq!(report.nodes_applied, 0);
+        assert_eq!(report.slots_compacted, 99);
+        assert!(report.bytes_reclaimed > 0);
+        assert_eq!(report.validation, Ok(()));
+
+        // The remaining listener is untouched.
+        let waker = waker_fn::waker_fn(|| ());
+        assert!(first
+            .as_mut()
+            .poll(&mut core::task::Context::from_waker(&waker))
+            .is_ready());
+    }
+
+    #[test]
+    fn compact() {
+        use core::future::Future;
+
+        let event = crate::Event::new();
+        let waker = waker_fn::waker_fn(|| ());
+        let mut cx = core::task::Context::from_waker(&waker);
+
+        // Free the slots in front of the remaining listeners.
+        let mut listeners = (0..6).map(|_| event.listen()).collect::<Vec<_>>();
+        drop(listeners.drain(..3));
+        assert_eq!(event.compact(), 3);
+        assert_eq!(event.compact(), 0);
+        assert_eq!(event.maintain().validation, Ok(()));
+
+        // The listeners pick up their new slots.
+        event.notify(2);
+        assert!(listeners[0].as_mut().poll(&mut cx).is_ready());
+        assert!(listeners[1].as_mut().poll(&mut cx).is_ready());
+        assert!(listeners[2].as_mut().poll(&mut cx).is_pending());
+        drop(listeners);
+
+        // Every slot was freed, including the ones the listeners were moved out of.
+        let report = event.maintain();
+        assert_eq!(report.slots_compacted, 6);
+        assert_eq!(report.validation, Ok(()));
+    }
+
+    #[test]
+    fn wake_by_ref_keeps_task() {
+        use crate::sync::atomic::AtomicUsize;
+
+        let woken = Arc::new(AtomicUsize::new(0));
+        let waker = waker_fn::waker_fn({
+            let woken = woken.clone();
+            move || {
+                woken.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        let mut listeners = ListenerSlab::<()>::new();
+        listeners.wake_strategy = WakeStrategy::ByRef;
+
+        let key = listeners.insert(State::Task(Task::Waker(waker.clone())));
+        listeners.notify(1, false, TraceId::default(), None);
+
+        assert_eq!(woken.load(Ordering::SeqCst), 1);
+        assert_eq!(listeners.notified, 1);
+        assert_eq!(
+            listeners.listeners[key.get()],
+            Entry::Listener {
+                id: 1,
+                priority: 0,
+                waiting_since: Cell::new(0),
+                epoch: 0,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                state: Cell::new(State::NotifiedWithTask(
+                    false,
+                    TraceId::default(),
+                    None,
+                    Task::Waker(waker)
+                )),
+                prev: Cell::new(None),
+                next: Cell::new(None),
+            }
+        );
+    }
+
+    #[test]
+    fn len_mirror_converges() {
+        let inner = crate::Inner::<()>::new(0, core::usize::MAX, 0);
+        let len = || inner.len.load(Ordering::Relaxed);
+
+        // Fast path.
+        let (mut listener1, mut listener2) = (None, None);
+        inner.insert(Pin::new(&mut listener1), 0);
+        assert_eq!(len(), 1);
+
+        // Slow path, while the list is locked.
+        let guard = inner.list.inner.lock();
+        inner.insert(Pin::new(&mut listener2), 0);
+        assert!(matches!(listener2, Some(Listener::Queued(_))));
+        drop(guard);
+        assert_eq!(len(), 1);
+
+        // Draining the queue updates the mirror.
+        drop(inner.lock());
+        assert_eq!(len(), 2);
+
+        // Removals on both paths.
+        inner.remove(Pin::new(&mut listener1), false);
+        assert_eq!(len(), 1);
+
+        let guard = inner.list.inner.lock();
+        listener2 = Some(Listener::HasNode(NonZeroUsize::new(2).unwrap()));
+        inner.remove(Pin::new(&mut listener2), false);
+        drop(guard);
+        drop(inner.lock());
+        assert_eq!(len(), 0);
+        assert_eq!(len(), inner.total_listeners());
+    }
+
+    #[test]
+    fn task_buffer_spills() {
+        use crate::sync::atomic::AtomicUsize;
+
+        let woken = Arc::new(AtomicUsize::new(0));
+        let waker = waker_fn::waker_fn({
+            let woken = woken.clone();
+            move || {
+                woken.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        // A few tasks are stored inline.
+        let mut tasks = TaskBuffer::new();
+        tasks.extend((0..INLINE_TASKS).map(|_| Task::Waker(waker.clone())));
+        assert_eq!(tasks.len, INLINE_TASKS);
+        assert_eq!(tasks.spilled.capacity(), 0);
+
+        // Larger batches spill to the heap.
+        tasks.push(Task::Waker(waker.clone()));
+        tasks.push(Task::Waker(waker));
+        assert_eq!(tasks.spilled.len(), 2);
+
+        tasks.wake();
+        assert_eq!(woken.load(Ordering::SeqCst), INLINE_TASKS + 2);
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut listeners = ListenerSlab::<()>::new();
+
+        // A burst of listeners that are all removed releases the slab.
+        let keys = (0..SHRINK_THRESHOLD * 2)
+            .map(|_| listeners.insert(State::Created))
+            .collect::<Vec<_>>();
+        for key in keys {
+            listeners.remove(key, false);
+        }
+        assert_eq!(listeners.listeners.len(), 1);
+        assert_eq!(listeners.first_empty.get(), 1);
+        assert_eq!(listeners.validate(), Ok(()));
+
+        // Slots are reused again from the start.
+        let key = listeners.insert(State::Created);
+        assert_eq!(key.get(), 1);
+
+        // While a listener is alive, only trailing empty slots are trimmed.
+        let keys = (0..4)
+            .map(|_| listeners.insert(State::Created))
+            .collect::<Vec<_>>();
+        for key in keys {
+            listeners.remove(key, false);
+        }
+        assert!(listeners.shrink_to_fit() > 0);
+        assert_eq!(listeners.listeners.len(), 2);
+        assert_eq!(listeners.validate(), Ok(()));
+
+        // Orphans keep their slots until their owners are removed.
+        let mut tasks = TaskBuffer::new();
+        listeners.drain_all(&mut tasks, false);
+        listeners.shrink_to_fit();
+        assert_eq!(listeners.listeners.len(), 2);
+        listeners.remove(key, false);
+        listeners.shrink_to_fit();
+        assert_eq!(listeners.listeners.len(), 1);
+        assert_eq!(listeners.validate(), Ok(()));
+    }
+
+    #[test]
+    fn reset_keeps_slot() {
+        let mut listeners = ListenerSlab::<()>::new();
+
+        let key1 = listeners.insert(State::Created);
+        let key2 = listeners.insert(State::Created);
+        listeners.notify(1, false, TraceId::default(), None);
+        assert_eq!(listeners.notified, 1);
+
+        // The notified entry moves behind the other one, in the same slot.
+        listeners.reset(key1);
+        assert_eq!(listeners.notified, 0);
+        assert_eq!(listeners.head, Some(key2));
+        assert_eq!(listeners.tail, Some(key1));
+        assert_eq!(listeners.start, Some(key2));
+        assert_eq!(listeners.len, 2);
+        assert_eq!(listeners.validate(), Ok(()));
+
+        listeners.notify(2, false, TraceId::default(), None);
+        assert_eq!(listeners.start, None);
+        listeners.reset(key2);
+        assert_eq!(listeners.start, Some(key2));
+        assert_eq!(listeners.tail, Some(key2));
+        assert_eq!(listeners.validate(), Ok(()));
+    }
+
+    #[cfg(not(miri))]
+    // A blocking lock parks the notifying thread instead of queueing.
+    #[cfg(not(feature = "blocking-mutex"))]
+    #[test]
+    fn queue_saturated() {
+        let inner = Arc::new(crate::Inner::<()>::new(0, 1, 0));
+
+        let (mut listener1, mut listener2) = (None, None);
+        inner.insert(Pin::new(&mut listener1), 0);
+        inner.insert(Pin::new(&mut listener2), 0);
+
+        // Hold the lock so that notifications are deferred.
+        let guard = inner.list.inner.lock();
+
+        let handle = std::thread::spawn({
+            let inner = inner.clone();
+            move || {
+                // The first notification is queued, the second one waits for the lock.
+                inner.notify(1, true);
+                inner.notify(1, true);
+            }
+        });
+
+        while !inner.list.saturated.load(Ordering::Relaxed) {
+            std::thread::yield_now();
+        }
+        drop(guard);
+        handle.join().unwrap();
+
+        assert!(inner.take_saturated());
+        assert!(!inner.take_saturated());
+        assert_eq!(inner.lock().notified, 2);
+    }
+
+    #[test]
+    fn prune_notified_deferred() {
+        let inner = crate::Inner::<()>::new(0, 8, 0);
+
+        let (mut listener1, mut listener2) = (None, None);
+        inner.insert(Pin::new(&mut listener1), 0);
+        inner.insert(Pin::new(&mut listener2), 0);
+        inner.notify(1, false);
+
+        // The list is contended, so the pruning is queued.
+        let guard = inner.list.inner.lock();
+        assert_eq!(inner.prune_notified(), 0);
+        drop(guard);
+
+        let mut list = inner.lock();
+        list.apply_queue();
+        assert_eq!(list.notified, 0);
+        assert_eq!(list.len, 1);
+        assert_eq!(list.listeners[1], Entry::Orphan(0));
+    }
+
+    #[test]
+    fn fill_stats() {
+        let inner = crate::Inner::<()>::new(0, 8, 0);
+
+        let (mut listener1, mut listener2, mut listener3) = (None, None, None);
+        inner.insert(Pin::new(&mut listener1), 0);
+        inner.insert(Pin::new(&mut listener2), 0);
+        inner.insert(Pin::new(&mut listener3), 0);
+        inner.remove(Pin::new(&mut listener2), false);
+
+        let mut stats = EventStats::default();
+        inner.fill_stats(&mut stats);
+        assert_eq!(stats.empty_slots, Some(1));
+        assert!(stats.slab_capacity.unwrap() >= 3);
+
+        // The slab is locked, so only the queue is visible.
+        let guard = inner.list.inner.lock();
+        inner.notify(1, true);
+
+        let mut stats = EventStats::default();
+        inner.fill_stats(&mut stats);
+        assert_eq!(stats.queue_len, 1);
+        assert_eq!(stats.slab_capacity, None);
+        assert_eq!(stats.empty_slots, None);
+        drop(guard);
+    }
+
+    #[test]
+    fn zero_notifications_skip_lock() {
+        let event = crate::Event::with_spin_budget(0);
+        let _listener = event.listen();
+        let inner = unsafe { &*event.inner() };
+
+        // Hold the lock, so that any operation would be queued.
+        let guard = inner.list.inner.lock();
+        event.notify_additional(0);
+        event.notify_oldest(0);
+        event.coalesce_additional(0);
+        assert_eq!(event.notify_to_capacity(0), 0);
+        assert_eq!(event.debug_stats().queue_len, 0);
+
+        event.notify_additional(1);
+        assert_eq!(event.debug_stats().queue_len, 1);
+        drop(guard);
+    }
+
+    #[test]
+    fn notify_with_path_info() {
+        let event = crate::Event::with_spin_budget(0);
+        let _listener1 = event.listen();
+        let _listener2 = event.listen();
+        let inner = unsafe { &*event.inner() };
+
+        assert_eq!(event.notify_with_path_info(1, false), NotifyPath::Fast);
+
+        // Hold the lock, so that the notifications are queued.
+        let guard = inner.list.inner.lock();
+        assert_eq!(
+            event.notify_with_path_info(1, true),
+            NotifyPath::Queued { depth: 1 }
+        );
+        assert_eq!(
+            event.notify_with_path_info(1, true),
+            NotifyPath::Queued { depth: 2 }
+        );
+        drop(guard);
+
+        // The queue is applied once the lock is taken again.
+        assert_eq!(event.debug_stats().queue_len, 2);
+        assert_eq!(event.drain_all().len(), 2);
+        assert_eq!(event.debug_stats().queue_len, 0);
+    }
+
+    #[test]
+    fn notify_with_fallback_poll() {
+        fn notified(event: &crate::Event) -> usize {
+            // Apply the queue before counting.
+            drop(unsafe { &*event.inner() }.lock());
+            event.debug_stats().notified
+        }
+
+        let event = Arc::new(crate::Event::with_spin_budget(0));
+        let _listener1 = event.listen();
+        let _listener2 = event.listen();
+        let inner = unsafe { &*event.inner() };
+
+        // Without retries, a held lock sends the notification to the queue.
+        let guard = inner.list.inner.lock();
+        assert_eq!(event.notify_with_fallback_poll(1, true, 0), 0);
+        assert_eq!(event.debug_stats().queue_len, 1);
+        drop(guard);
+        assert_eq!(notified(&event), 1);
+
+        // A brief hold is outlasted by the retries.
+        let guard = inner.list.inner.lock();
+        let handle = std::thread::spawn({
+            let event = event.clone();
+            move || event.notify_with_fallback_poll(1, true, core::u32::MAX)
+        });
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        drop(guard);
+
+        assert_eq!(handle.join().unwrap(), 1);
+        assert_eq!(event.debug_stats().queue_len, 0);
+        assert_eq!(notified(&event), 2);
+    }
+
+    #[test]
+    fn metrics_count_fallbacks() {
+        let event = crate::Event::with_spin_budget(0);
+        let _listener = event.listen();
+        let inner = unsafe { &*event.inner() };
+
+        // Hold the lock, so that the notifications are queued.
+        let guard = inner.list.inner.lock();
+        event.notify_additional(1);
+        event.notify_additional(1);
+        assert_eq!(event.metrics().fallbacks, 2);
+        assert_eq!(event.metrics().notifies, 0);
+        drop(guard);
+
+        // The queued notifications are counted once they are applied.
+        drop(inner.lock());
+        let metrics = event.metrics();
+        assert_eq!(metrics.fallbacks, 2);
+        assert_eq!(metrics.notifies, 1);
+        assert_eq!(metrics.listens, 1);
+        assert_eq!(metrics.notified, 1);
+    }
+
+    #[test]
+    fn try_notify_never_queues() {
+        let event = crate::Event::new();
+        let _listener = event.listen();
+        let inner = unsafe { &*event.inner() };
+
+        // The lock is held, so the notification is refused instead of queued.
+        let guard = inner.list.inner.lock();
+        assert!(!event.try_notify(1, false));
+        assert_eq!(event.debug_stats().queue_len, 0);
+        drop(guard);
+
+        assert!(event.try_notify(1, false));
+        assert_eq!(event.debug_stats().notified, 1);
+    }
+
+    #[test]
+    fn try_notify_applies_queue() {
+        let event = crate::Event::new();
+        let _listener1 = event.listen();
+        let _listener2 = event.listen();
+        let inner = unsafe { &*event.inner() };
+
+        // Queue a notification behind the raw lock, which doesn't apply it on release.
+        let guard = inner.list.inner.lock();
+        event.notify_additional(1);
+        drop(guard);
+        assert_eq!(event.debug_stats().queue_len, 1);
+
+        // Releasing the lock in `try_notify` applies the queued notification too.
+        assert!(event.try_notify(1, true));
+        assert_eq!(event.debug_stats().queue_len, 0);
+        assert_eq!(event.debug_stats().notified, 2);
+    }
+
+    #[test]
+    fn with_capacity() {
+        let event: crate::Event = crate::Event::with_capacity(16);
+        let inner = unsafe { &*event.inner() };
+        let capacity = inner.list.inner.lock().listeners.capacity();
+        assert!(capacity >= 17);
+
+        // Inserting the listeners never reallocates.
+        let mut listeners = Vec::new();
+        for _ in 0..16 {
+            listeners.push(event.listen());
+            assert_eq!(inner.list.inner.lock().listeners.capacity(), capacity);
+        }
+        assert_eq!(event.total_listeners(), 16);
+    }
+
+    #[test]
+    fn with_capacity_keeps_capacity() {
+        let event: crate::Event = crate::Event::with_capacity(SHRINK_THRESHOLD * 2);
+        let inner = unsafe { &*event.inner() };
+        let capacity = || inner.list.inner.lock().listeners.capacity();
+
+        // Emptying a large slab shrinks it, but not below the requested capacity.
+        let listeners = (0..SHRINK_THRESHOLD * 4)
+            .map(|_| event.listen())
+            .collect::<Vec<_>>();
+        assert!(capacity() > SHRINK_THRESHOLD * 4);
+        drop(listeners);
+        assert!(capacity() > SHRINK_THRESHOLD * 2);
+        assert!(capacity() < SHRINK_THRESHOLD * 4);
+
+        event.shrink_to_fit();
+        assert!(capacity() > SHRINK_THRESHOLD * 2);
+    }
+
+    #[test]
+    fn queued_insert_then_queued_notify() {
+        let inner = crate::Inner::<()>::new(0, core::usize::MAX, 0);
+        let mut listener = None;
+
+        // Both the insertion and the notification are deferred while the lock is held.
+        let guard = inner.list.inner.lock();
+        inner.insert(Pin::new(&mut listener), 0);
+        assert!(matches!(listener, Some(Listener::Queued(_))));
+        assert_eq!(inner.notify(1, false), 0);
+        assert_eq!(inner.list.queue.len(), 2);
+        drop(guard);
+
+        // The queue is applied in order, so the notification reaches the listener.
+        drop(inner.lock());
+        let waker = waker_fn::waker_fn(|| ());
+        assert_eq!(
+            inner.register(Pin::new(&mut listener), TaskRef::Waker(&waker)),
+            RegisterResult::Notified(TraceId::default(), None)
+        );
+    }
+
+    #[cfg(feature = "debug-stats")]
+    #[test]
+    fn slab_stats_reuse() {
+        let event = crate::Event::<()>::new();
+
+        // Removed listeners free their slots for the next ones.
+        for _ in 0..100 {
+            let listeners = (0..4).map(|_| event.listen()).collect::<Vec<_>>();
+            drop(listeners);
+        }
+
+        let stats = event.slab_stats();
+        assert_eq!(stats.inserts, 400);
+        assert_eq!(stats.pushes, 4);
+        assert_eq!(stats.reuses, 396);
+    }
+
+    #[test]
+    fn try_insert_contended() {
+        let inner = crate::Inner::<()>::new(0, core::usize::MAX, 0);
+        let mut listener = None;
+
+        // A locked list neither registers nor queues the listener.
+        let guard = inner.list.inner.lock();
+        assert!(!inner.try_insert(Pin::new(&mut listener), 0));
+        assert!(listener.is_none());
+        assert_eq!(inner.list.queue.len(), 0);
+        drop(guard);
+
+        assert!(inner.try_insert(Pin::new(&mut listener), 0));
+        assert_eq!(inner.lock().len, 1);
+    }
+
+    #[test]
+    fn queued_notify_handle() {
+        let inner = crate::Inner::<()>::new(0, core::usize::MAX, 0);
+        let mut listener1 = None;
+        let mut listener2 = None;
+        inner.insert(Pin::new(&mut listener1), 0);
+        inner.insert(Pin::new(&mut listener2), 0);
+        let handle = inner.handle(Pin::new(&mut listener2)).unwrap();
+
+        // The notification is deferred while the lock is held.
+        let guard = inner.list.inner.lock();
+        assert!(inner.notify_handle_deferred(handle));
+        assert_eq!(inner.list.queue.len(), 1);
+        drop(guard);
+
+        // Only the listener with the handle is notified once the queue is applied.
+        drop(inner.lock());
+        assert!(!inner.is_notified(&listener1));
+        assert!(inner.is_notified(&listener2));
+    }
+
+    #[test]
+    fn try_lock_applies_queue() {
+        let inner = crate::Inner::<()>::new(0, core::usize::MAX, 0);
+        let mut listener = None;
+
+        // The insertion is queued while the lock is held.
+        let guard = inner.list.inner.lock();
+        inner.insert(Pin::new(&mut listener), 0);
+        assert_eq!(inner.list.queue.len(), 1);
+        drop(guard);
+
+        // The next locked operation sees the queued listener.
+        assert_eq!(inner.notify(1, false), 1);
+        assert_eq!(inner.list.queue.len(), 0);
+
+        // Dropping the guard didn't apply the insertion a second time.
+        let list = inner.lock();
+        assert_eq!(list.len, 1);
+        assert_eq!(list.notified, 1);
+        drop(list);
+        assert!(inner.is_notified(&listener));
+    }
+
+    #[test]
+    fn propagate_past_queued_remove() {
+        let inner = crate::Inner::<()>::new(0, core::usize::MAX, 0);
+        let (mut listener1, mut listener2, mut listener3) = (None, None, None);
+        inner.insert(Pin::new(&mut listener1), 0);
+        inner.insert(Pin::new(&mut listener2), 0);
+        inner.insert(Pin::new(&mut listener3), 0);
+        inner.notify(1, false);
+
+        // The second listener is discarded while the lock is held.
+        let guard = inner.list.inner.lock();
+        assert_eq!(inner.remove(Pin::new(&mut listener2), false), None);
+        assert_eq!(inner.list.queue.len(), 1);
+        drop(guard);
+
+        // The notification of the first listener skips the second one.
+        assert_eq!(
+            inner.remove(Pin::new(&mut listener1), true),
+            Some(State::Notified(false, TraceId::default(), None))
         );
+        assert_eq!(inner.list.queue.len(), 0);
+        assert!(inner.is_notified(&listener3));
+    }
+
+    #[cfg(not(miri))]
+    #[test]
+    fn queued_notify_never_lost() {
+        use std::time::{Duration, Instant};
+
+        const ROUNDS: usize = 200;
+
+        // Without a spin budget, every contended operation falls back to the queue.
+        let inner = Arc::new(crate::Inner::<()>::new(0, core::usize::MAX, 0));
+        let inserted = Arc::new(crate::sync::atomic::AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // Keep taking and releasing the lock, so both sides race with its holder.
+        let holder = std::thread::spawn({
+            let inner = inner.clone();
+            let stop = stop.clone();
+            move || {
+                while !stop.load(Ordering::Relaxed) {
+                    drop(inner.lock());
+                }
+            }
+        });
+
+        // Notify once every listener is inserted, possibly through the queue.
+        let notifier = std::thread::spawn({
+            let inner = inner.clone();
+            let inserted = inserted.clone();
+            move || {
+                for round in 1..=ROUNDS {
+                    while inserted.load(Ordering::SeqCst) < round {
+                        std::thread::yield_now();
+                    }
+                    inner.notify(1, false);
+                }
+            }
+        });
+
+        let waker = waker_fn::waker_fn(|| ());
+        for round in 1..=ROUNDS {
+            let mut listener = None;
+            inner.insert(Pin::new(&mut listener), 0);
+            inserted.store(round, Ordering::SeqCst);
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            loop {
+                match inner.register(Pin::new(&mut listener), TaskRef::Waker(&waker)) {
+                    RegisterResult::Notified(..) => break,
+                    RegisterResult::Registered => {
+                        assert!(Instant::now() < deadline, "lost notification {}", round);
+                        std::thread::yield_now();
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        notifier.join().unwrap();
+        stop.store(true, Ordering::Relaxed);
+        holder.join().unwrap();
+    }
+
+    #[test]
+    fn entry_debug_and_eq() {
+        let mut listeners = ListenerSlab::<()>::new();
+        let waker = waker_fn::waker_fn(|| ());
+        let key = listeners.insert(State::Task(Task::Waker(waker)));
+        let other = listeners.insert(State::Created);
+        let entry = &listeners.listeners[key.get()];
+
+        // Formatting only looks at the kind of the state.
+        assert!(format!("{:?}", entry).contains("state: Task"));
+
+        // An entry is equal to itself, and comparing it leaves the task in place.
+        assert_eq!(entry, entry);
+        assert!(matches!(
+            TakenState::new(entry.state()).state,
+            State::Task(_)
+        ));
+        assert_ne!(entry, &listeners.listeners[other.get()]);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn queued_notify_never_lost() {
+        let mut model = loom::model::Builder::new();
+        model.preemption_bound = Some(3);
+
+        model.check(|| {
+            // Without a spin budget, every contended operation falls back to the queue.
+            let inner = Arc::new(crate::Inner::<()>::new(0, core::usize::MAX, 0));
+            let woken = Arc::new(AtomicBool::new(false));
+
+            // Take and release the lock once, so the others may find it held.
+            let holder = thread::spawn({
+                let inner = inner.clone();
+                move || drop(inner.lock())
+            });
+
+            // The insertion may be deferred to the queue.
+            let mut listener = None;
+            inner.insert(Pin::new(&mut listener), 0);
+
+            // The notification may be queued behind the insertion.
+            let notifier = thread::spawn({
+                let inner = inner.clone();
+                move || inner.notify(1, false)
+            });
+
+            let waker = waker_fn::waker_fn({
+                let woken = woken.clone();
+                move || woken.store(true, Ordering::SeqCst)
+            });
+            let first = inner.register(Pin::new(&mut listener), TaskRef::Waker(&waker));
+
+            notifier.join().unwrap();
+            holder.join().unwrap();
+
+            // A registered task is woken once the queue is applied, without anyone locking again.
+            if let RegisterResult::Registered = first {
+                assert!(woken.load(Ordering::SeqCst), "task not woken");
+                match inner.register(Pin::new(&mut listener), TaskRef::Waker(&waker)) {
+                    RegisterResult::Notified(..) => {}
+                    _ => panic!("lost notification"),
+                }
+            }
+        });
     }
 }
@@ -4,8 +4,8 @@
 
 use crate::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 use crate::sync::Arc;
-use crate::sys::ListenerSlab;
-use crate::{State, Task};
+use crate::sys::{Handle, ListenerSlab};
+use crate::{Task, TraceId};
 
 use alloc::boxed::Box;
 
@@ -13,13 +13,16 @@ use core::num::NonZeroUsize;
 use core::ptr;
 
 /// A node in the backup queue.
-pub(crate) enum Node {
+pub(crate) enum Node<T> {
     /// This node is requesting to add a listener.
     // For some reason, the MSRV build says this variant is never constructed.
     #[allow(dead_code)]
     AddListener {
         /// The state of the listener that wants to be added.
         task_waiting: Arc<TaskWaiting>,
+
+        /// The priority of the listener.
+        priority: u8,
     },
 
     /// This node is notifying a listener.
@@ -29,8 +32,32 @@ pub(crate) enum Node {
 
         /// Whether to wake up notified listeners.
         additional: bool,
+
+        /// The trace id attached to the notifications.
+        trace_id: TraceId,
+
+        /// The tag attached to the notifications.
+        tag: Option<T>,
+    },
+
+    /// This node is issuing a permit.
+    NotifyPermit,
+
+    /// This node is notifying a listener, or marking the list if there is none.
+    NotifyOrMark,
+
+    /// This node is notifying additional listeners, deferring the rest.
+    CoalesceAdditional {
+        /// The number of listeners to notify.
+        count: usize,
     },
 
+    /// This node is detaching notified listeners that no task is waiting on.
+    PruneNotified,
+
+    /// This node is notifying the listener with a handle.
+    NotifyHandle(Handle),
+
     /// This node is removing a listener.
     RemoveListener {
         /// The ID of the listener to remove.
@@ -55,8 +82,8 @@ pub(crate) struct TaskWaiting {
     entry_id: AtomicUsize,
 }
 
-impl Node {
-    pub(crate) fn listener() -> (Self, Arc<TaskWaiting>) {
+impl<T: Clone> Node<T> {
+    pub(crate) fn listener(priority: u8) -> (Self, Arc<TaskWaiting>) {
         // Create a new `TaskWaiting` structure.
         let task_waiting = Arc::new(TaskWaiting {
             task: AtomicCell::new(),
@@ -66,26 +93,57 @@ impl Node {
         (
             Self::AddListener {
                 task_waiting: task_waiting.clone(),
+                priority,
             },
             task_waiting,
         )
     }
 
     /// Apply the node to the list.
-    pub(super) fn apply(self, list: &mut ListenerSlab) -> Option<Task> {
+    pub(super) fn apply(self, list: &mut ListenerSlab<T>) -> Option<Task> {
         match self {
-            Node::AddListener { task_waiting } => {
+            Node::AddListener {
+                task_waiting,
+                priority,
+            } => {
                 // Add a new entry to the list.
-                let key = list.insert(State::Created);
+                let key = list.listen(priority);
 
                 // Send the new key to the listener and wake it if necessary.
-                task_waiting.entry_id.store(key.get(), Ordering::Release);
+                //
+                // Either this sees the task, or the listener sees the key after registering it.
+                task_waiting.entry_id.store(key.get(), Ordering::SeqCst);
 
                 return task_waiting.task.take().map(|t| *t);
             }
-            Node::Notify { count, additional } => {
+            Node::Notify {
+                count,
+                additional,
+                trace_id,
+                tag,
+            } => {
                 // Notify the next `count` listeners.
-                list.notify(count, additional);
+                list.notify(count, additional, trace_id, tag);
+            }
+            Node::NotifyPermit => {
+                // Hand the permit to the next listener.
+                list.notify_permit();
+            }
+            Node::NotifyOrMark => {
+                // Notify the next listener, or mark the list.
+                list.notify_or_mark();
+            }
+            Node::CoalesceAdditional { count } => {
+                // Notify the next `count` listeners, or defer the notifications.
+                list.coalesce_additional(count);
+            }
+            Node::PruneNotified => {
+                // Detach the idle notified listeners.
+                list.prune_notified();
+            }
+            Node::NotifyHandle(handle) => {
+                // Notify the listener, if it's still there.
+                list.notify_handle(handle);
             }
             Node::RemoveListener {
                 listener,
@@ -108,7 +166,7 @@ impl TaskWaiting {
     ///
     /// Returns `Some` with the entry ID if we are no longer queued.
     pub(crate) fn status(&self) -> Option<NonZeroUsize> {
-        NonZeroUsize::new(self.entry_id.load(Ordering::Acquire))
+        NonZeroUsize::new(self.entry_id.load(Ordering::SeqCst))
     }
 
     /// Register a listener.
@@ -120,8 +178,10 @@ impl TaskWaiting {
 
         // If the entry ID is non-zero, then we are no longer queued.
         if self.status().is_some() {
-            // Wake the task.
-            self.task.take().unwrap().wake();
+            // Wake the task, unless the list already took it.
+            if let Some(task) = self.task.take() {
+                task.wake();
+            }
         }
     }
 }
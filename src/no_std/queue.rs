@@ -1,129 +1,179 @@
 //! An atomic queue of operations to process.
 
 use super::node::Node;
-use crate::sync::atomic::{AtomicPtr, Ordering};
+use crate::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use crate::sync::cell::UnsafeCell;
 
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::ptr;
 
-/// An naive atomic queue of operations to process.
-pub(super) struct Queue {
-    /// The head of the queue.
-    head: AtomicPtr<Link>,
+/// An atomic queue of operations to process.
+///
+/// Pushers prepend to a lock-free stack. The consumer detaches the whole stack at once and keeps
+/// it in pushing order, so a pusher never links onto a node that is being taken out.
+pub(super) struct Queue<T> {
+    /// The most recently pushed node.
+    head: AtomicPtr<Link<T>>,
 
-    /// The tail of the queue.
-    tail: AtomicPtr<Link>,
+    /// The detached nodes, oldest first, which only the consumer touches.
+    pending: UnsafeCell<*mut Link<T>>,
+
+    /// The number of nodes in the queue.
+    len: AtomicUsize,
+
+    /// The maximum number of nodes in the queue.
+    capacity: usize,
 }
 
-struct Link {
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+struct Link<T> {
     /// The inner node.
-    node: Node,
+    node: Node<T>,
 
     /// The next node in the queue.
-    next: AtomicPtr<Link>,
+    next: *mut Link<T>,
 }
 
-impl Queue {
-    /// Create a new, empty queue.
-    pub(super) fn new() -> Self {
+impl<T> Queue<T> {
+    /// Create a new, empty queue that holds at most `capacity` nodes.
+    pub(super) fn new(capacity: usize) -> Self {
         Self {
             head: AtomicPtr::new(ptr::null_mut()),
-            tail: AtomicPtr::new(ptr::null_mut()),
+            pending: UnsafeCell::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
+            capacity,
         }
     }
 
+    /// The number of nodes in the queue.
+    pub(super) fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if no node was pushed since the consumer last detached the queue.
+    ///
+    /// Unlike [`Queue::len`], this doesn't count the nodes that are still being pushed.
+    pub(super) fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire).is_null()
+    }
+
     /// Push a new node onto the queue.
-    pub(super) fn push(&self, node: Node) {
+    ///
+    /// Returns the number of nodes in the queue after the push, or the node back if the queue is
+    /// full.
+    pub(super) fn push(&self, node: Node<T>) -> Result<usize, Node<T>> {
+        // Reserve room for the node.
+        let mut len = self.len.load(Ordering::Relaxed);
+        loop {
+            if len >= self.capacity {
+                return Err(node);
+            }
+
+            match self
+                .len
+                .compare_exchange_weak(len, len + 1, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(actual) => len = actual,
+            }
+        }
+
         // Allocate a new link.
         let link = Box::into_raw(Box::new(Link {
             node,
-            next: AtomicPtr::new(ptr::null_mut()),
+            next: ptr::null_mut(),
         }));
 
-        // Push the link onto the queue.
-        let mut tail = self.tail.load(Ordering::Acquire);
+        // Push the link onto the stack.
+        let mut head = self.head.load(Ordering::Relaxed);
         loop {
-            // If the tail is null, then the queue is empty.
-            if tail.is_null() {
-                // Try to set the head to the new link.
-                if self
-                    .head
-                    .compare_exchange(ptr::null_mut(), link, Ordering::AcqRel, Ordering::Acquire)
-                    .is_ok()
-                {
-                    // We successfully set the head, so we can set the tail.
-                    self.tail.store(link, Ordering::Release);
-                    return;
-                }
-
-                // The head was set by another thread, so we need to try again.
-                tail = self.tail.load(Ordering::Acquire);
-            }
-
             unsafe {
-                // Try to set the next pointer of the current tail.
-                let next = (*tail).next.load(Ordering::Acquire);
-                if next.is_null()
-                    && (*tail)
-                        .next
-                        .compare_exchange(
-                            ptr::null_mut(),
-                            link,
-                            Ordering::AcqRel,
-                            Ordering::Acquire,
-                        )
-                        .is_ok()
-                {
-                    // We successfully set the next pointer, so we can set the tail.
-                    self.tail.store(link, Ordering::Release);
-                    return;
-                }
+                (*link).next = head;
             }
 
-            // The next pointer was set by another thread, so we need to try again.
-            tail = self.tail.load(Ordering::Acquire);
+            match self
+                .head
+                .compare_exchange_weak(head, link, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return Ok(len + 1),
+                Err(actual) => head = actual,
+            }
         }
     }
 
-    /// Pop a node from the queue.
-    pub(super) fn pop(&self) -> Option<Node> {
-        // Pop the head of the queue.
-        let mut head = self.head.load(Ordering::Acquire);
-        loop {
-            // If the head is null, then the queue is empty.
-            if head.is_null() {
-                return None;
+    /// Pop the oldest node from the queue.
+    ///
+    /// # Safety
+    ///
+    /// Only one thread may pop at a time, which holding the list's lock guarantees.
+    pub(super) unsafe fn pop(&self) -> Option<Node<T>> {
+        let pending = &mut *self.pending.get();
+
+        if pending.is_null() {
+            // Detach the stack and reverse it, so the oldest node comes first.
+            let mut link = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+            while !link.is_null() {
+                let next = (*link).next;
+                (*link).next = *pending;
+                *pending = link;
+                link = next;
             }
+        }
 
-            unsafe {
-                // Try to set the head to the next pointer.
-                let next = (*head).next.load(Ordering::Acquire);
-                if self
-                    .head
-                    .compare_exchange(head, next, Ordering::AcqRel, Ordering::Acquire)
-                    .is_ok()
-                {
-                    // We successfully set the head, so we can set the tail if the queue is now empty.
-                    if next.is_null() {
-                        self.tail.store(ptr::null_mut(), Ordering::Release);
-                    }
-
-                    // Return the popped node.
-                    let boxed = Box::from_raw(head);
-                    return Some(boxed.node);
-                }
-            }
+        if pending.is_null() {
+            return None;
+        }
+
+        // Take the oldest node.
+        let link = Box::from_raw(*pending);
+        *pending = link.next;
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        Some(link.node)
+    }
+}
+
+impl<T> Queue<T> {
+    /// Move every node into `buf`, in order, and return how many were moved.
+    ///
+    /// The pushed nodes are detached with a single swap, rather than taken one at a time.
+    ///
+    /// # Safety
+    ///
+    /// Only one thread may pop at a time, which holding the list's lock guarantees.
+    pub(super) unsafe fn drain_into(&self, buf: &mut Vec<Node<T>>) -> usize {
+        let start = buf.len();
+
+        // The nodes left over from the last detach are older than the pushed ones.
+        let mut link = ptr::replace(self.pending.get(), ptr::null_mut());
+        while !link.is_null() {
+            let boxed = Box::from_raw(link);
+            link = boxed.next;
+            buf.push(boxed.node);
+        }
 
-            // The head was set by another thread, so we need to try again.
-            head = self.head.load(Ordering::Acquire);
+        // The stack holds the newest node first, so reverse it once it's moved.
+        let pushed = buf.len();
+        let mut link = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+        while !link.is_null() {
+            let boxed = Box::from_raw(link);
+            link = boxed.next;
+            buf.push(boxed.node);
         }
+        buf[pushed..].reverse();
+
+        let moved = buf.len() - start;
+        self.len.fetch_sub(moved, Ordering::Relaxed);
+        moved
     }
 }
 
-impl Drop for Queue {
+impl<T> Drop for Queue<T> {
     fn drop(&mut self) {
         // Pop all nodes from the queue.
-        while self.pop().is_some() {}
+        while unsafe { self.pop() }.is_some() {}
     }
 }
 
@@ -131,18 +181,21 @@ impl Drop for Queue {
 mod tests {
     use super::*;
 
-    fn node_from_num(num: usize) -> Node {
+    fn node_from_num(num: usize) -> Node<()> {
         Node::Notify {
             count: num,
             additional: true,
+            trace_id: crate::TraceId::default(),
+            tag: None,
         }
     }
 
-    fn node_to_num(node: Node) -> usize {
+    fn node_to_num(node: Node<()>) -> usize {
         match node {
             Node::Notify {
                 count,
                 additional: true,
+                ..
             } => count,
             _ => panic!("unexpected node"),
         }
@@ -150,16 +203,16 @@ mod tests {
 
     #[test]
     fn push_pop() {
-        let queue = Queue::new();
+        let queue = Queue::new(core::usize::MAX);
 
-        queue.push(node_from_num(1));
-        queue.push(node_from_num(2));
-        queue.push(node_from_num(3));
+        assert!(queue.push(node_from_num(1)).is_ok());
+        assert!(queue.push(node_from_num(2)).is_ok());
+        assert!(queue.push(node_from_num(3)).is_ok());
 
-        assert_eq!(node_to_num(queue.pop().unwrap()), 1);
-        assert_eq!(node_to_num(queue.pop().unwrap()), 2);
-        assert_eq!(node_to_num(queue.pop().unwrap()), 3);
-        assert!(queue.pop().is_none());
+        assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), 1);
+        assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), 2);
+        assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), 3);
+        assert!(unsafe { queue.pop() }.is_none());
     }
 
     #[test]
@@ -167,18 +220,42 @@ mod tests {
         const COUNT: usize = if cfg!(miri) { 10 } else { 1_000 };
 
         for i in 0..COUNT {
-            let queue = Queue::new();
+            let queue = Queue::new(core::usize::MAX);
 
             for j in 0..i {
-                queue.push(node_from_num(j));
+                assert!(queue.push(node_from_num(j)).is_ok());
             }
 
             for j in 0..i {
-                assert_eq!(node_to_num(queue.pop().unwrap()), j);
+                assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), j);
             }
 
-            assert!(queue.pop().is_none());
+            assert!(unsafe { queue.pop() }.is_none());
+        }
+    }
+
+    #[test]
+    fn drain_into() {
+        let queue = Queue::new(core::usize::MAX);
+        let mut buf = Vec::new();
+        assert_eq!(unsafe { queue.drain_into(&mut buf) }, 0);
+
+        for i in 0..4 {
+            assert!(queue.push(node_from_num(i)).is_ok());
         }
+
+        // Leave some nodes of the last detach behind, which come before the new ones.
+        assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), 0);
+        assert!(queue.push(node_from_num(4)).is_ok());
+        assert_eq!(unsafe { queue.drain_into(&mut buf) }, 4);
+        assert_eq!(queue.len(), 0);
+        assert!(unsafe { queue.pop() }.is_none());
+
+        // The nodes are appended in order.
+        assert!(queue.push(node_from_num(5)).is_ok());
+        assert_eq!(unsafe { queue.drain_into(&mut buf) }, 1);
+        let nums = buf.into_iter().map(node_to_num).collect::<Vec<_>>();
+        assert_eq!(nums, [1, 2, 3, 4, 5]);
     }
 
     #[cfg(not(miri))]
@@ -190,14 +267,14 @@ mod tests {
         const COUNT: usize = 50;
 
         let mut handles = Vec::new();
-        let queue = Arc::new(Queue::new());
+        let queue = Arc::new(Queue::new(core::usize::MAX));
 
         for _ in 0..NUM_THREADS {
             let queue = queue.clone();
 
             handles.push(std::thread::spawn(move || {
                 for i in 0..COUNT {
-                    queue.push(node_from_num(i));
+                    assert!(queue.push(node_from_num(i)).is_ok());
                 }
             }));
         }
@@ -207,7 +284,7 @@ mod tests {
         }
 
         let mut items = Vec::new();
-        while let Some(node) = queue.pop() {
+        while let Some(node) = unsafe { queue.pop() } {
             items.push(node_to_num(node));
         }
 
@@ -218,4 +295,20 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn push_full() {
+        let queue = Queue::new(2);
+
+        assert!(queue.push(node_from_num(1)).is_ok());
+        assert!(queue.push(node_from_num(2)).is_ok());
+        assert_eq!(node_to_num(queue.push(node_from_num(3)).err().unwrap()), 3);
+
+        // Popping a node makes room for another one.
+        assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), 1);
+        assert!(queue.push(node_from_num(3)).is_ok());
+        assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), 2);
+        assert_eq!(node_to_num(unsafe { queue.pop() }.unwrap()), 3);
+        assert!(unsafe { queue.pop() }.is_none());
+    }
 }
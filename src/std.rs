@@ -4,113 +4,614 @@
 
 use crate::sync::atomic::Ordering;
 use crate::sync::cell::{Cell, UnsafeCell};
-use crate::sync::{Mutex, MutexGuard};
-use crate::{State, TaskRef};
+use crate::sync::{Mutex, MutexGuard, TryLockError};
+use crate::{
+    state_kind, Callback, Counters, EventStats, ListenerHandle, ListenerState, MaintenanceReport,
+    NotifyPath, RegisterResult, SlabError, State, Task, TaskRef, TraceId, WakeStrategy,
+};
+
+use alloc::vec::Vec;
 
 use core::marker::PhantomPinned;
-use core::mem;
 use core::ops::{Deref, DerefMut};
 use core::pin::Pin;
 use core::ptr::NonNull;
 
-pub(super) struct List(Mutex<Inner>);
+pub(super) struct List<T>(Mutex<Inner<T>>);
 
-struct Inner {
+struct Inner<T> {
     /// The head of the linked list.
-    head: Option<NonNull<Link>>,
+    head: Option<NonNull<Link<T>>>,
 
     /// The tail of the linked list.
-    tail: Option<NonNull<Link>>,
+    tail: Option<NonNull<Link<T>>>,
 
     /// The first unnotified listener.
-    next: Option<NonNull<Link>>,
+    next: Option<NonNull<Link<T>>>,
 
     /// Total number of listeners.
     len: usize,
 
     /// The number of notified listeners.
     notified: usize,
+
+    /// Called when the last listener is removed.
+    on_empty: Option<Callback>,
+
+    /// Set when a removal has emptied the list.
+    emptied: bool,
+
+    /// Set when a notified entry received or gave up its notification.
+    released: bool,
+
+    /// The number of permits waiting for a listener.
+    permits: usize,
+
+    /// The number of additional notifications waiting for a listener.
+    deferred: usize,
+
+    /// Set when the next inserted entry starts out notified, see `notify_or_mark`.
+    marked: bool,
+
+    /// The identifier given to the next inserted listener.
+    next_id: usize,
+
+    /// The counter used to order listeners by when their tasks started waiting.
+    next_wait: usize,
+
+    /// The id of the entry notified last by `notify_round_robin`, or zero.
+    cursor: usize,
+
+    /// The current round of `notify_fair`, which new entries are inserted in.
+    epoch: usize,
+
+    /// How tasks are woken up when their listener is notified.
+    wake_strategy: WakeStrategy,
+
+    /// Set once the list was shut down.
+    shutdown: bool,
+
+    /// Set once the list was latched, so that new listeners start out notified.
+    latched: bool,
+
+    /// Whether tasks that wake the same task are only woken once.
+    dedup_wakes: bool,
+
+    /// The tasks to wake once the list is unlocked, if wakes are deduplicated.
+    woken: Cell<Vec<Task>>,
+
+    /// Totals of operations on the list.
+    counters: Counters,
 }
 
-impl List {
+// SAFETY: The links are only accessed while the mutex is locked.
+unsafe impl<T: Send> Send for Inner<T> {}
+
+impl<T> List<T> {
     /// Create a new, empty event listener list.
-    pub(super) fn new() -> Self {
+    ///
+    /// The list is protected by a blocking mutex and the entries live in the listeners, so the
+    /// spin budget, the queue capacity and the capacity are unused.
+    pub(super) fn new(_spin_budget: u32, _queue_capacity: usize, _capacity: usize) -> Self {
         Self(Mutex::new(Inner {
             head: None,
             tail: None,
             next: None,
             len: 0,
             notified: 0,
+            on_empty: None,
+            emptied: false,
+            released: false,
+            permits: 0,
+            deferred: 0,
+            marked: false,
+            next_id: 1,
+            next_wait: 0,
+            cursor: 0,
+            epoch: 0,
+            wake_strategy: WakeStrategy::Consume,
+            shutdown: false,
+            latched: false,
+            dedup_wakes: false,
+            woken: Cell::new(Vec::new()),
+            counters: Counters::default(),
         }))
     }
 }
 
-impl crate::Inner {
-    fn lock(&self) -> ListLock<'_, '_> {
+impl<T: Clone> crate::Inner<T> {
+    fn lock(&self) -> ListLock<'_, '_, T> {
         ListLock {
             inner: self,
-            lock: self.list.0.lock().unwrap_or_else(|e| e.into_inner()),
+            lock: Some(self.list.0.lock().unwrap_or_else(|e| e.into_inner())),
+            notified_ordering: Ordering::Release,
         }
     }
 
+    /// Locks the list if it isn't locked already.
+    fn try_lock(&self) -> Option<ListLock<'_, '_, T>> {
+        let lock = match self.list.0.try_lock() {
+            Ok(lock) => lock,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+
+        Some(ListLock {
+            inner: self,
+            lock: Some(lock),
+            notified_ordering: Ordering::Release,
+        })
+    }
+
     /// Add a new listener to the list.
     ///
-    /// Does nothing is the listener is already registered.
-    pub(crate) fn insert(&self, listener: Pin<&mut Option<Listener>>) {
+    /// The listener is placed ahead of unnotified listeners with a lower priority. Does nothing is
+    /// the listener is already registered.
+    pub(crate) fn insert(&self, listener: Pin<&mut Option<Listener<T>>>, priority: u8) {
+        self.insert_at(listener, priority, false);
+    }
+
+    /// Add a new listener to the list, ahead of every unnotified listener.
+    ///
+    /// Does nothing if the listener is already registered.
+    pub(crate) fn insert_front(&self, listener: Pin<&mut Option<Listener<T>>>) {
+        self.insert_at(listener, 0, true);
+    }
+
+    /// Add a new listener to the back of the list and return the number of listeners that were
+    /// registered before it.
+    pub(crate) fn insert_positioned(&self, listener: Pin<&mut Option<Listener<T>>>) -> usize {
+        self.insert_at(listener, 0, false)
+    }
+
+    /// Add a new listener to the back of the list, which can take `capacity` items.
+    ///
+    /// Does nothing if the listener is already registered.
+    pub(crate) fn insert_with_capacity(
+        &self,
+        mut listener: Pin<&mut Option<Listener<T>>>,
+        capacity: usize,
+    ) {
         let mut inner = self.lock();
+        Self::insert_locked(&mut inner, listener.as_mut(), 0, false);
 
         // SAFETY: We are locked, so we can access the inner `link`.
-        let entry = unsafe {
-            // SAFETY: We never move out the `link` field.
-            let listener = match listener.get_unchecked_mut() {
-                listener @ None => {
-                    // TODO: Use Option::insert once the MSRV is high enough.
-                    *listener = Some(Listener {
-                        link: UnsafeCell::new(Link {
-                            state: Cell::new(State::Created),
-                            prev: Cell::new(inner.tail),
-                            next: Cell::new(None),
-                        }),
-                        _pin: PhantomPinned,
-                    });
+        if let Some(listener) = listener.as_ref().get_ref() {
+            unsafe { &*listener.link.get() }.capacity.set(capacity);
+        }
+    }
 
-                    listener.as_mut().unwrap()
-                }
-                Some(_) => return,
-            };
+    /// Add a new listener to the list if it isn't locked, returning `false` otherwise.
+    ///
+    /// Returns `true` if the listener is already registered.
+    pub(crate) fn try_insert(&self, listener: Pin<&mut Option<Listener<T>>>, priority: u8) -> bool {
+        match self.try_lock() {
+            Some(mut inner) => {
+                Self::insert_locked(&mut inner, listener, priority, false);
+                true
+            }
+            None => false,
+        }
+    }
 
-            // Get the inner pointer.
-            &*listener.link.get()
-        };
+    /// Add a new listener to the list, either in line by its `priority` or at the `front`.
+    ///
+    /// A listener at the front takes the priority of the listener it goes ahead of, so the
+    /// unnotified listeners stay sorted by priority. Returns the number of entries in the list
+    /// before the listener was added.
+    fn insert_at(
+        &self,
+        listener: Pin<&mut Option<Listener<T>>>,
+        priority: u8,
+        front: bool,
+    ) -> usize {
+        Self::insert_locked(&mut self.lock(), listener, priority, front)
+    }
+
+    /// Add a new listener to the locked list, like `insert_at`.
+    fn insert_locked(
+        inner: &mut ListLock<'_, '_, T>,
+        listener: Pin<&mut Option<Listener<T>>>,
+        priority: u8,
+        front: bool,
+    ) -> usize {
+        let position = inner.len;
+
+        // SAFETY: We never move out the `link` field.
+        let listener = unsafe { listener.get_unchecked_mut() };
+        if listener.is_some() {
+            return position;
+        }
 
-        // Replace the tail with the new entry.
-        match mem::replace(&mut inner.tail, Some(entry.into())) {
-            None => inner.head = Some(entry.into()),
-            Some(t) => unsafe { t.as_ref().next.set(Some(entry.into())) },
+        // After a shutdown or a latch, the new entry is never linked. Otherwise, it takes a
+        // waiting permit, deferred notification or mark if there is one.
+        let linked = !inner.shutdown && !inner.latched;
+        let state = if inner.shutdown {
+            State::Shutdown
+        } else if inner.latched {
+            State::Notified(false, TraceId::default(), None)
+        } else {
+            inner.admit()
+        };
+        let notified = state.is_notified();
+
+        // Notified entries go at the end.
+        let (before, priority) = if notified {
+            (None, priority)
+        } else if front {
+            let next = inner.next;
+            (
+                next,
+                next.map_or(0, |link| unsafe { link.as_ref() }.priority),
+            )
+        } else {
+            (inner.position(priority), priority)
         };
 
-        // If there are no unnotified entries, this is the first one.
-        if inner.next.is_none() {
+        // TODO: Use Option::insert once the MSRV is high enough.
+        *listener = Some(Listener {
+            link: UnsafeCell::new(Link {
+                id: inner.next_id,
+                priority,
+                waiting_since: Cell::new(0),
+                epoch: inner.epoch,
+                capacity: Cell::new(1),
+                group: Cell::new(None),
+                linked: Cell::new(linked),
+                state: Cell::new(state),
+                prev: Cell::new(None),
+                next: Cell::new(None),
+            }),
+            _pin: PhantomPinned,
+        });
+
+        // SAFETY: We are locked, so we can access the inner `link`.
+        let entry = unsafe { &*listener.as_ref().unwrap().link.get() };
+
+        inner.next_id = inner.next_id.wrapping_add(1);
+        if !linked {
+            return position;
+        }
+
+        inner.link(entry.into(), before);
+
+        if notified {
+            // The entry starts out notified.
+            inner.notified += 1;
+            inner.counters.notifies += 1;
+        } else if inner.next.is_none() {
+            // If there are no unnotified entries, this is the first one.
             inner.next = inner.tail;
         }
 
         // Bump the entry count.
         inner.len += 1;
+        inner.counters.listens += 1;
+        position
+    }
+
+    /// Discard the notification of a listener and move it back in line.
+    ///
+    /// A listener that is still part of the list is moved in place. Otherwise, it is inserted
+    /// again.
+    pub(crate) fn reset(&self, mut listener: Pin<&mut Option<Listener<T>>>) {
+        {
+            let mut inner = self.lock();
+
+            // SAFETY: We are locked, so we can access the inner `link`.
+            let link = listener
+                .as_ref()
+                .get_ref()
+                .as_ref()
+                .map(|listener| unsafe { NonNull::new_unchecked(listener.link.get()) });
+
+            let mut priority = 0;
+            if let Some(link) = link {
+                let entry = unsafe { link.as_ref() };
+                if entry.linked.get() {
+                    inner.reset(link);
+                    return;
+                }
+
+                // The listener was drained, so take it out.
+                priority = entry.priority;
+                inner.remove(listener.as_mut(), false);
+            }
+
+            Self::insert_locked(&mut inner, listener, priority, false);
+        }
     }
 
     /// Remove a listener from the list.
     pub(crate) fn remove(
         &self,
-        listener: Pin<&mut Option<Listener>>,
+        listener: Pin<&mut Option<Listener<T>>>,
         propogate: bool,
-    ) -> Option<State> {
+    ) -> Option<State<T>> {
         self.lock().remove(listener, propogate)
     }
 
-    /// Notifies a number of entries.
+    /// Remove a listener from the list, taking the task out of its state.
+    ///
+    /// The task is returned rather than dropped, so it outlives the lock.
+    pub(crate) fn remove_taking_task(
+        &self,
+        listener: Pin<&mut Option<Listener<T>>>,
+        propogate: bool,
+    ) -> Option<(State<T>, Option<Task>)> {
+        let mut state = self.lock().remove(listener, propogate)?;
+        let task = state.take_task();
+        Some((state, task))
+    }
+
+    /// Remove a listener from the list if it was notified, leaving it in place otherwise.
+    pub(crate) fn take_notification(
+        &self,
+        listener: Pin<&mut Option<Listener<T>>>,
+    ) -> Option<State<T>> {
+        let mut inner = self.lock();
+
+        // SAFETY: We are locked, so we can access the inner `link`.
+        let entry = unsafe { &*listener.as_ref().get_ref().as_ref()?.link.get() };
+
+        let state = entry.state.replace(State::NotifiedTaken);
+        let notified = matches!(state.kind(), ListenerState::Notified(_));
+        entry.state.set(state);
+        if !notified {
+            return None;
+        }
+
+        inner.remove(listener, false)
+    }
+
+    /// Notifies a number of entries and returns how many were notified.
     #[cold]
-    pub(crate) fn notify(&self, n: usize, additional: bool) {
-        self.lock().notify(n, additional)
+    pub(crate) fn notify(&self, n: usize, additional: bool) -> usize {
+        self.notify_with(n, additional, TraceId::default(), None, Ordering::Release)
+            .0
+    }
+
+    /// Notifies a number of listeners with a trace id and a tag, publishing the new `notified`
+    /// count with `ordering`.
+    ///
+    /// Returns the number of listeners that were notified.
+    pub(crate) fn notify_with(
+        &self,
+        n: usize,
+        additional: bool,
+        trace_id: TraceId,
+        tag: Option<T>,
+        ordering: Ordering,
+    ) -> (usize, NotifyPath) {
+        let mut inner = self.lock();
+        inner.notified_ordering = ordering;
+        let count = inner.notify(n, additional, trace_id, tag);
+        (count, NotifyPath::Fast)
+    }
+
+    /// Notifies a number of listeners, giving each of them the tag returned by `f`.
+    pub(crate) fn notify_each(&self, n: usize, f: &mut dyn FnMut() -> T) -> usize {
+        self.lock()
+            .notify_each(n, false, TraceId::default(), || Some(f()))
+    }
+
+    /// Notifies a number of listeners without raising the number of notified ones above `cap`.
+    pub(crate) fn notify_capped(&self, n: usize, additional: bool, cap: usize) {
+        self.lock().notify_capped(n, additional, cap)
+    }
+
+    /// Notifies a number of listeners.
+    ///
+    /// The list is protected by a blocking mutex, so there is nothing to retry.
+    pub(crate) fn notify_with_retry(&self, n: usize, additional: bool, _retry_spins: u32) -> usize {
+        self.notify(n, additional)
+    }
+
+    /// Notifies a number of listeners if the list isn't locked, returning `false` otherwise.
+    pub(crate) fn try_notify(&self, n: usize, additional: bool) -> bool {
+        match self.try_lock() {
+            Some(mut inner) => {
+                inner.notify(n, additional, TraceId::default(), None);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Notifies a single listener.
+    pub(crate) fn notify_one(&self) {
+        self.lock().notify_one()
+    }
+
+    /// Notifies a number of listeners, preferring the ones that have been waiting the longest.
+    pub(crate) fn notify_oldest(&self, n: usize) {
+        self.lock().notify_oldest(n)
+    }
+
+    /// Notifies a number of listeners, newest first.
+    pub(crate) fn notify_lifo(&self, n: usize) {
+        self.lock().notify_lifo(n)
+    }
+
+    /// Notifies a number of additional listeners, taking turns.
+    pub(crate) fn notify_round_robin(&self, n: usize) -> usize {
+        self.lock().notify_round_robin(n)
+    }
+
+    /// Notifies listeners until a number of them hold a notification.
+    pub(crate) fn notify_saturating(&self, target: usize) -> usize {
+        self.lock().notify_saturating(target)
+    }
+
+    /// Notifies a number of additional listeners, preferring the ones inserted the longest ago.
+    pub(crate) fn notify_fair(&self, n: usize) -> usize {
+        self.lock().notify_fair(n)
+    }
+
+    /// Notifies a number of listeners that match a predicate.
+    pub(crate) fn notify_filtered(&self, n: usize, pred: &mut dyn FnMut(&ListenerState) -> bool) {
+        self.lock().notify_filtered(n, pred)
+    }
+
+    /// Hands a permit to the next unnotified listener, or stores it.
+    pub(crate) fn notify_permit(&self) -> bool {
+        self.lock().notify_permit()
+    }
+
+    /// Notifies the next unnotified listener, or marks the list for the next listener.
+    pub(crate) fn notify_or_mark(&self) -> bool {
+        self.lock().notify_or_mark()
+    }
+
+    /// Notifies a number of additional listeners, deferring the rest to future listeners.
+    pub(crate) fn coalesce_additional(&self, n: usize) {
+        self.lock().coalesce_additional(n)
+    }
+
+    /// Return the handles and states of every listener in the list.
+    #[cfg(feature = "debug")]
+    pub(crate) fn listener_states(&self) -> Vec<(ListenerHandle, ListenerState)> {
+        let states = self.lock().listener_states();
+        ListenerHandle::with_states(self, states)
+    }
+
+    /// Remove every listener from the list and return their final states.
+    pub(crate) fn drain_all(&self) -> Vec<(ListenerHandle, ListenerState)> {
+        let mut tasks = Vec::new();
+        let drained = self.lock().drain_all(&mut tasks, false);
+
+        // Wake up the tasks after releasing the lock.
+        for task in tasks {
+            task.wake();
+        }
+
+        ListenerHandle::with_states(self, drained)
+    }
+
+    /// Notify every listener and remove them from the list.
+    ///
+    /// Returns the number of listeners that weren't notified yet.
+    pub(crate) fn notify_drain(&self) -> usize {
+        let mut tasks = Vec::new();
+        let count = {
+            let mut inner = self.lock();
+            let count = inner.len - inner.notified;
+            inner.counters.notifies += count;
+            inner.drain_all(&mut tasks, false);
+            count
+        };
+
+        // Wake up the tasks after releasing the lock.
+        for task in tasks {
+            task.wake();
+        }
+
+        count
+    }
+
+    /// Remove every listener from the list, waking up their tasks if `wake` is set.
+    pub(crate) fn clear(&self, wake: bool) {
+        let mut tasks = Vec::new();
+        self.lock().drain_all(&mut tasks, true);
+
+        // Wake up the tasks after releasing the lock.
+        if wake {
+            for task in tasks {
+                task.wake();
+            }
+        }
+    }
+
+    /// Remove every listener from the list and refuse new ones.
+    pub(crate) fn shutdown(&self) {
+        let mut tasks = Vec::new();
+        {
+            let mut inner = self.lock();
+            inner.shutdown = true;
+            inner.drain_all(&mut tasks, true);
+        }
+
+        // Wake up the tasks after releasing the lock.
+        for task in tasks {
+            task.wake();
+        }
+    }
+
+    /// Returns `true` if the list was shut down.
+    pub(crate) fn is_shutdown(&self) -> bool {
+        self.lock().shutdown
+    }
+
+    /// Notify every listener and make new ones start out notified.
+    pub(crate) fn latch(&self) {
+        let mut tasks = Vec::new();
+        {
+            let mut inner = self.lock();
+            inner.latched = true;
+            self.latched.store(true, Ordering::Release);
+            inner.drain_all(&mut tasks, false);
+        }
+
+        // Wake up the tasks after releasing the lock.
+        for task in tasks {
+            task.wake();
+        }
+    }
+
+    /// Set the callback that is called when the last listener is removed.
+    pub(crate) fn set_on_empty(&self, callback: Callback) {
+        self.lock().on_empty = Some(callback);
+    }
+
+    /// Set how tasks are woken up when their listener is notified.
+    pub(crate) fn set_wake_strategy(&self, strategy: WakeStrategy) {
+        self.lock().wake_strategy = strategy;
+    }
+
+    /// Set whether tasks that wake the same task are only woken once.
+    pub(crate) fn set_wake_deduplication(&self, enabled: bool) {
+        self.lock().dedup_wakes = enabled;
+    }
+
+    /// Validate the list.
+    ///
+    /// There is no slab to compact on `std`, so this only checks the consistency of the list.
+    pub(crate) fn maintain(&self) -> MaintenanceReport {
+        MaintenanceReport {
+            nodes_applied: 0,
+            slots_compacted: 0,
+            bytes_reclaimed: 0,
+            validation: self.lock().validate(),
+        }
+    }
+
+    /// Release the memory of unused slots.
+    ///
+    /// Listeners are stored in their owners on `std`, so there is nothing to release.
+    pub(crate) fn shrink_to_fit(&self) -> usize {
+        0
+    }
+
+    /// Move the listeners into the lowest empty slots.
+    ///
+    /// Listeners are stored in their owners on `std`, so there is nothing to move.
+    pub(crate) fn compact(&self) -> usize {
+        0
+    }
+
+    /// Returns `true` if the queue was full since the last call.
+    ///
+    /// There is no queue on `std`, so this is always `false`.
+    pub(crate) fn take_saturated(&self) -> bool {
+        false
+    }
+
+    /// Get the number of listeners in the list.
+    ///
+    /// Every operation updates the count before unlocking the list, so it is read without
+    /// locking.
+    pub(crate) fn total_listeners(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
     }
 
     /// Register a task to be notified when the event is triggered.
@@ -119,24 +620,34 @@ impl crate::Inner {
     /// isn't inserted, returns `None`.
     pub(crate) fn register(
         &self,
-        mut listener: Pin<&mut Option<Listener>>,
+        mut listener: Pin<&mut Option<Listener<T>>>,
         task: TaskRef<'_>,
-    ) -> Option<bool> {
+    ) -> RegisterResult<T> {
         let mut inner = self.lock();
 
         // SAFETY: We are locked, so we can access the inner `link`.
         let entry = unsafe {
             // SAFETY: We never move out the `link` field.
-            let listener = listener.as_mut().get_unchecked_mut().as_mut()?;
-            &*listener.link.get()
+            match listener.as_mut().get_unchecked_mut().as_mut() {
+                Some(listener) => &*listener.link.get(),
+                None => return RegisterResult::NeverInserted,
+            }
         };
 
         // Take out the state and check it.
         match entry.state.replace(State::NotifiedTaken) {
-            State::Notified(_) => {
+            state @ State::Notified(..)
+            | state @ State::NotifiedWithTask(..)
+            | state @ State::Permit => {
                 // We have been notified, remove the listener.
                 inner.remove(listener, false);
-                Some(true)
+                RegisterResult::Notified(state.trace_id(), state.into_tag())
+            }
+
+            State::Shutdown => {
+                // The event was shut down, remove the listener.
+                inner.remove(listener, false);
+                RegisterResult::Shutdown
             }
 
             State::Task(other_task) => {
@@ -149,140 +660,1218 @@ impl crate::Inner {
                     }
                 }));
 
-                Some(false)
+                RegisterResult::Registered
             }
 
             _ => {
                 // We have not been notified, register the task.
                 entry.state.set(State::Task(task.into_task()));
-                Some(false)
+                entry.waiting_since.set(inner.next_wait);
+                inner.next_wait = inner.next_wait.wrapping_add(1);
+                RegisterResult::Registered
             }
         }
     }
-}
 
-impl Inner {
-    fn remove(
-        &mut self,
-        mut listener: Pin<&mut Option<Listener>>,
-        propogate: bool,
-    ) -> Option<State> {
-        let entry = unsafe {
-            // SAFETY: We never move out the `link` field.
-            let listener = listener.as_mut().get_unchecked_mut().as_mut()?;
+    /// Register a task to be notified when the event is triggered, keeping a notified listener
+    /// registered.
+    ///
+    /// A notified listener has its notification consumed and is moved back in line in place,
+    /// with the task registered. Listeners that aren't linked are handled like in `register`, and
+    /// then inserted again with their priority.
+    pub(crate) fn register_renew(
+        &self,
+        mut listener: Pin<&mut Option<Listener<T>>>,
+        task: TaskRef<'_>,
+    ) -> RegisterResult<T> {
+        let priority = {
+            let mut inner = self.lock();
+
+            // SAFETY: We are locked, so we can access the inner `link`.
+            let link = match listener.as_ref().get_ref() {
+                Some(listener) => unsafe { NonNull::new_unchecked(listener.link.get()) },
+                None => return RegisterResult::NeverInserted,
+            };
+            let entry = unsafe { link.as_ref() };
+
+            if entry.linked.get() {
+                let state = entry.state.replace(State::NotifiedTaken);
+
+                if state.is_notified() {
+                    // Consume the notification and wait for the next one, unless the entry was
+                    // notified again right away.
+                    inner.reset(link);
+                    if state_kind(&entry.state) == ListenerState::Created {
+                        entry.state.set(State::Task(task.into_task()));
+                        entry.waiting_since.set(inner.next_wait);
+                        inner.next_wait = inner.next_wait.wrapping_add(1);
+                    }
+                    return RegisterResult::Notified(state.trace_id(), state.into_tag());
+                }
 
-            // Get the inner pointer.
-            &*listener.link.get()
+                entry.state.set(state);
+            }
+
+            entry.priority
         };
 
-        let prev = entry.prev.get();
-        let next = entry.next.get();
+        let result = self.register(listener.as_mut(), task);
 
-        // Unlink from the previous entry.
-        match prev {
-            None => self.head = next,
-            Some(p) => unsafe {
-                p.as_ref().next.set(next);
-            },
+        // A listener that was drained or shut down is removed instead, so insert it again.
+        if let RegisterResult::Notified(..) | RegisterResult::Shutdown = result {
+            self.insert(listener, priority);
         }
 
-        // Unlink from the next entry.
-        match next {
-            None => self.tail = prev,
-            Some(n) => unsafe {
-                n.as_ref().prev.set(prev);
-            },
-        }
+        result
+    }
 
-        // If this was the first unnotified entry, update the next pointer.
-        if self.next == Some(entry.into()) {
-            self.next = next;
-        }
+    /// Get the state of a listener without consuming its notification.
+    ///
+    /// Returns `None` if the listener isn't inserted.
+    pub(crate) fn state(&self, listener: Pin<&mut Option<Listener<T>>>) -> Option<ListenerState> {
+        let _inner = self.lock();
 
-        // The entry is now fully unlinked, so we can now take it out safely.
-        let entry = unsafe {
-            listener
-                .get_unchecked_mut()
-                .take()
-                .unwrap()
-                .link
-                .into_inner()
-        };
+        // SAFETY: We are locked, so we can access the inner `link`.
+        let entry = unsafe { &*listener.as_ref().get_ref().as_ref()?.link.get() };
 
-        let state = entry.state.into_inner();
+        let state = entry.state.replace(State::NotifiedTaken);
+        let kind = state.kind();
+        entry.state.set(state);
+        Some(kind)
+    }
 
-        // Update the notified count.
-        if state.is_notified() {
-            self.notified -= 1;
+    /// Returns `true` if a listener was notified, without consuming the notification.
+    pub(crate) fn is_notified(&self, listener: &Option<Listener<T>>) -> bool {
+        let _inner = self.lock();
 
-            if propogate {
-                if let State::Notified(additional) = state {
-                    self.notify(1, additional);
-                }
-            }
-        }
-        self.len -= 1;
+        // SAFETY: We are locked, so we can access the inner `link`.
+        let entry = match listener {
+            Some(listener) => unsafe { &*listener.link.get() },
+            None => return false,
+        };
 
-        Some(state)
+        let state = entry.state.replace(State::NotifiedTaken);
+        let notified = state.is_notified();
+        entry.state.set(state);
+        notified
     }
 
-    #[cold]
-    fn notify(&mut self, mut n: usize, additional: bool) {
-        if !additional {
-            // Make sure we're not notifying more than we have.
-            if n <= self.notified {
-                return;
-            }
-            n -= self.notified;
-        }
+    /// Replace the state of a listener that was just inserted.
+    pub(crate) fn restore(&self, listener: Pin<&mut Option<Listener<T>>>, state: State<T>) {
+        let mut inner = self.lock();
 
-        while n > 0 {
-            n -= 1;
+        // SAFETY: We are locked, so we can access the inner `link`.
+        let link = match listener.as_ref().get_ref() {
+            Some(listener) => unsafe { NonNull::new_unchecked(listener.link.get()) },
+            None => return,
+        };
 
-            // Notify the next entry.
-            match self.next {
-                None => break,
+        inner.restore(link, state);
+    }
 
-                Some(e) => {
-                    // Get the entry and move the pointer forwards.
+    /// Notifies listeners until the items they can take add up to `needed`.
+    pub(crate) fn notify_to_capacity(&self, needed: usize) -> usize {
+        self.lock().notify_to_capacity(needed)
+    }
+
+    /// Set the group of a listener.
+    pub(crate) fn set_group(&self, listener: Pin<&mut Option<Listener<T>>>, group: T) {
+        let _inner = self.lock();
+
+        // SAFETY: We are locked, so we can access the inner `link`.
+        if let Some(listener) = listener.as_ref().get_ref() {
+            unsafe { &*listener.link.get() }.group.set(Some(group));
+        }
+    }
+
+    /// Notifies the first unnotified listener of every group.
+    pub(crate) fn notify_per_group(&self, first: &mut dyn FnMut(&T) -> bool) -> usize {
+        self.lock().notify_per_group(first)
+    }
+
+    /// Fills in the statistics of the backend.
+    ///
+    /// The linked list has neither a slab nor a queue, so there is nothing to add.
+    #[inline]
+    pub(crate) fn fill_stats(&self, _stats: &mut EventStats) {}
+
+    /// Get the slot statistics of the slab, which doesn't exist on `std`.
+    #[cfg(feature = "debug-stats")]
+    pub(crate) fn slab_stats(&self) -> crate::SlabStats {
+        crate::SlabStats::default()
+    }
+
+    /// Notifies the listener with `handle`, returning `false` if it isn't registered.
+    pub(crate) fn notify_handle(&self, handle: Handle) -> bool {
+        self.lock().notify_handle(handle)
+    }
+
+    /// Notifies the listener with `handle`, returning `false` if it isn't registered.
+    ///
+    /// The list is always locked on `std`, so nothing is deferred.
+    pub(crate) fn notify_handle_deferred(&self, handle: Handle) -> bool {
+        self.notify_handle(handle)
+    }
+
+    /// Returns the handle of a listener, or `None` if it isn't in the list.
+    pub(crate) fn handle(&self, listener: Pin<&mut Option<Listener<T>>>) -> Option<Handle> {
+        let _inner = self.lock();
+
+        // SAFETY: We are locked, so we can access the inner `link`.
+        let entry = unsafe { &*listener.as_ref().get_ref().as_ref()?.link.get() };
+
+        if entry.linked.get() {
+            Some(Handle { id: entry.id })
+        } else {
+            None
+        }
+    }
+
+    /// Detaches the notified listeners that no task is waiting on.
+    pub(crate) fn prune_notified(&self) -> usize {
+        self.lock().prune_notified()
+    }
+
+    /// Notifies a number of listeners and collects the listeners that are left without a task.
+    pub(crate) fn notify_and_harvest(
+        &self,
+        n: usize,
+        additional: bool,
+    ) -> (usize, Vec<ListenerHandle>) {
+        let (notified, created) = self.lock().notify_and_harvest(n, additional);
+        let created = created
+            .into_iter()
+            .map(|handle| ListenerHandle::new(self, handle))
+            .collect();
+        (notified, created)
+    }
+
+    /// Notifies the first unnotified listener and returns its handle.
+    pub(crate) fn notify_rendezvous(&self) -> Option<Handle> {
+        self.lock().notify_rendezvous()
+    }
+
+    /// Returns `true` if the listener with `handle` is still in the list and notified.
+    pub(crate) fn holds_notification(&self, handle: Handle) -> bool {
+        self.lock().holds_notification(handle)
+    }
+}
+
+impl<T: Clone> Inner<T> {
+    /// Find the entry that a new unnotified entry with `priority` goes in front of.
+    ///
+    /// Returns `None` if the entry goes at the end of the list.
+    fn position(&self, priority: u8) -> Option<NonNull<Link<T>>> {
+        match self.tail {
+            Some(tail) if unsafe { tail.as_ref().priority } < priority => {
+                let mut current = self.next;
+                while let Some(link) = current {
+                    let link = unsafe { link.as_ref() };
+                    if link.priority < priority {
+                        break;
+                    }
+                    current = link.next.get();
+                }
+                current
+            }
+            _ => None,
+        }
+    }
+
+    /// Links an entry in front of `before`, or at the end of the list.
+    fn link(&mut self, link: NonNull<Link<T>>, before: Option<NonNull<Link<T>>>) {
+        let entry = unsafe { link.as_ref() };
+
+        if let Some(before) = before {
+            // Link the entry in front of `before`.
+            let prev = unsafe { before.as_ref().prev.replace(Some(link)) };
+            match prev {
+                None => self.head = Some(link),
+                Some(p) => unsafe { p.as_ref().next.set(Some(link)) },
+            }
+
+            entry.prev.set(prev);
+            entry.next.set(Some(before));
+
+            if self.next == Some(before) {
+                self.next = Some(link);
+            }
+        } else {
+            // Replace the tail with the entry.
+            let prev = self.tail.replace(link);
+            match prev {
+                None => self.head = Some(link),
+                Some(t) => unsafe { t.as_ref().next.set(Some(link)) },
+            };
+
+            entry.prev.set(prev);
+            entry.next.set(None);
+        }
+    }
+
+    /// Unlinks an entry from its neighbors.
+    fn unlink(&mut self, link: NonNull<Link<T>>) {
+        let entry = unsafe { link.as_ref() };
+        let prev = entry.prev.get();
+        let next = entry.next.get();
+
+        // Unlink from the previous entry.
+        match prev {
+            None => self.head = next,
+            Some(p) => unsafe {
+                p.as_ref().next.set(next);
+            },
+        }
+
+        // Unlink from the next entry.
+        match next {
+            None => self.tail = prev,
+            Some(n) => unsafe {
+                n.as_ref().prev.set(prev);
+            },
+        }
+
+        // If this was the first unnotified entry, update the next pointer.
+        if self.next == Some(link) {
+            self.next = next;
+        }
+    }
+
+    /// Takes a waiting permit, deferred notification or mark for a new entry, and returns the
+    /// state the entry starts out in.
+    fn admit(&mut self) -> State<T> {
+        if self.permits > 0 {
+            self.permits -= 1;
+            State::Permit
+        } else if self.deferred > 0 {
+            self.deferred -= 1;
+            State::Notified(true, TraceId::default(), None)
+        } else if self.marked {
+            self.marked = false;
+            State::Notified(true, TraceId::default(), None)
+        } else {
+            State::Created
+        }
+    }
+
+    /// Discards the notification of an entry and moves it to where a new entry would go.
+    ///
+    /// Like a new entry, it takes a waiting permit, deferred notification or mark.
+    fn reset(&mut self, link: NonNull<Link<T>>) {
+        let entry = unsafe { link.as_ref() };
+        self.unlink(link);
+
+        if entry.state.replace(State::Created).is_notified() {
+            self.notified -= 1;
+            self.released = true;
+        }
+
+        let state = self.admit();
+        if state.is_notified() {
+            // The entry starts out notified, so it goes at the end.
+            entry.state.set(state);
+            self.link(link, None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            return;
+        }
+
+        let before = self.position(entry.priority);
+        self.link(link, before);
+
+        if self.next.is_none() {
+            self.next = Some(link);
+        }
+    }
+
+    /// Replace the state of a listener that was just inserted.
+    ///
+    /// A notified listener is moved in front of `next`.
+    fn restore(&mut self, link: NonNull<Link<T>>, state: State<T>) {
+        let entry = unsafe { link.as_ref() };
+        let notified = state.is_notified();
+        let old = entry.state.replace(state);
+        debug_assert!(!old.is_notified());
+
+        if notified {
+            if self.next == Some(link) {
+                self.next = entry.next.get();
+            } else {
+                self.move_before_next(link);
+            }
+
+            self.notified += 1;
+            self.counters.notifies += 1;
+        }
+    }
+
+    fn remove(
+        &mut self,
+        mut listener: Pin<&mut Option<Listener<T>>>,
+        propogate: bool,
+    ) -> Option<State<T>> {
+        let entry = unsafe {
+            // SAFETY: We never move out the `link` field.
+            let listener = listener.as_mut().get_unchecked_mut().as_mut()?;
+
+            // Get the inner pointer.
+            &*listener.link.get()
+        };
+
+        if !entry.linked.get() {
+            // The listener was drained, so it is no longer part of the list.
+            let entry = unsafe {
+                listener
+                    .get_unchecked_mut()
+                    .take()
+                    .unwrap()
+                    .link
+                    .into_inner()
+            };
+
+            return Some(entry.state.into_inner());
+        }
+
+        self.unlink(entry.into());
+
+        // The entry is now fully unlinked, so we can now take it out safely.
+        let entry = unsafe {
+            listener
+                .get_unchecked_mut()
+                .take()
+                .unwrap()
+                .link
+                .into_inner()
+        };
+
+        let state = entry.state.into_inner();
+
+        // Update the notified count.
+        if state.is_notified() {
+            self.notified -= 1;
+            self.released = true;
+
+            if propogate {
+                match &state {
+                    State::Notified(additional, trace_id, tag)
+                    | State::NotifiedWithTask(additional, trace_id, tag, _) => {
+                        self.notify(1, *additional, *trace_id, tag.clone());
+                    }
+                    State::Permit => {
+                        self.notify_permit();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.len -= 1;
+        self.counters.removes += 1;
+        if self.len == 0 {
+            self.emptied = true;
+        }
+
+        Some(state)
+    }
+
+    /// Returns the handles and states of the linked entries, in list order.
+    #[cfg(feature = "debug")]
+    fn listener_states(&self) -> Vec<(Handle, ListenerState)> {
+        let mut states = Vec::with_capacity(self.len);
+        let mut current = self.head;
+
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            let state = entry.state.replace(State::NotifiedTaken);
+            states.push((Handle { id: entry.id }, state.kind()));
+            entry.state.set(state);
+        }
+
+        states
+    }
+
+    /// Unlinks every entry and returns their final states.
+    ///
+    /// The entries are left notified, or shut down if `shutdown` is set, so their owners complete
+    /// once they are polled. Notified entries keep their notification when shutting down. Tasks
+    /// waiting on the entries are pushed to `tasks`.
+    fn drain_all(&mut self, tasks: &mut Vec<Task>, shutdown: bool) -> Vec<(Handle, ListenerState)> {
+        let mut drained = Vec::with_capacity(self.len);
+        let mut current = self.head.take();
+
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            // Detach the entry from the list.
+            entry.linked.set(false);
+            entry.prev.set(None);
+            entry.next.set(None);
+
+            let state = entry.state.replace(State::NotifiedTaken);
+            drained.push((Handle { id: entry.id }, state.kind()));
+
+            if shutdown && state.is_notified() {
+                entry.state.set(state);
+                continue;
+            }
+
+            entry.state.set(if shutdown {
+                State::Shutdown
+            } else {
+                State::Notified(false, TraceId::default(), None)
+            });
+
+            if let State::Task(task) = state {
+                tasks.push(task);
+            }
+        }
+
+        self.tail = None;
+        self.next = None;
+        self.released |= self.notified > 0;
+        self.notified = 0;
+        if self.len > 0 {
+            self.counters.removes += self.len;
+            self.len = 0;
+            self.emptied = true;
+        }
+
+        drained
+    }
+
+    /// Notifies the entry with `handle` unless it is already notified.
+    ///
+    /// The entry is moved in front of `next`. Returns `false` if the entry isn't in the list.
+    fn notify_handle(&mut self, handle: Handle) -> bool {
+        let mut current = self.head;
+
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            if entry.id != handle.id {
+                continue;
+            }
+
+            let state = entry.state.replace(State::NotifiedTaken);
+            let notified = state.is_notified();
+            entry.state.set(state);
+
+            if !notified {
+                if self.next == Some(link) {
+                    self.next = current;
+                } else {
+                    self.move_before_next(link);
+                }
+
+                self.notify_entry(entry, true, TraceId::default(), None);
+                self.notified += 1;
+                self.counters.notifies += 1;
+            }
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Gives the first unnotified entry an additional notification and returns its handle.
+    fn notify_rendezvous(&mut self) -> Option<Handle> {
+        let link = self.next?;
+        let entry = unsafe { link.as_ref() };
+        self.next = entry.next.get();
+
+        self.notify_entry(entry, true, TraceId::default(), None);
+        self.notified += 1;
+        self.counters.notifies += 1;
+        Some(Handle { id: entry.id })
+    }
+
+    /// Returns `true` if the entry with `handle` is linked and notified.
+    fn holds_notification(&self, handle: Handle) -> bool {
+        let mut current = self.head;
+
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            if entry.id == handle.id {
+                let state = entry.state.replace(State::NotifiedTaken);
+                let notified = state.is_notified();
+                entry.state.set(state);
+                return notified;
+            }
+        }
+
+        false
+    }
+
+    /// Unlinks the notified entries that no task is waiting on, without propagating their
+    /// notifications.
+    ///
+    /// Like drained entries, the entries are left notified, so their owners complete once they
+    /// are polled.
+    fn prune_notified(&mut self) -> usize {
+        let mut pruned = 0;
+        let mut current = self.head;
+
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            let state = entry.state.replace(State::NotifiedTaken);
+            let idle = match state {
+                State::Notified(..) | State::NotifiedTaken => true,
+                _ => false,
+            };
+            entry.state.set(state);
+
+            if !idle {
+                continue;
+            }
+
+            // Detach the entry from the list.
+            self.unlink(link);
+            entry.linked.set(false);
+            entry.prev.set(None);
+            entry.next.set(None);
+            entry
+                .state
+                .set(State::Notified(false, TraceId::default(), None));
+
+            self.notified -= 1;
+            self.released = true;
+            self.len -= 1;
+            self.counters.removes += 1;
+            pruned += 1;
+        }
+
+        if pruned > 0 && self.len == 0 {
+            self.emptied = true;
+        }
+
+        pruned
+    }
+
+    /// Notifies `n` additional entries, deferring the notifications that can't be delivered to
+    /// the next entries to be inserted.
+    fn coalesce_additional(&mut self, n: usize) {
+        let notified = self.notified;
+        self.notify(n, true, TraceId::default(), None);
+        self.deferred = self.deferred.saturating_add(n - (self.notified - notified));
+    }
+
+    /// Notifies the next unnotified entry, or marks the list so that the next entry to be
+    /// inserted starts out notified.
+    fn notify_or_mark(&mut self) -> bool {
+        if self.next.is_none() {
+            self.marked = true;
+            return false;
+        }
+
+        self.notify(1, true, TraceId::default(), None);
+        true
+    }
+
+    /// Hands a permit to the next unnotified entry, or stores it if there is none.
+    fn notify_permit(&mut self) -> bool {
+        match self.next {
+            None => {
+                self.permits += 1;
+                false
+            }
+
+            Some(e) => {
+                // Get the entry and move the pointer forwards.
+                let entry = unsafe { e.as_ref() };
+                self.next = entry.next.get();
+
+                // Set the state to `Permit` and notify.
+                if let State::Task(task) = entry.state.replace(State::Permit) {
+                    self.wake(task);
+                }
+
+                // Bump the notified count.
+                self.notified += 1;
+                self.counters.notifies += 1;
+                true
+            }
+        }
+    }
+
+    /// Check that the links, the length and the notified count are consistent.
+    fn validate(&self) -> Result<(), SlabError> {
+        let mut len = 0;
+        let mut notified = 0;
+        let mut found_next = self.next.is_none();
+        let mut prev = None;
+        let mut current = self.head;
+
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+
+            if entry.prev.get() != prev {
+                return Err(SlabError::BrokenLink);
+            }
+
+            if self.next == Some(link) {
+                found_next = true;
+            }
+
+            // SAFETY: We are locked, so nobody else is accessing the state.
+            let state = entry.state.replace(State::NotifiedTaken);
+            if state.is_notified() {
+                notified += 1;
+            }
+            entry.state.set(state);
+
+            len += 1;
+            prev = Some(link);
+            current = entry.next.get();
+        }
+
+        if prev != self.tail {
+            return Err(SlabError::TailMismatch);
+        }
+
+        if !found_next {
+            return Err(SlabError::DanglingStart);
+        }
+
+        if len != self.len {
+            return Err(SlabError::LengthMismatch {
+                expected: self.len,
+                found: len,
+            });
+        }
+
+        if notified != self.notified {
+            return Err(SlabError::NotifiedMismatch {
+                expected: self.notified,
+                found: notified,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Notifies a number of entries and returns how many were notified by this call.
+    #[cold]
+    fn notify(&mut self, n: usize, additional: bool, trace_id: TraceId, tag: Option<T>) -> usize {
+        self.notify_each(n, additional, trace_id, || tag.clone())
+    }
+
+    /// Notifies a number of entries, giving each of them the tag returned by `tag`.
+    ///
+    /// `tag` is called once per notified entry, in notification order. Returns how many entries
+    /// were notified by this call.
+    fn notify_each(
+        &mut self,
+        mut n: usize,
+        additional: bool,
+        trace_id: TraceId,
+        mut tag: impl FnMut() -> Option<T>,
+    ) -> usize {
+        if !additional {
+            #[cfg(feature = "trace-id")]
+            {
+                if trace_id.0.is_some() {
+                    self.retrace(n, trace_id);
+                }
+            }
+
+            // Make sure we're not notifying more than we have.
+            if n <= self.notified {
+                return 0;
+            }
+            n -= self.notified;
+        }
+
+        let mut count = 0;
+        while n > 0 {
+            n -= 1;
+
+            // Notify the next entry.
+            match self.next {
+                None => break,
+
+                Some(e) => {
+                    // Get the tag before touching the list, in case `tag` panics.
+                    let tag = tag();
+
+                    // Get the entry and move the pointer forwards.
                     let entry = unsafe { e.as_ref() };
                     self.next = entry.next.get();
 
+                    // Notified entries, including those whose notification is being consumed,
+                    // are kept in front of `next`, so they are never notified twice.
+                    debug_assert!(!{
+                        let state = entry.state.replace(State::NotifiedTaken);
+                        let notified = state.is_notified();
+                        entry.state.set(state);
+                        notified
+                    });
+
                     // Set the state to `Notified` and notify.
-                    if let State::Task(task) = entry.state.replace(State::Notified(additional)) {
-                        task.wake();
-                    }
+                    self.notify_entry(entry, additional, trace_id, tag);
 
                     // Bump the notified count.
                     self.notified += 1;
+                    self.counters.notifies += 1;
+                    count += 1;
                 }
             }
         }
+
+        count
+    }
+
+    /// Notifies a number of listeners without raising the number of notified ones above `cap`.
+    ///
+    /// The excess is discarded. Listeners that are already notified stay notified, even if
+    /// there are more than `cap` of them.
+    fn notify_capped(&mut self, n: usize, additional: bool, cap: usize) {
+        let n = if additional {
+            n.min(cap.saturating_sub(self.notified))
+        } else {
+            n.min(cap)
+        };
+
+        self.notify(n, additional, TraceId::default(), None);
+    }
+
+    /// Notifies the first unnotified entry, unless an entry is already notified.
+    fn notify_one(&mut self) {
+        if self.notified > 0 {
+            return;
+        }
+
+        if let Some(e) = self.next {
+            // Get the entry and move the pointer forwards.
+            let entry = unsafe { e.as_ref() };
+            self.next = entry.next.get();
+
+            self.notify_entry(entry, false, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+        }
+    }
+
+    /// Notifies `n` additional listeners for which `pred` returns `true`.
+    ///
+    /// Notified listeners are moved in front of `next`, so that skipped listeners stay
+    /// eligible for later notifications.
+    fn notify_filtered(&mut self, mut n: usize, pred: &mut dyn FnMut(&ListenerState) -> bool) {
+        let mut current = self.next;
+
+        while n > 0 {
+            let link = match current {
+                Some(link) => link,
+                None => break,
+            };
+
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            // Skip listeners that are already notified or don't match.
+            let kind = state_kind(&entry.state);
+            let matched = !kind.is_notified() && pred(&kind);
+
+            if !matched {
+                continue;
+            }
+
+            if self.next == Some(link) {
+                self.next = current;
+            } else {
+                self.move_before_next(link);
+            }
+
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            n -= 1;
+        }
+    }
+
+    /// Notifies unnotified entries in order until the items they can take add up to `needed`.
+    ///
+    /// Returns the number of items the notified entries can take.
+    fn notify_to_capacity(&mut self, needed: usize) -> usize {
+        let mut notified = 0;
+
+        while notified < needed {
+            let e = match self.next {
+                Some(e) => e,
+                None => break,
+            };
+
+            // Get the entry and move the pointer forwards.
+            let entry = unsafe { e.as_ref() };
+            self.next = entry.next.get();
+
+            notified = notified.saturating_add(entry.capacity.get());
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+        }
+
+        notified
+    }
+
+    /// Notifies a number of entries and returns how many were notified, along with the handles
+    /// of the unnotified entries that don't have a task yet.
+    fn notify_and_harvest(&mut self, n: usize, additional: bool) -> (usize, Vec<Handle>) {
+        let before = self.notified;
+        self.notify(n, additional, TraceId::default(), None);
+
+        let mut created = Vec::new();
+        let mut current = self.next;
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            let state = entry.state.replace(State::NotifiedTaken);
+            if let State::Created = state {
+                created.push(Handle { id: entry.id });
+            }
+            entry.state.set(state);
+        }
+
+        (self.notified - before, created)
+    }
+
+    /// Notifies the first unnotified entry of every group for which `first` returns `true`.
+    ///
+    /// `first` is called with the group of every unnotified entry that has one, in order.
+    /// Notified entries are moved in front of `next`. Returns the number of notified entries.
+    fn notify_per_group(&mut self, first: &mut dyn FnMut(&T) -> bool) -> usize {
+        let mut notified = 0;
+        let mut current = self.next;
+
+        while let Some(link) = current {
+            let entry = unsafe { link.as_ref() };
+            current = entry.next.get();
+
+            // Skip listeners that are already notified, have no group or aren't the first of it.
+            // SAFETY: We are locked, so nothing writes to the group while `first` looks at it.
+            let group = unsafe { &*entry.group.as_ptr() };
+            let matched = !state_kind(&entry.state).is_notified()
+                && group.as_ref().map_or(false, &mut *first);
+
+            if !matched {
+                continue;
+            }
+
+            if self.next == Some(link) {
+                self.next = current;
+            } else {
+                self.move_before_next(link);
+            }
+
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            notified += 1;
+        }
+
+        notified
+    }
+
+    /// Notifies `n` additional entries, preferring the ones whose tasks have been waiting the
+    /// longest.
+    ///
+    /// Once no unnotified entry has a waiting task, the rest are notified in order.
+    fn notify_oldest(&mut self, mut n: usize) {
+        while n > 0 {
+            // Find the unnotified entry whose task has been waiting the longest.
+            let mut oldest = None;
+            let mut current = self.next;
+            while let Some(link) = current {
+                let entry = unsafe { link.as_ref() };
+                current = entry.next.get();
+
+                let state = entry.state.replace(State::NotifiedTaken);
+                let waiting = matches!(state, State::Task(_));
+                entry.state.set(state);
+
+                let since = entry.waiting_since.get();
+                if waiting && oldest.map_or(true, |(_, oldest)| since < oldest) {
+                    oldest = Some((link, since));
+                }
+            }
+
+            let link = match oldest {
+                Some((link, _)) => link,
+                None => break,
+            };
+
+            let entry = unsafe { link.as_ref() };
+            if self.next == Some(link) {
+                self.next = entry.next.get();
+            } else {
+                self.move_before_next(link);
+            }
+
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            n -= 1;
+        }
+
+        self.notify(n, true, TraceId::default(), None);
+    }
+
+    /// Notifies `n` additional entries, starting from the end of the list.
+    ///
+    /// Notified entries are moved in front of `next`, so the notified entries stay in front of
+    /// the unnotified ones.
+    fn notify_lifo(&mut self, mut n: usize) {
+        while n > 0 {
+            // The last entry is unnotified as long as any entry is.
+            if self.next.is_none() {
+                break;
+            }
+            let link = self.tail.unwrap();
+
+            let entry = unsafe { link.as_ref() };
+            if self.next == Some(link) {
+                self.next = None;
+            } else {
+                self.move_before_next(link);
+            }
+
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            n -= 1;
+        }
+    }
+
+    /// Notifies `n` additional entries, taking turns by id.
+    ///
+    /// Entries live in their listeners, so there are no slots to rotate over. Instead, the
+    /// unnotified entry with the lowest id above that of the entry notified last by this method
+    /// is notified, wrapping around to the lowest id. Ids are never reused, so the cursor stays
+    /// meaningful after its entry is removed.
+    ///
+    /// Returns the number of entries that were notified.
+    fn notify_round_robin(&mut self, mut n: usize) -> usize {
+        let mut count = 0;
+        while n > 0 {
+            // Find the next entry after the cursor, and the first one to wrap around to.
+            let (mut after, mut first) = (None, None);
+            let mut current = self.next;
+            while let Some(link) = current {
+                let entry = unsafe { link.as_ref() };
+                current = entry.next.get();
+
+                let id = entry.id;
+                if id > self.cursor && after.map_or(true, |(_, after)| id < after) {
+                    after = Some((link, id));
+                }
+                if first.map_or(true, |(_, first)| id < first) {
+                    first = Some((link, id));
+                }
+            }
+
+            let (link, id) = match after.or(first) {
+                Some(next) => next,
+                None => break,
+            };
+
+            let entry = unsafe { link.as_ref() };
+            if self.next == Some(link) {
+                self.next = entry.next.get();
+            } else {
+                self.move_before_next(link);
+            }
+
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            self.cursor = id;
+            count += 1;
+            n -= 1;
+        }
+
+        count
+    }
+
+    /// Notifies entries until `target` of them hold a notification.
+    ///
+    /// The notified entries are counted again instead of relying on `notified`. Returns the
+    /// number of entries that were notified, which is zero if `target` is already reached.
+    fn notify_saturating(&mut self, target: usize) -> usize {
+        let mut live = 0;
+        let mut current = self.head;
+        while current != self.next {
+            let entry = unsafe { current.unwrap().as_ref() };
+            current = entry.next.get();
+
+            let state = entry.state.replace(State::NotifiedTaken);
+            if state.is_notified() {
+                live += 1;
+            }
+            entry.state.set(state);
+        }
+
+        self.notify(target.saturating_sub(live), true, TraceId::default(), None)
+    }
+
+    /// Notifies `n` additional entries, preferring the ones inserted in the oldest round.
+    ///
+    /// Entries inserted in the same round are notified in order. Every call starts a new round,
+    /// so an entry is never passed over for one inserted after it, regardless of priorities.
+    ///
+    /// Returns the number of entries that were notified.
+    fn notify_fair(&mut self, mut n: usize) -> usize {
+        let mut count = 0;
+        while n > 0 {
+            // Find the first unnotified entry from the oldest round.
+            let mut oldest = None;
+            let mut current = self.next;
+            while let Some(link) = current {
+                let entry = unsafe { link.as_ref() };
+                current = entry.next.get();
+
+                if oldest.map_or(true, |(_, oldest)| entry.epoch < oldest) {
+                    oldest = Some((link, entry.epoch));
+                }
+            }
+
+            let link = match oldest {
+                Some((link, _)) => link,
+                None => break,
+            };
+
+            let entry = unsafe { link.as_ref() };
+            if self.next == Some(link) {
+                self.next = entry.next.get();
+            } else {
+                self.move_before_next(link);
+            }
+
+            self.notify_entry(entry, true, TraceId::default(), None);
+            self.notified += 1;
+            self.counters.notifies += 1;
+            count += 1;
+            n -= 1;
+        }
+
+        self.epoch = self.epoch.wrapping_add(1);
+        count
+    }
+
+    /// Moves a link after `next` to the position right in front of it.
+    fn move_before_next(&mut self, link: NonNull<Link<T>>) {
+        let next_unnotified = self.next.unwrap();
+        let entry = unsafe { link.as_ref() };
+        let (prev, next) = (entry.prev.get(), entry.next.get());
+
+        // Unlink the entry. It comes after `next`, so it always has a previous entry.
+        unsafe { prev.unwrap().as_ref().next.set(next) };
+        match next {
+            None => self.tail = prev,
+            Some(next) => unsafe { next.as_ref().prev.set(prev) },
+        }
+
+        // Link it in front of `next`.
+        let before = unsafe { next_unnotified.as_ref().prev.replace(Some(link)) };
+        match before {
+            None => self.head = Some(link),
+            Some(before) => unsafe { before.as_ref().next.set(Some(link)) },
+        }
+
+        entry.prev.set(before);
+        entry.next.set(Some(next_unnotified));
+    }
+
+    /// Sets the state of an entry to `Notified` and wakes its task.
+    fn notify_entry(&self, entry: &Link<T>, additional: bool, trace_id: TraceId, tag: Option<T>) {
+        let notified = State::Notified(additional, trace_id, tag);
+        if let State::Task(task) = entry.state.replace(notified) {
+            match self.wake_strategy {
+                WakeStrategy::Consume => self.wake(task),
+                WakeStrategy::ByRef => {
+                    // Keep the task around so it can be reused.
+                    self.wake_by_ref(&task);
+                    let tag = entry.state.replace(State::NotifiedTaken).into_tag();
+                    entry
+                        .state
+                        .set(State::NotifiedWithTask(additional, trace_id, tag, task));
+                }
+            }
+        }
+    }
+
+    /// Wakes a task, or defers it until the list is unlocked if wakes are deduplicated.
+    fn wake(&self, task: Task) {
+        if self.dedup_wakes {
+            let mut woken = self.woken.take();
+            task.push_deduplicated(&mut woken);
+            self.woken.set(woken);
+        } else {
+            task.wake();
+        }
+    }
+
+    /// Wakes a task by reference, or defers a clone of it if wakes are deduplicated.
+    fn wake_by_ref(&self, task: &Task) {
+        if self.dedup_wakes {
+            self.wake(task.clone());
+        } else {
+            task.wake_by_ref();
+        }
+    }
+
+    /// Replaces the trace id of the first `n` listeners that are notified, but haven't received
+    /// their notification yet.
+    #[cfg(feature = "trace-id")]
+    fn retrace(&mut self, n: usize, trace_id: TraceId) {
+        let mut current = self.head;
+
+        for _ in 0..n {
+            match current {
+                Some(link) if current != self.next => {
+                    let entry = unsafe { link.as_ref() };
+                    let state = entry.state.replace(State::NotifiedTaken);
+                    entry.state.set(state.with_trace_id(trace_id));
+                    current = entry.next.get();
+                }
+                _ => break,
+            }
+        }
     }
 }
 
-struct ListLock<'a, 'b> {
-    lock: MutexGuard<'a, Inner>,
-    inner: &'b crate::Inner,
+struct ListLock<'a, 'b, T> {
+    lock: Option<MutexGuard<'a, Inner<T>>>,
+    inner: &'b crate::Inner<T>,
+
+    /// The ordering used to publish the `notified` count.
+    notified_ordering: Ordering,
 }
 
-impl Deref for ListLock<'_, '_> {
-    type Target = Inner;
+impl<T> Deref for ListLock<'_, '_, T> {
+    type Target = Inner<T>;
 
     fn deref(&self) -> &Self::Target {
-        &self.lock
+        self.lock.as_ref().unwrap()
     }
 }
 
-impl DerefMut for ListLock<'_, '_> {
+impl<T> DerefMut for ListLock<'_, '_, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.lock
+        self.lock.as_mut().unwrap()
     }
 }
 
-impl Drop for ListLock<'_, '_> {
+impl<T> Drop for ListLock<'_, '_, T> {
     fn drop(&mut self) {
-        let list = &mut **self;
+        let mut list = self.lock.take().unwrap();
 
         // Update the notified count.
         let notified = if list.notified < list.len {
@@ -291,31 +1880,91 @@ impl Drop for ListLock<'_, '_> {
             core::usize::MAX
         };
 
-        self.inner.notified.store(notified, Ordering::Release);
+        self.inner.notified.store(notified, self.notified_ordering);
+        self.inner
+            .notified_count
+            .store(list.notified, Ordering::Relaxed);
+        let len_changed = self.inner.set_len(list.len);
+        self.inner.publish_counters(&list.counters);
+
+        let released = list.released;
+        list.released = false;
+
+        // Check if the list was emptied.
+        let on_empty = if list.emptied {
+            list.emptied = false;
+            list.on_empty.clone()
+        } else {
+            None
+        };
+
+        // Wake the deferred tasks and call the callback after releasing the lock.
+        let woken = list.woken.take();
+        drop(list);
+        for task in woken {
+            task.wake();
+        }
+        if len_changed {
+            self.inner.len_changed();
+        }
+        if released {
+            self.inner.released();
+        }
+        if let Some(on_empty) = on_empty {
+            on_empty();
+        }
     }
 }
 
-pub(crate) struct Listener {
+pub(crate) struct Listener<T> {
     /// The inner link in the linked list.
     ///
     /// # Safety
     ///
     /// This can only be accessed while the central mutex is locked.
-    link: UnsafeCell<Link>,
+    link: UnsafeCell<Link<T>>,
 
     /// This listener cannot be moved after being pinned.
     _pin: PhantomPinned,
 }
 
-struct Link {
+/// The identity of a listener in the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Handle {
+    /// The identifier of the listener.
+    id: usize,
+}
+
+struct Link<T> {
+    /// The unique identifier of the listener.
+    id: usize,
+
+    /// The priority of the listener.
+    priority: u8,
+
+    /// When the task of the listener started waiting, relative to other listeners.
+    waiting_since: Cell<usize>,
+
+    /// The `notify_fair` round in which the listener was inserted.
+    epoch: usize,
+
+    /// The number of items the listener can take.
+    capacity: Cell<usize>,
+
+    /// The group of the listener.
+    group: Cell<Option<T>>,
+
+    /// Whether the link is still part of the list.
+    linked: Cell<bool>,
+
     /// The current state of the listener.
-    state: Cell<State>,
+    state: Cell<State<T>>,
 
     /// The previous link in the linked list.
-    prev: Cell<Option<NonNull<Link>>>,
+    prev: Cell<Option<NonNull<Link<T>>>>,
 
     /// The next link in the linked list.
-    next: Cell<Option<NonNull<Link>>>,
+    next: Cell<Option<NonNull<Link<T>>>>,
 }
 
 #[cfg(test)]
@@ -326,7 +1975,7 @@ mod tests {
     macro_rules! make_listeners {
         ($($id:ident),*) => {
             $(
-                let $id = Option::<Listener>::None;
+                let $id = Option::<Listener<()>>::None;
                 pin!($id);
             )*
         };
@@ -334,13 +1983,13 @@ mod tests {
 
     #[test]
     fn insert() {
-        let inner = crate::Inner::new();
+        let inner = crate::Inner::new(crate::DEFAULT_SPIN_BUDGET, core::usize::MAX, 0);
         make_listeners!(listen1, listen2, listen3);
 
         // Register the listeners.
-        inner.insert(listen1.as_mut());
-        inner.insert(listen2.as_mut());
-        inner.insert(listen3.as_mut());
+        inner.insert(listen1.as_mut(), 0);
+        inner.insert(listen2.as_mut(), 0);
+        inner.insert(listen3.as_mut(), 0);
 
         assert_eq!(inner.lock().len, 3);
 
@@ -355,13 +2004,13 @@ mod tests {
 
     #[test]
     fn drop_non_notified() {
-        let inner = crate::Inner::new();
+        let inner = crate::Inner::new(crate::DEFAULT_SPIN_BUDGET, core::usize::MAX, 0);
         make_listeners!(listen1, listen2, listen3);
 
         // Register the listeners.
-        inner.insert(listen1.as_mut());
-        inner.insert(listen2.as_mut());
-        inner.insert(listen3.as_mut());
+        inner.insert(listen1.as_mut(), 0);
+        inner.insert(listen2.as_mut(), 0);
+        inner.insert(listen3.as_mut(), 0);
 
         // Notify one.
         inner.notify(1, false);
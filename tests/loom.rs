@@ -0,0 +1,40 @@
+//! Models of the event under loom.
+//!
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --test loom --release`.
+
+#![cfg(all(loom, feature = "std"))]
+
+use event_listener::Event;
+use loom::cell::UnsafeCell;
+use loom::sync::Arc;
+use loom::thread;
+
+/// A value written without synchronization, relying on the event for visibility.
+struct Slot(UnsafeCell<usize>);
+
+unsafe impl Send for Slot {}
+unsafe impl Sync for Slot {}
+
+#[test]
+fn notify_happens_before_wake() {
+    loom::model(|| {
+        let event = Arc::new(Event::new());
+        let slot = Arc::new(Slot(UnsafeCell::new(0)));
+        let listener = event.listen();
+
+        let producer = thread::spawn({
+            let event = event.clone();
+            let slot = slot.clone();
+            move || {
+                slot.0.with_mut(|value| unsafe { *value = 1 });
+                event.notify(1);
+            }
+        });
+
+        // Loom reports a data race if the write isn't ordered before the read.
+        loom::future::block_on(listener);
+        assert_eq!(slot.0.with(|value| unsafe { *value }), 1);
+
+        producer.join().unwrap();
+    });
+}
@@ -1,13 +1,20 @@
 use std::future::Future;
+use std::panic;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::task::Context;
+use std::task::{Context, Poll};
 use std::usize;
 
-use event_listener::{Event, EventListener};
+#[cfg(feature = "debug")]
+use event_listener::ListenerStateKind;
+use event_listener::{
+    listen_pinned, Additional, BalancedNotifier, Event, EventGone, EventGroup, EventListener,
+    ListenerState, LocalEvent, Notification, Shutdown, StateChange, TaskHandle, WakeStrategy,
+};
 use waker_fn::waker_fn;
 
-fn is_notified(listener: Pin<&mut EventListener>) -> bool {
+fn is_notified<T: Clone>(listener: Pin<&mut EventListener<T>>) -> bool {
     let waker = waker_fn(|| ());
     listener.poll(&mut Context::from_waker(&waker)).is_ready()
 }
@@ -49,6 +56,40 @@ fn notify_additional() {
     assert!(!is_notified(l3.as_mut()));
 }
 
+#[test]
+fn notify_saturating() {
+    let event = Event::new();
+    assert_eq!(event.notify_saturating(1), 0);
+
+    let l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    let mut l4 = event.listen();
+
+    // Targets at or below the number of notified listeners do nothing.
+    assert_eq!(event.notify_saturating(2), 2);
+    assert_eq!(event.notify_saturating(2), 0);
+    assert_eq!(event.notify_saturating(1), 0);
+
+    // Unlike with `notify(2)`, a dropped listener passes its notification on.
+    drop(l1);
+    assert!(is_notified(l3.as_mut()));
+
+    // Listeners that took their notification don't count anymore.
+    assert_eq!(event.notify_saturating(2), 1);
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l4.as_mut()));
+
+    let event = Event::new();
+    let l1 = event.listen();
+    let _l2 = event.listen();
+    let mut l3 = event.listen();
+
+    event.notify(2);
+    drop(l1);
+    assert!(!is_notified(l3.as_mut()));
+}
+
 #[test]
 fn notify_one() {
     let event = Event::new();
@@ -186,3 +227,2513 @@ fn notify_all_fair() {
         .poll(&mut Context::from_waker(&waker3))
         .is_ready());
 }
+
+#[test]
+fn maintain() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let l2 = event.listen();
+    let _l3 = event.listen();
+
+    event.notify(1);
+    drop(l2);
+
+    let report = event.maintain();
+    assert_eq!(report.validation, Ok(()));
+    assert!(is_notified(l1.as_mut()));
+}
+
+#[test]
+fn on_empty() {
+    let event = Event::new();
+    let count = Arc::new(AtomicUsize::new(0));
+    event.set_on_empty({
+        let count = count.clone();
+        move || {
+            count.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    // Nothing happens when the event is already empty.
+    event.notify(1);
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+
+    let l1 = event.listen();
+    let l2 = event.listen();
+
+    drop(l1);
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+
+    drop(l2);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn notify_permit() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+
+    // The permit goes to the first waiter.
+    assert!(event.notify_permit());
+    assert!(is_notified(l1.as_mut()));
+    assert!(!is_notified(l2.as_mut()));
+
+    // The permit was not stored.
+    let mut l3 = event.listen();
+    assert!(!is_notified(l3.as_mut()));
+}
+
+#[test]
+fn notify_permit_stored() {
+    let event = Event::new();
+
+    // Nobody is waiting, so the permit is stored.
+    assert!(!event.notify_permit());
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(is_notified(l1.as_mut()));
+    assert!(!is_notified(l2.as_mut()));
+}
+
+#[test]
+fn drop_permit() {
+    let event = Event::new();
+
+    let l1 = event.listen();
+    assert!(event.notify_permit());
+
+    // The permit is returned to the event.
+    drop(l1);
+
+    let mut l2 = event.listen();
+    assert!(is_notified(l2.as_mut()));
+}
+
+#[test]
+fn notify_or_mark() {
+    let event = Event::new();
+
+    // Marks don't add up.
+    assert!(!event.notify_or_mark());
+    assert!(!event.notify_or_mark());
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(is_notified(l1.as_mut()));
+    assert!(!is_notified(l2.as_mut()));
+
+    // The mark was consumed, so this goes to the waiter.
+    assert!(event.notify_or_mark());
+    assert!(is_notified(l2.as_mut()));
+    let mut l3 = event.listen();
+    assert!(!is_notified(l3.as_mut()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_timeout() {
+    use std::thread;
+    use std::time::Duration;
+
+    let event = Arc::new(Event::new());
+
+    // Nobody notifies the first listener.
+    let mut l1 = event.listen();
+    assert!(!l1.as_mut().wait_timeout(Duration::from_millis(50)));
+
+    // The timed out listener was removed, so the next one gets the notification.
+    let mut l2 = event.listen();
+    thread::spawn({
+        let event = event.clone();
+        move || {
+            thread::sleep(Duration::from_millis(50));
+            event.notify(1);
+        }
+    });
+    assert!(l2.as_mut().wait_timeout(Duration::from_secs(10)));
+}
+
+#[test]
+fn drain_all() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let l3 = event.listen();
+
+    let woken = Arc::new(AtomicBool::new(false));
+    let waker = {
+        let woken = woken.clone();
+        waker_fn(move || woken.store(true, Ordering::SeqCst))
+    };
+    assert!(Pin::new(&mut l2)
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    event.notify(1);
+
+    let drained = event.drain_all();
+    let states = drained.iter().map(|(_, state)| *state).collect::<Vec<_>>();
+    assert_eq!(
+        states,
+        [
+            ListenerState::Notified(false),
+            ListenerState::Task,
+            ListenerState::Created
+        ]
+    );
+    assert_ne!(drained[0].0, drained[1].0);
+    assert_ne!(drained[1].0, drained[2].0);
+    assert!(woken.load(Ordering::SeqCst));
+
+    // Drained listeners complete and can be dropped safely.
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    drop(l3);
+    assert!(event.drain_all().is_empty());
+
+    // The event still works.
+    let mut l4 = event.listen();
+    event.notify(1);
+    assert!(is_notified(l4.as_mut()));
+}
+
+#[test]
+fn total_listeners() {
+    let event = Event::new();
+    assert_eq!(event.total_listeners(), 0);
+
+    let mut l1 = event.listen();
+    let l2 = event.listen();
+    let l3 = event.listen();
+    assert_eq!(event.total_listeners(), 3);
+
+    drop(l2);
+    assert_eq!(event.total_listeners(), 2);
+
+    event.notify(1);
+    assert!(is_notified(l1.as_mut()));
+    assert_eq!(event.total_listeners(), 1);
+
+    drop(l3);
+    assert_eq!(event.total_listeners(), 0);
+}
+
+#[test]
+fn listen_coalesced() {
+    let event = Event::new();
+    let mut slot = None;
+
+    event.listen_coalesced(&mut slot);
+    event.listen_coalesced(&mut slot);
+    event.listen_coalesced(&mut slot);
+    assert_eq!(event.total_listeners(), 1);
+
+    // A notified listener is replaced by a fresh one.
+    event.notify(1);
+    assert!(is_notified(event.listen_coalesced(&mut slot)));
+    assert!(!is_notified(event.listen_coalesced(&mut slot)));
+    assert_eq!(event.total_listeners(), 1);
+}
+
+#[test]
+fn notify_relaxed() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    event.notify_relaxed(2);
+    event.notify_relaxed(1);
+
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+}
+
+#[test]
+fn notify_additional_relaxed() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    event.notify_additional_relaxed(1);
+    event.notify_additional_relaxed(1);
+
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+}
+
+#[test]
+fn wake_by_ref() {
+    let event = Event::new();
+    event.set_wake_strategy(WakeStrategy::ByRef);
+
+    let woken = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || {
+            woken.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(l1
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    event.notify(1);
+    assert_eq!(woken.load(Ordering::SeqCst), 1);
+
+    // Dropping the notified listener passes the notification on.
+    drop(l1);
+    assert!(is_notified(l2.as_mut()));
+}
+
+#[test]
+fn listen_with_priority() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen_with_priority(2);
+    let mut l3 = event.listen_with_priority(1);
+    let mut l4 = event.listen_with_priority(2);
+    let mut l5 = event.listen();
+
+    // Highest priority first, FIFO among equal priorities.
+    event.notify(2);
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l4.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+
+    event.notify(1);
+    assert!(is_notified(l3.as_mut()));
+    assert!(!is_notified(l1.as_mut()));
+
+    event.notify(2);
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l5.as_mut()));
+    assert_eq!(event.maintain().validation, Ok(()));
+}
+
+#[cfg(feature = "trace-id")]
+#[test]
+fn notify_traced() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    event.notify_traced(1, false, 99);
+    assert!(is_notified(l1.as_mut()));
+    assert_eq!(l1.trace_id(), Some(99));
+
+    // The last notification wins for listeners that haven't been woken yet.
+    event.notify_traced(1, false, 1);
+    event.notify_traced(1, false, 2);
+    assert!(is_notified(l2.as_mut()));
+    assert_eq!(l2.trace_id(), Some(2));
+
+    // Untraced notifications don't carry an id.
+    event.notify(1);
+    assert!(is_notified(l3.as_mut()));
+    assert_eq!(l3.trace_id(), None);
+}
+
+#[test]
+fn with_spin_budget() {
+    for &spins in &[0, 100, 1000] {
+        let event = Event::with_spin_budget(spins);
+
+        let mut l1 = event.listen();
+        let mut l2 = event.listen();
+
+        event.notify(1);
+        assert!(is_notified(l1.as_mut()));
+        assert!(!is_notified(l2.as_mut()));
+    }
+}
+
+#[test]
+fn listener_count() {
+    let event = Event::new();
+    assert_eq!(event.listener_count(), 0);
+
+    let l1 = event.listen();
+    let l2 = event.listen();
+    assert_eq!(event.listener_count(), 2);
+    assert_eq!(event.listener_count(), event.total_listeners());
+
+    drop(l1);
+    drop(l2);
+    assert_eq!(event.listener_count(), 0);
+}
+
+#[test]
+fn notified_count() {
+    let event = Event::new();
+    assert_eq!(event.notified_count(), 0);
+
+    let mut l1 = event.listen();
+    let l2 = event.listen();
+    event.notify(1);
+    assert_eq!(event.notified_count(), 1);
+
+    // The count stays exact when every listener is notified.
+    event.notify(2);
+    assert_eq!(event.notified_count(), 2);
+
+    assert!(is_notified(l1.as_mut()));
+    drop(l1);
+    assert_eq!(event.notified_count(), 1);
+    drop(l2);
+    assert_eq!(event.notified_count(), 0);
+}
+
+#[test]
+fn notify_filtered() {
+    let event = Event::new();
+    let waker = waker_fn(|| ());
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    let mut l4 = event.listen();
+
+    // Register tasks for the second and fourth listeners.
+    assert!(l2
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+    assert!(l4
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    event.notify_filtered(usize::MAX, |state| *state == ListenerState::Task);
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l4.as_mut()));
+    assert_eq!(event.maintain().validation, Ok(()));
+
+    // Skipped listeners are still first in line.
+    event.notify(1);
+    assert!(is_notified(l1.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+    event.notify(1);
+    assert!(is_notified(l3.as_mut()));
+}
+
+#[test]
+fn notify_filtered_panic() {
+    let event = Event::new();
+    let waker = waker_fn(|| ());
+
+    let mut l1 = event.listen();
+    assert!(l1
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    // A panicking predicate leaves the listener as it was.
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        event.notify_filtered(1, |_| panic!("predicate"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(event.maintain().validation, Ok(()));
+
+    event.notify(1);
+    assert!(is_notified(l1.as_mut()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_deadline_while() {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let event = Arc::new(Event::new());
+    let flag = Arc::new(AtomicBool::new(false));
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    let handle = thread::spawn({
+        let event = event.clone();
+        let flag = flag.clone();
+        move || {
+            // Wake the waiter spuriously first.
+            thread::sleep(Duration::from_millis(50));
+            event.notify(usize::MAX);
+
+            thread::sleep(Duration::from_millis(50));
+            flag.store(true, Ordering::SeqCst);
+            event.notify(usize::MAX);
+        }
+    });
+
+    assert!(event.wait_deadline_while(deadline, || flag.load(Ordering::SeqCst)));
+    assert!(Instant::now() < deadline);
+    handle.join().unwrap();
+
+    // An unsatisfied predicate times out.
+    let deadline = Instant::now() + Duration::from_millis(20);
+    assert!(!event.wait_deadline_while(deadline, || false));
+    assert!(Instant::now() >= deadline);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_until() {
+    use event_listener::Shutdown;
+    use std::thread;
+    use std::time::Duration;
+
+    let event = Arc::new(Event::new());
+    let value = Arc::new(AtomicUsize::new(0));
+
+    let handle = thread::spawn({
+        let event = event.clone();
+        let value = value.clone();
+        move || {
+            // Wake the waiter spuriously first.
+            thread::sleep(Duration::from_millis(50));
+            event.notify(usize::MAX);
+
+            thread::sleep(Duration::from_millis(50));
+            value.store(7, Ordering::SeqCst);
+            event.notify(usize::MAX);
+        }
+    });
+
+    let mut checks = 0;
+    let result = EventListener::wait_until(&event, || {
+        checks += 1;
+
+        // The listener stays registered between checks.
+        assert_eq!(event.total_listeners(), 1);
+        match value.load(Ordering::SeqCst) {
+            0 => None,
+            n => Some(n),
+        }
+    });
+    assert_eq!(result, Ok(7));
+    assert!(checks >= 2);
+    assert_eq!(event.total_listeners(), 0);
+    handle.join().unwrap();
+
+    // A satisfied condition returns without waiting.
+    assert_eq!(EventListener::wait_until(&event, || Some(1)), Ok(1));
+
+    // The wait ends once the event is shut down.
+    event.shutdown();
+    assert_eq!(
+        EventListener::wait_until(&event, || None::<()>),
+        Err(Shutdown)
+    );
+}
+
+#[test]
+fn notify_round_robin() {
+    let event = Event::new();
+    assert_eq!(event.notify_round_robin(1), 0);
+
+    let mut workers = (0..3).map(|_| event.listen()).collect::<Vec<_>>();
+
+    // Workers that listen again after every notification still take turns.
+    let mut turns = Vec::new();
+    for _ in 0..6 {
+        assert_eq!(event.notify_round_robin(1), 1);
+        let index = workers
+            .iter_mut()
+            .position(|worker| is_notified(worker.as_mut()))
+            .unwrap();
+        workers[index] = event.listen();
+        turns.push(index);
+    }
+    assert_eq!(turns, [0, 1, 2, 0, 1, 2]);
+
+    // Removed listeners are skipped, and notified ones don't count.
+    drop(workers.remove(1));
+    assert_eq!(event.notify_round_robin(5), 2);
+    assert!(is_notified(workers[0].as_mut()));
+    assert!(is_notified(workers[1].as_mut()));
+}
+
+#[test]
+fn notify_fair() {
+    const LISTENERS: usize = 8;
+
+    let event = Event::new();
+    assert_eq!(event.notify_fair(1), 0);
+
+    // Listeners keep registering again with random priorities, some of them at the front.
+    let mut seed = 0x2545_f491_u32;
+    let mut random = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed
+    };
+    let mut listen = |event: &Event| match random() % 4 {
+        0 => event.listen_front(),
+        r => event.listen_with_priority(r as u8),
+    };
+
+    let mut listeners = (0..LISTENERS)
+        .map(|_| (listen(&event), 0))
+        .collect::<Vec<_>>();
+
+    // No listener waits for more rounds than there are listeners.
+    let mut max_wait = 0;
+    for round in 0..1000 {
+        assert_eq!(event.notify_fair(1), 1);
+        let index = listeners
+            .iter_mut()
+            .position(|(listener, _)| is_notified(listener.as_mut()))
+            .unwrap();
+
+        max_wait = max_wait.max(round - listeners[index].1);
+        listeners[index] = (listen(&event), round + 1);
+    }
+    assert!(max_wait < LISTENERS, "max wait: {}", max_wait);
+}
+
+#[test]
+fn notify_handle() {
+    let event = Event::new();
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    let handle = l2.as_mut().handle().unwrap();
+
+    // Only the listener with the handle is notified, even though others wait in front of it.
+    assert!(event.notify_handle(handle));
+    assert!(event.notify_handle(handle));
+    event.notify(1);
+    assert!(!is_notified(l1.as_mut()));
+    event.notify(2);
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+
+    // The listener received its notification, so it's no longer registered.
+    assert!(l2.as_mut().handle().is_none());
+    assert!(!event.notify_handle(handle));
+}
+
+#[test]
+fn notify_handle_other_event() {
+    let (event1, event2) = (Event::new(), Event::new());
+    let mut l1 = event1.listen();
+    let mut l2 = event2.listen();
+    let handle = l1.as_mut().handle().unwrap();
+
+    // The handle doesn't name the listener in the same slot of another event.
+    assert!(!event2.notify_handle(handle));
+    assert!(!is_notified(l2.as_mut()));
+    assert!(event1.notify_handle(handle));
+    assert!(is_notified(l1.as_mut()));
+}
+
+#[test]
+fn listener_id() {
+    let (event1, event2) = (Event::new(), Event::new());
+    let mut l1 = event1.listen();
+    let mut l2 = event1.listen();
+    let mut l3 = event2.listen();
+
+    // The first listeners of both events have different handles and ids.
+    assert_ne!(l1.as_mut().handle(), l3.as_mut().handle());
+    let ids = [&mut l1, &mut l2, &mut l3]
+        .iter_mut()
+        .map(|listener| listener.as_mut().id().unwrap())
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(ids.len(), 3);
+    assert!(ids.contains(&l2.as_mut().id().unwrap()));
+
+    // A listener that received its notification has no id.
+    event1.notify(1);
+    assert!(is_notified(l1.as_mut()));
+    assert!(l1.as_mut().id().is_none());
+}
+
+#[test]
+fn waker() {
+    let event = Event::new();
+    let weak = event.downgrade();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let waker = event.waker();
+
+    // Every wake notifies another listener.
+    let cloned = waker.clone();
+    cloned.wake_by_ref();
+    cloned.wake();
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+
+    // The wakers keep the event alive until the last one is dropped.
+    let cloned = waker.clone();
+    drop((event, l1, l2, waker));
+    assert!(weak.upgrade().is_some());
+    drop(cloned);
+    assert!(weak.upgrade().is_none());
+}
+
+#[cfg(feature = "into-future")]
+#[test]
+fn into_future() {
+    use futures_lite::future;
+    use std::future::IntoFuture;
+
+    let event = Event::new();
+
+    // The listener is registered before the notification, so it isn't missed before the first
+    // poll.
+    let notified = (&event).into_future();
+    assert_eq!(event.total_listeners(), 1);
+    event.notify(1);
+    future::block_on(notified);
+
+    // A notification before the future is created isn't received.
+    event.notify(1);
+    let mut notified = (&event).into_future();
+    assert!(future::block_on(future::poll_once(notified.as_mut())).is_none());
+
+    future::block_on(async {
+        event.notify_additional(1);
+        notified.await;
+    });
+}
+
+#[test]
+fn weak_listener() {
+    let woken = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || {
+            woken.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    let mut cx = Context::from_waker(&waker);
+
+    let event = Event::new();
+    let weak = event.downgrade();
+
+    // Weak listeners are notified like strong ones.
+    let mut l1 = event.listen_weak();
+    let mut l2 = event.listen_weak();
+    assert!(l1.as_mut().poll(&mut cx).is_pending());
+    event.notify(1);
+    assert_eq!(woken.load(Ordering::SeqCst), 1);
+    assert_eq!(l1.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+    drop(l1);
+
+    // Dropping the event wakes up the waiting listeners instead of leaking the event.
+    assert!(l2.as_mut().poll(&mut cx).is_pending());
+    assert!(!l2.is_gone());
+    drop(event);
+    assert!(weak.upgrade().is_none());
+    assert_eq!(woken.load(Ordering::SeqCst), 2);
+    assert!(l2.is_gone());
+    assert_eq!(l2.as_mut().poll(&mut cx), Poll::Ready(Err(EventGone)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn notify_rendezvous() {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let event = Arc::new(Event::new());
+    assert!(!event.notify_rendezvous(None));
+
+    // Nobody receives the notification, so it stays with the listener.
+    let mut idle = event.listen();
+    assert!(!event.notify_rendezvous(Some(Duration::from_millis(50))));
+    assert!(!event.notify_rendezvous(None));
+
+    // The rendezvous waits until the listener is waited on.
+    let mut listener = event.listen();
+    let start = Instant::now();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        listener.as_mut().wait();
+    });
+    assert!(event.notify_rendezvous(Some(Duration::from_secs(10))));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    handle.join().unwrap();
+
+    // Dropping the listener gives up the notification as well.
+    let listener = event.listen();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(listener);
+    });
+    assert!(event.notify_rendezvous(Some(Duration::from_secs(10))));
+    handle.join().unwrap();
+
+    assert!(is_notified(idle.as_mut()));
+}
+
+#[test]
+fn take_notification() {
+    let event = Event::with_tag();
+
+    let woken = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || {
+            woken.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(l1
+        .as_mut()
+        .poll_consume(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    // Nothing to take, and the registered task is kept.
+    assert!(!l1.as_mut().take_notification());
+    event.notify_tagged(1, 5);
+    assert_eq!(woken.load(Ordering::SeqCst), 1);
+
+    // The taken notification isn't passed on.
+    assert!(l1.as_mut().take_notification());
+    assert_eq!(l1.tag(), Some(&5));
+    drop(l1);
+    assert!(!is_notified(l2.as_mut()));
+}
+
+#[test]
+fn take_notification_races_notify() {
+    use std::thread;
+
+    let rounds = if cfg!(miri) { 10 } else { 1000 };
+
+    for _ in 0..rounds {
+        let event = Arc::new(Event::new());
+        let mut l1 = event.listen();
+        let mut l2 = event.listen();
+
+        let handle = thread::spawn({
+            let event = event.clone();
+            move || {
+                event.notify(1);
+            }
+        });
+
+        // Either the notification is taken, or dropping the listener passes it on.
+        let taken = l1.as_mut().take_notification();
+        drop(l1);
+        handle.join().unwrap();
+        assert_ne!(taken, is_notified(l2.as_mut()));
+    }
+}
+
+#[test]
+fn take_notification_contended() {
+    use std::thread;
+
+    let rounds = if cfg!(miri) { 10 } else { 10_000 };
+
+    // Without a spin budget, a contended removal would be queued on `no_std`.
+    let event = Arc::new(Event::with_spin_budget(0));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    // The listener polled by the other thread is notified first, so it doesn't take the
+    // notifications below. Insert the rest before the lock becomes contended.
+    let other = event.listen();
+    event.notify(1);
+    let mut listeners = (0..rounds).map(|_| event.listen()).collect::<Vec<_>>();
+
+    // Keep taking the lock, so that taking the notification races with it.
+    let handle = thread::spawn({
+        let stop = stop.clone();
+        move || {
+            while !stop.load(Ordering::Relaxed) {
+                other.is_notified();
+            }
+        }
+    });
+
+    let waker = waker_fn(|| ());
+    for listener in &mut listeners {
+        assert!(listener
+            .as_mut()
+            .poll_consume(&mut Context::from_waker(&waker))
+            .is_pending());
+
+        event.notify_additional(1);
+        assert!(listener.as_mut().take_notification());
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    handle.join().unwrap();
+}
+
+#[test]
+fn discard() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+
+    // Discarding a notified listener swallows its notification.
+    event.notify(1);
+    assert!(l1.as_mut().discard());
+    assert!(!is_notified(l2.as_mut()));
+
+    // Discarding an unnotified listener reports that nothing was discarded.
+    assert!(!l2.as_mut().discard());
+    assert_eq!(event.total_listeners(), 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn notify_then_listen() {
+    use std::thread;
+    use std::time::Duration;
+
+    const ROUNDS: usize = 1000;
+
+    let ping = Arc::new(Event::new());
+    let pong = Arc::new(Event::new());
+    let timeout = Duration::from_secs(10);
+
+    // Listen for the first ping before the other side can send it.
+    let mut listener = ping.listen();
+
+    let handle = thread::spawn({
+        let ping = ping.clone();
+        let pong = pong.clone();
+        move || {
+            for _ in 0..ROUNDS {
+                let mut reply = ping.notify_then_listen(&pong);
+                assert!(reply.as_mut().wait_timeout(timeout));
+            }
+        }
+    });
+
+    for _ in 0..ROUNDS {
+        assert!(listener.as_mut().wait_timeout(timeout));
+        listener = pong.notify_then_listen(&ping);
+    }
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn from_state() {
+    let (event, listeners) = Event::from_state(&[
+        ListenerState::Created,
+        ListenerState::Notified(true),
+        ListenerState::Task,
+    ]);
+    assert_eq!(listeners.len(), 3);
+    assert_eq!(event.total_listeners(), 3);
+
+    // The notified listener counts towards `notify()`, and waking the `Task` listener is a no-op.
+    event.notify(3);
+
+    let states = event
+        .drain_all()
+        .into_iter()
+        .map(|(_, state)| state)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        states,
+        [
+            ListenerState::Notified(true),
+            ListenerState::Notified(false),
+            ListenerState::Notified(false),
+        ]
+    );
+}
+
+#[test]
+fn from_state_notified_taken() {
+    let (event, mut listeners) = Event::from_state(&[ListenerState::NotifiedTaken]);
+
+    // The restored listener completes, and doesn't swallow a later notification.
+    assert!(is_notified(listeners[0].as_mut()));
+    let mut listener = event.listen();
+    event.notify(1);
+    assert!(is_notified(listener.as_mut()));
+}
+
+#[test]
+fn try_listen_uncontended() {
+    let event = Event::new();
+    let mut l1 = Box::pin(EventListener::new(&event));
+    let mut l2 = event_listener::StackListener::new(&event);
+    let mut l2 = unsafe { Pin::new_unchecked(&mut l2) };
+
+    assert!(l1.as_mut().try_listen());
+    assert!(l1.as_mut().try_listen());
+    assert!(l2.as_mut().try_listen());
+    assert_eq!(event.total_listeners(), 2);
+
+    event.notify(2);
+    assert!(is_notified(l1.as_mut()));
+    assert!(l2.as_mut().discard());
+}
+
+#[test]
+fn listen_pinned() {
+    let event = Event::new();
+    let woken = Arc::new(AtomicBool::new(false));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || woken.store(true, Ordering::SeqCst)
+    });
+
+    listen_pinned!(event => listener);
+    assert_eq!(event.total_listeners(), 1);
+    assert!(listener
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    event.notify(1);
+    assert!(woken.load(Ordering::SeqCst));
+    assert!(listener
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_ready());
+    assert_eq!(event.total_listeners(), 0);
+}
+
+#[test]
+fn coalesce_additional() {
+    let event = Event::new();
+
+    // Nobody is waiting, so both notifications are deferred.
+    event.coalesce_additional(2);
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+
+    // One notification is delivered to `l3` and the other one is deferred.
+    event.coalesce_additional(2);
+    assert!(is_notified(l3.as_mut()));
+
+    let mut l4 = event.listen();
+    let mut l5 = event.listen();
+    assert!(is_notified(l4.as_mut()));
+    assert!(!is_notified(l5.as_mut()));
+}
+
+#[test]
+fn coalesce_additional_all() {
+    let event = Event::new();
+    let mut l1 = event.listen();
+
+    // `usize::MAX` notifies everyone, so deferring it twice doesn't overflow.
+    event.coalesce_additional(usize::MAX);
+    event.coalesce_additional(usize::MAX);
+    assert!(is_notified(l1.as_mut()));
+
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+}
+
+#[test]
+fn notify_one_fast_path() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+
+    // Only one listener is notified, however often this is called.
+    event.notify_one();
+    event.notify_one();
+    assert!(is_notified(l1.as_mut()));
+    assert!(!is_notified(l2.as_mut()));
+
+    // Now that `l1` received its notification, `l2` is next.
+    drop(l1);
+    event.notify_one();
+    assert!(is_notified(l2.as_mut()));
+}
+
+#[test]
+fn diff_states() {
+    let event = Event::new();
+    let _l1 = event.listen();
+    let _l2 = event.listen();
+    let _l3 = event.listen();
+
+    event.notify(1);
+    let after = event.drain_all();
+    let (h1, h2, h3) = (after[0].0, after[1].0, after[2].0);
+
+    // A single state transition.
+    let before = [
+        (h1, ListenerState::Created),
+        (h2, ListenerState::Created),
+        (h3, ListenerState::Created),
+    ];
+    assert_eq!(
+        Event::diff_states(&before, &after),
+        [StateChange::Changed {
+            handle: h1,
+            before: ListenerState::Created,
+            after: ListenerState::Notified(false),
+        }]
+    );
+
+    // Listeners that come and go.
+    assert_eq!(
+        Event::diff_states(&after[..2], &after[1..]),
+        [
+            StateChange::Removed(h1, ListenerState::Notified(false)),
+            StateChange::Added(h3, ListenerState::Created),
+        ]
+    );
+}
+
+#[test]
+fn discard_taking_waker() {
+    let event = Event::new();
+    let woken = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || {
+            woken.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    // The waker of a waiting listener is handed back instead of being dropped.
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(l1
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+    let (notified, task) = l1.as_mut().discard_taking_waker();
+    assert!(!notified);
+    assert_eq!(woken.load(Ordering::SeqCst), 0);
+    task.unwrap().wake();
+    assert_eq!(woken.load(Ordering::SeqCst), 1);
+
+    // A notified listener whose task was already woken has no waker left.
+    event.notify(1);
+    let (notified, task) = l2.as_mut().discard_taking_waker();
+    assert!(notified);
+    assert!(task.is_none());
+
+    // A removed listener has nothing left to hand back.
+    let (notified, task) = l1.as_mut().discard_taking_waker();
+    assert!(!notified);
+    assert!(task.is_none());
+}
+
+#[test]
+fn notify_lifo() {
+    let event = Event::new();
+    let woken = Arc::new(Mutex::new(Vec::new()));
+
+    let mut listeners = (0..4).map(|_| event.listen()).collect::<Vec<_>>();
+    for (i, listener) in listeners.iter_mut().enumerate() {
+        let woken = woken.clone();
+        let waker = waker_fn(move || woken.lock().unwrap().push(i));
+        assert!(listener
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+    }
+
+    // The newest listener is woken first.
+    event.notify_lifo(1);
+    assert_eq!(*woken.lock().unwrap(), [3]);
+
+    // FIFO notifications go on from the front, and the counts stay consistent.
+    event.notify(2);
+    assert_eq!(*woken.lock().unwrap(), [3, 0]);
+    event.notify_lifo(2);
+    assert_eq!(*woken.lock().unwrap(), [3, 0, 2, 1]);
+    event.notify_lifo(1);
+    assert_eq!(woken.lock().unwrap().len(), 4);
+
+    // A dropped notified listener passes its notification on to a new one.
+    let mut l5 = event.listen();
+    drop(listeners.remove(3));
+    assert!(is_notified(l5.as_mut()));
+}
+
+#[test]
+fn notify_oldest() {
+    let event = Event::new();
+    let woken = Arc::new(Mutex::new(Vec::new()));
+    let waker = |i| {
+        let woken = woken.clone();
+        waker_fn(move || woken.lock().unwrap().push(i))
+    };
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    // Tasks start waiting in a different order than the listeners were created.
+    assert!(l3
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker(3)))
+        .is_pending());
+    assert!(l1
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker(1)))
+        .is_pending());
+
+    // The task that has been waiting the longest is woken first.
+    event.notify_oldest(1);
+    assert_eq!(*woken.lock().unwrap(), [3]);
+
+    // Listeners without a task are notified last.
+    event.notify_oldest(2);
+    assert_eq!(*woken.lock().unwrap(), [3, 1]);
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+}
+
+#[test]
+fn reset() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+
+    // A pending notification is discarded and the listener goes to the back of the line.
+    event.notify(1);
+    l1.as_mut().reset();
+    assert_eq!(event.total_listeners(), 2);
+    event.notify(1);
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l1.as_mut()));
+
+    // A listener that received its notification is registered again.
+    event.notify(1);
+    assert!(is_notified(l1.as_mut()));
+    l1.as_mut().reset();
+    assert_eq!(event.total_listeners(), 1);
+    event.notify(1);
+    assert!(is_notified(l1.as_mut()));
+
+    assert!(event.maintain().validation.is_ok());
+}
+
+#[test]
+fn reset_like_listen() {
+    let event = Event::new();
+
+    let mut low = event.listen();
+    let mut high = event.listen_with_priority(1);
+
+    // A drained listener is inserted again with its priority.
+    event.drain_all();
+    low.as_mut().reset();
+    high.as_mut().reset();
+    event.notify(1);
+    assert!(high.is_notified());
+    assert!(!low.is_notified());
+
+    // Like a new listener, a reset listener takes a mark.
+    event.notify(2);
+    assert!(!event.notify_or_mark());
+    high.as_mut().reset();
+    assert!(high.is_notified());
+    assert!(low.is_notified());
+
+    // The same goes for a drained listener that is renewed.
+    let waker = waker_fn(|| ());
+    event.drain_all();
+    assert!(low
+        .as_mut()
+        .poll_renew(&mut Context::from_waker(&waker))
+        .is_ready());
+    assert!(high
+        .as_mut()
+        .poll_renew(&mut Context::from_waker(&waker))
+        .is_ready());
+    event.notify(1);
+    assert!(high.is_notified());
+    assert!(!low.is_notified());
+
+    assert!(event.maintain().validation.is_ok());
+}
+
+#[test]
+fn with_listener_unwind() {
+    let event = Event::new();
+    let mut other = None;
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        event.with_listener(|_| {
+            other = Some(event.listen());
+            assert_eq!(event.total_listeners(), 2);
+
+            // Notify the scoped listener, then bail out before it receives the notification.
+            event.notify(1);
+            panic!("oops");
+        })
+    }));
+    assert!(result.is_err());
+
+    // The scoped listener was removed and its notification passed on.
+    assert_eq!(event.total_listeners(), 1);
+    assert!(is_notified(other.as_mut().unwrap().as_mut()));
+}
+
+#[test]
+fn notify_to_capacity() {
+    let event = Event::new();
+
+    let mut l1 = event.listen_with_capacity(2);
+    let mut l2 = event.listen_with_capacity(3);
+    let mut l3 = event.listen_with_capacity(5);
+
+    assert_eq!(event.notify_to_capacity(4), 5);
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+
+    // A single listener covers more than what is needed.
+    let mut l4 = event.listen();
+    assert_eq!(event.notify_to_capacity(1), 5);
+    assert!(is_notified(l3.as_mut()));
+    assert!(!is_notified(l4.as_mut()));
+
+    // Running out of listeners.
+    assert_eq!(event.notify_to_capacity(10), 1);
+    assert!(is_notified(l4.as_mut()));
+    assert_eq!(event.notify_to_capacity(1), 0);
+}
+
+#[test]
+fn notify_tagged() {
+    let event = Event::with_tag();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    event.notify_tagged(1, 1);
+    event.notify_additional_tagged(1, 2);
+
+    // Untagged notifications carry no tag.
+    event.notify(3);
+
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+    assert_eq!(l1.tag(), Some(&1));
+    assert_eq!(l2.tag(), Some(&2));
+    assert_eq!(l3.tag(), None);
+}
+
+#[test]
+fn notify_each() {
+    let event = Event::with_tag();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    let mut l4 = event.listen();
+
+    let mut ticket = 0;
+    let mut next = || {
+        ticket += 1;
+        ticket
+    };
+
+    // The closure is only called for listeners notified by this call.
+    assert_eq!(event.notify_each(2, &mut next), 2);
+    assert_eq!(event.notify_each(1, &mut next), 0);
+    assert_eq!(event.notify_each(4, &mut next), 2);
+    assert_eq!(ticket, 4);
+
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+    assert!(is_notified(l4.as_mut()));
+    assert_eq!(l1.tag(), Some(&1));
+    assert_eq!(l2.tag(), Some(&2));
+    assert_eq!(l3.tag(), Some(&3));
+    assert_eq!(l4.tag(), Some(&4));
+}
+
+#[test]
+fn notify_each_panic() {
+    let event = Event::with_tag();
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+
+    // The listener whose tag panicked is still notified by later calls.
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        event.notify_each(2, || -> u32 { panic!("tag") })
+    }));
+    assert!(result.is_err());
+    assert_eq!(event.maintain().validation, Ok(()));
+    assert!(!is_notified(l1.as_mut()));
+
+    assert_eq!(event.notify_each(2, || 1), 2);
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+}
+
+#[test]
+fn notify_tagged_propagates() {
+    let event = Event::with_tag();
+
+    let l1 = event.listen();
+    let mut l2 = event.listen();
+
+    event.notify_tagged(1, "tag");
+
+    // Dropping the notified listener passes the tag on.
+    drop(l1);
+    assert!(is_notified(l2.as_mut()));
+    assert_eq!(l2.tag(), Some(&"tag"));
+}
+
+#[test]
+fn is_notified_pending() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let l2 = event.listen();
+    assert!(!l1.is_notified());
+
+    event.notify(1);
+    assert!(l1.is_notified());
+    assert!(!l2.is_notified());
+
+    // Peeking doesn't consume the notification, so it still counts towards `notify()`.
+    event.notify(1);
+    assert!(!l2.is_notified());
+    assert!(l1.is_notified());
+
+    assert!(is_notified(l1.as_mut()));
+    assert!(!l1.is_notified());
+}
+
+#[test]
+fn shutdown() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    assert!(!is_notified(l2.as_mut()));
+
+    event.notify(1);
+    event.shutdown();
+    assert!(event.is_shutdown());
+
+    // The notified listener keeps its notification, the others are shut down.
+    assert!(is_notified(l1.as_mut()));
+    assert!(!l1.is_shutdown());
+    assert!(is_notified(l2.as_mut()));
+    assert!(l2.is_shutdown());
+    assert!(is_notified(l3.as_mut()));
+    assert!(l3.is_shutdown());
+
+    // New listeners complete right away.
+    assert!(event.try_listen().is_err());
+    let mut l4 = event.listen();
+    assert!(is_notified(l4.as_mut()));
+    assert!(l4.is_shutdown());
+}
+
+#[test]
+fn try_listen_shutdown() {
+    let event = Event::new();
+    let waker = waker_fn(|| ());
+
+    let mut l1 = event.try_listen().unwrap();
+    let mut l2 = event.try_listen().unwrap();
+    let mut l3 = event.try_listen().unwrap();
+    assert!(Pin::new(&mut l2)
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+    assert!(Pin::new(&mut l3)
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    event.notify(1);
+    event.shutdown();
+
+    // A notification is told apart from a shutdown.
+    assert_eq!(
+        Pin::new(&mut l1).poll(&mut Context::from_waker(&waker)),
+        Poll::Ready(Ok(()))
+    );
+    assert_eq!(
+        Pin::new(&mut l2).poll(&mut Context::from_waker(&waker)),
+        Poll::Ready(Err(Shutdown))
+    );
+    assert_eq!(
+        Pin::new(&mut l3).poll(&mut Context::from_waker(&waker)),
+        Poll::Ready(Err(Shutdown))
+    );
+    assert_eq!(event.try_listen().err(), Some(Shutdown));
+}
+
+#[test]
+fn notify_and_harvest() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let _l2 = event.listen();
+    let mut l3 = event.listen();
+    let _l4 = event.listen();
+    let mut l5 = event.listen();
+
+    assert!(!is_notified(l1.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+    assert!(!is_notified(l5.as_mut()));
+
+    let (woken, created) = event.notify_and_harvest(3, false);
+    assert_eq!(woken, 3);
+
+    // `l4` is the only unnotified listener without a task.
+    let handles = event
+        .drain_all()
+        .into_iter()
+        .map(|(handle, _)| handle)
+        .collect::<Vec<_>>();
+    assert_eq!(created, [handles[3]]);
+
+    // Nothing is left to notify.
+    assert_eq!(event.notify_and_harvest(1, true), (0, Vec::new()));
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn count_change_stream() {
+    use futures_lite::{future, StreamExt};
+
+    let event = Arc::new(Event::new());
+    let mut counts = Box::pin(event.clone().count_change_stream());
+    assert_eq!(future::block_on(counts.next()), Some(0));
+
+    // Nothing changed yet.
+    assert!(future::block_on(future::poll_once(counts.next())).is_none());
+
+    let _l1 = event.listen();
+    assert_eq!(future::block_on(counts.next()), Some(1));
+
+    let l2 = event.listen();
+    assert_eq!(future::block_on(counts.next()), Some(2));
+
+    drop(l2);
+    assert_eq!(future::block_on(counts.next()), Some(1));
+}
+
+#[test]
+fn prune_notified() {
+    let event = Event::new();
+    event.set_wake_strategy(WakeStrategy::ByRef);
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    assert!(!is_notified(l1.as_mut()));
+
+    // `l1` keeps its task, `l2` is idle.
+    event.notify(2);
+    assert_eq!(event.prune_notified(), 1);
+    assert_eq!(event.total_listeners(), 2);
+    #[cfg(feature = "debug")]
+    assert_eq!(
+        event.listener_states(),
+        [ListenerStateKind::Notified, ListenerStateKind::Created]
+    );
+    assert_eq!(event.prune_notified(), 0);
+
+    // `l2` no longer counts towards `notify()`, but keeps its notification.
+    event.notify(2);
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+}
+
+#[test]
+fn balanced_notifier() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    let (_, handles) = event.notify_and_harvest(0, true);
+    assert_eq!(handles.len(), 3);
+    let mut notifier = BalancedNotifier::new(handles.clone());
+
+    // Each handle is notified once, in order.
+    for handle in &handles {
+        assert_eq!(notifier.notify_next(&event), Some(*handle));
+    }
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+
+    // Handles of removed listeners are dropped from the set.
+    assert_eq!(notifier.notify_next(&event), None);
+    assert!(notifier.handles().is_empty());
+}
+
+#[test]
+fn wake_deduplication() {
+    let event = Event::new();
+    event.set_wake_deduplication(true);
+
+    let wakes = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let wakes = wakes.clone();
+        move || {
+            wakes.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    // Three listeners share the same waker.
+    let mut listeners = (0..3).map(|_| event.listen()).collect::<Vec<_>>();
+    for listener in &mut listeners {
+        assert!(listener
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+    }
+
+    event.notify(3);
+    assert_eq!(wakes.load(Ordering::SeqCst), 1);
+
+    for listener in &mut listeners {
+        assert!(is_notified(listener.as_mut()));
+    }
+}
+
+#[test]
+fn listen_guarded() {
+    let event = Event::new();
+
+    let guard = event.listen_guarded();
+    let mut listener = event.listen();
+    assert!(!is_notified(listener.as_mut()));
+
+    // Dropping the notified guard wakes the second waiter.
+    let woken = Arc::new(AtomicBool::new(false));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || woken.store(true, Ordering::SeqCst)
+    });
+    assert!(listener
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    event.notify(1);
+    drop(guard);
+    assert!(woken.load(Ordering::SeqCst));
+    assert!(is_notified(listener.as_mut()));
+
+    // A guard converted back into a listener works like one.
+    let mut listener = event.listen_guarded().into_listener();
+    event.notify(1);
+    assert!(is_notified(listener.as_mut()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn poll_timeout() {
+    use std::task::Poll;
+    use std::time::{Duration, Instant};
+
+    let event = Event::new();
+    let waker = waker_fn(|| ());
+    let mut cx = Context::from_waker(&waker);
+
+    // Pending until the deadline, then times out.
+    let mut l1 = event.listen();
+    let deadline = Instant::now() + Duration::from_millis(50);
+    assert_eq!(l1.as_mut().poll_timeout(&mut cx, deadline), Poll::Pending);
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(
+        l1.as_mut().poll_timeout(&mut cx, deadline),
+        Poll::Ready(false)
+    );
+
+    // A notification is received even after the deadline.
+    let mut l2 = event.listen();
+    event.notify(1);
+    assert_eq!(
+        l2.as_mut().poll_timeout(&mut cx, Instant::now()),
+        Poll::Ready(true)
+    );
+}
+
+#[test]
+fn notify_per_group() {
+    let event = Event::with_tag();
+
+    let mut a1 = event.listen_in_group('a');
+    let mut a2 = event.listen_in_group('a');
+    let mut b = event.listen_in_group('b');
+    let mut ungrouped = event.listen();
+
+    assert_eq!(event.notify_per_group(|group| *group), 2);
+    assert!(is_notified(a1.as_mut()));
+    assert!(!is_notified(a2.as_mut()));
+    assert!(is_notified(b.as_mut()));
+    assert!(!is_notified(ungrouped.as_mut()));
+
+    // The second listener of `a` is the only one left in a group.
+    assert_eq!(event.notify_per_group(|group| *group), 1);
+    assert!(is_notified(a2.as_mut()));
+    assert_eq!(event.notify_per_group(|group| *group), 0);
+}
+
+#[test]
+fn notify_per_group_panic() {
+    let event = Event::with_tag();
+
+    let mut a = event.listen_in_group('a');
+
+    // A panicking `by` leaves the listener and its group as they were.
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        event.notify_per_group(|_: &char| -> char { panic!("by") })
+    }));
+    assert!(result.is_err());
+    assert_eq!(event.maintain().validation, Ok(()));
+
+    assert_eq!(event.notify_per_group(|group| *group), 1);
+    assert!(is_notified(a.as_mut()));
+}
+
+#[test]
+fn notify_custom_notification() {
+    /// Notifies listeners until all but one of them are notified.
+    struct AllButOne;
+
+    impl Notification for AllButOne {
+        fn next_count(&mut self, _notified: usize, len: usize) -> usize {
+            len.saturating_sub(1)
+        }
+    }
+
+    let event = Event::new();
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    event.notify(AllButOne);
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+
+    // The already notified listeners count towards the total.
+    event.notify(AllButOne);
+    assert!(!is_notified(l3.as_mut()));
+
+    event.notify(Additional(1));
+    assert!(is_notified(l3.as_mut()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_spin() {
+    use std::thread;
+    use std::time::Duration;
+
+    let event = Arc::new(Event::new());
+
+    // The notification arrives while spinning, so the thread is never parked.
+    let mut listener = event.listen();
+    let handle = thread::spawn({
+        let event = event.clone();
+        move || event.notify(1)
+    });
+    assert!(listener.as_mut().wait_spin(std::u32::MAX));
+    handle.join().unwrap();
+
+    // Without a budget the thread is parked until the notification arrives.
+    let mut listener = event.listen();
+    let handle = thread::spawn({
+        let event = event.clone();
+        move || {
+            thread::sleep(Duration::from_millis(50));
+            event.notify(1);
+        }
+    });
+    assert!(!listener.as_mut().wait_spin(0));
+    handle.join().unwrap();
+}
+
+#[test]
+fn notify_capped() {
+    let event = Event::new();
+    let mut listeners = (0..10).map(|_| event.listen()).collect::<Vec<_>>();
+
+    for _ in 0..5 {
+        event.notify_capped(2, true, 3);
+    }
+    assert_eq!(event.debug_stats().notified, 3);
+
+    // A lower cap doesn't take back notifications that were already delivered.
+    event.notify_capped(2, true, 1);
+    event.notify_capped(5, false, 2);
+    assert_eq!(event.debug_stats().notified, 3);
+
+    let notified = listeners.iter_mut().map(|l| is_notified(l.as_mut()));
+    assert_eq!(notified.filter(|&n| n).count(), 3);
+}
+
+#[test]
+fn event_group() {
+    let readable = Event::new();
+    let writable = Event::new();
+    let closed = Event::new();
+
+    let mut r1 = readable.listen();
+    let mut r2 = readable.listen();
+    let mut w = writable.listen();
+    let mut c = closed.listen();
+
+    let events = [&readable, &writable];
+    let group = EventGroup::new(&events);
+    assert_eq!(group.events().len(), 2);
+
+    group.notify_all(1);
+    assert!(is_notified(r1.as_mut()));
+    assert!(!is_notified(r2.as_mut()));
+    assert!(is_notified(w.as_mut()));
+    assert!(!is_notified(c.as_mut()));
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn filtered_stream() {
+    use futures_lite::{future, StreamExt};
+
+    let event = Arc::new(Event::with_tag());
+    let mut tags = Box::pin(event.clone().filtered_stream(|tag| *tag == 'b'));
+    let mut other = event.listen();
+
+    // Notifications that don't match leave the stream pending, and go to the next listener.
+    event.notify_tagged(1, 'a');
+    assert!(future::block_on(future::poll_once(tags.next())).is_none());
+    assert!(is_notified(other.as_mut()));
+    assert_eq!(other.tag(), Some(&'a'));
+
+    event.notify_tagged(1, 'b');
+    assert_eq!(future::block_on(tags.next()), Some('b'));
+
+    event.notify_tagged(1, 'c');
+    event.notify(1);
+    assert!(future::block_on(future::poll_once(tags.next())).is_none());
+
+    // The stream ends when the event shuts down.
+    event.shutdown();
+    assert_eq!(future::block_on(tags.next()), None);
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn stream() {
+    use futures_lite::{future, StreamExt};
+
+    let event = Event::new();
+    let mut wakeups = event.stream();
+    assert!(future::block_on(future::poll_once(wakeups.next())).is_none());
+
+    event.notify(1);
+    assert_eq!(future::block_on(wakeups.next()), Some(()));
+    assert!(future::block_on(future::poll_once(wakeups.next())).is_none());
+
+    // Notifications that arrive before the next poll are coalesced.
+    event.notify_additional(1);
+    event.notify_additional(1);
+    assert_eq!(future::block_on(wakeups.next()), Some(()));
+    assert!(future::block_on(future::poll_once(wakeups.next())).is_none());
+
+    // The listener stays registered between items.
+    assert_eq!(event.total_listeners(), 1);
+    event.notify_drain();
+    assert_eq!(future::block_on(wakeups.next()), Some(()));
+    assert_eq!(event.total_listeners(), 1);
+
+    // The stream ends when the event shuts down.
+    event.shutdown();
+    assert_eq!(future::block_on(wakeups.next()), None);
+    assert_eq!(future::block_on(wakeups.next()), None);
+}
+
+#[test]
+fn listen_front() {
+    let event = Event::new();
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen_front();
+    let mut l3 = event.listen_front();
+    let mut high = event.listen_with_priority(1);
+
+    // Listeners at the front are notified last-in first-out, but still behind higher priorities.
+    event.notify(2);
+    assert!(is_notified(high.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+    assert!(!is_notified(l2.as_mut()));
+    assert!(!is_notified(l1.as_mut()));
+
+    event.notify_additional(1);
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l1.as_mut()));
+}
+
+#[test]
+fn arm() {
+    let event = Event::new();
+
+    let (mut listener, result) = event.arm(|| {
+        event.notify(1);
+        7
+    });
+    assert_eq!(result, 7);
+    assert!(is_notified(listener.as_mut()));
+
+    // A panicking action leaves no listener behind.
+    let result = std::panic::catch_unwind(|| event.arm(|| panic!("failed")));
+    assert!(result.is_err());
+    assert_eq!(event.total_listeners(), 0);
+}
+
+#[test]
+fn try_notify() {
+    let event = Event::new();
+
+    // There is nobody to notify, which counts as success.
+    assert!(event.try_notify(1, false));
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+
+    assert!(event.try_notify(1, false));
+    assert!(event.try_notify(1, true));
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+}
+
+#[test]
+fn latch() {
+    use futures_lite::future;
+
+    let event = Event::new();
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+
+    event.latch();
+    assert!(event.is_latched());
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!l1.is_shutdown());
+
+    // Fresh listeners complete right away, and plain notifications don't change that.
+    future::block_on(event.listen());
+    event.notify(1);
+    let mut l3 = event.listen();
+    assert!(is_notified(l3.as_mut()));
+    assert!(!l3.is_shutdown());
+}
+
+#[test]
+fn is_latched_publishes() {
+    let event = Arc::new(Event::new());
+    let data = Arc::new(AtomicUsize::new(0));
+    assert!(!event.is_latched());
+
+    let handle = {
+        let event = event.clone();
+        let data = data.clone();
+        std::thread::spawn(move || {
+            data.store(1, Ordering::Relaxed);
+            event.latch();
+        })
+    };
+
+    // Whatever happened before the latch is visible once it is seen.
+    while !event.is_latched() {
+        std::thread::yield_now();
+    }
+    assert_eq!(data.load(Ordering::Relaxed), 1);
+
+    handle.join().unwrap();
+}
+
+#[cfg(feature = "debug")]
+#[test]
+fn listener_states() {
+    let event = Event::new();
+    assert!(event.listener_states().is_empty());
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let _l3 = event.listen();
+    assert!(!is_notified(l1.as_mut()));
+    assert!(!is_notified(l2.as_mut()));
+    event.notify(1);
+
+    let before = event.listener_states();
+    assert_eq!(
+        before,
+        [
+            ListenerStateKind::Notified,
+            ListenerStateKind::Task,
+            ListenerStateKind::Created
+        ]
+    );
+
+    // Taking a snapshot doesn't change anything.
+    assert_eq!(event.listener_states(), before);
+    let drained = event
+        .drain_all()
+        .into_iter()
+        .map(|(_, state)| ListenerStateKind::from(state))
+        .collect::<Vec<_>>();
+    assert_eq!(drained, before);
+}
+
+#[cfg(all(feature = "linux-futex", target_os = "linux"))]
+#[test]
+fn futex_wait() {
+    use std::thread;
+    use std::time::Duration;
+
+    let event = Arc::new(Event::new());
+
+    // Every notification wakes the waiting thread, however the two threads interleave.
+    for _ in 0..1000 {
+        let mut listener = event.listen();
+        let handle = thread::spawn({
+            let event = event.clone();
+            move || event.notify(1)
+        });
+
+        listener.as_mut().wait();
+        handle.join().unwrap();
+    }
+
+    // Waits with a deadline still time out.
+    let mut listener = event.listen();
+    assert!(!listener.as_mut().wait_timeout(Duration::from_millis(20)));
+}
+
+#[test]
+fn notify_returns_count() {
+    let event = Event::new();
+    assert_eq!(event.notify(1), 0);
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    // Only the listeners that exist are counted.
+    assert_eq!(event.notify(2), 2);
+    assert_eq!(event.notify(5), 1);
+    assert_eq!(event.notify_additional(1), 0);
+
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+
+    let _l4 = event.listen();
+    let _l5 = event.listen();
+    assert_eq!(event.notify_additional(3), 2);
+}
+
+#[test]
+fn clear() {
+    let event = Event::new();
+
+    let woken = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || {
+            woken.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(l1
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    // Without waking, the task isn't woken but still observes the shutdown.
+    event.clear(false);
+    assert_eq!(woken.load(Ordering::SeqCst), 0);
+    assert_eq!(event.total_listeners(), 0);
+    assert!(is_notified(l1.as_mut()));
+    assert!(l1.is_shutdown());
+    assert!(is_notified(l2.as_mut()));
+    assert!(l2.is_shutdown());
+
+    // The event is still usable.
+    assert!(!event.is_shutdown());
+    let mut l3 = event.listen();
+    let mut l4 = event.listen();
+    assert!(l3
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    event.clear(true);
+    assert_eq!(woken.load(Ordering::SeqCst), 1);
+    assert!(is_notified(l3.as_mut()));
+    assert!(l3.is_shutdown());
+    assert!(is_notified(l4.as_mut()));
+
+    let mut l5 = event.listen();
+    assert!(!is_notified(l5.as_mut()));
+    event.notify(1);
+    assert!(is_notified(l5.as_mut()));
+    assert!(!l5.is_shutdown());
+}
+
+#[test]
+fn notify_drain() {
+    let event = Event::new();
+    assert_eq!(event.notify_drain(), 0);
+
+    let woken = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || {
+            woken.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    event.notify(1);
+    assert!(l2
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    // Only the unnotified listeners are counted, but every listener is woken and removed.
+    assert_eq!(event.notify_drain(), 2);
+    assert_eq!(woken.load(Ordering::SeqCst), 1);
+    assert_eq!(event.total_listeners(), 0);
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(is_notified(l3.as_mut()));
+    assert!(!l3.is_shutdown());
+
+    // New listeners only see later notifications.
+    let mut l4 = event.listen();
+    assert!(!is_notified(l4.as_mut()));
+    assert_eq!(event.notify(1), 1);
+    assert!(is_notified(l4.as_mut()));
+}
+
+#[test]
+fn poll_with_handle() {
+    struct Handle {
+        id: usize,
+        wakers: Arc<AtomicUsize>,
+        woken: Arc<AtomicUsize>,
+    }
+
+    impl TaskHandle for Handle {
+        fn task_id(&self) -> usize {
+            self.id
+        }
+
+        fn to_waker(&self) -> std::task::Waker {
+            self.wakers.fetch_add(1, Ordering::SeqCst);
+            let woken = self.woken.clone();
+            waker_fn(move || {
+                woken.fetch_add(1, Ordering::SeqCst);
+            })
+        }
+    }
+
+    let wakers = Arc::new(AtomicUsize::new(0));
+    let woken = Arc::new(AtomicUsize::new(0));
+    let handle = |id| Handle {
+        id,
+        wakers: wakers.clone(),
+        woken: woken.clone(),
+    };
+
+    let event = Event::new();
+    let mut listener = event.listen();
+
+    // A waker is only created when a different task registers.
+    assert!(listener.as_mut().poll_with_handle(&handle(1)).is_pending());
+    assert!(listener.as_mut().poll_with_handle(&handle(1)).is_pending());
+    assert_eq!(wakers.load(Ordering::SeqCst), 1);
+    assert!(listener.as_mut().poll_with_handle(&handle(2)).is_pending());
+    assert_eq!(wakers.load(Ordering::SeqCst), 2);
+
+    event.notify(1);
+    assert_eq!(woken.load(Ordering::SeqCst), 1);
+    assert!(listener.as_mut().poll_with_handle(&handle(2)).is_ready());
+}
+
+#[test]
+fn downgrade_upgrade() {
+    let event = Event::new();
+    let weak = event.downgrade();
+
+    let mut l1 = event.listen();
+    let upgraded = weak.upgrade().unwrap();
+    assert!(l1.listens_to(&upgraded));
+    upgraded.notify(1);
+    assert!(is_notified(l1.as_mut()));
+
+    // Listeners keep the event alive.
+    drop(event);
+    drop(upgraded);
+    assert!(weak.upgrade().is_some());
+
+    drop(l1);
+    assert!(weak.upgrade().is_none());
+    assert!(weak.clone().upgrade().is_none());
+}
+
+#[test]
+fn metrics() {
+    let event = Event::new();
+    assert_eq!(event.metrics(), Default::default());
+
+    let mut l1 = event.listen();
+    let l2 = event.listen();
+    let l3 = event.listen();
+
+    event.notify(2);
+    assert!(is_notified(l1.as_mut()));
+
+    // Dropping a notified listener passes its notification on.
+    drop(l1);
+    drop(l2);
+
+    let metrics = event.metrics();
+    assert_eq!(metrics.listens, 3);
+    assert_eq!(metrics.notifies, 3);
+    assert_eq!(metrics.removes, 2);
+    assert_eq!(metrics.len, 1);
+    assert_eq!(metrics.notified, 1);
+    #[cfg(feature = "std")]
+    assert_eq!(metrics.fallbacks, 0);
+
+    drop(l3);
+    let _l4 = event.listen();
+    event.drain_all();
+
+    let metrics = event.metrics();
+    assert_eq!(metrics.listens, 4);
+    assert_eq!(metrics.removes, 4);
+    assert_eq!(metrics.len, 0);
+    assert_eq!(metrics.notified, 0);
+}
+
+#[test]
+fn event_id() {
+    let event1 = Event::new();
+    let event2 = Event::new();
+
+    let l1 = event1.listen();
+    let l2 = event1.listen();
+    let l3 = event2.listen();
+
+    assert!(l1.same_event(&l2));
+    assert!(!l1.same_event(&l3));
+    assert_eq!(l1.event_id(), l2.event_id());
+    assert_ne!(l1.event_id(), l3.event_id());
+
+    // Listeners of an upgraded handle belong to the same event.
+    let l4 = event1.downgrade().upgrade().unwrap().listen();
+    assert_eq!(l1.event_id(), l4.event_id());
+}
+
+#[test]
+fn notify_additional_skips_notified() {
+    let event = Event::new();
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+
+    // `l1` is notified, but hasn't consumed its notification yet.
+    assert_eq!(event.notify(1), 1);
+    assert_eq!(event.notify_additional(1), 1);
+    assert_eq!(event.debug_stats().notified, 2);
+
+    assert!(is_notified(l1.as_mut()));
+    assert!(is_notified(l2.as_mut()));
+    assert!(!is_notified(l3.as_mut()));
+}
+
+#[test]
+fn listen_positioned() {
+    let event = Event::new();
+
+    let (l1, p1) = event.listen_positioned();
+    let (_l2, p2) = event.listen_positioned();
+    let (_l3, p3) = event.listen_positioned();
+    assert_eq!((p1, p2, p3), (0, 1, 2));
+
+    // Positions are taken at registration.
+    drop(l1);
+    let (_l4, p4) = event.listen_positioned();
+    assert_eq!(p4, 2);
+}
+
+#[test]
+fn notify_present() {
+    let event = Event::new();
+    assert!(!event.notify_present(1));
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(event.notify_present(1));
+    assert!(is_notified(l1.as_mut()));
+    assert!(!is_notified(l2.as_mut()));
+
+    // Listeners that are all notified are still present.
+    assert!(event.notify_present(2));
+    assert!(event.notify_present(2));
+    assert!(is_notified(l2.as_mut()));
+
+    drop(l1);
+    drop(l2);
+    assert!(!event.notify_present(1));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn notify_happens_before_wake() {
+    use std::cell::UnsafeCell;
+    use std::thread;
+
+    /// A value written without synchronization, relying on the event for visibility.
+    struct Slot(UnsafeCell<usize>);
+    unsafe impl Sync for Slot {}
+
+    const ROUNDS: usize = 1000;
+
+    for round in 0..ROUNDS {
+        let event = Arc::new(Event::new());
+        let slot = Arc::new(Slot(UnsafeCell::new(0)));
+        let mut listener = event.listen();
+
+        let handle = thread::spawn({
+            let event = event.clone();
+            let slot = slot.clone();
+            move || {
+                unsafe { *slot.0.get() = round + 1 };
+                event.notify(1);
+            }
+        });
+
+        // The write before `notify()` must be visible once the listener completes. Data race
+        // detectors like ThreadSanitizer or Miri report a race here if the publish is too weak.
+        listener.as_mut().wait();
+        assert_eq!(unsafe { *slot.0.get() }, round + 1);
+
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn poll_renew() {
+    let event = Event::with_tag();
+
+    let woken = Arc::new(AtomicUsize::new(0));
+    let waker = waker_fn({
+        let woken = woken.clone();
+        move || {
+            woken.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    let mut cx = Context::from_waker(&waker);
+
+    let mut l1 = event.listen();
+    let mut l2 = event.listen();
+    assert!(l1.as_mut().poll_renew(&mut cx).is_pending());
+
+    for round in 0..3 {
+        event.notify_tagged(1, round);
+        assert_eq!(woken.load(Ordering::SeqCst), round + 1);
+        assert!(l1.as_mut().poll_renew(&mut cx).is_ready());
+        assert_eq!(l1.tag(), Some(&round));
+
+        // `l1` went to the back of the line, so `l2` is notified next.
+        event.notify_tagged(1, round);
+        assert!(is_notified(l2.as_mut()));
+        l2.as_mut().reset();
+        assert!(l1.as_mut().poll_renew(&mut cx).is_pending());
+    }
+
+    // The listener was never removed and inserted again.
+    let metrics = event.metrics();
+    assert_eq!(metrics.listens, 2 + 3);
+    assert_eq!(metrics.len, 2);
+
+    // After a shutdown, the listener is registered again and completes right away.
+    event.shutdown();
+    assert!(l1.as_mut().poll_renew(&mut cx).is_ready());
+    assert!(l1.is_shutdown());
+    assert!(l1.as_mut().poll_renew(&mut cx).is_ready());
+}
+
+#[test]
+fn forget_listener() {
+    // Under Miri, run with `-Zmiri-ignore-leaks`, since the shared state is leaked on purpose.
+    let event = Event::new();
+    let weak = event.downgrade();
+
+    let mut l1 = event.listen();
+    let l2 = event.listen();
+    let mut l3 = event.listen();
+    event.notify(1);
+    assert!(!is_notified(l3.as_mut()));
+
+    // The notification of a forgotten listener is not passed on.
+    std::mem::forget(l1);
+    assert_eq!(event.total_listeners(), 3);
+    assert!(!is_notified(l3.as_mut()));
+
+    // Everything else keeps working.
+    event.notify(3);
+    assert!(is_notified(l3.as_mut()));
+    drop(l2);
+    drop(l3);
+
+    // The forgotten listener keeps the shared state alive.
+    drop(event);
+    let event = weak.upgrade().unwrap();
+    assert_eq!(event.total_listeners(), 1);
+    l1 = event.listen();
+    event.notify_additional(1);
+    assert!(is_notified(l1.as_mut()));
+}
+
+#[test]
+fn local_event() {
+    let event = LocalEvent::new();
+    let waker = waker_fn(|| ());
+    let mut cx = Context::from_waker(&waker);
+
+    let l1 = event.listen();
+    let mut l2 = event.listen();
+    let mut l3 = event.listen();
+    assert!(Pin::new(&mut l2).poll(&mut cx).is_pending());
+    assert_eq!(event.total_listeners(), 3);
+
+    assert_eq!(event.notify(1), 1);
+    assert_eq!(event.notify(1), 0);
+    assert!(l1.is_notified());
+    assert!(!l2.is_notified());
+
+    // A dropped listener passes its notification on.
+    drop(l1);
+    assert!(l2.is_notified());
+    assert!(!l3.is_notified());
+    assert!(Pin::new(&mut l2).poll(&mut cx).is_ready());
+
+    assert_eq!(event.notify(usize::MAX), 1);
+    assert!(Pin::new(&mut l3).poll(&mut cx).is_ready());
+    assert_eq!(event.total_listeners(), 0);
+
+    // A discarded one doesn't.
+    let mut l4 = event.listen();
+    let mut l5 = event.listen();
+    assert_eq!(event.notify_additional(1), 1);
+    assert!(l4.discard());
+    assert!(!l5.is_notified());
+    assert!(!l5.discard());
+}
+
+#[cfg(feature = "no-alloc")]
+#[test]
+fn no_alloc_event() {
+    use event_listener::no_alloc::{Event, ListenError};
+
+    static EVENT: Event<3> = Event::new();
+    let waker = waker_fn(|| ());
+    let mut cx = Context::from_waker(&waker);
+
+    let l1 = EVENT.listen().unwrap();
+    let mut l2 = EVENT.listen().unwrap();
+    let mut l3 = EVENT.listen().unwrap();
+    assert_eq!(EVENT.listen().unwrap_err(), ListenError::Full);
+    assert!(Pin::new(&mut l2).poll(&mut cx).is_pending());
+    assert_eq!(EVENT.total_listeners(), 3);
+
+    assert_eq!(EVENT.notify(1), 1);
+    assert_eq!(EVENT.notify(1), 0);
+    assert!(l1.is_notified());
+    assert!(!l2.is_notified());
+
+    // A dropped listener passes its notification on and frees its slot.
+    drop(l1);
+    assert!(l2.is_notified());
+    assert!(!l3.is_notified());
+    let mut l4 = EVENT.listen().unwrap();
+    assert!(Pin::new(&mut l2).poll(&mut cx).is_ready());
+
+    assert_eq!(EVENT.notify(usize::MAX), 2);
+    assert!(Pin::new(&mut l3).poll(&mut cx).is_ready());
+    assert!(Pin::new(&mut l4).poll(&mut cx).is_ready());
+    assert_eq!(EVENT.total_listeners(), 0);
+}
+
+#[cfg(feature = "no-alloc")]
+#[test]
+fn no_alloc_event_threads() {
+    use event_listener::no_alloc::Event;
+    use futures_lite::future;
+    use std::thread;
+
+    static EVENT: Event<4> = Event::new();
+    static DONE: AtomicUsize = AtomicUsize::new(0);
+
+    let handles = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..100 {
+                    let listener = EVENT.listen().unwrap();
+                    EVENT.notify_additional(1);
+                    future::block_on(listener);
+                }
+                DONE.fetch_add(1, Ordering::SeqCst);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(DONE.load(Ordering::SeqCst), 4);
+    assert_eq!(EVENT.total_listeners(), 0);
+}